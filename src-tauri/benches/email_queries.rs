@@ -0,0 +1,114 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use dueam_lib::bench::{get_emails, search_emails};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use tauri::test::{mock_builder, MockRuntime};
+use tauri::Manager;
+
+const MESSAGES: usize = 100_000;
+const SENDERS: usize = 500;
+/// Messages per conversation
+const THREAD_LENGTH: usize = 4;
+
+const WORDS: &[&str] = &[
+    "invoice", "meeting", "quarterly", "report", "travel", "lunch", "release", "review",
+    "budget", "contract", "schedule", "update", "launch", "feedback", "shipping", "order",
+];
+
+/// A database shaped like a large mailbox: two accounts, the usual folders, threads and repeat senders.
+async fn fixture() -> (SqlitePool, tempfile::TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let options = SqliteConnectOptions::new()
+        .filename(dir.path().join("bench.sqlite"))
+        .create_if_missing(true);
+    let pool = SqlitePool::connect_with(options).await.unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+    let mut folders = Vec::new();
+    for account in ["bench@example.com", "work@example.com"] {
+        let (account_id,): (i64,) = sqlx::query_as("INSERT INTO accounts (email, account_type) VALUES (?, 'imap_smtp') RETURNING id")
+            .bind(account)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        for role in ["inbox", "sent", "archive"] {
+            let (folder_id,): (i64,) = sqlx::query_as("INSERT INTO folders (account_id, name, path, role) VALUES (?, ?, ?, ?) RETURNING id")
+                .bind(account_id)
+                .bind(role)
+                .bind(role.to_uppercase())
+                .bind(role)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            folders.push((account_id, folder_id));
+        }
+    }
+
+    let base = chrono::Utc::now();
+    let mut tx = pool.begin().await.unwrap();
+    for i in 0..MESSAGES {
+        let (account_id, folder_id) = folders[i % folders.len()];
+        let thread = i / THREAD_LENGTH;
+        let topic = WORDS[thread % WORDS.len()];
+        let subject = if i % THREAD_LENGTH == 0 { format!("{} {}", topic, thread) } else { format!("Re: {} {}", topic, thread) };
+        let sender = format!("sender{}@example{}.com", i % SENDERS, i % 7);
+
+        sqlx::query(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, normalized_subject, sender_name, sender_address, recipient_to, date, flags, snippet, body_text, has_attachments)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(account_id)
+        .bind(folder_id)
+        .bind(i.to_string())
+        .bind(format!("<{}@bench>", i))
+        .bind(format!("<{}@bench>", thread * THREAD_LENGTH))
+        .bind(&subject)
+        .bind(format!("{} {}", topic, thread))
+        .bind(format!("Sender {}", i % SENDERS))
+        .bind(&sender)
+        .bind("bench@example.com")
+        .bind((base - chrono::Duration::minutes(i as i64)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .bind(if i % 3 == 0 { "[]" } else { "[\"seen\"]" })
+        .bind(format!("About the {} for week {}", topic, i % 52))
+        .bind(format!("Hi, here is the {} we talked about. {} {}", topic, WORDS[i % WORDS.len()], WORDS[(i / 3) % WORDS.len()]))
+        .bind(i % 10 == 0)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    }
+    tx.commit().await.unwrap();
+    sqlx::query("ANALYZE").execute(&pool).await.unwrap();
+
+    (pool, dir)
+}
+
+fn bench_email_queries(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (pool, _dir) = runtime.block_on(fixture());
+    let app: tauri::App<MockRuntime> = mock_builder().build(tauri::generate_context!()).unwrap();
+    app.manage(pool);
+    let handle = app.handle().clone();
+
+    let mut group = c.benchmark_group("email_queries");
+    group.sample_size(20);
+
+    group.bench_function("get_emails_primary", |b| {
+        b.to_async(&runtime).iter(|| get_emails(handle.clone(), None, Some("primary".to_string()), None, Some(50), None, None, None, None))
+    });
+    group.bench_function("get_emails_unread_single_account", |b| {
+        b.to_async(&runtime).iter(|| get_emails(handle.clone(), Some(1), Some("primary".to_string()), Some("unread".to_string()), Some(50), None, None, None, None))
+    });
+    group.bench_function("get_emails_deep_page", |b| {
+        let before = (chrono::Utc::now() - chrono::Duration::days(50)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        b.to_async(&runtime).iter(|| get_emails(handle.clone(), None, Some("primary".to_string()), None, Some(50), Some(before.clone()), Some(i64::MAX), None, None))
+    });
+    group.bench_function("search_emails_word", |b| {
+        b.to_async(&runtime).iter(|| search_emails(handle.clone(), "invoice".to_string(), None, None, Some(50), None, None, None, None, None, None, None, None))
+    });
+    group.bench_function("search_emails_phrase", |b| {
+        b.to_async(&runtime).iter(|| search_emails(handle.clone(), "here is the budget".to_string(), None, None, Some(50), None, None, None, None, None, None, None, None))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_email_queries);
+criterion_main!(benches);
@@ -8,6 +8,7 @@ fn main() {
         "GOOGLE_CLIENT_SECRET",
         "MICROSOFT_CLIENT_ID",
         "MICROSOFT_CLIENT_SECRET",
+        "UPDATER_PUBKEY",
     ];
 
     for var in vars {
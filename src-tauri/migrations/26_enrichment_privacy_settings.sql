@@ -0,0 +1,5 @@
+-- Global switch for external sender enrichment (Gravatar, People APIs, AI)
+INSERT OR IGNORE INTO settings (key, value) VALUES ('enrichmentEnabled', 'true');
+
+-- Offline mode turns every network-backed enrichment provider into a no-op
+INSERT OR IGNORE INTO settings (key, value) VALUES ('offlineMode', 'false');
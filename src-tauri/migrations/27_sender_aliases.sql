@@ -0,0 +1,8 @@
+-- Migration: Link multiple sender addresses to a single contact
+CREATE TABLE IF NOT EXISTS sender_aliases (
+    alias_address TEXT PRIMARY KEY,
+    primary_address TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_sender_aliases_primary ON sender_aliases(primary_address);
@@ -0,0 +1,2 @@
+-- How often the periodic background sync runs, in minutes
+INSERT OR IGNORE INTO settings (key, value) VALUES ('syncIntervalMinutes', '5');
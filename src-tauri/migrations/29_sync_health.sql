@@ -0,0 +1,6 @@
+-- Migration: Track sync outcomes for the sync health view
+ALTER TABLE accounts ADD COLUMN last_synced_at DATETIME;
+ALTER TABLE accounts ADD COLUMN last_sync_error TEXT;
+
+ALTER TABLE folders ADD COLUMN last_synced_at DATETIME;
+ALTER TABLE folders ADD COLUMN last_sync_error TEXT;
@@ -0,0 +1,5 @@
+-- Migration: Resumable full sync
+-- full_sync_next_seq: highest sequence number still to fetch (NULL when no full sync is in progress)
+-- full_sync_uid_next: UIDNEXT when the full sync started, incremental sync continues from there
+ALTER TABLE folders ADD COLUMN full_sync_next_seq INTEGER;
+ALTER TABLE folders ADD COLUMN full_sync_uid_next INTEGER;
@@ -0,0 +1,5 @@
+-- Initial sync (backfill) throttling
+INSERT OR IGNORE INTO settings (key, value) VALUES ('syncBatchSize', '100');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('syncBatchDelayMs', '0');
+-- Maximum number of messages to backfill per folder, 0 means no limit
+INSERT OR IGNORE INTO settings (key, value) VALUES ('syncBackfillDepth', '0');
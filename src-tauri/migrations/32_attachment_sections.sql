@@ -0,0 +1,5 @@
+-- Migration: Lazy attachment downloads
+-- section: IMAP body section (e.g. "2" or "1.3") the attachment lives in
+-- encoding: Content-Transfer-Encoding of that section, needed to decode it
+ALTER TABLE attachments ADD COLUMN section TEXT;
+ALTER TABLE attachments ADD COLUMN encoding TEXT;
@@ -0,0 +1,5 @@
+-- Migration: Bounce / DSN tracking on sent messages
+-- delivery_status: NULL when nothing is known, 'failed' or 'delayed' once a report arrived
+ALTER TABLE emails ADD COLUMN delivery_status TEXT;
+ALTER TABLE emails ADD COLUMN delivery_error TEXT;
+ALTER TABLE emails ADD COLUMN bounce_email_id INTEGER;
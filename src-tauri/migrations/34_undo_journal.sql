@@ -0,0 +1,10 @@
+-- Migration: Undo for destructive actions
+-- entries: JSON list of the moved emails with their source and target folders
+CREATE TABLE IF NOT EXISTS undo_journal (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    token TEXT NOT NULL UNIQUE,
+    action TEXT NOT NULL,
+    entries TEXT NOT NULL,
+    undone BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,30 @@
+-- Migration: Auto-archive / auto-trash rules for noisy senders and mailing lists
+-- list_id: the <...> part of the List-Id header, filled in when the body is indexed
+ALTER TABLE emails ADD COLUMN list_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_emails_list_id ON emails(list_id);
+
+-- match_type: 'sender' (address, or '@domain') or 'list' (List-Id)
+-- action: 'archive' or 'trash'
+CREATE TABLE IF NOT EXISTS retention_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    match_type TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    action TEXT NOT NULL,
+    after_days INTEGER NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(match_type, pattern)
+);
+
+-- What the rules did, kept so the user can review it
+CREATE TABLE IF NOT EXISTS retention_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rule_id INTEGER,
+    email_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    subject TEXT,
+    sender_address TEXT,
+    applied_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (rule_id) REFERENCES retention_rules (id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_retention_log_applied_at ON retention_log(applied_at);
@@ -0,0 +1,9 @@
+-- Migration: Daily newsletter roll-ups in the inbox
+-- Per-sender override of the List-Id based detection: rollup = 1 opts a sender in, 0 opts it out
+CREATE TABLE IF NOT EXISTS newsletter_senders (
+    address TEXT PRIMARY KEY,
+    rollup BOOLEAN NOT NULL,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO settings (key, value) VALUES ('newsletterRollupEnabled', 'true');
@@ -0,0 +1,14 @@
+-- Migration: Screener for first-time senders
+-- status: 'pending' until the user decides, then 'approved' or 'screened_out'
+CREATE TABLE IF NOT EXISTS screened_senders (
+    address TEXT PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'pending',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    decided_at DATETIME
+);
+
+-- screening: NULL for regular inbox mail, otherwise the sender's status when the email arrived
+ALTER TABLE emails ADD COLUMN screening TEXT;
+CREATE INDEX IF NOT EXISTS idx_emails_screening ON emails(screening);
+
+INSERT OR IGNORE INTO settings (key, value) VALUES ('screenerEnabled', 'false');
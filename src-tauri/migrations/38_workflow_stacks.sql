@@ -0,0 +1,9 @@
+-- Migration: "Reply later" and "Set aside" stacks
+-- stack: 'reply_later' or 'set_aside', set on every email of the thread
+ALTER TABLE emails ADD COLUMN stack TEXT;
+ALTER TABLE emails ADD COLUMN stacked_at DATETIME;
+ALTER TABLE emails ADD COLUMN nudged_at DATETIME;
+CREATE INDEX IF NOT EXISTS idx_emails_stack ON emails(stack);
+
+-- Days before a reply later thread triggers a reminder, 0 turns reminders off
+INSERT OR IGNORE INTO settings (key, value) VALUES ('replyLaterNudgeDays', '0');
@@ -0,0 +1,56 @@
+-- Migration: Unicode aware full text search
+-- emails_fts folds case and diacritics ("cafe" finds "Café"), emails_fts_trigram indexes
+-- character trigrams for scripts without spaces between words (Chinese, Japanese, ...)
+DROP TRIGGER IF EXISTS emails_ai;
+DROP TRIGGER IF EXISTS emails_ad;
+DROP TRIGGER IF EXISTS emails_au;
+DROP TABLE IF EXISTS emails_fts;
+
+CREATE VIRTUAL TABLE emails_fts USING fts5(
+    subject,
+    sender_name,
+    sender_address,
+    body_text,
+    content='emails',
+    content_rowid='id',
+    tokenize='unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE emails_fts_trigram USING fts5(
+    subject,
+    sender_name,
+    sender_address,
+    body_text,
+    content='emails',
+    content_rowid='id',
+    tokenize='trigram'
+);
+
+CREATE TRIGGER emails_ai AFTER INSERT ON emails BEGIN
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text);
+  INSERT INTO emails_fts_trigram(rowid, subject, sender_name, sender_address, body_text)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text);
+END;
+
+CREATE TRIGGER emails_ad AFTER DELETE ON emails BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text);
+  INSERT INTO emails_fts_trigram(emails_fts_trigram, rowid, subject, sender_name, sender_address, body_text)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text);
+END;
+
+-- Only the indexed columns, flag and folder changes don't need reindexing
+CREATE TRIGGER emails_au AFTER UPDATE OF subject, sender_name, sender_address, body_text ON emails BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text);
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text);
+  INSERT INTO emails_fts_trigram(emails_fts_trigram, rowid, subject, sender_name, sender_address, body_text)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text);
+  INSERT INTO emails_fts_trigram(rowid, subject, sender_name, sender_address, body_text)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text);
+END;
+
+INSERT INTO emails_fts(emails_fts) VALUES('rebuild');
+INSERT INTO emails_fts_trigram(emails_fts_trigram) VALUES('rebuild');
@@ -0,0 +1,11 @@
+-- Migration: Links found in email bodies, for the contact panel's "files & links" tab
+CREATE TABLE IF NOT EXISTS links (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    email_id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    title TEXT,
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE CASCADE,
+    UNIQUE(email_id, url)
+);
+CREATE INDEX IF NOT EXISTS idx_links_email_id ON links(email_id);
@@ -0,0 +1,3 @@
+-- Migration: Sync scheduling mode
+-- 'auto' picks mobile scheduling on iOS/Android builds, 'desktop' and 'mobile' force one. Applied at startup.
+INSERT OR IGNORE INTO settings (key, value) VALUES ('syncMode', '"auto"');
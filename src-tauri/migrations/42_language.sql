@@ -0,0 +1,3 @@
+-- Migration: Language of backend strings (notifications, tray menu, errors)
+-- 'system' follows the OS locale, otherwise a language tag such as 'de'
+INSERT OR IGNORE INTO settings (key, value) VALUES ('language', '"system"');
@@ -0,0 +1,9 @@
+-- Migration: Proxy for outgoing HTTP requests
+-- proxyMode: 'system' (OS settings and *_PROXY variables), 'none' or 'manual'
+-- proxyType: 'http' or 'socks5'; the password lives in the keyring under proxyPassword
+INSERT OR IGNORE INTO settings (key, value) VALUES ('proxyMode', '"system"');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('proxyType', '"http"');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('proxyHost', '""');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('proxyPort', '0');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('proxyUsername', '""');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('proxyPassword', '""');
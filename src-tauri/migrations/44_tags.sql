@@ -0,0 +1,21 @@
+-- Migration: Local color tags
+-- sync_to_server: also keep the tag on the server, as a keyword or, on Gmail, as a label
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    color TEXT NOT NULL,
+    sync_to_server BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Keyed by Message-ID rather than email id so tags stay on a message moved to another folder
+CREATE TABLE IF NOT EXISTS email_tags (
+    account_id INTEGER NOT NULL,
+    message_id TEXT NOT NULL,
+    tag_id INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, message_id, tag_id),
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_email_tags_tag ON email_tags(tag_id);
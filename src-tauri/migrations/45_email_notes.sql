@@ -0,0 +1,59 @@
+-- Migration: Private notes on emails
+-- note: written by the user, kept locally and never sent; both search indexes cover it
+ALTER TABLE emails ADD COLUMN note TEXT;
+
+DROP TRIGGER IF EXISTS emails_ai;
+DROP TRIGGER IF EXISTS emails_ad;
+DROP TRIGGER IF EXISTS emails_au;
+DROP TABLE IF EXISTS emails_fts;
+DROP TABLE IF EXISTS emails_fts_trigram;
+
+CREATE VIRTUAL TABLE emails_fts USING fts5(
+    subject,
+    sender_name,
+    sender_address,
+    body_text,
+    note,
+    content='emails',
+    content_rowid='id',
+    tokenize='unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE emails_fts_trigram USING fts5(
+    subject,
+    sender_name,
+    sender_address,
+    body_text,
+    note,
+    content='emails',
+    content_rowid='id',
+    tokenize='trigram'
+);
+
+CREATE TRIGGER emails_ai AFTER INSERT ON emails BEGIN
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note);
+  INSERT INTO emails_fts_trigram(rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note);
+END;
+
+CREATE TRIGGER emails_ad AFTER DELETE ON emails BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note);
+  INSERT INTO emails_fts_trigram(emails_fts_trigram, rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note);
+END;
+
+CREATE TRIGGER emails_au AFTER UPDATE OF subject, sender_name, sender_address, body_text, note ON emails BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note);
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note);
+  INSERT INTO emails_fts_trigram(emails_fts_trigram, rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note);
+  INSERT INTO emails_fts_trigram(rowid, subject, sender_name, sender_address, body_text, note)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note);
+END;
+
+INSERT INTO emails_fts(emails_fts) VALUES('rebuild');
+INSERT INTO emails_fts_trigram(emails_fts_trigram) VALUES('rebuild');
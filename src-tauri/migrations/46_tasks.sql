@@ -0,0 +1,18 @@
+-- Migration: Tasks made from emails
+-- email_id goes NULL when the email row is replaced, message_id finds it again
+-- due_at, completed_at and notified_at are UTC, formatted like emails.date
+CREATE TABLE IF NOT EXISTS tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    email_id INTEGER,
+    message_id TEXT,
+    title TEXT NOT NULL,
+    note TEXT,
+    due_at TEXT,
+    completed_at TEXT,
+    notified_at TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_tasks_due_at ON tasks(due_at) WHERE completed_at IS NULL;
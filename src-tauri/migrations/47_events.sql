@@ -0,0 +1,29 @@
+-- Migration: Events from received calendar invites
+-- recurrence_id: '' for an event or a whole series, the original start of a single changed occurrence
+-- start_at/end_at are UTC formatted like emails.date, all-day events start at midnight UTC of their date
+-- rrule/exdates: the series' RRULE and excluded starts (comma separated), expanded when listed
+-- status: 'confirmed', 'tentative' or 'cancelled'
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    email_id INTEGER,
+    uid TEXT NOT NULL,
+    recurrence_id TEXT NOT NULL DEFAULT '',
+    sequence INTEGER NOT NULL DEFAULT 0,
+    summary TEXT,
+    location TEXT,
+    description TEXT,
+    organizer TEXT,
+    start_at TEXT NOT NULL,
+    end_at TEXT,
+    all_day BOOLEAN NOT NULL DEFAULT FALSE,
+    status TEXT NOT NULL DEFAULT 'confirmed',
+    rrule TEXT,
+    exdates TEXT,
+    join_url TEXT,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(account_id, uid, recurrence_id),
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE,
+    FOREIGN KEY (email_id) REFERENCES emails (id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_events_start_at ON events(start_at);
@@ -0,0 +1,3 @@
+-- Migration: Release channel for automatic updates
+-- 'stable' or 'beta', read on every update check
+INSERT OR IGNORE INTO settings (key, value) VALUES ('updateChannel', '"stable"');
@@ -0,0 +1,99 @@
+-- Migration: One row per message, however many folders hold a copy
+-- Gmail files a message in every label's folder as well as All Mail, each copy is an `emails` row
+-- with its own folder, UID and flags. `messages` groups the copies by Message-ID (per account) and
+-- points at the copy lists show: the inbox one, else the sent one, else the newest.
+-- message_key: the Message-ID, or 'email:<id>' for a message without one, which only ever has one copy
+-- email_id: the listed copy, NULL only for a moment while the last copy is deleted
+CREATE TABLE IF NOT EXISTS messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    message_key TEXT NOT NULL,
+    email_id INTEGER,
+    UNIQUE(account_id, message_key),
+    FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_messages_email_id ON messages(email_id);
+CREATE INDEX IF NOT EXISTS idx_emails_account_message_id ON emails(account_id, message_id);
+
+-- Which folders a message is in, one row per copy
+CREATE VIEW IF NOT EXISTS message_folders AS
+SELECT m.id AS message_row_id, m.account_id, m.message_key, e.id AS email_id, e.folder_id, e.remote_id, e.flags
+FROM messages m
+JOIN emails e ON e.account_id = m.account_id AND (e.message_id = m.message_key OR (e.message_id IS NULL AND e.id = m.email_id));
+
+INSERT INTO messages (account_id, message_key, email_id)
+SELECT account_id, message_key, id FROM (
+    SELECT e.id, e.account_id, COALESCE(e.message_id, 'email:' || e.id) AS message_key,
+        ROW_NUMBER() OVER (
+            PARTITION BY e.account_id, COALESCE(e.message_id, 'email:' || e.id)
+            ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC, e.id DESC
+        ) AS rn
+    FROM emails e
+    JOIN folders f ON e.folder_id = f.id
+)
+WHERE rn = 1;
+
+CREATE TRIGGER IF NOT EXISTS emails_messages_ai AFTER INSERT ON emails BEGIN
+    INSERT INTO messages (account_id, message_key, email_id)
+    VALUES (new.account_id, COALESCE(new.message_id, 'email:' || new.id), new.id)
+    ON CONFLICT(account_id, message_key) DO NOTHING;
+
+    UPDATE messages SET email_id = (
+        SELECT e.id FROM emails e JOIN folders f ON e.folder_id = f.id
+        WHERE e.account_id = messages.account_id AND e.message_id = messages.message_key
+        ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC, e.id DESC
+        LIMIT 1
+    )
+    WHERE new.message_id IS NOT NULL AND account_id = new.account_id AND message_key = new.message_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS emails_messages_ad AFTER DELETE ON emails BEGIN
+    UPDATE messages SET email_id = (
+        SELECT e.id FROM emails e JOIN folders f ON e.folder_id = f.id
+        WHERE e.account_id = messages.account_id AND e.message_id = messages.message_key
+        ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC, e.id DESC
+        LIMIT 1
+    )
+    WHERE old.message_id IS NOT NULL AND account_id = old.account_id AND message_key = old.message_id;
+
+    -- Last copy gone
+    DELETE FROM messages
+    WHERE account_id = old.account_id
+      AND (message_key = 'email:' || old.id OR (message_key = old.message_id AND email_id IS NULL));
+END;
+
+-- Moves change which copy comes first
+CREATE TRIGGER IF NOT EXISTS emails_messages_au AFTER UPDATE OF folder_id, message_id, date ON emails BEGIN
+    INSERT INTO messages (account_id, message_key, email_id)
+    VALUES (new.account_id, COALESCE(new.message_id, 'email:' || new.id), new.id)
+    ON CONFLICT(account_id, message_key) DO NOTHING;
+
+    UPDATE messages SET email_id = (
+        SELECT e.id FROM emails e JOIN folders f ON e.folder_id = f.id
+        WHERE e.account_id = messages.account_id AND e.message_id = messages.message_key
+        ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC, e.id DESC
+        LIMIT 1
+    )
+    WHERE account_id = new.account_id AND message_key IN (old.message_id, new.message_id);
+
+    DELETE FROM messages
+    WHERE account_id = new.account_id AND email_id IS NULL AND message_key = old.message_id;
+
+    -- A copy that got its Message-ID leaves its placeholder behind
+    DELETE FROM messages
+    WHERE old.message_id IS NULL AND new.message_id IS NOT NULL
+      AND account_id = new.account_id AND message_key = 'email:' || new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS folders_messages_au AFTER UPDATE OF role ON folders BEGIN
+    UPDATE messages SET email_id = (
+        SELECT e.id FROM emails e JOIN folders f ON e.folder_id = f.id
+        WHERE e.account_id = messages.account_id AND e.message_id = messages.message_key
+        ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC, e.id DESC
+        LIMIT 1
+    )
+    WHERE account_id = new.account_id AND message_key IN (
+        SELECT message_id FROM emails WHERE folder_id = new.id AND message_id IS NOT NULL
+    );
+END;
@@ -0,0 +1,27 @@
+-- Migration: Permanently delete mail left in the trash
+-- Days in the trash before mail is deleted for good, 0 keeps it until emptied by hand
+INSERT OR IGNORE INTO settings (key, value) VALUES ('trashRetentionDays', '0');
+
+-- Per account override of trashRetentionDays, NULL follows the setting
+ALTER TABLE accounts ADD COLUMN trash_retention_days INTEGER;
+
+-- When mail landed in the trash, moved there or first synced from it
+ALTER TABLE emails ADD COLUMN trashed_at DATETIME;
+
+UPDATE emails SET trashed_at = CURRENT_TIMESTAMP
+WHERE folder_id IN (SELECT id FROM folders WHERE role = 'trash');
+
+CREATE TRIGGER IF NOT EXISTS emails_trashed_ai AFTER INSERT ON emails
+WHEN (SELECT role FROM folders WHERE id = new.folder_id) = 'trash'
+BEGIN
+    UPDATE emails SET trashed_at = CURRENT_TIMESTAMP WHERE id = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS emails_trashed_au AFTER UPDATE OF folder_id ON emails
+WHEN new.folder_id IS NOT old.folder_id
+BEGIN
+    UPDATE emails SET trashed_at = CASE
+        WHEN (SELECT role FROM folders WHERE id = new.folder_id) = 'trash' THEN CURRENT_TIMESTAMP
+    END
+    WHERE id = new.id;
+END;
@@ -0,0 +1,8 @@
+-- Migration: Spam signals learned from what the user moves
+-- spam_count: mail from the sender reported as spam, rescued_count: moved from spam back to the inbox
+CREATE TABLE IF NOT EXISTS spam_signals (
+    address TEXT PRIMARY KEY,
+    spam_count INTEGER NOT NULL DEFAULT 0,
+    rescued_count INTEGER NOT NULL DEFAULT 0,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
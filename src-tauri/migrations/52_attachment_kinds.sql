@@ -0,0 +1,51 @@
+-- Migration: Attachment kinds, indexed for the type quick filters
+-- kind: image, pdf, document, spreadsheet, presentation, archive or other, derived from mime_type
+ALTER TABLE attachments ADD COLUMN kind TEXT;
+
+UPDATE attachments SET kind = CASE
+        WHEN mime_type LIKE 'image/%' THEN 'image'
+        WHEN mime_type = 'application/pdf' THEN 'pdf'
+        WHEN mime_type IN ('application/msword', 'application/vnd.openxmlformats-officedocument.wordprocessingml.document',
+                           'application/vnd.oasis.opendocument.text', 'application/rtf', 'text/plain') THEN 'document'
+        WHEN mime_type IN ('application/vnd.ms-excel', 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet',
+                           'application/vnd.oasis.opendocument.spreadsheet', 'text/csv') THEN 'spreadsheet'
+        WHEN mime_type IN ('application/vnd.ms-powerpoint', 'application/vnd.openxmlformats-officedocument.presentationml.presentation',
+                           'application/vnd.oasis.opendocument.presentation') THEN 'presentation'
+        WHEN mime_type IN ('application/zip', 'application/x-zip-compressed', 'application/x-7z-compressed',
+                           'application/x-rar-compressed', 'application/gzip', 'application/x-tar') THEN 'archive'
+        ELSE 'other'
+    END;
+
+CREATE TRIGGER IF NOT EXISTS attachments_kind_ai AFTER INSERT ON attachments BEGIN
+    UPDATE attachments SET kind = CASE
+        WHEN new.mime_type LIKE 'image/%' THEN 'image'
+        WHEN new.mime_type = 'application/pdf' THEN 'pdf'
+        WHEN new.mime_type IN ('application/msword', 'application/vnd.openxmlformats-officedocument.wordprocessingml.document',
+                               'application/vnd.oasis.opendocument.text', 'application/rtf', 'text/plain') THEN 'document'
+        WHEN new.mime_type IN ('application/vnd.ms-excel', 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet',
+                               'application/vnd.oasis.opendocument.spreadsheet', 'text/csv') THEN 'spreadsheet'
+        WHEN new.mime_type IN ('application/vnd.ms-powerpoint', 'application/vnd.openxmlformats-officedocument.presentationml.presentation',
+                               'application/vnd.oasis.opendocument.presentation') THEN 'presentation'
+        WHEN new.mime_type IN ('application/zip', 'application/x-zip-compressed', 'application/x-7z-compressed',
+                               'application/x-rar-compressed', 'application/gzip', 'application/x-tar') THEN 'archive'
+        ELSE 'other'
+    END WHERE id = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS attachments_kind_au AFTER UPDATE OF mime_type ON attachments BEGIN
+    UPDATE attachments SET kind = CASE
+        WHEN new.mime_type LIKE 'image/%' THEN 'image'
+        WHEN new.mime_type = 'application/pdf' THEN 'pdf'
+        WHEN new.mime_type IN ('application/msword', 'application/vnd.openxmlformats-officedocument.wordprocessingml.document',
+                               'application/vnd.oasis.opendocument.text', 'application/rtf', 'text/plain') THEN 'document'
+        WHEN new.mime_type IN ('application/vnd.ms-excel', 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet',
+                               'application/vnd.oasis.opendocument.spreadsheet', 'text/csv') THEN 'spreadsheet'
+        WHEN new.mime_type IN ('application/vnd.ms-powerpoint', 'application/vnd.openxmlformats-officedocument.presentationml.presentation',
+                               'application/vnd.oasis.opendocument.presentation') THEN 'presentation'
+        WHEN new.mime_type IN ('application/zip', 'application/x-zip-compressed', 'application/x-7z-compressed',
+                               'application/x-rar-compressed', 'application/gzip', 'application/x-tar') THEN 'archive'
+        ELSE 'other'
+    END WHERE id = new.id;
+END;
+
+CREATE INDEX IF NOT EXISTS idx_attachments_kind ON attachments (kind, email_id);
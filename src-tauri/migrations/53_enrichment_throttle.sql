@@ -0,0 +1,14 @@
+-- Migration: Remember enrichment lookups that found nothing, and providers backing off after errors
+CREATE TABLE IF NOT EXISTS enrichment_misses (
+    provider TEXT NOT NULL,
+    key TEXT NOT NULL,
+    checked_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (provider, key)
+);
+
+-- failures: errors in a row, each one doubles the cooldown
+CREATE TABLE IF NOT EXISTS enrichment_cooldowns (
+    provider TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    until DATETIME NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Migration: Notification sound and dock/taskbar badge preferences
+-- notificationSound: "default" for the system sound, "none" for silent, or a system sound name
+INSERT OR IGNORE INTO settings (key, value) VALUES ('notificationSound', '"default"');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('badgeEnabled', 'true');
+-- Count only unread mail in the inbox rather than every folder
+INSERT OR IGNORE INTO settings (key, value) VALUES ('badgePrimaryOnly', 'false');
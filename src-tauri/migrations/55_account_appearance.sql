@@ -0,0 +1,4 @@
+-- Migration: Per account color and label, to tell accounts apart in a unified inbox
+-- color: '#rrggbb', label: shown instead of the address, NULL for the defaults
+ALTER TABLE accounts ADD COLUMN color TEXT;
+ALTER TABLE accounts ADD COLUMN label TEXT;
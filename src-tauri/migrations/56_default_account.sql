@@ -0,0 +1,2 @@
+-- Migration: Account preselected when composing new mail, null for the first account
+INSERT OR IGNORE INTO settings (key, value) VALUES ('defaultAccount', 'null');
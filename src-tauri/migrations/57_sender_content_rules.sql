@@ -0,0 +1,4 @@
+-- Migration: Per-sender decisions on remote content and attachments
+-- 'always' or 'never', NULL leaves it to the user each time
+ALTER TABLE senders ADD COLUMN remote_content TEXT;
+ALTER TABLE senders ADD COLUMN auto_attachments TEXT;
@@ -0,0 +1,16 @@
+-- Migration: Addresses the user also sends from, besides the account's own
+CREATE TABLE IF NOT EXISTS account_aliases (
+    account_id INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, address),
+    FOREIGN KEY (account_id) REFERENCES accounts (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_account_aliases_address ON account_aliases(address);
+
+-- Every address that is the user, lowercased
+CREATE VIEW IF NOT EXISTS own_addresses AS
+SELECT id AS account_id, LOWER(email) AS address FROM accounts
+UNION
+SELECT account_id, address FROM account_aliases;
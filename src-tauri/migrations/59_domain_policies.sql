@@ -0,0 +1,9 @@
+-- Migration: Per-domain handling of new mail, for domains sending automated mail in bulk
+-- A policy for a domain covers its subdomains too
+CREATE TABLE IF NOT EXISTS domain_policies (
+    domain TEXT PRIMARY KEY,
+    mute_notifications BOOLEAN NOT NULL DEFAULT 0,
+    auto_archive BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+-- Migration: Cap the size of cached bodies
+-- Kilobytes of HTML and text kept per email, 0 keeps bodies whole
+INSERT OR IGNORE INTO settings (key, value) VALUES ('cachedHtmlLimitKb', '512');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('cachedTextLimitKb', '128');
+
+-- The cached body is a cut-down copy, opening the email fetches all of it
+ALTER TABLE emails ADD COLUMN body_truncated BOOLEAN NOT NULL DEFAULT 0;
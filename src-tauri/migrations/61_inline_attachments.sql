@@ -0,0 +1,10 @@
+-- Migration: Tell images shown in the body apart from files sent along
+-- Older rows only have the Content-ID to go by, the disposition wasn't kept
+ALTER TABLE attachments ADD COLUMN is_inline BOOLEAN NOT NULL DEFAULT 0;
+
+UPDATE attachments SET is_inline = 1 WHERE content_id IS NOT NULL AND mime_type LIKE 'image/%';
+
+UPDATE emails SET has_attachments = 0
+WHERE has_attachments = 1
+  AND EXISTS (SELECT 1 FROM attachments a WHERE a.email_id = emails.id)
+  AND NOT EXISTS (SELECT 1 FROM attachments a WHERE a.email_id = emails.id AND a.is_inline = 0);
@@ -0,0 +1,9 @@
+-- Migration: Confidential mail that is never cached locally
+-- Bodies and attachments of such emails are always fetched live, only the envelope is stored
+ALTER TABLE emails ADD COLUMN no_cache BOOLEAN NOT NULL DEFAULT 0;
+
+-- An address, or `@domain` for every sender of a domain
+CREATE TABLE IF NOT EXISTS no_cache_senders (
+    pattern TEXT PRIMARY KEY,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
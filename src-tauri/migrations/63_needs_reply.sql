@@ -0,0 +1,10 @@
+-- Migration: Reminders for received mail still waiting on the user's reply
+-- What the AI made of an email, NULL until it was asked
+ALTER TABLE emails ADD COLUMN needs_reply_ai BOOLEAN;
+-- Set when the user waves the reminder off
+ALTER TABLE emails ADD COLUMN needs_reply_dismissed BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE emails ADD COLUMN reply_nudged_at DATETIME;
+
+INSERT OR IGNORE INTO settings (key, value) VALUES ('needsReplyDays', '3');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('needsReplyNudgesEnabled', 'false');
+INSERT OR IGNORE INTO settings (key, value) VALUES ('aiNeedsReplyEnabled', 'false');
@@ -0,0 +1,6 @@
+-- Migration: Where the user left off in each email, and which messages of a thread are expanded
+-- read_position: share of the body scrolled past, from 0 to 1
+ALTER TABLE emails ADD COLUMN read_position REAL;
+ALTER TABLE emails ADD COLUMN last_opened_at DATETIME;
+-- NULL leaves it to the reader, which expands unread and latest messages
+ALTER TABLE emails ADD COLUMN expanded BOOLEAN;
@@ -0,0 +1,2 @@
+-- Migration: Give up on IMAP requests the server never answers
+INSERT OR IGNORE INTO settings (key, value) VALUES ('imapTimeoutSecs', '60');
@@ -0,0 +1,61 @@
+-- Migration: Snippets and AI summaries in both search indexes
+-- Mail whose body was never downloaded is still found by its preview and summary
+DROP TRIGGER IF EXISTS emails_ai;
+DROP TRIGGER IF EXISTS emails_ad;
+DROP TRIGGER IF EXISTS emails_au;
+DROP TABLE IF EXISTS emails_fts;
+DROP TABLE IF EXISTS emails_fts_trigram;
+
+CREATE VIRTUAL TABLE emails_fts USING fts5(
+    subject,
+    sender_name,
+    sender_address,
+    body_text,
+    note,
+    snippet,
+    summary,
+    content='emails',
+    content_rowid='id',
+    tokenize='unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE emails_fts_trigram USING fts5(
+    subject,
+    sender_name,
+    sender_address,
+    body_text,
+    note,
+    snippet,
+    summary,
+    content='emails',
+    content_rowid='id',
+    tokenize='trigram'
+);
+
+CREATE TRIGGER emails_ai AFTER INSERT ON emails BEGIN
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note, new.snippet, new.summary);
+  INSERT INTO emails_fts_trigram(rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note, new.snippet, new.summary);
+END;
+
+CREATE TRIGGER emails_ad AFTER DELETE ON emails BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note, old.snippet, old.summary);
+  INSERT INTO emails_fts_trigram(emails_fts_trigram, rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note, old.snippet, old.summary);
+END;
+
+CREATE TRIGGER emails_au AFTER UPDATE OF subject, sender_name, sender_address, body_text, note, snippet, summary ON emails BEGIN
+  INSERT INTO emails_fts(emails_fts, rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note, old.snippet, old.summary);
+  INSERT INTO emails_fts(rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note, new.snippet, new.summary);
+  INSERT INTO emails_fts_trigram(emails_fts_trigram, rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES('delete', old.id, old.subject, old.sender_name, old.sender_address, old.body_text, old.note, old.snippet, old.summary);
+  INSERT INTO emails_fts_trigram(rowid, subject, sender_name, sender_address, body_text, note, snippet, summary)
+  VALUES (new.id, new.subject, new.sender_name, new.sender_address, new.body_text, new.note, new.snippet, new.summary);
+END;
+
+INSERT INTO emails_fts(emails_fts) VALUES('rebuild');
+INSERT INTO emails_fts_trigram(emails_fts_trigram) VALUES('rebuild');
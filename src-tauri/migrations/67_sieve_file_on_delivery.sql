@@ -0,0 +1,4 @@
+-- Migration: Retention rules the mail server may apply on delivery
+-- Pushed to Sieve, such a rule files matching mail right away instead of after its days,
+-- so only rules the user marked for it are pushed
+ALTER TABLE retention_rules ADD COLUMN file_on_delivery BOOLEAN NOT NULL DEFAULT FALSE;
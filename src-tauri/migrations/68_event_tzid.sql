@@ -0,0 +1,3 @@
+-- Migration: The zone a series repeats in
+-- tzid: the IANA name of the DTSTART's TZID, NULL for UTC and floating times which repeat in the user's zone
+ALTER TABLE events ADD COLUMN tzid TEXT;
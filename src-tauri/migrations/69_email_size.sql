@@ -0,0 +1,3 @@
+-- Migration: Message sizes
+-- size: RFC822.SIZE as the server reported it with the envelope, NULL for mail synced before
+ALTER TABLE emails ADD COLUMN size INTEGER;
@@ -62,6 +62,7 @@ pub fn try_from_imap_fetch(fetch: &FlagFetch<'_>) -> Result<Self, Error> {
             FlagFetch::Flag(ImapFlag::Flagged) => Ok(Flag::Flagged),
             FlagFetch::Flag(ImapFlag::Deleted) => Ok(Flag::Deleted),
             FlagFetch::Flag(ImapFlag::Draft) => Ok(Flag::Draft),
+            FlagFetch::Flag(ImapFlag::Keyword(keyword)) => Ok(Flag::Custom(keyword.to_string())),
             FlagFetch::Flag(flag) => Err(Error::ParseFlagImapError(flag.to_string())),
             FlagFetch::Recent => Err(Error::ParseFlagImapError("\\Recent".into())),
         }
@@ -19,14 +19,15 @@
 };
 
 /// The IMAP fetch items needed to retrieve everything we need to
-/// build an envelope: UID, flags and envelope (Message-ID, From, To,
-/// Subject, Date).
+/// build an envelope: UID, flags, envelope (Message-ID, From, To,
+/// Subject, Date), body structure and size.
 pub static FETCH_ENVELOPES: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
     MacroOrMessageDataItemNames::MessageDataItemNames(vec![
         MessageDataItemName::Uid,
         MessageDataItemName::Flags,
         MessageDataItemName::Envelope,
         MessageDataItemName::BodyStructure,
+        MessageDataItemName::Rfc822Size,
     ])
 });
 
@@ -0,0 +1,27 @@
+//! Module dedicated to the address connections are made to.
+//!
+//! IMAP and SMTP clients connect to the host and port of their
+//! configuration. An application can install a resolver to send a
+//! connection elsewhere, a local relay for example, and have it asked
+//! again every time the client (re)connects.
+
+use std::sync::OnceLock;
+
+/// Maps the configured host and port to the address to connect to,
+/// [`None`] keeps them as they are.
+pub type Resolver = dyn Fn(&str, u16) -> Option<(String, u16)> + Send + Sync;
+
+static RESOLVER: OnceLock<Box<Resolver>> = OnceLock::new();
+
+/// Installs the resolver. Only the first one installed is used.
+pub fn set_resolver(resolver: impl Fn(&str, u16) -> Option<(String, u16)> + Send + Sync + 'static) {
+    let _ = RESOLVER.set(Box::new(resolver));
+}
+
+/// The address to connect to for the configured `host` and `port`.
+pub fn resolve(host: &str, port: u16) -> (String, u16) {
+    RESOLVER
+        .get()
+        .and_then(|resolver| resolver(host, port))
+        .unwrap_or_else(|| (host.to_owned(), port))
+}
@@ -316,6 +316,35 @@ pub async fn expunge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
         Ok(expunged.len())
     }
 
+    /// Whether the server announced UIDPLUS, which
+    /// `expunge_messages` needs.
+    pub fn ext_uidplus_supported(&self) -> bool {
+        self.inner.state.ext_uidplus_supported()
+    }
+
+    /// Expunges only the given messages of the selected mailbox
+    /// (`UID EXPUNGE`), leaving anything else flagged `\Deleted`
+    /// there alone.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn expunge_messages(&mut self, uids: SequenceSet) -> Result<usize> {
+        self.retry.reset();
+
+        let expunged = loop {
+            let res = self
+                .retry
+                .timeout(self.inner.uid_expunge(uids.clone()))
+                .await;
+
+            match self.retry(res).await? {
+                ImapRetryState::Retry => continue,
+                ImapRetryState::TimedOut => break Err(Error::ExpungeMailboxTimedOutError),
+                ImapRetryState::Ok(res) => break res.map_err(Error::ExpungeMailboxError),
+            }
+        }?;
+
+        Ok(expunged.len())
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn purge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
         self.select_mailbox(mbox).await?;
@@ -422,6 +451,28 @@ pub async fn fetch_first_envelope(&mut self, uid: u32) -> Result<Envelope> {
         Ok(Envelope::from_imap_data_items(items.as_ref()))
     }
 
+    /// Fetches arbitrary data items (BODYSTRUCTURE, single body
+    /// sections, partial ranges…) for one UID, without mapping them
+    /// to a `Message`.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_first_items(
+        &mut self,
+        uid: NonZeroU32,
+        items: MacroOrMessageDataItemNames<'static>,
+    ) -> Result<Vec1<MessageDataItem<'static>>> {
+        loop {
+            let task = self.inner.uid_fetch_first(uid, items.clone());
+
+            let res = self.retry.timeout(task).await;
+
+            match self.retry(res).await? {
+                ImapRetryState::Retry => continue,
+                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+            }
+        }
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn fetch_envelopes_by_sequence(&mut self, seq: SequenceSet) -> Result<Envelopes> {
         let fetches = loop {
@@ -440,6 +491,48 @@ pub async fn fetch_envelopes_by_sequence(&mut self, seq: SequenceSet) -> Result<
         Ok(Envelopes::from_imap_data_items(fetches))
     }
 
+    /// Same fetch as `fetch_envelopes`, returning the raw data items
+    /// by UID so the full BODYSTRUCTURE is not lost.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_envelope_items(
+        &mut self,
+        uids: SequenceSet,
+    ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
+        loop {
+            let res = self
+                .retry
+                .timeout(self.inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone()))
+                .await;
+
+            match self.retry(res).await? {
+                ImapRetryState::Retry => continue,
+                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+            }
+        }
+    }
+
+    /// Same fetch as `fetch_envelopes_by_sequence`, returning the raw
+    /// data items by sequence number.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_envelope_items_by_sequence(
+        &mut self,
+        seq: SequenceSet,
+    ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
+        loop {
+            let res = self
+                .retry
+                .timeout(self.inner.fetch(seq.clone(), FETCH_ENVELOPES.clone()))
+                .await;
+
+            match self.retry(res).await? {
+                ImapRetryState::Retry => continue,
+                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+            }
+        }
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn fetch_all_envelopes(&mut self) -> Result<Envelopes> {
         self.fetch_envelopes_by_sequence("1:*".try_into().unwrap())
@@ -1078,8 +1171,10 @@ pub fn new(config: Arc<ImapConfig>, credentials: Option<String>) -> Self {
     /// a row.
     #[instrument(name = "client::build", skip(self))]
     pub async fn build(&mut self) -> Result<Client> {
+        let (host, port) = crate::endpoint::resolve(&self.config.host, self.config.port);
+
         let mut client = match &self.config.encryption {
-            Some(Encryption::None) => Client::insecure(&self.config.host, self.config.port)
+            Some(Encryption::None) => Client::insecure(&host, port)
                 .await
                 .map_err(|err| {
                     let host = self.config.host.clone();
@@ -1098,7 +1193,7 @@ pub async fn build(&mut self) -> Result<Client> {
             Some(Encryption::Tls(Tls {
                 provider: Some(TlsProvider::Rustls(_)) | None,
             }))
-            | None => Client::rustls(&self.config.host, self.config.port, false)
+            | None => Client::rustls(&host, port, false)
                 .await
                 .map_err(|err| {
                     let host = self.config.host.clone();
@@ -1108,7 +1203,7 @@ pub async fn build(&mut self) -> Result<Client> {
             #[cfg(feature = "native-tls")]
             Some(Encryption::Tls(Tls {
                 provider: Some(TlsProvider::NativeTls(_)),
-            })) => Client::native_tls(&self.config.host, self.config.port, false)
+            })) => Client::native_tls(&host, port, false)
                 .await
                 .map_err(|err| {
                     let host = self.config.host.clone();
@@ -1118,7 +1213,7 @@ pub async fn build(&mut self) -> Result<Client> {
             #[cfg(feature = "rustls")]
             Some(Encryption::StartTls(Tls {
                 provider: Some(TlsProvider::Rustls(_)) | None,
-            })) => Client::rustls(&self.config.host, self.config.port, true)
+            })) => Client::rustls(&host, port, true)
                 .await
                 .map_err(|err| {
                     let host = self.config.host.clone();
@@ -1128,7 +1223,7 @@ pub async fn build(&mut self) -> Result<Client> {
             #[cfg(feature = "native-tls")]
             Some(Encryption::StartTls(Tls {
                 provider: Some(TlsProvider::NativeTls(_)),
-            })) => Client::native_tls(&self.config.host, self.config.port, true)
+            })) => Client::native_tls(&host, port, true)
                 .await
                 .map_err(|err| {
                     let host = self.config.host.clone();
@@ -54,6 +54,8 @@
 pub mod backend;
 pub mod config;
 pub mod email;
+#[cfg(any(feature = "imap", feature = "smtp"))]
+pub mod endpoint;
 mod error;
 pub mod folder;
 #[cfg(feature = "imap")]
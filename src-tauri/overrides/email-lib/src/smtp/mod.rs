@@ -113,6 +113,7 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
 
                     debug!("re-connecting…");
 
+                    self.client_builder = new_client_builder(&self.smtp_config).await?;
                     self.client = if self.smtp_config.is_encryption_enabled() {
                         build_tls_client(&self.client_builder).await
                     } else {
@@ -178,15 +179,7 @@ fn send_message(&self) -> Option<BackendFeature<Self::Context, dyn SendMessage>>
     async fn build(self) -> AnyResult<Self::Context> {
         info!("building new smtp context");
 
-        let mut client_builder =
-            SmtpClientBuilder::new(self.smtp_config.host.clone(), self.smtp_config.port)
-                .credentials(self.smtp_config.credentials().await?)
-                .implicit_tls(!self.smtp_config.is_start_tls_encryption_enabled());
-
-        if self.smtp_config.is_encryption_disabled() {
-            client_builder = client_builder.allow_invalid_certs();
-        }
-
+        let client_builder = new_client_builder(&self.smtp_config).await?;
         let (client_builder, client) = build_client(&self.smtp_config, client_builder).await?;
 
         let ctx = SmtpContext {
@@ -248,6 +241,25 @@ async fn check_up(&self) -> AnyResult<()> {
     }
 }
 
+/// Creates a client builder for the address the configuration
+/// resolves to, see [`crate::endpoint`]. It is created again for each
+/// connection so that the resolver is asked every time.
+pub async fn new_client_builder(
+    smtp_config: &SmtpConfig,
+) -> Result<mail_send::SmtpClientBuilder<String>> {
+    let (host, port) = crate::endpoint::resolve(&smtp_config.host, smtp_config.port);
+
+    let mut client_builder = SmtpClientBuilder::new(host, port)
+        .credentials(smtp_config.credentials().await?)
+        .implicit_tls(!smtp_config.is_start_tls_encryption_enabled());
+
+    if smtp_config.is_encryption_disabled() {
+        client_builder = client_builder.allow_invalid_certs();
+    }
+
+    Ok(client_builder)
+}
+
 pub async fn build_client(
     smtp_config: &SmtpConfig,
     #[cfg_attr(not(feature = "oauth2"), allow(unused_mut))]
@@ -0,0 +1,71 @@
+use sqlx::SqlitePool;
+use std::future::Future;
+
+/// Overridden with `DUEAM_SLOW_QUERY_MS`
+#[cfg(feature = "query-timing")]
+const DEFAULT_THRESHOLD_MS: u64 = 100;
+
+#[cfg(feature = "query-timing")]
+fn threshold() -> std::time::Duration {
+    static THRESHOLD: std::sync::OnceLock<std::time::Duration> = std::sync::OnceLock::new();
+    *THRESHOLD.get_or_init(|| {
+        let ms = std::env::var("DUEAM_SLOW_QUERY_MS").ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_THRESHOLD_MS);
+        std::time::Duration::from_millis(ms)
+    })
+}
+
+/// Awaits `query`, logging `sql` with its query plan when it took longer than the threshold.
+/// Only with the `query-timing` feature, otherwise this is just the query.
+#[cfg(feature = "query-timing")]
+pub async fn timed<T>(pool: &SqlitePool, label: &str, sql: &str, query: impl Future<Output = Result<T, sqlx::Error>>) -> Result<T, sqlx::Error> {
+    let started = std::time::Instant::now();
+    let result = query.await;
+    let elapsed = started.elapsed();
+
+    if elapsed >= threshold() {
+        log::warn!("Slow query {} took {:?}:\n{}\n{}", label, elapsed, sql, explain(pool, sql).await);
+    }
+    result
+}
+
+#[cfg(not(feature = "query-timing"))]
+pub async fn timed<T>(_pool: &SqlitePool, _label: &str, _sql: &str, query: impl Future<Output = Result<T, sqlx::Error>>) -> Result<T, sqlx::Error> {
+    query.await
+}
+
+/// The plan as an indented tree. Parameters are left unbound, SQLite reads them as NULL
+/// and the plan doesn't depend on their values.
+#[cfg(feature = "query-timing")]
+async fn explain(pool: &SqlitePool, sql: &str) -> String {
+    let rows: Vec<(i64, i64, i64, String)> = match sqlx::query_as(&format!("EXPLAIN QUERY PLAN {}", sql)).fetch_all(pool).await {
+        Ok(rows) => rows,
+        Err(e) => return format!("(no plan: {})", e),
+    };
+
+    let mut depths: std::collections::HashMap<i64, usize> = std::collections::HashMap::new();
+    let mut plan = String::from("QUERY PLAN");
+    for (id, parent, _, detail) in rows {
+        let depth = depths.get(&parent).map_or(0, |d| d + 1);
+        depths.insert(id, depth);
+        plan.push_str(&format!("\n{}|--{}", "   ".repeat(depth), detail));
+    }
+    plan
+}
+
+#[cfg(all(test, feature = "query-timing"))]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    #[tokio::test]
+    async fn test_explain_nests_plan_steps() {
+        let pool = setup_test_db().await;
+        let plan = explain(&pool, "SELECT e.id FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.account_id = ?").await;
+
+        assert!(plan.starts_with("QUERY PLAN\n|--"));
+        assert!(plan.contains("emails"));
+        assert!(plan.contains("folders"));
+    }
+}
@@ -1,6 +1,265 @@
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 use sqlx::sqlite::SqlitePool;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+use crate::utils::security::{get_secret, set_secret, delete_secret};
+
+/// Settings whose real value lives in the keyring; the table only holds `SECRET_REFERENCE`.
+pub const SECRET_SETTING_KEYS: &[&str] = &["aiApiKey", "proxyPassword"];
+
+/// Stored in place of a secret once it has been moved to the keyring.
+pub(crate) const SECRET_REFERENCE: &str = "\"keyring\"";
+
+/// What `get_settings` returns for a secret that is set.
+pub const MASKED_SECRET: &str = "********";
+
+/// Typed view over the key/value `settings` table.
+///
+/// Values are stored as JSON (`true`, `3`, `"blue"`), keys match the frontend's camelCase names.
+/// Missing or unparsable rows fall back to the defaults below, which mirror the migrations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub theme: String,
+    pub accent_color: String,
+    pub density: String,
+    pub font_size: u32,
+    pub font_family: String,
+    pub ai_enabled: bool,
+    pub ai_base_url: String,
+    pub ai_api_key: String,
+    pub ai_model: String,
+    pub ai_sender_enrichment_enabled: bool,
+    pub ai_summarization_enabled: bool,
+    /// Lets the AI decide whether received mail asks for a reply, instead of the heuristics
+    pub ai_needs_reply_enabled: bool,
+    pub notifications_enabled: bool,
+    pub notification_sound: String,
+    pub badge_enabled: bool,
+    pub badge_primary_only: bool,
+    pub sync_limit_enabled: bool,
+    pub sync_months: u32,
+    pub sync_interval_minutes: u32,
+    pub sync_batch_size: u32,
+    pub sync_batch_delay_ms: u64,
+    pub sync_backfill_depth: u32,
+    pub sync_mode: String,
+    /// Seconds to wait on the IMAP server before dropping the connection
+    pub imap_timeout_secs: u64,
+    pub language: String,
+    pub proxy_mode: String,
+    pub proxy_type: String,
+    pub proxy_host: String,
+    pub proxy_port: u16,
+    pub proxy_username: String,
+    pub proxy_password: String,
+    pub enrichment_enabled: bool,
+    pub offline_mode: bool,
+    pub newsletter_rollup_enabled: bool,
+    pub screener_enabled: bool,
+    pub reply_later_nudge_days: u32,
+    /// Days received mail waits for a reply before it is listed as needing one
+    pub needs_reply_days: u32,
+    pub needs_reply_nudges_enabled: bool,
+    pub trash_retention_days: u32,
+    /// Size caps of cached bodies in KB, 0 for none
+    pub cached_html_limit_kb: u32,
+    pub cached_text_limit_kb: u32,
+    /// Id of the account compose preselects, `None` for the first account
+    pub default_account: Option<i64>,
+    pub update_channel: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: "system".to_string(),
+            accent_color: "blue".to_string(),
+            density: "comfortable".to_string(),
+            font_size: 14,
+            font_family: "Inter".to_string(),
+            ai_enabled: false,
+            ai_base_url: "https://api.openai.com/v1".to_string(),
+            ai_api_key: String::new(),
+            ai_model: String::new(),
+            ai_sender_enrichment_enabled: true,
+            ai_summarization_enabled: false,
+            ai_needs_reply_enabled: false,
+            notifications_enabled: true,
+            notification_sound: "default".to_string(),
+            badge_enabled: true,
+            badge_primary_only: false,
+            sync_limit_enabled: false,
+            sync_months: 3,
+            sync_interval_minutes: 5,
+            sync_batch_size: 100,
+            sync_batch_delay_ms: 0,
+            sync_backfill_depth: 0,
+            sync_mode: "auto".to_string(),
+            imap_timeout_secs: 60,
+            language: "system".to_string(),
+            proxy_mode: "system".to_string(),
+            proxy_type: "http".to_string(),
+            proxy_host: String::new(),
+            proxy_port: 0,
+            proxy_username: String::new(),
+            proxy_password: String::new(),
+            enrichment_enabled: true,
+            offline_mode: false,
+            newsletter_rollup_enabled: true,
+            screener_enabled: false,
+            reply_later_nudge_days: 0,
+            needs_reply_days: 3,
+            needs_reply_nudges_enabled: false,
+            trash_retention_days: 0,
+            cached_html_limit_kb: 512,
+            cached_text_limit_kb: 128,
+            default_account: None,
+            update_channel: "stable".to_string(),
+        }
+    }
+}
+
+/// Loaded settings per database file. Opening an email and the background loops read settings
+/// all the time, this spares them the query and the keyring lookups.
+static CACHE: OnceLock<RwLock<HashMap<PathBuf, Settings>>> = OnceLock::new();
+
+/// Bumped by every invalidation, so a load that raced with a write doesn't cache what it read.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn cache() -> &'static RwLock<HashMap<PathBuf, Settings>> {
+    CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Payload of the `settings-changed` event, emitted after every successful `update_setting`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingChanged {
+    pub key: String,
+    pub value: String,
+}
+
+impl Settings {
+    /// Served from the cache after the first load, anything writing the table must call `invalidate_cache`.
+    pub async fn load(pool: &SqlitePool) -> Result<Self, String> {
+        let key = pool.connect_options().get_filename().to_path_buf();
+        if let Some(settings) = cache().read().ok().and_then(|c| c.get(&key).cloned()) {
+            return Ok(settings);
+        }
+
+        let generation = GENERATION.load(Ordering::SeqCst);
+        let (settings, secrets_resolved) = Self::load_uncached(pool).await?;
+        if !secrets_resolved {
+            // Try the keyring again on the next load instead of caching the blank secret
+            return Ok(settings);
+        }
+        if let Ok(mut c) = cache().write() {
+            if GENERATION.load(Ordering::SeqCst) == generation {
+                c.insert(key, settings.clone());
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Drops every cached copy, after a write to the table or when keyring secrets become readable.
+    pub fn invalidate_cache() {
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut c) = cache().write() {
+            c.clear();
+        }
+    }
+
+    /// Also returns whether every keyring secret could be read.
+    async fn load_uncached(pool: &SqlitePool) -> Result<(Self, bool), String> {
+        let rows: Vec<(String, String)> = sqlx::query_as("SELECT key, value FROM settings")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut settings = Self::from_rows(rows);
+        let mut resolved = true;
+        // `SECRET_REFERENCE` parses to the bare word, swap in the real key
+        if settings.ai_api_key == "keyring" {
+            settings.ai_api_key = resolve_secret("aiApiKey", &mut resolved).await;
+        }
+        if settings.proxy_password == "keyring" {
+            settings.proxy_password = resolve_secret("proxyPassword", &mut resolved).await;
+        }
+        Ok((settings, resolved))
+    }
+
+    pub fn from_rows(rows: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut settings = Self::default();
+        for (key, value) in rows {
+            if let Err(e) = settings.apply(&key, &value) {
+                log::warn!("Ignoring stored setting: {}", e);
+            }
+        }
+        settings
+    }
+
+    /// Validates a raw stored value against the field's type and applies it.
+    /// Keys the backend doesn't know about are accepted untouched.
+    pub fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let mut fields = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        let map = fields.as_object_mut().ok_or("Settings must serialize to an object")?;
+        if !map.contains_key(key) {
+            return Ok(());
+        }
+
+        // Older rows may hold bare strings instead of JSON-quoted ones
+        let parsed = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+        map.insert(key.to_string(), parsed);
+
+        *self = serde_json::from_value(fields).map_err(|e| format!("Invalid value for setting '{}': {}", key, e))?;
+        Ok(())
+    }
+}
+
+/// Reads a secret for `Settings::load`. A locked or missing keyring leaves the field empty,
+/// it must not make every other setting fall back to its default.
+async fn resolve_secret(key: &str, resolved: &mut bool) -> String {
+    match get_secret(key).await {
+        Ok(secret) => secret.unwrap_or_default(),
+        Err(e) => {
+            log::warn!("Could not read setting {} from the keyring: {}", key, e);
+            *resolved = false;
+            String::new()
+        }
+    }
+}
+
+/// Moves any secret still stored in plaintext (from before the keyring existed) into the keyring.
+pub async fn migrate_plaintext_secrets(pool: &SqlitePool) -> Result<(), String> {
+    for key in SECRET_SETTING_KEYS {
+        let stored: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let secret = match stored {
+            Some(v) if v != SECRET_REFERENCE => serde_json::from_str::<String>(&v).unwrap_or(v),
+            _ => continue,
+        };
+        if secret.is_empty() {
+            continue;
+        }
+
+        set_secret(key, &secret).await?;
+        sqlx::query("UPDATE settings SET value = ? WHERE key = ?")
+            .bind(SECRET_REFERENCE)
+            .bind(key)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        log::info!("Moved setting {} into the keyring", key);
+    }
+    Settings::invalidate_cache();
+    Ok(())
+}
 
 #[tauri::command]
 pub async fn get_settings(app_handle: AppHandle) -> Result<HashMap<String, String>, String> {
@@ -10,18 +269,96 @@ pub async fn get_settings(app_handle: AppHandle) -> Result<HashMap<String, Strin
         .await
         .map_err(|e| e.to_string())?;
 
-    Ok(rows.into_iter().collect())
+    Ok(rows
+        .into_iter()
+        .map(|(key, value)| {
+            if SECRET_SETTING_KEYS.contains(&key.as_str()) && value == SECRET_REFERENCE {
+                (key, format!("\"{}\"", MASKED_SECRET))
+            } else {
+                (key, value)
+            }
+        })
+        .collect())
 }
 
 #[tauri::command]
-pub async fn update_setting(app_handle: AppHandle, key: String, value: String) -> Result<(), String> {
+pub async fn update_setting<R: tauri::Runtime>(app_handle: AppHandle<R>, key: String, value: String) -> Result<(), String> {
     let pool = app_handle.state::<SqlitePool>();
+
+    let mut settings = Settings::load(&pool).await?;
+    settings.apply(&key, &value)?;
+
+    let mut stored_value = value.clone();
+    if SECRET_SETTING_KEYS.contains(&key.as_str()) {
+        let secret = serde_json::from_str::<String>(&value).unwrap_or_else(|_| value.clone());
+        if secret == MASKED_SECRET {
+            // The frontend echoed the masked value back, nothing changed
+            return Ok(());
+        }
+
+        if secret.is_empty() {
+            delete_secret(&key).await?;
+            stored_value = "\"\"".to_string();
+        } else {
+            set_secret(&key, &secret).await?;
+            stored_value = SECRET_REFERENCE.to_string();
+        }
+    }
+
     sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
-        .bind(key)
-        .bind(value)
+        .bind(&key)
+        .bind(&stored_value)
         .execute(&*pool)
         .await
         .map_err(|e| e.to_string())?;
+    Settings::invalidate_cache();
 
+    let _ = app_handle.emit("settings-changed", SettingChanged { key, value: stored_value });
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_from_rows_parses_json_values() {
+        let settings = Settings::from_rows(vec![
+            ("aiEnabled".to_string(), "true".to_string()),
+            ("syncMonths".to_string(), "6".to_string()),
+            ("aiModel".to_string(), "\"gpt-4o-mini\"".to_string()),
+            ("aiBaseUrl".to_string(), "http://localhost:11434/v1".to_string()),
+            ("somethingElse".to_string(), "42".to_string()),
+        ]);
+
+        assert!(settings.ai_enabled);
+        assert_eq!(settings.sync_months, 6);
+        assert_eq!(settings.ai_model, "gpt-4o-mini");
+        assert_eq!(settings.ai_base_url, "http://localhost:11434/v1");
+        assert!(settings.notifications_enabled);
+    }
+
+    #[test]
+    fn test_settings_apply_rejects_wrong_type() {
+        let mut settings = Settings::default();
+        assert!(settings.apply("aiEnabled", "\"yes\"").is_err());
+        assert!(settings.apply("syncMonths", "-1").is_err());
+        assert!(!settings.ai_enabled);
+        assert_eq!(settings.sync_months, 3);
+    }
+
+    #[tokio::test]
+    async fn test_load_sees_writes_after_invalidation() {
+        // The cache is shared by every test in the process and any of them may invalidate it,
+        // so only the fresh read is asserted, not that the stale value is still served
+        let pool = crate::utils::test_utils::setup_test_db().await;
+        assert!(!Settings::load(&pool).await.unwrap().screener_enabled);
+
+        sqlx::query("INSERT INTO settings (key, value) VALUES ('screenerEnabled', 'true') ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+            .execute(&pool)
+            .await
+            .unwrap();
+        Settings::invalidate_cache();
+        assert!(Settings::load(&pool).await.unwrap().screener_enabled);
+    }
+}
@@ -1,24 +1,37 @@
-use sqlx::sqlite::{SqlitePool, SqliteConnectOptions};
+use sqlx::sqlite::{SqlitePool, SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous};
+use std::time::Duration;
 use tauri::AppHandle;
-use tauri::Manager;
+use crate::db::writer::WritePool;
 
-pub async fn setup_database(app_handle: &AppHandle) -> Result<SqlitePool, String> {
-    let app_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+/// How long a single statement waits on the write lock before failing
+const BUSY_TIMEOUT_SECS: u64 = 10;
+
+pub async fn setup_database(app_handle: &AppHandle) -> Result<(SqlitePool, WritePool), String> {
+    let app_dir = crate::utils::instance::data_dir(app_handle)?;
     std::fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
     let db_path = app_dir.join("dueam.db");
 
     log::info!("Database path: {:?}", db_path);
 
+    // WAL lets the UI read while sync writes
     let options = SqliteConnectOptions::new()
         .filename(&db_path)
-        .create_if_missing(true);
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(Duration::from_secs(BUSY_TIMEOUT_SECS));
 
-    let pool = SqlitePool::connect_with(options).await.map_err(|e| e.to_string())?;
+    let pool = SqlitePool::connect_with(options.clone()).await.map_err(|e| e.to_string())?;
 
     sqlx::migrate!("./migrations")
         .run(&pool)
         .await
         .map_err(|e| e.to_string())?;
 
-    Ok(pool)
+    if let Err(e) = crate::db::settings::migrate_plaintext_secrets(&pool).await {
+        log::error!("Failed to move secrets into the keyring: {}", e);
+    }
+
+    let writer = WritePool::connect(options).await?;
+    Ok((pool, writer))
 }
@@ -0,0 +1,35 @@
+use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Transaction;
+use std::time::Duration;
+
+/// How long a transaction waits for its turn before giving up
+const WRITE_QUEUE_TIMEOUT_SECS: u64 = 60;
+
+/// Single connection pool every write transaction goes through. SQLite only has one writer at a
+/// time, and a transaction that reads before writing fails with `database is locked` instead of
+/// waiting when another one got there first. Here transactions queue for the connection in the
+/// order they asked for it, so sync batches, the worker and user commands take turns and none of
+/// them starves. Reads and single statement writes stay on the main pool.
+pub struct WritePool(pub SqlitePool);
+
+impl WritePool {
+    pub async fn connect(options: SqliteConnectOptions) -> Result<Self, String> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(Duration::from_secs(WRITE_QUEUE_TIMEOUT_SECS))
+            .connect_with(options)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Self(pool))
+    }
+
+    /// Takes the write lock up front, so a transaction that reads first can't be turned down
+    /// halfway by a write from a connection outside this pool.
+    pub async fn begin(&self) -> Result<Transaction<'static, Sqlite>, String> {
+        self.0.begin_with("BEGIN IMMEDIATE").await.map_err(|e| e.to_string())
+    }
+
+    pub async fn close(&self) {
+        self.0.close().await;
+    }
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// One of the user's addresses, `is_alias` when it isn't the account's sign-in address.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct OwnAddress {
+    pub account_id: i64,
+    pub address: String,
+    pub is_alias: bool,
+}
+
+pub(crate) async fn own_addresses(pool: &SqlitePool) -> Result<Vec<OwnAddress>, String> {
+    sqlx::query_as(
+        "SELECT o.account_id, o.address, EXISTS(SELECT 1 FROM account_aliases a WHERE a.account_id = o.account_id AND a.address = o.address) as is_alias
+         FROM own_addresses o
+         ORDER BY o.account_id, is_alias, o.address"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn normalize(address: &str) -> Result<String, String> {
+    let address = address.trim().to_lowercase();
+    match address.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && domain.contains('.') && !address.contains(char::is_whitespace) => Ok(address),
+        _ => Err(format!("Invalid email address: {}", address)),
+    }
+}
+
+pub(crate) async fn add_alias(conn: &mut sqlx::SqliteConnection, account_id: i64, address: &str) -> Result<(), String> {
+    sqlx::query("INSERT OR IGNORE INTO account_aliases (account_id, address) VALUES (?, ?)")
+        .bind(account_id)
+        .bind(normalize(address)?)
+        .execute(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub(crate) async fn remove_alias(conn: &mut sqlx::SqliteConnection, account_id: i64, address: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM account_aliases WHERE account_id = ? AND address = ?")
+        .bind(account_id)
+        .bind(address.trim().to_lowercase())
+        .execute(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    #[tokio::test]
+    async fn test_own_addresses_include_aliases() {
+        let pool = setup_test_db().await;
+        let (account_id,): (i64,) = sqlx::query_as("INSERT INTO accounts (email, account_type) VALUES ('Me@Example.com', 'imap') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        add_alias(&mut conn, account_id, " Sales@Example.com ").await.unwrap();
+        assert!(add_alias(&mut conn, account_id, "not an address").await.is_err());
+
+        let addresses = own_addresses(&pool).await.unwrap();
+        assert_eq!(addresses, vec![
+            OwnAddress { account_id, address: "me@example.com".to_string(), is_alias: false },
+            OwnAddress { account_id, address: "sales@example.com".to_string(), is_alias: true },
+        ]);
+
+        let from_me: bool = sqlx::query_scalar("SELECT LOWER('SALES@example.com') IN (SELECT address FROM own_addresses)")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(from_me);
+
+        remove_alias(&mut conn, account_id, "sales@example.com").await.unwrap();
+        assert_eq!(own_addresses(&pool).await.unwrap().len(), 1);
+    }
+}
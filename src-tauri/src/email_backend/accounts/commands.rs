@@ -1,9 +1,19 @@
+use sqlx::SqlitePool;
 use tauri::{AppHandle, Emitter, Manager};
-use crate::email_backend::accounts::google::get_auth_url;
+use crate::email_backend::accounts::google::{get_auth_url, GoogleAccount};
 use crate::email_backend::accounts::microsoft::login_with_microsoft as microsoft_login;
+use crate::email_backend::accounts::discovery::{self, DiscoveredConfig};
+use crate::email_backend::accounts::health::{self, ConnectionStep};
 use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+use crate::email_backend::accounts::oauth2;
+use crate::email_backend::accounts::aliases::{self, OwnAddress};
 use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::email_backend::accounts::relay::{self, Protocol, Route, Security};
+use crate::email_backend::accounts::tls::{self, Trust};
 use crate::email_backend::sync::SyncEngine;
+use crate::db::writer::WritePool;
+use crate::utils::i18n;
+use crate::utils::proxy;
 use email::backend::context::BackendContextBuilder;
 use email::imap::ImapContextBuilder;
 use email::smtp::SmtpContextBuilder;
@@ -21,40 +31,144 @@ pub async fn login_with_microsoft(app_handle: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Signs in to one of the OAuth presets (`yahoo`, `aol`).
+#[tauri::command]
+pub async fn login_with_oauth_provider(app_handle: AppHandle, provider: String) -> Result<(), String> {
+    oauth2::login(&app_handle, &provider).await;
+    Ok(())
+}
+
+/// Tells certificate failures apart from other connection errors, the user can then trust the
+/// server's CA or certificate for the account.
+pub(crate) fn connection_error(protocol: &str, e: impl std::fmt::Display + std::fmt::Debug) -> String {
+    // Display only shows the outermost error, the rustls cause is in the Debug chain
+    let details = format!("{:?}", e);
+    let lower = details.to_lowercase();
+    if lower.contains("certificate") || details.contains("UnknownIssuer") {
+        return i18n::t("error.untrusted_certificate", &[("protocol", protocol), ("error", &e.to_string())]);
+    }
+    format!("{} Error: {}", protocol, e)
+}
+
+/// Looks up server settings for an address so the manual account form can be prefilled.
+#[tauri::command]
+pub async fn discover_account_config(email: String) -> Result<Vec<DiscoveredConfig>, String> {
+    discovery::discover(&email).await
+}
+
 #[tauri::command]
 pub async fn verify_imap_smtp_credentials(account: ImapSmtpAccount) -> Result<(), String> {
-    let account_enum = Account::ImapSmtp(account);
-    let (account_config, imap_config, smtp_config) = account_enum.get_configs()?;
+    verify_credentials(&Account::ImapSmtp(account)).await
+}
+
+async fn verify_credentials(account: &Account) -> Result<(), String> {
+    let (account_config, imap_config, smtp_config) = account.get_configs()?;
 
     // 1. Verify IMAP
     let imap_ctx_builder = ImapContextBuilder::new(account_config.clone(), imap_config);
     let _imap_context = BackendContextBuilder::build(imap_ctx_builder).await
-        .map_err(|e| format!("IMAP Error: {}", e))?;
+        .map_err(|e| connection_error("IMAP", e))?;
     
     // 2. Verify SMTP
     let smtp_ctx_builder = SmtpContextBuilder::new(account_config.clone(), smtp_config);
     let _smtp_backend = BackendBuilder::new(account_config, smtp_ctx_builder).build().await
-        .map_err(|e| format!("SMTP Error: {}", e))?;
+        .map_err(|e| connection_error("SMTP", e))?;
 
     Ok(())
 }
 
-#[tauri::command]
-pub async fn add_imap_smtp_account(app_handle: AppHandle, account: ImapSmtpAccount) -> Result<(), String> {
-    let manager = AccountManager::new(&app_handle).await?;
-    manager.add_account(Account::ImapSmtp(account.clone())).await?;
-    
-    // Trigger initial sync
+/// Saves an account and starts its first sync.
+async fn add_and_sync(app_handle: &AppHandle, account: Account) -> Result<(), String> {
+    let manager = AccountManager::new(app_handle).await?;
+    manager.add_account(account.clone()).await?;
+
     if let Some(sync_engine) = app_handle.try_state::<SyncEngine>() {
         let registry = manager.load().await?;
-        let added_account = registry.accounts.iter().find(|a| a.email() == account.email).unwrap().clone();
-        sync_engine.trigger_sync_for_account(added_account);
+        if let Some(added_account) = registry.accounts.iter().find(|a| a.email() == account.email()) {
+            sync_engine.trigger_sync_for_account(added_account.clone());
+        }
     }
 
     let _ = app_handle.emit("emails-updated", ());
     Ok(())
 }
 
+/// Adds an account on any IMAP/SMTP server, Fastmail or a self-hosted Dovecot for example,
+/// once signing in to both servers worked. The passwords are kept in the encrypted account store.
+#[tauri::command]
+pub async fn login_with_imap_smtp(app_handle: AppHandle, mut account: ImapSmtpAccount) -> Result<(), String> {
+    account.email = account.email.trim().to_string();
+    account.trust = account.trust.normalized()?;
+    let account = Account::ImapSmtp(account);
+    verify_credentials(&account).await?;
+    add_and_sync(&app_handle, account).await
+}
+
+/// Adds a Gmail or Workspace account with an app password, for domains that block OAuth clients.
+#[tauri::command]
+pub async fn add_google_app_password_account(
+    app_handle: AppHandle,
+    email: String,
+    name: Option<String>,
+    app_password: String,
+) -> Result<(), String> {
+    // Google shows app passwords in groups of four separated by spaces
+    let app_password: String = app_password.chars().filter(|c| !c.is_whitespace()).collect();
+    let account = Account::Google(GoogleAccount {
+        id: None,
+        email: email.trim().to_string(),
+        name,
+        picture: None,
+        color: None,
+        label: None,
+        access_token: None,
+        refresh_token: None,
+        app_password: Some(app_password),
+    });
+    verify_credentials(&account).await?;
+    add_and_sync(&app_handle, account).await
+}
+
+/// The SHA-256 fingerprint of the certificate the IMAP or SMTP server of the account form presents,
+/// `AB:CD:…`, for the user to compare with what their admin gave them before trusting it.
+#[tauri::command]
+pub async fn get_server_certificate_fingerprint(account: ImapSmtpAccount, protocol: Protocol) -> Result<String, String> {
+    let (host, port, encryption) = match protocol {
+        Protocol::Imap => (account.imap_host.trim().to_string(), account.imap_port()?, account.imap_encryption()?),
+        Protocol::Smtp => (account.smtp_host.trim().to_string(), account.smtp_port()?, account.smtp_encryption()?),
+    };
+    let tunnel = proxy::tunnel_for(&host);
+    let route = Route { protocol, host, port, security: Some(&encryption).into(), trust: Trust::default(), tunnel };
+    if route.security == Security::None {
+        return Err(format!("The {} connection isn't encrypted, there is no certificate to trust", protocol.name()));
+    }
+
+    let (stream, _) = relay::negotiate(relay::connect(&route).await?, &route).await?;
+    let fingerprint = tls::server_fingerprint(stream, &route.host).await?;
+    Ok(tls::display_fingerprint(&fingerprint))
+}
+
+/// Sets the private CA and pinned certificate fingerprints an IMAP/SMTP account trusts, and
+/// reconnects it with them.
+#[tauri::command]
+pub async fn set_account_trust(app_handle: AppHandle, account_id: i64, trust: Trust) -> Result<(), String> {
+    let manager = AccountManager::new(&app_handle).await?;
+    manager.set_trust(account_id, trust.normalized()?).await?;
+    relay::close_listeners();
+
+    if let Some(sync_engine) = app_handle.try_state::<SyncEngine>() {
+        sync_engine.close_account_connections(account_id).await;
+        sync_engine.trigger_sync_for_account(manager.get_account_by_id(account_id).await?);
+    }
+    Ok(())
+}
+
+/// Checks each stage of connecting to an account, for the troubleshooting screen.
+#[tauri::command]
+pub async fn test_account_connection(app_handle: AppHandle, account_id: i64) -> Result<Vec<ConnectionStep>, String> {
+    health::check_connection(&app_handle, account_id).await
+}
+
 #[tauri::command]
 pub async fn get_accounts(app_handle: AppHandle) -> Result<Vec<Account>, String> {
     let manager = AccountManager::new(&app_handle).await?;
@@ -65,6 +179,75 @@ pub async fn get_accounts(app_handle: AppHandle) -> Result<Vec<Account>, String>
     Ok(registry.accounts)
 }
 
+/// Whether `color` is a `#rrggbb` hex color.
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Sets the color and label the account is shown with, `None` or blank restores the default.
+#[tauri::command]
+pub async fn set_account_appearance(app_handle: AppHandle, account_id: i64, color: Option<String>, label: Option<String>) -> Result<(), String> {
+    let color = color.map(|c| c.trim().to_lowercase()).filter(|c| !c.is_empty());
+    if let Some(color) = &color {
+        if !is_hex_color(color) {
+            return Err(format!("Invalid account color: {}", color));
+        }
+    }
+    let label = label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+
+    let mut tx = app_handle.state::<WritePool>().begin().await?;
+    sqlx::query("UPDATE accounts SET color = ?, label = ? WHERE id = ?")
+        .bind(color)
+        .bind(label)
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+/// Saves the order accounts are listed in, `ids` first to last.
+#[tauri::command]
+pub async fn reorder_accounts(app_handle: AppHandle, ids: Vec<i64>) -> Result<(), String> {
+    AccountManager::new(&app_handle).await?.reorder(&ids).await
+}
+
+/// The account compose preselects for new mail.
+#[tauri::command]
+pub async fn get_default_account(app_handle: AppHandle) -> Result<Option<i64>, String> {
+    AccountManager::new(&app_handle).await?.default_account_id().await
+}
+
+/// The user's own addresses, account emails and their aliases, for telling mail from the user apart.
+#[tauri::command]
+pub async fn get_own_addresses(app_handle: AppHandle) -> Result<Vec<OwnAddress>, String> {
+    aliases::own_addresses(&app_handle.state::<SqlitePool>()).await
+}
+
+/// Adds an address the user also sends from with this account.
+#[tauri::command]
+pub async fn add_account_alias(app_handle: AppHandle, account_id: i64, address: String) -> Result<(), String> {
+    let mut tx = app_handle.state::<WritePool>().begin().await?;
+    aliases::add_alias(&mut *tx, account_id, &address).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_account_alias(app_handle: AppHandle, account_id: i64, address: String) -> Result<(), String> {
+    let mut tx = app_handle.state::<WritePool>().begin().await?;
+    aliases::remove_alias(&mut *tx, account_id, &address).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn remove_account(app_handle: AppHandle, index: usize) -> Result<(), String> {
     let manager = AccountManager::new(&app_handle).await?;
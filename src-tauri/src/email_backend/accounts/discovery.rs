@@ -0,0 +1,269 @@
+use std::time::Duration;
+use email::autoconfig::config::{AutoConfig, SecurityType, Server, ServerType};
+use email::autoconfig::dns::DnsClient;
+use serde::Serialize;
+use crate::utils::proxy;
+
+const AUTOCONFIG_TIMEOUT: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// One server of a discovered configuration, in the shape of the `ImapSmtpAccount` fields.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+    /// `tls`, `starttls` or `none`
+    pub encryption: String,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiscoveredConfig {
+    /// `autoconfig`, `srv` or `guess`, from most to least trustworthy.
+    pub source: String,
+    pub provider_name: Option<String>,
+    pub imap: ServerSettings,
+    pub smtp: ServerSettings,
+}
+
+fn split_address(email: &str) -> Result<(&str, String), String> {
+    let (local_part, domain) = email.trim().rsplit_once('@').ok_or("Invalid email address")?;
+    let domain = domain.trim_matches('.').to_lowercase();
+    if local_part.is_empty() || domain.is_empty() {
+        return Err("Invalid email address".to_string());
+    }
+    Ok((local_part, domain))
+}
+
+fn server_settings(server: &Server, email: &str) -> Option<ServerSettings> {
+    let (local_part, domain) = split_address(email).ok()?;
+    let port = *server.port()?;
+    let encryption = match server.security_type() {
+        Some(SecurityType::Tls) => "tls",
+        Some(SecurityType::Starttls) => "starttls",
+        Some(SecurityType::Plain) => "none",
+        None if port == 993 || port == 465 => "tls",
+        None => "starttls",
+    };
+    // Autoconfig files describe the login with placeholders, most often the whole address
+    let username = server.username()
+        .map(|username| username
+            .replace("%EMAILADDRESS%", email)
+            .replace("%EMAILLOCALPART%", local_part)
+            .replace("%EMAILDOMAIN%", &domain))
+        .unwrap_or_else(|| email.to_string());
+
+    Some(ServerSettings {
+        host: server.hostname()?.to_string(),
+        port,
+        encryption: encryption.to_string(),
+        username,
+    })
+}
+
+/// The preferred IMAP and SMTP servers of an autoconfig file, servers are listed by preference.
+fn from_autoconfig(config: &AutoConfig, email: &str) -> Option<DiscoveredConfig> {
+    let provider = config.email_provider();
+    let imap = provider.incoming_servers().into_iter()
+        .filter(|server| matches!(server.server_type(), ServerType::Imap))
+        .find_map(|server| server_settings(server, email))?;
+    let smtp = provider.outgoing_servers().into_iter()
+        .filter(|server| matches!(server.server_type(), ServerType::Smtp))
+        .find_map(|server| server_settings(server, email))?;
+
+    Some(DiscoveredConfig {
+        source: "autoconfig".to_string(),
+        provider_name: provider.display_name().map(|name| name.to_string()),
+        imap,
+        smtp,
+    })
+}
+
+async fn fetch_autoconfig(client: &reqwest::Client, url: &str) -> Option<AutoConfig> {
+    let response = client.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let body = response.text().await.ok()?;
+    serde_xml_rs::from_str(&body)
+        .map_err(|e| log::debug!("Ignoring unreadable autoconfig at {}: {}", url, e))
+        .ok()
+}
+
+/// Thunderbird autoconfig: the domain's own file, then the ISPDB for the domain and for the
+/// domain its MX records point to, which finds hosted mail on custom domains.
+///
+/// Only https locations are tried, a tampered plain-http answer could send the password anywhere.
+async fn discover_autoconfig(email: &str, domain: &str) -> Option<DiscoveredConfig> {
+    let client = proxy::http_client_builder().timeout(AUTOCONFIG_TIMEOUT).build().ok()?;
+    let encoded_email = url::form_urlencoded::byte_serialize(email.as_bytes()).collect::<String>();
+
+    let mut urls = vec![
+        format!("https://autoconfig.{}/mail/config-v1.1.xml?emailaddress={}", domain, encoded_email),
+        format!("https://{}/.well-known/autoconfig/mail/config-v1.1.xml", domain),
+        format!("https://autoconfig.thunderbird.net/v1.1/{}", domain),
+    ];
+    if let Ok(mx_domain) = DnsClient::new().get_mx_domain(domain).await {
+        let mx_domain = mx_domain.trim_matches('.').to_lowercase();
+        if mx_domain != domain {
+            urls.push(format!("https://autoconfig.thunderbird.net/v1.1/{}", mx_domain));
+        }
+    }
+
+    for url in urls {
+        if let Some(config) = fetch_autoconfig(&client, &url).await.and_then(|config| from_autoconfig(&config, email)) {
+            return Some(config);
+        }
+    }
+    None
+}
+
+/// RFC 6186 and RFC 8314 SRV records, implicit TLS preferred when both are published.
+async fn discover_srv(email: &str, domain: &str) -> Option<DiscoveredConfig> {
+    let dns = DnsClient::new();
+    let settings = |target: String, port: u16, encryption: &str| ServerSettings {
+        host: target.trim_end_matches('.').to_string(),
+        port,
+        encryption: encryption.to_string(),
+        username: email.to_string(),
+    };
+
+    let imap = match dns.get_imaps_srv(domain).await {
+        Ok(record) => settings(record.target().to_string(), record.port(), "tls"),
+        Err(_) => {
+            let record = dns.get_imap_srv(domain).await.ok()?;
+            settings(record.target().to_string(), record.port(), "starttls")
+        }
+    };
+    let smtp = match dns.get_srv(domain, "submissions").await {
+        Ok(record) => settings(record.target().to_string(), record.port(), "tls"),
+        Err(_) => {
+            let record = dns.get_submission_srv(domain).await.ok()?;
+            let encryption = if record.port() == 465 { "tls" } else { "starttls" };
+            settings(record.target().to_string(), record.port(), encryption)
+        }
+    };
+
+    Some(DiscoveredConfig { source: "srv".to_string(), provider_name: None, imap, smtp })
+}
+
+async fn reachable(host: &str, port: u16) -> bool {
+    matches!(
+        tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect((host, port))).await,
+        Ok(Ok(_))
+    )
+}
+
+/// The first of `candidates` accepting connections, all of them are probed at once.
+async fn first_reachable(candidates: Vec<(String, u16, &'static str)>) -> Option<(String, u16, &'static str)> {
+    let probes: Vec<_> = candidates.into_iter()
+        .map(|(host, port, encryption)| tokio::spawn(async move {
+            reachable(&host, port).await.then_some((host, port, encryption))
+        }))
+        .collect();
+
+    for probe in probes {
+        if let Ok(Some(found)) = probe.await {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Common host names on the usual ports, kept when something answers there.
+async fn discover_guess(email: &str, domain: &str) -> Option<DiscoveredConfig> {
+    let hosts = |prefixes: &[&str], ports: &[(u16, &'static str)]| {
+        ports.iter()
+            .flat_map(|&(port, encryption)| prefixes.iter().map(move |prefix| (format!("{}.{}", prefix, domain), port, encryption)))
+            .collect::<Vec<_>>()
+    };
+
+    let (imap, smtp) = tokio::join!(
+        first_reachable(hosts(&["imap", "mail"], &[(993, "tls"), (143, "starttls")])),
+        first_reachable(hosts(&["smtp", "mail"], &[(465, "tls"), (587, "starttls")])),
+    );
+    let settings = |(host, port, encryption): (String, u16, &str)| ServerSettings {
+        host,
+        port,
+        encryption: encryption.to_string(),
+        username: email.to_string(),
+    };
+
+    Some(DiscoveredConfig {
+        source: "guess".to_string(),
+        provider_name: None,
+        imap: settings(imap?),
+        smtp: settings(smtp?),
+    })
+}
+
+/// Server settings candidates for an address, best first. Empty when nothing was found and the
+/// user has to enter the servers themselves.
+pub async fn discover(email: &str) -> Result<Vec<DiscoveredConfig>, String> {
+    let email = email.trim();
+    let (_, domain) = split_address(email)?;
+
+    let (autoconfig, srv, guess) = tokio::join!(
+        discover_autoconfig(email, &domain),
+        discover_srv(email, &domain),
+        discover_guess(email, &domain),
+    );
+
+    let mut candidates: Vec<DiscoveredConfig> = Vec::new();
+    for candidate in [autoconfig, srv, guess].into_iter().flatten() {
+        if !candidates.iter().any(|c| c.imap == candidate.imap && c.smtp == candidate.smtp) {
+            candidates.push(candidate);
+        }
+    }
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autoconfig_picks_preferred_servers_and_fills_username() {
+        let xml = r#"<?xml version="1.0"?>
+<clientConfig version="1.1">
+  <emailProvider id="example.com">
+    <domain>example.com</domain>
+    <displayName>Example Mail</displayName>
+    <incomingServer type="pop3">
+      <hostname>pop.example.com</hostname>
+      <port>995</port>
+      <socketType>SSL</socketType>
+      <username>%EMAILADDRESS%</username>
+      <authentication>password-cleartext</authentication>
+    </incomingServer>
+    <incomingServer type="imap">
+      <hostname>imap.example.com</hostname>
+      <port>143</port>
+      <socketType>STARTTLS</socketType>
+      <username>%EMAILLOCALPART%</username>
+      <authentication>password-cleartext</authentication>
+    </incomingServer>
+    <outgoingServer type="smtp">
+      <hostname>smtp.example.com</hostname>
+      <port>465</port>
+      <socketType>SSL</socketType>
+      <username>%EMAILADDRESS%</username>
+      <authentication>password-cleartext</authentication>
+    </outgoingServer>
+  </emailProvider>
+</clientConfig>"#;
+
+        let config: AutoConfig = serde_xml_rs::from_str(xml).unwrap();
+        let discovered = from_autoconfig(&config, "jane@example.com").unwrap();
+
+        assert_eq!(discovered.provider_name.as_deref(), Some("Example Mail"));
+        assert_eq!(discovered.imap, ServerSettings {
+            host: "imap.example.com".to_string(),
+            port: 143,
+            encryption: "starttls".to_string(),
+            username: "jane".to_string(),
+        });
+        assert_eq!(discovered.smtp.encryption, "tls");
+        assert_eq!(discovered.smtp.username, "jane@example.com");
+    }
+}
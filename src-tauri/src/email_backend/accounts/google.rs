@@ -1,21 +1,29 @@
 use email::account::config::oauth2::{OAuth2Config, OAuth2Scopes::Scopes};
 use email::account::Error;
 use email::imap::config::ImapConfig;
-use oauth::v2_0::{AuthorizationCodeGrant, Client};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager};
-use tauri_plugin_opener::OpenerExt;
+use crate::email_backend::accounts::oauth2;
+use crate::utils::proxy;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GoogleAccount {
     pub id: Option<i64>,
     pub email: String,
     pub name: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
     pub picture: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub access_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refresh_token: Option<String>,
+    /// Set for accounts signed in with an app password instead of OAuth, for Workspace
+    /// domains that block third-party OAuth clients.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_password: Option<String>,
 }
 
 pub struct GoogleOAuth2Config {
@@ -40,6 +48,7 @@ impl GoogleOAuth2Config {
             "https://www.googleapis.com/auth/userinfo.email".into(),
             "https://www.googleapis.com/auth/userinfo.profile".into(),
             "https://www.googleapis.com/auth/contacts.readonly".into(),
+            "https://www.googleapis.com/auth/gmail.settings.basic".into(),
         ]);
 
         Ok(GoogleOAuth2Config {
@@ -50,55 +59,10 @@ impl GoogleOAuth2Config {
     }
 
     pub async fn get_url(&self, app_handle: &AppHandle) -> Result<GoogleAccount, Error> {
-        let redirect_scheme = match self.base.redirect_scheme.as_ref() {
-            Some(scheme) => scheme.clone(),
-            None => "http".into(),
-        };
-
-        let redirect_host = match self.base.redirect_host.as_ref() {
-            Some(host) => host.clone(),
-            None => OAuth2Config::LOCALHOST.to_owned(),
-        };
-
-        let redirect_port = match self.base.redirect_port {
-            Some(port) => port,
-            None => OAuth2Config::get_first_available_port()?,
-        };
-
-
-        let client = Client::new(
-            self.base.client_id.clone(),
-            self.client_secret.clone(),
-            self.base.auth_url.clone(),
-            self.base.token_url.clone(),
-            redirect_scheme,
-            redirect_host,
-            redirect_port,
-        )
-            .map_err(Error::BuildOauthClientError)?;
-
-        let mut auth_code_grant = AuthorizationCodeGrant::new();
-
-        if self.base.pkce {
-            auth_code_grant = auth_code_grant.with_pkce();
-        }
-
-        for scope in self.base.scopes.clone() {
-            auth_code_grant = auth_code_grant.with_scope(scope);
-        }
-
-        let (redirect_url, csrf_token) = auth_code_grant.get_redirect_url(&client);
-
-        app_handle.opener().open_url(redirect_url, None::<&str>).expect("Error when opening oauth url");
-
-
-        let (access_token, refresh_token) = auth_code_grant
-            .wait_for_redirection(&client, csrf_token)
-            .await
-            .map_err(Error::WaitForOauthRedirectionError)?;
+        let (access_token, refresh_token) = oauth2::authorize(app_handle, &self.base, self.client_secret.clone()).await?;
 
         // Fetch user info from Google API
-        let user_info_client = reqwest::Client::new();
+        let user_info_client = proxy::http_client().map_err(Error::GetAccountConfigNotFoundError)?;
         let user_info: serde_json::Value = user_info_client
             .get("https://www.googleapis.com/oauth2/v3/userinfo")
             .bearer_auth(&access_token)
@@ -118,8 +82,11 @@ impl GoogleOAuth2Config {
             email,
             name,
             picture,
+            color: None,
+            label: None,
             access_token: Some(access_token),
             refresh_token,
+            app_password: None,
         })
     }
 }
@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+use email::backend::BackendBuilder;
+use email::folder::list::ListFolders;
+use email::imap::ImapContextBuilder;
+use email::smtp::SmtpContextBuilder;
+use serde::Serialize;
+use tauri::AppHandle;
+use crate::email_backend::accounts::commands::connection_error;
+use crate::email_backend::accounts::manager::AccountManager;
+use crate::utils::proxy;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Ok,
+    Failed,
+    /// An earlier step failed, this one could not run.
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConnectionStep {
+    /// `imap_connect`, `imap_login`, `imap_folders`, `smtp_connect` or `smtp_auth`
+    pub step: String,
+    pub status: StepStatus,
+    pub duration_ms: u64,
+    /// The error when failed, a short summary otherwise.
+    pub detail: Option<String>,
+}
+
+struct Steps(Vec<ConnectionStep>);
+
+impl Steps {
+    /// Records the outcome of `step`, or skips it without running when `run` is false.
+    async fn run<F>(&mut self, step: &str, run: bool, f: F) -> bool
+    where
+        F: std::future::Future<Output = Result<Option<String>, String>>,
+    {
+        if !run {
+            self.0.push(ConnectionStep { step: step.to_string(), status: StepStatus::Skipped, duration_ms: 0, detail: None });
+            return false;
+        }
+
+        let started = Instant::now();
+        let result = f.await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let (status, detail) = match result {
+            Ok(detail) => (StepStatus::Ok, detail),
+            Err(e) => (StepStatus::Failed, Some(e)),
+        };
+        self.0.push(ConnectionStep { step: step.to_string(), status, duration_ms, detail });
+        status == StepStatus::Ok
+    }
+}
+
+async fn tcp_connect(host: &str, port: u16) -> Result<Option<String>, String> {
+    let tunnel = proxy::tunnel_for(host);
+    match tokio::time::timeout(CONNECT_TIMEOUT, proxy::connect(tunnel.as_ref(), host, port)).await {
+        Ok(Ok(_)) => Ok(Some(match tunnel {
+            Some(tunnel) => format!("{}:{} through the proxy {}:{}", host, port, tunnel.host, tunnel.port),
+            None => format!("{}:{}", host, port),
+        })),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(format!("Timed out reaching {}:{}", host, port)),
+    }
+}
+
+/// Runs each stage of connecting to an account on fresh connections, separate from the sync
+/// engine's, and reports how far it got.
+pub async fn check_connection(app_handle: &AppHandle, account_id: i64) -> Result<Vec<ConnectionStep>, String> {
+    let manager = AccountManager::new(app_handle).await?;
+    let account = manager.get_account_by_id(account_id).await?;
+    let (account_config, imap_config, smtp_config) = account.get_configs()?;
+    // The configs may point at the local relay, reachability is about the servers themselves
+    let (_, imap_server, smtp_server) = account.server_configs()?;
+    let mut steps = Steps(Vec::new());
+
+    let reachable = steps.run("imap_connect", true, tcp_connect(&imap_server.host, imap_server.port)).await;
+
+    let mut backend = None;
+    let logged_in = steps.run("imap_login", reachable, async {
+        let builder = BackendBuilder::new(account_config.clone(), ImapContextBuilder::new(account_config.clone(), imap_config.clone()));
+        let built = match builder.build().await {
+            Ok(built) => built,
+            // An expired access token looks like bad credentials, refresh it once like the sync does
+            Err(e) => {
+                if manager.refresh_access_token(account.email()).await.is_err() {
+                    return Err(connection_error("IMAP", e));
+                }
+                let (account_config, imap_config, _) = manager.get_account_by_id(account_id).await?.get_configs()?;
+                BackendBuilder::new(account_config.clone(), ImapContextBuilder::new(account_config, imap_config))
+                    .build()
+                    .await
+                    .map_err(|e| connection_error("IMAP", e))?
+            }
+        };
+        backend = Some(built);
+        Ok::<_, String>(None)
+    }).await;
+
+    steps.run("imap_folders", logged_in, async {
+        let backend = backend.as_ref().ok_or("Not logged in")?;
+        let folders = backend.list_folders().await.map_err(|e| e.to_string())?;
+        Ok::<_, String>(Some(format!("{} folders", folders.len())))
+    }).await;
+
+    // Reload the account, the IMAP step may have refreshed its token
+    let (account_config, _, smtp_config) = match manager.get_account_by_id(account_id).await.and_then(|a| a.get_configs()) {
+        Ok(configs) => configs,
+        Err(_) => (account_config, imap_config, smtp_config),
+    };
+
+    let reachable = steps.run("smtp_connect", true, tcp_connect(&smtp_server.host, smtp_server.port)).await;
+
+    // Building the SMTP backend says EHLO, upgrades to TLS when asked and authenticates
+    steps.run("smtp_auth", reachable, async {
+        BackendBuilder::new(account_config.clone(), SmtpContextBuilder::new(account_config.clone(), smtp_config.clone()))
+            .build()
+            .await
+            .map_err(|e| connection_error("SMTP", e))?;
+        Ok::<_, String>(None)
+    }).await;
+
+    Ok(steps.0)
+}
@@ -1,10 +1,16 @@
+use email::tls::{Encryption, Tls};
 use serde::{Deserialize, Serialize};
+use crate::email_backend::accounts::tls::Trust;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ImapSmtpAccount {
     pub id: Option<i64>,
     pub email: String,
     pub name: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
     pub imap_host: String,
     pub imap_port: u16,
     pub imap_username: String,
@@ -18,4 +24,91 @@ pub struct ImapSmtpAccount {
     pub password: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub smtp_password: Option<String>,
+    /// A private CA or pinned certificates, for both servers
+    #[serde(default)]
+    pub trust: Trust,
+}
+
+/// `tls` (implicit TLS), `starttls` or `none`. An unset (empty) value means implicit TLS,
+/// what accounts were always connected with before the choice was honored.
+fn parse_encryption(value: &str) -> Result<Encryption, String> {
+    match value {
+        "tls" | "" => Ok(Encryption::Tls(Tls::default())),
+        "starttls" => Ok(Encryption::StartTls(Tls::default())),
+        "none" => Ok(Encryption::None),
+        other => Err(format!("Unknown encryption: {}", other)),
+    }
+}
+
+impl ImapSmtpAccount {
+    pub fn imap_encryption(&self) -> Result<Encryption, String> {
+        parse_encryption(&self.imap_encryption)
+    }
+
+    pub fn smtp_encryption(&self) -> Result<Encryption, String> {
+        parse_encryption(&self.smtp_encryption)
+    }
+
+    /// The configured port, or the usual one for the encryption when left at 0.
+    pub fn imap_port(&self) -> Result<u16, String> {
+        Ok(match (self.imap_port, self.imap_encryption()?) {
+            (0, Encryption::Tls(_)) => 993,
+            (0, _) => 143,
+            (port, _) => port,
+        })
+    }
+
+    pub fn smtp_port(&self) -> Result<u16, String> {
+        Ok(match (self.smtp_port, self.smtp_encryption()?) {
+            (0, Encryption::Tls(_)) => 465,
+            (0, Encryption::StartTls(_)) => 587,
+            (0, Encryption::None) => 25,
+            (port, _) => port,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(imap_encryption: &str, smtp_encryption: &str) -> ImapSmtpAccount {
+        ImapSmtpAccount {
+            id: None,
+            email: "me@example.com".to_string(),
+            name: None,
+            color: None,
+            label: None,
+            imap_host: "mail.example.com".to_string(),
+            imap_port: 0,
+            imap_username: "me".to_string(),
+            imap_encryption: imap_encryption.to_string(),
+            smtp_host: "mail.example.com".to_string(),
+            smtp_port: 0,
+            smtp_username: "me".to_string(),
+            smtp_encryption: smtp_encryption.to_string(),
+            smtp_use_imap_credentials: true,
+            password: None,
+            smtp_password: None,
+            trust: Trust::default(),
+        }
+    }
+
+    #[test]
+    fn test_encryption_and_default_ports() {
+        let starttls = account("starttls", "starttls");
+        assert_eq!(starttls.imap_encryption(), Ok(Encryption::StartTls(Tls::default())));
+        assert_eq!((starttls.imap_port(), starttls.smtp_port()), (Ok(143), Ok(587)));
+
+        let plain = account("none", "none");
+        assert_eq!(plain.imap_encryption(), Ok(Encryption::None));
+        assert_eq!((plain.imap_port(), plain.smtp_port()), (Ok(143), Ok(25)));
+
+        let mut tls = account("tls", "tls");
+        assert_eq!((tls.imap_port(), tls.smtp_port()), (Ok(993), Ok(465)));
+        tls.imap_port = 10993;
+        assert_eq!(tls.imap_port(), Ok(10993));
+
+        assert!(account("ssl3", "tls").imap_encryption().is_err());
+    }
 }
@@ -1,9 +1,15 @@
 use serde::{Deserialize, Serialize};
 use tauri::Manager;
 use crate::email_backend::accounts::google::GoogleAccount;
-use crate::email_backend::accounts::microsoft::MicrosoftAccount;
+use crate::email_backend::accounts::microsoft::{self, MicrosoftAccount};
 use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+use crate::email_backend::accounts::oauth2::{self, OAuthAccount};
+use crate::email_backend::accounts::relay::{self, Protocol, Route};
+use crate::email_backend::accounts::tls::Trust;
+use crate::utils::proxy;
 use crate::utils::security::EncryptedStore;
+use crate::db::settings::Settings;
+use crate::email_backend::sync::SyncEngine;
 use std::path::PathBuf;
 use std::sync::Arc;
 use sqlx::sqlite::SqlitePool;
@@ -12,6 +18,7 @@ use email::account::config::oauth2::OAuth2Config;
 use email::account::config::passwd::PasswordConfig;
 use email::imap::config::{ImapConfig, ImapAuthConfig};
 use email::smtp::config::{SmtpConfig, SmtpAuthConfig};
+use email::tls::Encryption;
 use secret::Secret;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +27,7 @@ pub enum Account {
     Google(GoogleAccount),
     Microsoft(MicrosoftAccount),
     ImapSmtp(ImapSmtpAccount),
+    OAuth(OAuthAccount),
 }
 
 impl Account {
@@ -28,6 +36,7 @@ impl Account {
             Account::Google(a) => &a.email,
             Account::Microsoft(a) => &a.email,
             Account::ImapSmtp(a) => &a.email,
+            Account::OAuth(a) => &a.email,
         }
     }
 
@@ -36,6 +45,7 @@ impl Account {
             Account::Google(a) => a.id,
             Account::Microsoft(a) => a.id,
             Account::ImapSmtp(a) => a.id,
+            Account::OAuth(a) => a.id,
         }
     }
 
@@ -44,6 +54,7 @@ impl Account {
             Account::Google(a) => a.id = Some(id),
             Account::Microsoft(a) => a.id = Some(id),
             Account::ImapSmtp(a) => a.id = Some(id),
+            Account::OAuth(a) => a.id = Some(id),
         }
     }
 
@@ -52,6 +63,7 @@ impl Account {
             Account::Google(_) => "google",
             Account::Microsoft(_) => "microsoft",
             Account::ImapSmtp(_) => "imap_smtp",
+            Account::OAuth(a) => &a.provider,
         }
     }
 
@@ -60,6 +72,7 @@ impl Account {
             Account::Google(a) => {
                 a.access_token = None;
                 a.refresh_token = None;
+                a.app_password = None;
             }
             Account::Microsoft(a) => {
                 a.access_token = None;
@@ -69,23 +82,90 @@ impl Account {
                 a.password = None;
                 a.smtp_password = None;
             }
+            Account::OAuth(a) => {
+                a.access_token = None;
+                a.refresh_token = None;
+            }
+        }
+    }
+
+    /// What the account trusts besides the public CAs.
+    pub fn trust(&self) -> Trust {
+        match self {
+            Account::ImapSmtp(a) => a.trust.clone(),
+            _ => Trust::default(),
         }
     }
 
+    /// The configs to connect with, pointing at a local relay for servers the library can't
+    /// reach by itself, through a proxy or with the account's trust (see `relay::register`).
     pub fn get_configs(&self) -> Result<(Arc<AccountConfig>, Arc<ImapConfig>, Arc<SmtpConfig>), String> {
+        let (account_config, imap_config, smtp_config) = self.server_configs()?;
+        let trust = self.trust();
+
+        let imap_route = Route {
+            protocol: Protocol::Imap,
+            host: imap_config.host.clone(),
+            port: imap_config.port,
+            security: imap_config.encryption.as_ref().into(),
+            trust: trust.clone(),
+            tunnel: proxy::tunnel_for(&imap_config.host),
+        };
+        let imap_config = if imap_route.needs_relay() {
+            Arc::new(ImapConfig {
+                host: relay::register(&imap_route)?,
+                encryption: Some(Encryption::None),
+                ..(*imap_config).clone()
+            })
+        } else {
+            imap_config
+        };
+
+        let smtp_route = Route {
+            protocol: Protocol::Smtp,
+            host: smtp_config.host.clone(),
+            port: smtp_config.port,
+            security: smtp_config.encryption.as_ref().into(),
+            trust,
+            tunnel: proxy::tunnel_for(&smtp_config.host),
+        };
+        let smtp_config = if smtp_route.needs_relay() {
+            Arc::new(SmtpConfig {
+                host: relay::register(&smtp_route)?,
+                encryption: Some(Encryption::None),
+                ..(*smtp_config).clone()
+            })
+        } else {
+            smtp_config
+        };
+
+        Ok((account_config, imap_config, smtp_config))
+    }
+
+    /// The configs for connecting straight to the account's servers.
+    pub fn server_configs(&self) -> Result<(Arc<AccountConfig>, Arc<ImapConfig>, Arc<SmtpConfig>), String> {
         match self {
             Account::Google(google) => {
-                let client_id = env!("GOOGLE_CLIENT_ID").to_string();
-                let client_secret = env!("GOOGLE_CLIENT_SECRET").to_string();
-
-                let oauth2_config = OAuth2Config {
-                    client_id,
-                    client_secret: Some(Secret::new_raw(client_secret)),
-                    auth_url: "https://accounts.google.com/o/oauth2/auth".into(),
-                    token_url: "https://www.googleapis.com/oauth2/v3/token".into(),
-                    access_token: google.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    refresh_token: google.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    ..Default::default()
+                let (imap_auth, smtp_auth) = match &google.app_password {
+                    Some(app_password) => (
+                        ImapAuthConfig::Password(PasswordConfig(Secret::new_raw(app_password.clone()))),
+                        SmtpAuthConfig::Password(PasswordConfig(Secret::new_raw(app_password.clone()))),
+                    ),
+                    None => {
+                        let client_id = env!("GOOGLE_CLIENT_ID").to_string();
+                        let client_secret = env!("GOOGLE_CLIENT_SECRET").to_string();
+
+                        let oauth2_config = OAuth2Config {
+                            client_id,
+                            client_secret: Some(Secret::new_raw(client_secret)),
+                            auth_url: "https://accounts.google.com/o/oauth2/auth".into(),
+                            token_url: "https://www.googleapis.com/oauth2/v3/token".into(),
+                            access_token: google.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
+                            refresh_token: google.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
+                            ..Default::default()
+                        };
+                        (ImapAuthConfig::OAuth2(oauth2_config.clone()), SmtpAuthConfig::OAuth2(oauth2_config))
+                    }
                 };
 
                 let account_config = Arc::new(AccountConfig {
@@ -98,7 +178,7 @@ impl Account {
                     host: "imap.gmail.com".into(),
                     port: 993,
                     login: google.email.clone(),
-                    auth: ImapAuthConfig::OAuth2(oauth2_config.clone()),
+                    auth: imap_auth,
                     ..Default::default()
                 });
 
@@ -106,7 +186,7 @@ impl Account {
                     host: "smtp.gmail.com".into(),
                     port: 587,
                     login: google.email.clone(),
-                    auth: SmtpAuthConfig::OAuth2(oauth2_config),
+                    auth: smtp_auth,
                     encryption: Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
                     ..Default::default()
                 });
@@ -114,18 +194,7 @@ impl Account {
                 Ok((account_config, imap_config, smtp_config))
             }
             Account::Microsoft(microsoft) => {
-                let client_id = env!("MICROSOFT_CLIENT_ID").to_string();
-                let client_secret = option_env!("MICROSOFT_CLIENT_SECRET");
-
-                let oauth2_config = OAuth2Config {
-                    client_id,
-                    client_secret: client_secret.map(|s| Secret::new_raw(s.to_string())),
-                    auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into(),
-                    token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token".into(),
-                    access_token: microsoft.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    refresh_token: microsoft.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    ..Default::default()
-                };
+                let oauth2_config = microsoft::oauth2_config(microsoft.access_token.as_deref(), microsoft.refresh_token.as_deref())?;
 
                 let account_config = Arc::new(AccountConfig {
                     name: microsoft.email.clone(),
@@ -159,27 +228,15 @@ impl Account {
                     ..Default::default()
                 });
 
-                let imap_encryption = match imap_smtp.imap_encryption.as_str() {
-                    "tls" => Some(email::tls::Encryption::Tls(email::tls::Tls::default())),
-                    "starttls" => Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
-                    _ => None,
-                };
-
                 let imap_config = Arc::new(ImapConfig {
                     host: imap_smtp.imap_host.clone(),
-                    port: imap_smtp.imap_port,
+                    port: imap_smtp.imap_port()?,
                     login: imap_smtp.imap_username.clone(),
-                    encryption: imap_encryption,
+                    encryption: Some(imap_smtp.imap_encryption()?),
                     auth: ImapAuthConfig::Password(PasswordConfig(Secret::new_raw(imap_smtp.password.clone().unwrap_or_default()))),
                     ..Default::default()
                 });
 
-                let smtp_encryption = match imap_smtp.smtp_encryption.as_str() {
-                    "tls" => Some(email::tls::Encryption::Tls(email::tls::Tls::default())),
-                    "starttls" => Some(email::tls::Encryption::StartTls(email::tls::Tls::default())),
-                    _ => None,
-                };
-
                 let smtp_login = if imap_smtp.smtp_use_imap_credentials {
                     imap_smtp.imap_username.clone()
                 } else {
@@ -194,13 +251,42 @@ impl Account {
 
                 let smtp_config = Arc::new(SmtpConfig {
                     host: imap_smtp.smtp_host.clone(),
-                    port: imap_smtp.smtp_port,
+                    port: imap_smtp.smtp_port()?,
                     login: smtp_login,
-                    encryption: smtp_encryption,
+                    encryption: Some(imap_smtp.smtp_encryption()?),
                     auth: SmtpAuthConfig::Password(PasswordConfig(Secret::new_raw(smtp_password))),
                     ..Default::default()
                 });
 
+                Ok((account_config, imap_config, smtp_config))
+            }
+            Account::OAuth(account) => {
+                let preset = oauth2::preset(&account.provider)?;
+                let oauth2_config = preset.oauth2_config(account.access_token.as_deref(), account.refresh_token.as_deref())?;
+
+                let account_config = Arc::new(AccountConfig {
+                    name: account.email.clone(),
+                    email: account.email.clone(),
+                    ..Default::default()
+                });
+
+                let imap_config = Arc::new(ImapConfig {
+                    host: preset.imap_host.into(),
+                    port: preset.imap_port,
+                    login: account.email.clone(),
+                    auth: ImapAuthConfig::OAuth2(oauth2_config.clone()),
+                    ..Default::default()
+                });
+
+                let smtp_config = Arc::new(SmtpConfig {
+                    host: preset.smtp_host.into(),
+                    port: preset.smtp_port,
+                    login: account.email.clone(),
+                    auth: SmtpAuthConfig::OAuth2(oauth2_config),
+                    encryption: Some(email::tls::Encryption::Tls(email::tls::Tls::default())),
+                    ..Default::default()
+                });
+
                 Ok((account_config, imap_config, smtp_config))
             }
         }
@@ -236,7 +322,7 @@ impl<R: tauri::Runtime> AccountManager<R> {
             return path.clone();
         }
 
-        self.app_handle.path().app_data_dir()
+        crate::utils::instance::data_dir(&self.app_handle)
             .expect("Failed to get app data dir")
             .join("accounts.json.enc")
     }
@@ -252,30 +338,44 @@ impl<R: tauri::Runtime> AccountManager<R> {
 
         let pool = self.app_handle.state::<SqlitePool>();
 
+        // Profile details and the user's color and label live in the database, not the encrypted file
         for account in &mut registry.accounts {
-            let row: Option<(i64, Option<String>, Option<String>)> = sqlx::query_as(
-                "SELECT id, name, picture FROM accounts WHERE email = ?"
+            let row: Option<(i64, Option<String>, Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+                "SELECT id, name, picture, color, label FROM accounts WHERE email = ?"
             )
             .bind(account.email())
             .fetch_optional(&*pool)
             .await
             .map_err(|e| e.to_string())?;
 
-            if let Some((id, name, picture)) = row {
+            if let Some((id, name, picture, color, label)) = row {
                 match account {
                     Account::Google(google) => {
                         google.id = Some(id);
                         google.name = name;
                         google.picture = picture;
+                        google.color = color;
+                        google.label = label;
                     }
                     Account::Microsoft(microsoft) => {
                         microsoft.id = Some(id);
                         microsoft.name = name;
                         microsoft.picture = picture;
+                        microsoft.color = color;
+                        microsoft.label = label;
                     }
                     Account::ImapSmtp(imap_smtp) => {
                         imap_smtp.id = Some(id);
                         imap_smtp.name = name;
+                        imap_smtp.color = color;
+                        imap_smtp.label = label;
+                    }
+                    Account::OAuth(oauth) => {
+                        oauth.id = Some(id);
+                        oauth.name = name;
+                        oauth.picture = picture;
+                        oauth.color = color;
+                        oauth.label = label;
                     }
                 }
             }
@@ -302,8 +402,12 @@ impl<R: tauri::Runtime> AccountManager<R> {
         let account = registry.accounts.iter_mut()
             .find(|a| a.email() == email)
             .ok_or_else(|| format!("Account {} not found", email))?;
-            
-        match account {
+        let account_id = account.id();
+
+        let refreshed: Result<String, String> = match account {
+            Account::Google(google) if google.app_password.is_some() => {
+                Err("Google accounts added with an app password have no token to refresh".into())
+            }
             Account::Google(google) => {
                 let client_id = env!("GOOGLE_CLIENT_ID").to_string();
                 let client_secret = env!("GOOGLE_CLIENT_SECRET").to_string();
@@ -332,18 +436,7 @@ impl<R: tauri::Runtime> AccountManager<R> {
                 Ok(access_token_val)
             }
             Account::Microsoft(microsoft) => {
-                let client_id = env!("MICROSOFT_CLIENT_ID").to_string();
-                let client_secret = option_env!("MICROSOFT_CLIENT_SECRET");
-
-                let oauth2_config = OAuth2Config {
-                    client_id,
-                    client_secret: client_secret.map(|s| Secret::new_raw(s.to_string())),
-                    auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into(),
-                    token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token".into(),
-                    access_token: microsoft.access_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    refresh_token: microsoft.refresh_token.as_ref().map(|t| Secret::new_raw(t.clone())).unwrap_or_default(),
-                    ..Default::default()
-                };
+                let oauth2_config = microsoft::oauth2_config(microsoft.access_token.as_deref(), microsoft.refresh_token.as_deref())?;
 
                 let (access_token, new_refresh_token) = oauth2_config.refresh_access_token().await.map_err(|e| e.to_string())?;
                 
@@ -358,8 +451,31 @@ impl<R: tauri::Runtime> AccountManager<R> {
                 
                 Ok(access_token_val)
             }
+            Account::OAuth(oauth) => {
+                let oauth2_config = oauth2::preset(&oauth.provider)?
+                    .oauth2_config(oauth.access_token.as_deref(), oauth.refresh_token.as_deref())?;
+
+                let (access_token, new_refresh_token) = oauth2_config.refresh_access_token().await.map_err(|e| e.to_string())?;
+
+                oauth.access_token = Some(access_token.clone());
+                if let Some(new_refresh) = new_refresh_token {
+                    oauth.refresh_token = Some(new_refresh);
+                }
+
+                self.save(&registry).await?;
+
+                Ok(access_token)
+            }
             Account::ImapSmtp(_) => Err("IMAP/SMTP accounts do not support token refresh".into()),
+        };
+        let access_token = refreshed?;
+
+        // Pooled SMTP sessions signed in with the old token
+        if let (Some(id), Some(engine)) = (account_id, self.app_handle.try_state::<SyncEngine<R>>()) {
+            engine.invalidate_smtp_context(id).await;
         }
+
+        Ok(access_token)
     }
 
     pub async fn add_account(&self, mut account: Account) -> Result<(), String> {
@@ -377,11 +493,13 @@ impl<R: tauri::Runtime> AccountManager<R> {
             Account::Google(a) => a.name.as_deref(),
             Account::Microsoft(a) => a.name.as_deref(),
             Account::ImapSmtp(a) => a.name.as_deref(),
+            Account::OAuth(a) => a.name.as_deref(),
         })
         .bind(match &account {
             Account::Google(a) => a.picture.as_deref(),
             Account::Microsoft(a) => a.picture.as_deref(),
             Account::ImapSmtp(_) => None,
+            Account::OAuth(a) => a.picture.as_deref(),
         })
         .fetch_one(&*pool)
         .await
@@ -397,6 +515,36 @@ impl<R: tauri::Runtime> AccountManager<R> {
         self.save(&registry).await
     }
 
+    /// Puts the accounts in the order of `ids`. Accounts left out keep their order, after the others.
+    pub async fn reorder(&self, ids: &[i64]) -> Result<(), String> {
+        let mut registry = self.load().await?;
+        registry.accounts.sort_by_key(|a| a.id().and_then(|id| ids.iter().position(|i| *i == id)).unwrap_or(usize::MAX));
+        self.save(&registry).await
+    }
+
+    /// Sets what an IMAP/SMTP account trusts besides the public CAs.
+    pub async fn set_trust(&self, id: i64, trust: Trust) -> Result<(), String> {
+        let mut registry = self.load().await?;
+        match registry.accounts.iter_mut().find(|a| a.id() == Some(id)) {
+            Some(Account::ImapSmtp(account)) => account.trust = trust,
+            Some(other) => return Err(format!("{} connects to servers with public certificates", other.email())),
+            None => return Err(format!("Account with ID {} not found", id)),
+        }
+        self.save(&registry).await
+    }
+
+    /// The account new mail is sent from: the `defaultAccount` setting while that account
+    /// still exists, otherwise the first in the user's order.
+    pub async fn default_account_id(&self) -> Result<Option<i64>, String> {
+        let registry = self.load().await?;
+        let pool = self.app_handle.state::<SqlitePool>();
+        let preferred = Settings::load(&pool).await.unwrap_or_default().default_account;
+
+        Ok(preferred
+            .filter(|id| registry.accounts.iter().any(|a| a.id() == Some(*id)))
+            .or_else(|| registry.accounts.first().and_then(|a| a.id())))
+    }
+
     pub async fn remove_account(&self, index: usize) -> Result<(), String> {
         let mut registry = self.load().await?;
         if index < registry.accounts.len() {
@@ -439,20 +587,39 @@ mod tests {
             id: Some(1),
             email: "test@gmail.com".to_string(),
             name: Some("Test User".to_string()),
+            color: None,
+            label: None,
             picture: None,
             access_token: Some("secret_access".to_string()),
             refresh_token: Some("secret_refresh".to_string()),
+            app_password: Some("secret_app_password".to_string()),
         });
 
         account.strip_secrets();
 
-        match account {
-            Account::Google(a) => {
-                assert!(a.access_token.is_none());
-                assert!(a.refresh_token.is_none());
-                assert_eq!(a.email, "test@gmail.com");
-            }
-        }
+        let Account::Google(a) = account else { panic!("expected a Google account") };
+        assert!(a.access_token.is_none());
+        assert!(a.refresh_token.is_none());
+        assert!(a.app_password.is_none());
+        assert_eq!(a.email, "test@gmail.com");
+
+        let mut account = Account::Microsoft(MicrosoftAccount {
+            id: Some(2),
+            email: "test@outlook.com".to_string(),
+            name: None,
+            color: None,
+            label: None,
+            picture: None,
+            access_token: Some("secret_access".to_string()),
+            refresh_token: Some("secret_refresh".to_string()),
+        });
+
+        account.strip_secrets();
+
+        let Account::Microsoft(a) = account else { panic!("expected a Microsoft account") };
+        assert!(a.access_token.is_none());
+        assert!(a.refresh_token.is_none());
+        assert_eq!(a.email, "test@outlook.com");
     }
 
     #[tokio::test]
@@ -472,9 +639,12 @@ mod tests {
             id: None,
             email: "test@gmail.com".to_string(),
             name: Some("Test User".to_string()),
+            color: None,
+            label: None,
             picture: None,
             access_token: Some("access".to_string()),
             refresh_token: Some("refresh".to_string()),
+            app_password: None,
         });
 
         manager.add_account(account).await.expect("Failed to add account");
@@ -492,4 +662,44 @@ mod tests {
             .unwrap();
         assert_eq!(count.0, 1);
     }
+
+    #[tokio::test]
+    async fn test_reorder_and_default_account() {
+        let pool = setup_test_db().await;
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool.clone());
+
+        let dir = tempdir().unwrap();
+        let store = EncryptedStore::new_test([0u8; 32]);
+        let manager = AccountManager::new_test(app.handle().clone(), store, Some(dir.path().join("accounts.json.enc")));
+
+        for email in ["first@gmail.com", "second@gmail.com", "third@gmail.com"] {
+            manager.add_account(Account::Google(GoogleAccount {
+                id: None,
+                email: email.to_string(),
+                name: None,
+                color: None,
+                label: None,
+                picture: None,
+                access_token: Some("access".to_string()),
+                refresh_token: None,
+                app_password: None,
+            })).await.expect("Failed to add account");
+        }
+        let ids: Vec<i64> = manager.load().await.unwrap().accounts.iter().filter_map(|a| a.id()).collect();
+        assert_eq!(manager.default_account_id().await.unwrap(), Some(ids[0]));
+
+        manager.reorder(&[ids[2], ids[0]]).await.unwrap();
+        let order: Vec<String> = manager.load().await.unwrap().accounts.iter().map(|a| a.email().to_string()).collect();
+        assert_eq!(order, ["third@gmail.com", "first@gmail.com", "second@gmail.com"]);
+        assert_eq!(manager.default_account_id().await.unwrap(), Some(ids[2]));
+
+        sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES ('defaultAccount', ?)")
+            .bind(ids[1].to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        Settings::invalidate_cache();
+        assert_eq!(manager.default_account_id().await.unwrap(), Some(ids[1]));
+    }
 }
@@ -0,0 +1,242 @@
+use std::time::Duration;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_rustls::rustls::pki_types::ServerName;
+use crate::email_backend::accounts::commands::connection_error;
+use crate::email_backend::accounts::tls::{self, Trust};
+use crate::utils::proxy;
+
+/// The port ManageSieve (RFC 5804) listens on.
+pub const PORT: u16 = 4190;
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// The `OK`, `NO` or `BYE` that ends a response, with the server's text.
+struct Reply {
+    ok: bool,
+    text: String,
+}
+
+/// A signed-in ManageSieve session, enough of the protocol to manage one script.
+pub struct SieveClient {
+    stream: BufReader<Box<dyn Stream>>,
+    /// Sieve extensions the server supports, from its capabilities
+    pub extensions: Vec<String>,
+}
+
+/// A quoted string, the only escapes are for backslashes and quotes.
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn parse_script_line(line: &str) -> Option<(String, bool)> {
+    let rest = line.strip_prefix('"')?;
+    let end = rest.find('"')?;
+    Some((rest[..end].to_string(), rest[end + 1..].trim().eq_ignore_ascii_case("ACTIVE")))
+}
+
+fn parse_extensions(lines: &[String]) -> Vec<String> {
+    lines
+        .iter()
+        .find_map(|line| line.strip_prefix("\"SIEVE\""))
+        .map(|rest| rest.trim().trim_matches('"').split_whitespace().map(|e| e.to_lowercase()).collect())
+        .unwrap_or_default()
+}
+
+impl SieveClient {
+    fn new(stream: Box<dyn Stream>) -> Self {
+        SieveClient { stream: BufReader::new(stream), extensions: Vec::new() }
+    }
+
+    async fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        let read = tokio::time::timeout(TIMEOUT, self.stream.read_line(&mut line))
+            .await
+            .map_err(|_| "The Sieve server stopped answering".to_string())?
+            .map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Err("The Sieve server closed the connection".to_string());
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    /// Reads the `{N}` literal announced by `line`.
+    async fn read_literal(&mut self, line: &str) -> Result<String, String> {
+        let len: usize = line
+            .trim_start_matches('{')
+            .trim_end_matches('}')
+            .trim_end_matches('+')
+            .parse()
+            .map_err(|_| format!("Unexpected Sieve response: {}", line))?;
+        let mut data = vec![0; len];
+        tokio::time::timeout(TIMEOUT, self.stream.read_exact(&mut data))
+            .await
+            .map_err(|_| "The Sieve server stopped answering".to_string())?
+            .map_err(|e| e.to_string())?;
+        Ok(String::from_utf8_lossy(&data).to_string())
+    }
+
+    /// The lines of a response up to its final status, literals read in place.
+    async fn read_response(&mut self) -> Result<(Vec<String>, Reply), String> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            let upper = line.to_ascii_uppercase();
+            for (status, ok) in [("OK", true), ("NO", false), ("BYE", false)] {
+                if upper == status || upper.starts_with(&format!("{} ", status)) {
+                    return Ok((lines, Reply { ok, text: line[status.len()..].trim().to_string() }));
+                }
+            }
+            if line.starts_with('{') && line.ends_with('}') {
+                let literal = self.read_literal(&line).await?;
+                lines.push(literal);
+            } else if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+    }
+
+    async fn send(&mut self, command: &str) -> Result<(), String> {
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes()).await.map_err(|e| e.to_string())?;
+        stream.write_all(b"\r\n").await.map_err(|e| e.to_string())?;
+        stream.flush().await.map_err(|e| e.to_string())
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Vec<String>, String> {
+        self.send(command).await?;
+        let (lines, reply) = self.read_response().await?;
+        if reply.ok {
+            Ok(lines)
+        } else {
+            // Only the verb, AUTHENTICATE carries the password
+            let verb = command.split(' ').next().unwrap_or(command);
+            Err(format!("The Sieve server refused {}: {}", verb, reply.text))
+        }
+    }
+
+    async fn read_greeting(&mut self) -> Result<(), String> {
+        let (lines, reply) = self.read_response().await?;
+        if !reply.ok {
+            return Err(format!("The Sieve server turned the connection down: {}", reply.text));
+        }
+        self.extensions = parse_extensions(&lines);
+        Ok(())
+    }
+
+    /// Connects to `host` through the configured proxy, upgrades the connection with STARTTLS unless
+    /// `starttls` is off, trusting what `trust` adds to the public CAs, and signs in with `username`
+    /// and `password`.
+    pub async fn connect(host: &str, port: u16, starttls: bool, trust: &Trust, username: &str, password: &str) -> Result<Self, String> {
+        let tcp = tokio::time::timeout(TIMEOUT, proxy::connect(proxy::tunnel_for(host).as_ref(), host, port))
+            .await
+            .map_err(|_| format!("No Sieve server answered on {}:{}", host, port))?
+            .map_err(|e| format!("No Sieve server on {}:{}: {}", host, port, e))?;
+        let mut client = SieveClient::new(Box::new(tcp));
+        client.read_greeting().await?;
+
+        if starttls {
+            client.command("STARTTLS").await?;
+            let server_name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+            let tls = tls::connector(trust)?
+                .connect(server_name, client.stream.into_inner())
+                .await
+                .map_err(|e| connection_error("Sieve", e))?;
+            // The capabilities are sent again over the secured connection
+            client = SieveClient::new(Box::new(tls));
+            client.read_greeting().await?;
+        }
+
+        client.authenticate(username, password).await?;
+        Ok(client)
+    }
+
+    async fn authenticate(&mut self, username: &str, password: &str) -> Result<(), String> {
+        let token = STANDARD.encode(format!("\0{}\0{}", username, password));
+        self.command(&format!("AUTHENTICATE \"PLAIN\" {}", quote(&token))).await.map(|_| ())
+    }
+
+    /// Names of the scripts on the server and whether each is the active one.
+    pub async fn list_scripts(&mut self) -> Result<Vec<(String, bool)>, String> {
+        let lines = self.command("LISTSCRIPTS").await?;
+        Ok(lines.iter().filter_map(|line| parse_script_line(line)).collect())
+    }
+
+    /// The script's source, `None` when there is no script by that name.
+    pub async fn get_script(&mut self, name: &str) -> Result<Option<String>, String> {
+        self.send(&format!("GETSCRIPT {}", quote(name))).await?;
+        let (lines, reply) = self.read_response().await?;
+        if reply.ok {
+            Ok(lines.into_iter().next())
+        } else if reply.text.to_ascii_uppercase().contains("NONEXISTENT") {
+            Ok(None)
+        } else {
+            Err(format!("The Sieve server refused GETSCRIPT: {}", reply.text))
+        }
+    }
+
+    pub async fn put_script(&mut self, name: &str, script: &str) -> Result<(), String> {
+        self.command(&format!("PUTSCRIPT {} {{{}+}}\r\n{}", quote(name), script.len(), script)).await.map(|_| ())
+    }
+
+    /// Fails for the active script, servers won't delete the one that runs.
+    pub async fn delete_script(&mut self, name: &str) -> Result<(), String> {
+        self.command(&format!("DELETESCRIPT {}", quote(name))).await.map(|_| ())
+    }
+
+    /// Makes `name` the active script, an empty name deactivates all of them.
+    pub async fn set_active(&mut self, name: &str) -> Result<(), String> {
+        self.command(&format!("SETACTIVE {}", quote(name))).await.map(|_| ())
+    }
+
+    pub async fn logout(mut self) {
+        let _ = self.command("LOGOUT").await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_against_scripted_server() {
+        let (client_end, mut server_end) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            server_end
+                .write_all(b"\"IMPLEMENTATION\" \"Test\"\r\n\"SIEVE\" \"fileinto vacation date\"\r\nOK \"Ready\"\r\n")
+                .await
+                .unwrap();
+            let mut reader = BufReader::new(server_end);
+            let mut commands = Vec::new();
+            for reply in [
+                "OK\r\n",
+                "\"filters\"\r\n\"dueam-vacation\" ACTIVE\r\nOK\r\n",
+                "{9}\r\nkeep;\r\n\r\n\r\nOK\r\n",
+                "NO (NONEXISTENT) \"No such script\"\r\n",
+            ] {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                commands.push(line.trim_end().to_string());
+                reader.get_mut().write_all(reply.as_bytes()).await.unwrap();
+            }
+            commands
+        });
+
+        let mut client = SieveClient::new(Box::new(client_end));
+        client.read_greeting().await.unwrap();
+        assert_eq!(client.extensions, vec!["fileinto", "vacation", "date"]);
+
+        client.authenticate("me", "secret").await.unwrap();
+        assert_eq!(client.list_scripts().await.unwrap(), vec![("filters".to_string(), false), ("dueam-vacation".to_string(), true)]);
+        assert_eq!(client.get_script("dueam-vacation").await.unwrap().as_deref(), Some("keep;\r\n\r\n"));
+        assert_eq!(client.get_script("missing").await.unwrap(), None);
+
+        let commands = server.await.unwrap();
+        assert_eq!(commands[0], format!("AUTHENTICATE \"PLAIN\" \"{}\"", STANDARD.encode("\0me\0secret")));
+        assert_eq!(commands[3], "GETSCRIPT \"missing\"");
+    }
+}
@@ -1,15 +1,20 @@
 use email::account::config::oauth2::{OAuth2Config, OAuth2Scopes::Scopes};
-use email::account::Error;
-use oauth::v2_0::{AuthorizationCodeGrant, Client};
+use secret::Secret;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, Manager};
-use tauri_plugin_opener::OpenerExt;
+use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::email_backend::accounts::oauth2;
+use crate::utils::{i18n, proxy};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MicrosoftAccount {
     pub id: Option<i64>,
     pub email: String,
     pub name: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
     pub picture: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub access_token: Option<String>,
@@ -17,22 +22,27 @@ pub struct MicrosoftAccount {
     pub refresh_token: Option<String>,
 }
 
-pub struct MicrosoftOAuth2Config {
-    base: OAuth2Config,
-    client_secret: Option<String>,
-}
-
-impl MicrosoftOAuth2Config {
-    pub fn new() -> Result<Self, String> {
-        let client_id = env!("MICROSOFT_CLIENT_ID").to_string();
-        let client_secret = option_env!("MICROSOFT_CLIENT_SECRET").map(|s| s.to_string());
-
-        let mut config = OAuth2Config::default();
-        config.client_id = client_id;
-        // Using "common" endpoint for multi-tenant apps
-        config.auth_url = "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into();
-        config.token_url = "https://login.microsoftonline.com/common/oauth2/v2.0/token".into();
-        config.scopes = Scopes(vec![
+/// Registered redirect URIs are matched exactly, so sign-in always listens on this port.
+const REDIRECT_PORT: u16 = 11432;
+
+/// The OAuth2 settings Outlook IMAP/SMTP sign-in and token refresh use. Fails in builds
+/// without a Microsoft client id.
+pub fn oauth2_config(access_token: Option<&str>, refresh_token: Option<&str>) -> Result<OAuth2Config, String> {
+    let client_id = option_env!("MICROSOFT_CLIENT_ID")
+        .ok_or_else(|| i18n::t("error.oauth_not_configured", &[("provider", "Microsoft")]))?;
+
+    Ok(OAuth2Config {
+        client_id: client_id.to_string(),
+        client_secret: option_env!("MICROSOFT_CLIENT_SECRET").map(|s| Secret::new_raw(s.to_string())),
+        // The "common" endpoint lets work, school and personal accounts sign in
+        auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize".into(),
+        token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token".into(),
+        access_token: access_token.map(|t| Secret::new_raw(t.to_string())).unwrap_or_default(),
+        refresh_token: refresh_token.map(|t| Secret::new_raw(t.to_string())).unwrap_or_default(),
+        pkce: true,
+        redirect_host: Some("127.0.0.1".into()),
+        redirect_port: Some(REDIRECT_PORT),
+        scopes: Scopes(vec![
             "https://outlook.office.com/IMAP.AccessAsUser.All".into(),
             "https://outlook.office.com/SMTP.Send".into(),
             "User.Read".into(),
@@ -40,149 +50,83 @@ impl MicrosoftOAuth2Config {
             "openid".into(),
             "profile".into(),
             "email".into(),
-        ]);
-        config.pkce = true;
-        config.redirect_host = Some("127.0.0.1".into());
-        config.redirect_port = Some(11432);
+        ]),
+        ..Default::default()
+    })
+}
 
-        Ok(MicrosoftOAuth2Config {
-            base: config,
-            client_secret,
-        })
+async fn sign_in(app_handle: &AppHandle) -> Result<MicrosoftAccount, String> {
+    let config = oauth2_config(None, None)?;
+    let client_secret = option_env!("MICROSOFT_CLIENT_SECRET").map(|s| s.to_string());
+    let (access_token, refresh_token) = oauth2::authorize(app_handle, &config, client_secret)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Fetch user info from Microsoft Graph API
+    let response = proxy::http_client()?
+        .get("https://graph.microsoft.com/v1.0/me")
+        .bearer_auth(&access_token)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send userinfo request: {}", e))?;
+
+    let status = response.status();
+    let user_info: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse userinfo JSON: {}", e))?;
+
+    if !status.is_success() {
+        return Err(format!("Graph API error ({}): {}", status, user_info));
     }
 
-    pub async fn get_url(&self, app_handle: &AppHandle) -> Result<MicrosoftAccount, Error> {
-        let redirect_scheme = match self.base.redirect_scheme.as_ref() {
-            Some(scheme) => scheme.clone(),
-            None => "http".into(),
-        };
-
-        let redirect_host = match self.base.redirect_host.as_ref() {
-            Some(host) => host.clone(),
-            None => OAuth2Config::LOCALHOST.to_owned(),
-        };
-
-        let redirect_port = match self.base.redirect_port {
-            Some(port) => port,
-            None => OAuth2Config::get_first_available_port()?,
-        };
-
-        let client = Client::new(
-            self.base.client_id.clone(),
-            self.client_secret.clone(),
-            self.base.auth_url.clone(),
-            self.base.token_url.clone(),
-            redirect_scheme,
-            redirect_host,
-            redirect_port,
-        )
-            .map_err(Error::BuildOauthClientError)?;
-
-        let mut auth_code_grant = AuthorizationCodeGrant::new();
-
-        if self.base.pkce {
-            auth_code_grant = auth_code_grant.with_pkce();
-        }
-
-        for scope in self.base.scopes.clone() {
-            auth_code_grant = auth_code_grant.with_scope(scope);
-        }
-
-        let (redirect_url, csrf_token) = auth_code_grant.get_redirect_url(&client);
-
-        app_handle.opener().open_url(redirect_url, None::<&str>).expect("Error when opening oauth url");
-
-        let (access_token, refresh_token) = auth_code_grant
-            .wait_for_redirection(&client, csrf_token)
-            .await
-            .map_err(|e| {
-                use std::error::Error as _;
-                let mut msg = format!("OAuth redirection failed: {}", e);
-                if let Some(source) = e.source() {
-                    msg.push_str(&format!(" (caused by: {})", source));
-                }
-                Error::GetAccountConfigNotFoundError(msg)
-            })?;
-
-        // Fetch user info from Microsoft Graph API
-        let user_info_client = reqwest::Client::new();
-        let response = user_info_client
-            .get("https://graph.microsoft.com/v1.0/me")
-            .bearer_auth(&access_token)
-            .send()
-            .await
-            .map_err(|e| Error::GetAccountConfigNotFoundError(format!("Failed to send userinfo request: {}", e)))?;
-
-        let status = response.status();
-        let user_info: serde_json::Value = response
-            .json()
-            .await
-            .map_err(|e| Error::GetAccountConfigNotFoundError(format!("Failed to parse userinfo JSON: {}", e)))?;
-
-        if !status.is_success() {
-            return Err(Error::GetAccountConfigNotFoundError(format!("Graph API error ({}): {}", status, user_info)));
-        }
-
-        let email = user_info["mail"].as_str()
-            .or_else(|| user_info["userPrincipalName"].as_str())
-            .ok_or_else(|| Error::GetAccountConfigNotFoundError(format!("Email not found in Graph response: {}", user_info)))?.to_string();
-        let name = user_info["displayName"].as_str().map(|s| s.to_string());
-        
-        // Pictures are a bit more complex with Graph API, skipping for now or adding a placeholder
-        let picture = None;
-
-        Ok(MicrosoftAccount {
-            id: None,
-            email,
-            name,
-            picture,
-            access_token: Some(access_token),
-            refresh_token,
-        })
-    }
+    let email = user_info["mail"].as_str()
+        .or_else(|| user_info["userPrincipalName"].as_str())
+        .ok_or_else(|| format!("Email not found in Graph response: {}", user_info))?
+        .to_string();
+
+    Ok(MicrosoftAccount {
+        id: None,
+        email,
+        name: user_info["displayName"].as_str().map(|s| s.to_string()),
+        // Graph serves the photo as binary from another endpoint, there is no URL to keep
+        picture: None,
+        color: None,
+        label: None,
+        access_token: Some(access_token),
+        refresh_token,
+    })
 }
 
-use crate::email_backend::accounts::manager::{Account, AccountManager};
-
+/// Signs in to Outlook and adds the account, reporting back through `microsoft-account-added`
+/// or `microsoft-account-error`.
 pub async fn login_with_microsoft(app_handle: &AppHandle) {
-    let account_config = match MicrosoftOAuth2Config::new() {
-        Ok(config) => config,
-        Err(e) => {
-            let _ = app_handle.emit("microsoft-account-error", e);
-            return;
-        }
-    };
-    let res = account_config.get_url(app_handle).await;
+    let result = async {
+        let account = sign_in(app_handle).await?;
 
-    match res {
-        Ok(account) => {
-            match AccountManager::new(app_handle).await {
-                Ok(manager) => {
-                    if let Err(e) = manager.add_account(Account::Microsoft(account.clone())).await {
-                        let _ = app_handle.emit("microsoft-account-error", e);
-                    } else {
-                        let registry = manager.load().await.map_err(|e| e.to_string()).unwrap();
-                        let added_account = registry.accounts.iter().find(|a| a.email() == account.email).unwrap().clone();
+        let manager = AccountManager::new(app_handle).await?;
+        manager.add_account(Account::Microsoft(account.clone())).await?;
 
-                        if let Some(sync_engine) = app_handle.try_state::<crate::email_backend::sync::SyncEngine>() {
-                            sync_engine.trigger_sync_for_account(added_account);
-                        }
+        // Reload account to get the ID
+        let registry = manager.load().await?;
+        if let Some(added_account) = registry.accounts.iter().find(|a| a.email() == account.email) {
+            if let Some(sync_engine) = app_handle.try_state::<crate::email_backend::sync::SyncEngine>() {
+                sync_engine.trigger_sync_for_account(added_account.clone());
+            }
+        }
 
-                        let _ = app_handle.emit("emails-updated", ());
+        Ok::<_, String>(account)
+    }.await;
 
-                        let mut public_account = account;
-                        public_account.access_token = None;
-                        public_account.refresh_token = None;
-                        let _ = app_handle.emit("microsoft-account-added", public_account);
-                    }
-                }
-                Err(e) => {
-                    let _ = app_handle.emit("microsoft-account-error", e);
-                }
-            }
+    match result {
+        Ok(mut account) => {
+            let _ = app_handle.emit("emails-updated", ());
+            account.access_token = None;
+            account.refresh_token = None;
+            let _ = app_handle.emit("microsoft-account-added", account);
         }
         Err(e) => {
-            let _ = app_handle.emit("microsoft-account-error", e.to_string());
+            let _ = app_handle.emit("microsoft-account-error", e);
         }
     }
 }
@@ -1,5 +1,14 @@
 pub mod google;
 pub mod microsoft;
 pub mod imap_smtp;
+pub mod discovery;
+pub mod health;
+pub mod oauth2;
 pub mod manager;
+pub mod aliases;
+pub mod managesieve;
+pub mod tls;
+pub mod relay;
+pub mod sieve;
+pub mod vacation;
 pub mod commands;
@@ -0,0 +1,229 @@
+use email::account::config::oauth2::{OAuth2Config, OAuth2Scopes::Scopes};
+use email::account::Error;
+use oauth::v2_0::{AuthorizationCodeGrant, Client};
+use secret::Secret;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_opener::OpenerExt;
+use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::utils::{i18n, proxy};
+
+/// Endpoints and servers of a provider that signs in to IMAP/SMTP with OAuth2 (XOAUTH2).
+pub struct OAuthPreset {
+    pub provider: &'static str,
+    pub display_name: &'static str,
+    pub auth_url: &'static str,
+    pub token_url: &'static str,
+    /// OpenID userinfo endpoint, returns `email`, `name` and `picture`.
+    pub userinfo_url: &'static str,
+    pub scopes: &'static [&'static str],
+    pub pkce: bool,
+    /// Providers matching redirect URIs exactly need the port the app was registered with.
+    pub redirect_port: Option<u16>,
+    pub client_id: Option<&'static str>,
+    pub client_secret: Option<&'static str>,
+    pub imap_host: &'static str,
+    pub imap_port: u16,
+    pub smtp_host: &'static str,
+    pub smtp_port: u16,
+}
+
+// Yahoo and AOL share one login platform, only the hosts differ
+pub const PRESETS: &[OAuthPreset] = &[
+    OAuthPreset {
+        provider: "yahoo",
+        display_name: "Yahoo",
+        auth_url: "https://api.login.yahoo.com/oauth2/request_auth",
+        token_url: "https://api.login.yahoo.com/oauth2/get_token",
+        userinfo_url: "https://api.login.yahoo.com/openid/v1/userinfo",
+        scopes: &["openid", "email", "profile", "mail-w"],
+        pkce: true,
+        redirect_port: Some(11433),
+        client_id: option_env!("YAHOO_CLIENT_ID"),
+        client_secret: option_env!("YAHOO_CLIENT_SECRET"),
+        imap_host: "imap.mail.yahoo.com",
+        imap_port: 993,
+        smtp_host: "smtp.mail.yahoo.com",
+        smtp_port: 465,
+    },
+    OAuthPreset {
+        provider: "aol",
+        display_name: "AOL",
+        auth_url: "https://api.login.aol.com/oauth2/request_auth",
+        token_url: "https://api.login.aol.com/oauth2/get_token",
+        userinfo_url: "https://api.login.aol.com/openid/v1/userinfo",
+        scopes: &["openid", "email", "profile", "mail-w"],
+        pkce: true,
+        redirect_port: Some(11433),
+        client_id: option_env!("AOL_CLIENT_ID"),
+        client_secret: option_env!("AOL_CLIENT_SECRET"),
+        imap_host: "imap.aol.com",
+        imap_port: 993,
+        smtp_host: "smtp.aol.com",
+        smtp_port: 465,
+    },
+];
+
+pub fn preset(provider: &str) -> Result<&'static OAuthPreset, String> {
+    PRESETS.iter()
+        .find(|p| p.provider == provider)
+        .ok_or_else(|| format!("Unknown OAuth provider: {}", provider))
+}
+
+impl OAuthPreset {
+    /// OAuth2 config for this provider, with the account's tokens when it has any.
+    pub fn oauth2_config(&self, access_token: Option<&str>, refresh_token: Option<&str>) -> Result<OAuth2Config, String> {
+        // Client ids are baked in at build time, builds without them can't offer the provider
+        let client_id = self.client_id
+            .ok_or_else(|| i18n::t("error.oauth_not_configured", &[("provider", self.display_name)]))?;
+
+        Ok(OAuth2Config {
+            client_id: client_id.to_string(),
+            client_secret: self.client_secret.map(|s| Secret::new_raw(s.to_string())),
+            auth_url: self.auth_url.into(),
+            token_url: self.token_url.into(),
+            access_token: access_token.map(|t| Secret::new_raw(t.to_string())).unwrap_or_default(),
+            refresh_token: refresh_token.map(|t| Secret::new_raw(t.to_string())).unwrap_or_default(),
+            pkce: self.pkce,
+            redirect_port: self.redirect_port,
+            scopes: Scopes(self.scopes.iter().map(|s| s.to_string()).collect()),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OAuthAccount {
+    pub id: Option<i64>,
+    /// One of the `PRESETS` providers.
+    pub provider: String,
+    pub email: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub picture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+/// Runs the authorization code flow in the browser and returns the access and refresh tokens.
+pub async fn authorize(
+    app_handle: &AppHandle,
+    config: &OAuth2Config,
+    client_secret: Option<String>,
+) -> Result<(String, Option<String>), Error> {
+    let redirect_scheme = match config.redirect_scheme.as_ref() {
+        Some(scheme) => scheme.clone(),
+        None => "http".into(),
+    };
+
+    let redirect_host = match config.redirect_host.as_ref() {
+        Some(host) => host.clone(),
+        None => OAuth2Config::LOCALHOST.to_owned(),
+    };
+
+    let redirect_port = match config.redirect_port {
+        Some(port) => port,
+        None => OAuth2Config::get_first_available_port()?,
+    };
+
+    let client = Client::new(
+        config.client_id.clone(),
+        client_secret,
+        config.auth_url.clone(),
+        config.token_url.clone(),
+        redirect_scheme,
+        redirect_host,
+        redirect_port,
+    )
+        .map_err(Error::BuildOauthClientError)?;
+
+    let mut auth_code_grant = AuthorizationCodeGrant::new();
+
+    if config.pkce {
+        auth_code_grant = auth_code_grant.with_pkce();
+    }
+
+    for scope in config.scopes.clone() {
+        auth_code_grant = auth_code_grant.with_scope(scope);
+    }
+
+    let (redirect_url, csrf_token) = auth_code_grant.get_redirect_url(&client);
+
+    app_handle.opener().open_url(redirect_url, None::<&str>).expect("Error when opening oauth url");
+
+    auth_code_grant
+        .wait_for_redirection(&client, csrf_token)
+        .await
+        .map_err(Error::WaitForOauthRedirectionError)
+}
+
+async fn sign_in(app_handle: &AppHandle, preset: &OAuthPreset) -> Result<OAuthAccount, String> {
+    let config = preset.oauth2_config(None, None)?;
+    let client_secret = preset.client_secret.map(|s| s.to_string());
+    let (access_token, refresh_token) = authorize(app_handle, &config, client_secret)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let user_info: serde_json::Value = proxy::http_client()?
+        .get(preset.userinfo_url)
+        .bearer_auth(&access_token)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let email = user_info["email"].as_str().ok_or("Email not found in userinfo")?.to_string();
+
+    Ok(OAuthAccount {
+        id: None,
+        provider: preset.provider.to_string(),
+        email,
+        name: user_info["name"].as_str().map(|s| s.to_string()),
+        color: None,
+        label: None,
+        picture: user_info["picture"].as_str().map(|s| s.to_string()),
+        access_token: Some(access_token),
+        refresh_token,
+    })
+}
+
+/// Signs in to a preset provider and adds the account, reporting back through
+/// `oauth-account-added` or `oauth-account-error`.
+pub async fn login(app_handle: &AppHandle, provider: &str) {
+    let result = async {
+        let preset = preset(provider)?;
+        let account = sign_in(app_handle, preset).await?;
+
+        let manager = AccountManager::new(app_handle).await?;
+        manager.add_account(Account::OAuth(account.clone())).await?;
+
+        // Reload account to get the ID
+        let registry = manager.load().await?;
+        if let Some(added_account) = registry.accounts.iter().find(|a| a.email() == account.email) {
+            if let Some(sync_engine) = app_handle.try_state::<crate::email_backend::sync::SyncEngine>() {
+                sync_engine.trigger_sync_for_account(added_account.clone());
+            }
+        }
+
+        Ok::<_, String>(account)
+    }.await;
+
+    match result {
+        Ok(mut account) => {
+            let _ = app_handle.emit("emails-updated", ());
+            account.access_token = None;
+            account.refresh_token = None;
+            let _ = app_handle.emit("oauth-account-added", account);
+        }
+        Err(e) => {
+            let _ = app_handle.emit("oauth-account-error", e);
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, Once, OnceLock};
+use std::time::Duration;
+use email::tls::Encryption;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+use crate::email_backend::accounts::managesieve::Stream;
+use crate::email_backend::accounts::tls::{self, Trust};
+use crate::utils::proxy::{self, Tunnel};
+
+/// Where the relays listen.
+const LOCAL_HOST: &str = "127.0.0.1";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Imap,
+    Smtp,
+}
+
+impl Protocol {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Imap => "IMAP",
+            Protocol::Smtp => "SMTP",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Security {
+    Tls,
+    StartTls,
+    None,
+}
+
+impl From<Option<&Encryption>> for Security {
+    /// No encryption set means implicit TLS, like the library does.
+    fn from(encryption: Option<&Encryption>) -> Self {
+        match encryption {
+            Some(Encryption::StartTls(_)) => Security::StartTls,
+            Some(Encryption::None) => Security::None,
+            Some(Encryption::Tls(_)) | None => Security::Tls,
+        }
+    }
+}
+
+/// A server and how to connect to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Route {
+    pub protocol: Protocol,
+    pub host: String,
+    pub port: u16,
+    pub security: Security,
+    pub trust: Trust,
+    /// The proxy to go through, see `proxy::tunnel_for`
+    pub tunnel: Option<Tunnel>,
+}
+
+impl Route {
+    /// Whether the library can't connect by itself.
+    pub fn needs_relay(&self) -> bool {
+        self.tunnel.is_some() || (self.security != Security::None && !self.trust.is_default())
+    }
+}
+
+/// Relayed routes by the host name the library is given for them. The names end in `.invalid`,
+/// which never resolves, so a connection that doesn't go through a relay fails rather than
+/// reaching the server unencrypted.
+fn routes() -> &'static Mutex<HashMap<String, Route>> {
+    static ROUTES: OnceLock<Mutex<HashMap<String, Route>>> = OnceLock::new();
+    ROUTES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Bumped by `close_listeners`, listeners nothing connected to yet close when it changes.
+fn generation() -> &'static watch::Sender<u64> {
+    static GENERATION: OnceLock<watch::Sender<u64>> = OnceLock::new();
+    GENERATION.get_or_init(|| watch::channel(0).0)
+}
+
+/// The host name to give the library for `route`, each connection to it then gets a relay of its own.
+///
+/// imap-client and mail-send take a host and port, connect straight to it and do TLS against the
+/// public CAs only. When a proxy is set, or the account trusts a private CA or a pinned certificate,
+/// the library is given this name instead, and every time it connects the resolver installed in
+/// email-lib points it at a fresh listener on 127.0.0.1, in plain text. The relay behind it opens
+/// the real connection, through the proxy and STARTTLS included.
+pub fn register(route: &Route) -> Result<String, String> {
+    // Checks the CA bundle before anything connects
+    tls::connector(&route.trust)?;
+
+    let mut hasher = DefaultHasher::new();
+    route.hash(&mut hasher);
+    let host = format!("relay-{:016x}.invalid", hasher.finish());
+    routes().lock().map_err(|e| e.to_string())?.insert(host.clone(), route.clone());
+
+    static RESOLVER: Once = Once::new();
+    RESOLVER.call_once(|| email::endpoint::set_resolver(resolve));
+    Ok(host)
+}
+
+/// Closes the listeners handed out but not connected to yet, for when the proxy or an account's
+/// trust changed and connections are made again.
+pub fn close_listeners() {
+    generation().send_modify(|generation| *generation += 1);
+}
+
+/// Where the library connects to for a host name from `register`.
+fn resolve(host: &str, _port: u16) -> Option<(String, u16)> {
+    let route = routes().lock().ok()?.get(host).cloned()?;
+    match listen(route) {
+        Ok(port) => Some((LOCAL_HOST.to_string(), port)),
+        Err(e) => {
+            log::error!("Relay for {} failed to start: {}", host, e);
+            None
+        }
+    }
+}
+
+/// A listener relaying a single connection to `route`. It closes once that connection came in,
+/// or after `TIMEOUT` without one, so it isn't left open for anything else on the machine.
+fn listen(route: Route) -> Result<u16, String> {
+    let connector = tls::connector(&route.trust)?;
+    let listener = std::net::TcpListener::bind((LOCAL_HOST, 0)).map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let mut changed = generation().subscribe();
+
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("Relay to {}:{} failed to start: {}", route.host, route.port, e);
+                return;
+            }
+        };
+        let accepted = tokio::select! {
+            accepted = tokio::time::timeout(TIMEOUT, listener.accept()) => accepted,
+            _ = changed.changed() => return,
+        };
+        drop(listener);
+
+        match accepted {
+            Ok(Ok((client, _))) => relay(client, route, connector).await,
+            Ok(Err(e)) => log::warn!("{} relay to {}:{} failed: {}", route.protocol.name(), route.host, route.port, e),
+            Err(_) => log::warn!("Nothing connected to the {} relay to {}:{}", route.protocol.name(), route.host, route.port),
+        }
+    });
+
+    Ok(port)
+}
+
+async fn relay(mut client: TcpStream, route: Route, connector: TlsConnector) {
+    match open(&route, &connector).await {
+        Ok((mut server, greeting)) => {
+            if let Some(greeting) = greeting {
+                if client.write_all(greeting.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+        }
+        Err(e) => {
+            log::warn!("{} relay to {}:{} failed: {}", route.protocol.name(), route.host, route.port, e);
+            // Ends the session the way the server would, so the reason shows up in the library's error
+            let reason = e.replace(['\r', '\n'], " ");
+            let farewell = match route.protocol {
+                Protocol::Imap => format!("* BYE {}\r\n", reason),
+                Protocol::Smtp => format!("421 {}\r\n", reason),
+            };
+            let _ = client.write_all(farewell.as_bytes()).await;
+        }
+    }
+}
+
+/// Connects to the server of `route`, through its proxy and without TLS yet.
+pub async fn connect(route: &Route) -> Result<Box<dyn Stream>, String> {
+    let tcp = tokio::time::timeout(TIMEOUT, proxy::connect(route.tunnel.as_ref(), &route.host, route.port))
+        .await
+        .map_err(|_| format!("Timed out reaching {}:{}", route.host, route.port))??;
+    Ok(Box::new(tcp))
+}
+
+/// The secured connection, with the greeting read before STARTTLS for the library to get instead.
+async fn open(route: &Route, connector: &TlsConnector) -> Result<(Box<dyn Stream>, Option<String>), String> {
+    let (stream, greeting) = negotiate(connect(route).await?, route).await?;
+    if route.security == Security::None {
+        return Ok((stream, greeting));
+    }
+
+    let server_name = ServerName::try_from(route.host.clone()).map_err(|e| e.to_string())?;
+    let tls = tokio::time::timeout(TIMEOUT, connector.connect(server_name, stream))
+        .await
+        .map_err(|_| "The TLS handshake timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok((Box::new(tls), greeting))
+}
+
+/// Asks for STARTTLS when the route uses it, the stream is then ready for the handshake.
+pub async fn negotiate(stream: Box<dyn Stream>, route: &Route) -> Result<(Box<dyn Stream>, Option<String>), String> {
+    if route.security != Security::StartTls {
+        return Ok((stream, None));
+    }
+    let (stream, greeting) = tokio::time::timeout(TIMEOUT, start_tls(stream, route.protocol))
+        .await
+        .map_err(|_| format!("The {} server stopped answering", route.protocol.name()))??;
+    Ok((stream, Some(greeting)))
+}
+
+async fn read_line(reader: &mut BufReader<Box<dyn Stream>>) -> Result<String, String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.map_err(|e| e.to_string())? == 0 {
+        return Err("The server closed the connection".to_string());
+    }
+    Ok(line)
+}
+
+/// An SMTP reply, all of its lines. The last one has a space after the code.
+async fn read_reply(reader: &mut BufReader<Box<dyn Stream>>) -> Result<String, String> {
+    let mut reply = String::new();
+    loop {
+        let line = read_line(reader).await?;
+        reply.push_str(&line);
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(reply);
+        }
+    }
+}
+
+async fn send(reader: &mut BufReader<Box<dyn Stream>>, command: &str) -> Result<(), String> {
+    let stream = reader.get_mut();
+    stream.write_all(command.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.flush().await.map_err(|e| e.to_string())
+}
+
+/// Drops the capabilities from an IMAP greeting. Those before STARTTLS often leave out the
+/// sign-in methods, the library should ask again over the secured connection.
+fn strip_capabilities(greeting: &str) -> String {
+    let upper = greeting.to_ascii_uppercase();
+    let start = upper.find("[CAPABILITY ");
+    match start.and_then(|start| upper[start..].find(']').map(|end| (start, start + end))) {
+        Some((start, end)) => {
+            format!("{}{}", &greeting[..start], greeting[end + 1..].trim_start_matches(' '))
+        }
+        _ => greeting.to_string(),
+    }
+}
+
+/// Reads the greeting and asks for STARTTLS, returning the greeting to replay.
+async fn start_tls(stream: Box<dyn Stream>, protocol: Protocol) -> Result<(Box<dyn Stream>, String), String> {
+    let mut reader = BufReader::new(stream);
+    let greeting = match protocol {
+        Protocol::Imap => {
+            let greeting = read_line(&mut reader).await?;
+            send(&mut reader, "R1 STARTTLS\r\n").await?;
+            loop {
+                let line = read_line(&mut reader).await?;
+                if let Some(status) = line.strip_prefix("R1 ") {
+                    if !status.to_ascii_uppercase().starts_with("OK") {
+                        return Err(format!("The IMAP server refused STARTTLS: {}", status.trim_end()));
+                    }
+                    break;
+                }
+            }
+            strip_capabilities(&greeting)
+        }
+        Protocol::Smtp => {
+            let greeting = read_reply(&mut reader).await?;
+            send(&mut reader, "EHLO [127.0.0.1]\r\n").await?;
+            let ehlo = read_reply(&mut reader).await?;
+            if !ehlo.starts_with("250") {
+                return Err(format!("The SMTP server refused EHLO: {}", ehlo.trim_end()));
+            }
+            send(&mut reader, "STARTTLS\r\n").await?;
+            let reply = read_reply(&mut reader).await?;
+            if !reply.starts_with("220") {
+                return Err(format!("The SMTP server refused STARTTLS: {}", reply.trim_end()));
+            }
+            // The library says EHLO again and gets the extensions offered over TLS
+            greeting
+        }
+    };
+    // Nothing more is sent until the handshake, the buffer is empty
+    Ok((reader.into_inner(), greeting))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::proxy::TunnelKind;
+    use crate::utils::test_utils::MockMailServer;
+
+    async fn scripted_start_tls(protocol: Protocol, greeting: &'static str, replies: &'static [&'static str]) -> (String, Vec<String>) {
+        let (client_end, server_end) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let mut reader = BufReader::new(server_end);
+            reader.get_mut().write_all(greeting.as_bytes()).await.unwrap();
+            let mut commands = Vec::new();
+            for reply in replies {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                commands.push(line.trim_end().to_string());
+                reader.get_mut().write_all(reply.as_bytes()).await.unwrap();
+            }
+            commands
+        });
+
+        let (_, replayed) = start_tls(Box::new(client_end), protocol).await.unwrap();
+        (replayed, server.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_start_tls_replays_the_greeting() {
+        let (greeting, commands) = scripted_start_tls(
+            Protocol::Imap,
+            "* OK [CAPABILITY IMAP4rev1 STARTTLS LOGINDISABLED] Dovecot ready.\r\n",
+            &["R1 OK Begin TLS negotiation now.\r\n"],
+        )
+        .await;
+        assert_eq!(greeting, "* OK Dovecot ready.\r\n");
+        assert_eq!(commands, ["R1 STARTTLS"]);
+
+        let (greeting, commands) = scripted_start_tls(
+            Protocol::Smtp,
+            "220-mail.example.com ESMTP\r\n220 Postfix\r\n",
+            &["250-mail.example.com\r\n250 STARTTLS\r\n", "220 2.0.0 Ready to start TLS\r\n"],
+        )
+        .await;
+        assert_eq!(greeting, "220-mail.example.com ESMTP\r\n220 Postfix\r\n");
+        assert_eq!(commands, ["EHLO [127.0.0.1]", "STARTTLS"]);
+    }
+
+    #[test]
+    fn test_only_proxied_or_trusted_encrypted_routes_need_a_relay() {
+        let pinned = Trust { pinned_fingerprints: vec!["ab".repeat(32)], ..Default::default() };
+        let route = |security, trust: &Trust| Route {
+            protocol: Protocol::Imap,
+            host: "mail.example.com".to_string(),
+            port: 993,
+            security,
+            trust: trust.clone(),
+            tunnel: None,
+        };
+        assert!(route(Security::Tls, &pinned).needs_relay());
+        assert!(!route(Security::None, &pinned).needs_relay());
+        assert!(!route(Security::StartTls, &Trust::default()).needs_relay());
+
+        let mut proxied = route(Security::None, &Trust::default());
+        proxied.tunnel = Some(Tunnel { kind: TunnelKind::Socks5, host: "proxy".to_string(), port: 1080, credentials: None });
+        assert!(proxied.needs_relay());
+    }
+
+    #[tokio::test]
+    async fn test_relay_goes_through_http_proxy() {
+        let server = MockMailServer::start().await;
+
+        // Lets one tunnel through and remembers what was asked for
+        let proxy = TcpListener::bind((LOCAL_HOST, 0)).await.unwrap();
+        let proxy_port = proxy.local_addr().unwrap().port();
+        let asked = tokio::spawn(async move {
+            let (stream, _) = proxy.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            while !request.ends_with("\r\n\r\n") {
+                reader.read_line(&mut request).await.unwrap();
+            }
+            let target = request.split_whitespace().nth(1).unwrap().to_string();
+            let mut upstream = TcpStream::connect(target.as_str()).await.unwrap();
+            let mut client = reader.into_inner();
+            client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+            tokio::spawn(async move {
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+            target
+        });
+
+        let route = Route {
+            protocol: Protocol::Imap,
+            host: LOCAL_HOST.to_string(),
+            port: server.imap_port,
+            security: Security::None,
+            trust: Trust::default(),
+            tunnel: Some(Tunnel { kind: TunnelKind::Http, host: LOCAL_HOST.to_string(), port: proxy_port, credentials: None }),
+        };
+        let host = register(&route).unwrap();
+        assert!(host.ends_with(".invalid"));
+        let (local_host, port) = email::endpoint::resolve(&host, route.port);
+        assert_eq!(local_host, LOCAL_HOST);
+        assert_ne!(email::endpoint::resolve(&host, route.port).1, port);
+
+        let mut client = BufReader::new(TcpStream::connect((LOCAL_HOST, port)).await.unwrap());
+        let mut greeting = String::new();
+        client.read_line(&mut greeting).await.unwrap();
+        assert!(greeting.starts_with("* OK"), "{}", greeting);
+        assert_eq!(asked.await.unwrap(), format!("127.0.0.1:{}", server.imap_port));
+
+        // The listener only ever took that one connection
+        assert!(TcpStream::connect((LOCAL_HOST, port)).await.is_err());
+        assert_eq!(email::endpoint::resolve("mail.example.com", 993), ("mail.example.com".to_string(), 993));
+    }
+}
@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+use crate::email_backend::accounts::managesieve::{self, SieveClient};
+use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::email_backend::accounts::vacation::{self, VacationResponder};
+
+/// The script this app writes, holding the vacation reply and the pushed rules. Only one script
+/// runs at a time, so both share it.
+pub const SCRIPT_NAME: &str = "dueam";
+
+/// Comment lines carrying what the script was generated from as JSON, so it reads back as entered.
+const VACATION_MARKER: &str = "# dueam-vacation ";
+const RULES_MARKER: &str = "# dueam-rules ";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SieveScript {
+    pub name: String,
+    pub active: bool,
+}
+
+/// A local rule as the server runs it: matching mail is filed into `mailbox` on delivery.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SieveRule {
+    /// `sender` (address or `@domain`) or `list` (List-Id), as in retention rules
+    pub match_type: String,
+    pub pattern: String,
+    pub mailbox: String,
+}
+
+/// What the app keeps in its script.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManagedScript {
+    pub vacation: Option<VacationResponder>,
+    pub rules: Vec<SieveRule>,
+}
+
+impl ManagedScript {
+    pub fn parse(script: &str) -> Self {
+        let mut managed = ManagedScript::default();
+        for line in script.lines() {
+            if let Some(json) = line.strip_prefix(VACATION_MARKER) {
+                managed.vacation = serde_json::from_str(json).ok();
+            } else if let Some(json) = line.strip_prefix(RULES_MARKER) {
+                managed.rules = serde_json::from_str(json).unwrap_or_default();
+            }
+        }
+        managed
+    }
+
+    /// Whether the script does anything, one that doesn't is switched off.
+    pub fn is_active(&self) -> bool {
+        self.vacation.as_ref().is_some_and(|v| v.enabled) || !self.rules.is_empty()
+    }
+
+    pub fn to_sieve(&self) -> String {
+        let mut header = Vec::new();
+        let mut requires: Vec<&str> = Vec::new();
+        let mut body = Vec::new();
+
+        if let Some(responder) = &self.vacation {
+            header.push(format!("{}{}", VACATION_MARKER, serde_json::to_string(responder).unwrap_or_default()));
+            if responder.enabled {
+                let (needs, action) = vacation::to_sieve(responder);
+                requires.extend(needs);
+                body.push(action);
+            }
+        }
+
+        if !self.rules.is_empty() {
+            header.push(format!("{}{}", RULES_MARKER, serde_json::to_string(&self.rules).unwrap_or_default()));
+            requires.push("fileinto");
+            body.extend(self.rules.iter().map(rule_to_sieve));
+        }
+
+        let mut script = header.join("\r\n");
+        script.push_str("\r\n");
+        if !requires.is_empty() {
+            let names: Vec<String> = requires.iter().map(|r| managesieve::quote(r)).collect();
+            script.push_str(&format!("require [{}];\r\n", names.join(", ")));
+        }
+        for block in body {
+            script.push_str(&block);
+            script.push_str("\r\n");
+        }
+        script
+    }
+}
+
+fn rule_to_sieve(rule: &SieveRule) -> String {
+    let test = match (rule.match_type.as_str(), rule.pattern.strip_prefix('@')) {
+        ("sender", Some(domain)) => format!("address :domain :is \"from\" {}", managesieve::quote(domain)),
+        ("sender", None) => format!("address :is \"from\" {}", managesieve::quote(&rule.pattern)),
+        // List-Id wraps the id in angle brackets
+        _ => format!("header :contains \"list-id\" {}", managesieve::quote(&format!("<{}>", rule.pattern))),
+    };
+    format!("if {} {{\r\n    fileinto {};\r\n    stop;\r\n}}", test, managesieve::quote(&rule.mailbox))
+}
+
+/// The enabled archive rules marked `file_on_delivery`, filing into the account's archive
+/// folder. Sieve runs on delivery, so the server files matching mail right away rather than
+/// after the rule's days, the other rules keep waiting in the app. Trash rules are never
+/// pushed, and rules are left out when the account has no archive folder.
+pub(crate) async fn rules_for_account(pool: &SqlitePool, account_id: i64) -> Result<Vec<SieveRule>, String> {
+    sqlx::query_as::<_, (String, String, String)>(
+        "SELECT r.match_type, r.pattern, f.path
+         FROM retention_rules r
+         JOIN folders f ON f.account_id = ? AND f.role = r.action
+         WHERE r.enabled AND r.file_on_delivery AND r.action = 'archive'
+         ORDER BY r.match_type, r.pattern"
+    )
+    .bind(account_id)
+    .fetch_all(pool)
+    .await
+    .map(|rows| rows.into_iter().map(|(match_type, pattern, mailbox)| SieveRule { match_type, pattern, mailbox }).collect())
+    .map_err(|e| e.to_string())
+}
+
+/// Opens a ManageSieve session on the account's IMAP host. ManageSieve has no implicit TLS,
+/// the connection is upgraded with STARTTLS unless the account is set up without encryption.
+pub(crate) async fn connect(account: &ImapSmtpAccount) -> Result<SieveClient, String> {
+    SieveClient::connect(
+        &account.imap_host,
+        managesieve::PORT,
+        account.imap_encryption != "none",
+        &account.trust,
+        &account.imap_username,
+        account.password.as_deref().unwrap_or_default(),
+    )
+    .await
+}
+
+async fn connect_account<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, account_id: i64) -> Result<SieveClient, String> {
+    match AccountManager::new(app_handle).await?.get_account_by_id(account_id).await? {
+        Account::ImapSmtp(account) => connect(&account).await,
+        other => Err(format!("{} has no Sieve filters to manage", other.email())),
+    }
+}
+
+/// The app's script as last written, and whether it is the active one.
+pub(crate) async fn read_managed(client: &mut SieveClient) -> Result<(ManagedScript, bool), String> {
+    let active = client.list_scripts().await?.into_iter().any(|(name, active)| name == SCRIPT_NAME && active);
+    let managed = client.get_script(SCRIPT_NAME).await?.map(|s| ManagedScript::parse(&s)).unwrap_or_default();
+    Ok((managed, active))
+}
+
+/// Rewrites the app's script with `change` applied, activating it while it does anything.
+pub(crate) async fn update_managed(client: &mut SieveClient, change: impl FnOnce(&mut ManagedScript)) -> Result<(), String> {
+    let scripts = client.list_scripts().await?;
+    let mut managed = client.get_script(SCRIPT_NAME).await?.map(|s| ManagedScript::parse(&s)).unwrap_or_default();
+    change(&mut managed);
+
+    // Activating ours would switch off the user's own filters
+    if managed.is_active() {
+        if let Some((name, _)) = scripts.iter().find(|(name, active)| *active && name != SCRIPT_NAME) {
+            return Err(format!("The Sieve script \"{}\" is active on the server, switch it off first or add this to it", name));
+        }
+    }
+
+    client.put_script(SCRIPT_NAME, &managed.to_sieve()).await?;
+    let was_active = scripts.iter().any(|(name, active)| *active && name == SCRIPT_NAME);
+    match (managed.is_active(), was_active) {
+        (true, false) => client.set_active(SCRIPT_NAME).await,
+        (false, true) => client.set_active("").await,
+        _ => Ok(()),
+    }
+}
+
+fn check_editable(name: &str) -> Result<(), String> {
+    if name == SCRIPT_NAME {
+        return Err(format!("\"{}\" is kept up to date by the app, change it through the vacation reply and rules", SCRIPT_NAME));
+    }
+    if name.trim().is_empty() {
+        return Err("The script needs a name".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_sieve_scripts<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64) -> Result<Vec<SieveScript>, String> {
+    let mut client = connect_account(&app_handle, account_id).await?;
+    let scripts = client.list_scripts().await;
+    client.logout().await;
+    Ok(scripts?.into_iter().map(|(name, active)| SieveScript { name, active }).collect())
+}
+
+#[tauri::command]
+pub async fn get_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, name: String) -> Result<Option<String>, String> {
+    let mut client = connect_account(&app_handle, account_id).await?;
+    let script = client.get_script(&name).await;
+    client.logout().await;
+    script
+}
+
+/// Uploads a script, the server checks it and reports errors. `activate` makes it the script
+/// that runs, replacing whichever did.
+#[tauri::command]
+pub async fn save_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, name: String, script: String, activate: bool) -> Result<(), String> {
+    check_editable(&name)?;
+    let mut client = connect_account(&app_handle, account_id).await?;
+    let result = async {
+        client.put_script(&name, &script).await?;
+        if activate {
+            client.set_active(&name).await?;
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+    client.logout().await;
+    result
+}
+
+#[tauri::command]
+pub async fn delete_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, name: String) -> Result<(), String> {
+    check_editable(&name)?;
+    let mut client = connect_account(&app_handle, account_id).await?;
+    let result = client.delete_script(&name).await;
+    client.logout().await;
+    result
+}
+
+/// Makes `name` the script that runs, `None` switches filtering off.
+#[tauri::command]
+pub async fn activate_sieve_script<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, name: Option<String>) -> Result<(), String> {
+    let mut client = connect_account(&app_handle, account_id).await?;
+    let result = client.set_active(name.as_deref().unwrap_or("")).await;
+    client.logout().await;
+    result
+}
+
+/// The Sieve the local rules translate to for the account, to show before pushing them.
+#[tauri::command]
+pub async fn preview_sieve_rules<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64) -> Result<String, String> {
+    let rules = rules_for_account(&app_handle.state::<SqlitePool>(), account_id).await?;
+    Ok(ManagedScript { vacation: None, rules }.to_sieve())
+}
+
+/// Replaces the rules on the server with the local ones, so mail is filed while the app is
+/// closed too. Returns how many rules were pushed, none removes them from the server.
+#[tauri::command]
+pub async fn push_rules_to_sieve<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64) -> Result<usize, String> {
+    let rules = rules_for_account(&app_handle.state::<SqlitePool>(), account_id).await?;
+    let count = rules.len();
+
+    let mut client = connect_account(&app_handle, account_id).await?;
+    let result = if count > 0 && !client.extensions.iter().any(|e| e == "fileinto") {
+        Err("The mail server's Sieve can't file mail into folders".to_string())
+    } else {
+        update_managed(&mut client, |managed| managed.rules = rules).await
+    };
+    client.logout().await;
+    result.map(|_| count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[test]
+    fn test_managed_script_round_trip() {
+        let managed = ManagedScript {
+            vacation: Some(VacationResponder { enabled: true, subject: "Away".to_string(), message: "Back Monday".to_string(), ..Default::default() }),
+            rules: vec![
+                SieveRule { match_type: "sender".to_string(), pattern: "@news.example.com".to_string(), mailbox: "Archive".to_string() },
+                SieveRule { match_type: "list".to_string(), pattern: "dev.lists.example.org".to_string(), mailbox: "Trash".to_string() },
+            ],
+        };
+
+        let script = managed.to_sieve();
+        assert!(script.contains("require [\"vacation\", \"fileinto\"];"));
+        assert!(script.contains("if address :domain :is \"from\" \"news.example.com\" {\r\n    fileinto \"Archive\";"));
+        assert!(script.contains("if header :contains \"list-id\" \"<dev.lists.example.org>\""));
+        assert!(script.contains("vacation :days 1 :subject \"Away\" \"Back Monday\";"));
+        assert_eq!(ManagedScript::parse(&script), managed);
+
+        let idle = ManagedScript { vacation: Some(VacationResponder::default()), rules: Vec::new() };
+        assert!(!idle.is_active());
+        assert!(!idle.to_sieve().contains("require"));
+    }
+
+    #[tokio::test]
+    async fn test_rules_file_into_account_folders() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        for (name, role) in [("INBOX.Archive", "archive"), ("INBOX.Trash", "trash")] {
+            insert_test_folder(&pool, account_id, name, Some(role)).await;
+        }
+        sqlx::query(
+            "INSERT INTO retention_rules (match_type, pattern, action, after_days, enabled, file_on_delivery) VALUES
+             ('sender', 'promo@example.com', 'archive', 7, 1, 1),
+             ('sender', 'later@example.com', 'archive', 7, 1, 0),
+             ('sender', '@old.example.com', 'archive', 7, 0, 1),
+             ('list', 'noise.example.org', 'trash', 30, 1, 1)"
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Rules that wait their days, disabled rules and trash rules stay local
+        assert_eq!(rules_for_account(&pool, account_id).await.unwrap(), vec![SieveRule {
+            match_type: "sender".to_string(),
+            pattern: "promo@example.com".to_string(),
+            mailbox: "INBOX.Archive".to_string(),
+        }]);
+    }
+}
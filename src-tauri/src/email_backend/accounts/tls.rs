@@ -0,0 +1,215 @@
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::client::WebPkiServerVerifier;
+use tokio_rustls::rustls::crypto::CryptoProvider;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+use crate::email_backend::accounts::managesieve::Stream;
+
+/// What an account trusts besides the public CAs, for servers with a self-signed certificate
+/// or one issued by a private CA.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Trust {
+    /// PEM certificates of CAs whose certificates are accepted, a company's internal CA for example
+    #[serde(default)]
+    pub ca_pem: Option<String>,
+    /// SHA-256 fingerprints of server certificates accepted as they are, lowercase hex
+    #[serde(default)]
+    pub pinned_fingerprints: Vec<String>,
+}
+
+impl Trust {
+    /// Nothing beyond the public CAs.
+    pub fn is_default(&self) -> bool {
+        self.ca_pem.is_none() && self.pinned_fingerprints.is_empty()
+    }
+
+    /// Checked and in the form it is kept: fingerprints normalized, a blank CA bundle dropped.
+    pub fn normalized(self) -> Result<Trust, String> {
+        let ca_pem = self.ca_pem.filter(|pem| !pem.trim().is_empty());
+        let mut pinned_fingerprints = self.pinned_fingerprints.iter().map(|f| normalize_fingerprint(f)).collect::<Result<Vec<_>, _>>()?;
+        pinned_fingerprints.sort();
+        pinned_fingerprints.dedup();
+
+        let trust = Trust { ca_pem, pinned_fingerprints };
+        connector(&trust)?;
+        Ok(trust)
+    }
+}
+
+/// Lowercase hex without separators, how fingerprints are kept and compared. Takes them as
+/// `openssl x509 -fingerprint -sha256` and browsers show them, colons and all.
+pub fn normalize_fingerprint(value: &str) -> Result<String, String> {
+    let hex: String = value.chars().filter(|c| !matches!(c, ':' | ' ' | '-')).collect::<String>().to_lowercase();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid SHA-256 fingerprint: {}", value));
+    }
+    Ok(hex)
+}
+
+/// `AB:CD:…`, the way the fingerprint is shown to the user.
+pub fn display_fingerprint(hex: &str) -> String {
+    hex.as_bytes().chunks(2).map(|pair| String::from_utf8_lossy(pair).to_uppercase()).collect::<Vec<_>>().join(":")
+}
+
+fn fingerprint(cert: &CertificateDer<'_>) -> String {
+    hex::encode(Sha256::digest(cert.as_ref()))
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// The public roots plus the certificates in `ca_pem`.
+fn root_store(ca_pem: Option<&str>) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    if let Some(pem) = ca_pem {
+        let mut added = 0;
+        for cert in CertificateDer::pem_slice_iter(pem.as_bytes()) {
+            let cert = cert.map_err(|e| format!("Invalid CA certificate: {:?}", e))?;
+            roots.add(cert).map_err(|e| format!("Invalid CA certificate: {}", e))?;
+            added += 1;
+        }
+        if added == 0 {
+            return Err("No certificate found in the CA bundle".to_string());
+        }
+    }
+    Ok(roots)
+}
+
+/// Accepts the pinned certificates as they are and checks any other against the roots.
+#[derive(Debug)]
+struct PinningVerifier {
+    roots: Arc<WebPkiServerVerifier>,
+    pinned: Vec<String>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        // A pinned certificate is trusted for what it is, self-signed and whatever name it carries
+        if self.pinned.contains(&fingerprint(end_entity)) {
+            return Ok(ServerCertVerified::assertion());
+        }
+        self.roots.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.roots.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.roots.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.roots.supported_verify_schemes()
+    }
+}
+
+/// A connector trusting the public CAs and what the account added to them.
+pub fn connector(trust: &Trust) -> Result<TlsConnector, String> {
+    let provider = provider();
+    let roots = WebPkiServerVerifier::builder_with_provider(Arc::new(root_store(trust.ca_pem.as_deref())?), provider.clone())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let verifier = PinningVerifier { roots, pinned: trust.pinned_fingerprints.clone() };
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Notes the certificate the server presents and turns the handshake down.
+#[derive(Debug)]
+struct CertificateRecorder {
+    seen: Mutex<Option<String>>,
+    schemes: Vec<SignatureScheme>,
+}
+
+impl ServerCertVerifier for CertificateRecorder {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Ok(mut seen) = self.seen.lock() {
+            *seen = Some(fingerprint(end_entity));
+        }
+        Err(rustls::Error::General("Certificate recorded".to_string()))
+    }
+
+    fn verify_tls12_signature(&self, _message: &[u8], _cert: &CertificateDer<'_>, _dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::General("Certificate recorded".to_string()))
+    }
+
+    fn verify_tls13_signature(&self, _message: &[u8], _cert: &CertificateDer<'_>, _dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(rustls::Error::General("Certificate recorded".to_string()))
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.schemes.clone()
+    }
+}
+
+/// Starts a handshake on `stream` only to read the fingerprint of the server's certificate,
+/// for the user to compare before trusting it.
+pub async fn server_fingerprint(stream: Box<dyn Stream>, host: &str) -> Result<String, String> {
+    let provider = provider();
+    let recorder = Arc::new(CertificateRecorder {
+        seen: Mutex::new(None),
+        schemes: provider.signature_verification_algorithms.supported_schemes(),
+    });
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(recorder.clone())
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+
+    let handshake = TlsConnector::from(Arc::new(config)).connect(server_name, stream).await;
+    let seen = recorder.seen.lock().ok().and_then(|mut seen| seen.take());
+    match (seen, handshake) {
+        (Some(fingerprint), _) => Ok(fingerprint),
+        (None, Err(e)) => Err(format!("TLS handshake failed: {}", e)),
+        (None, Ok(_)) => Err("The server sent no certificate".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprints_and_ca_bundles() {
+        let shown = "AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89";
+        let hex = normalize_fingerprint(shown).unwrap();
+        assert_eq!(hex, "abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789");
+        assert_eq!(display_fingerprint(&hex), shown);
+        assert!(normalize_fingerprint("ab:cd").is_err());
+        assert!(normalize_fingerprint(&"zz".repeat(32)).is_err());
+
+        let trust = Trust { ca_pem: Some("  ".to_string()), pinned_fingerprints: vec![shown.to_string(), hex.clone()] };
+        assert_eq!(trust.normalized().unwrap(), Trust { ca_pem: None, pinned_fingerprints: vec![hex] });
+        let garbage = Trust { ca_pem: Some("not a certificate".to_string()), ..Default::default() };
+        assert!(garbage.normalized().is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use crate::email_backend::accounts::managesieve::{self, SieveClient};
+use crate::email_backend::accounts::sieve;
+use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::utils::proxy;
+
+const GMAIL_VACATION_URL: &str = "https://gmail.googleapis.com/gmail/v1/users/me/settings/vacation";
+
+/// The auto-reply sent while the user is away. Dates are `YYYY-MM-DD`, both days included.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VacationResponder {
+    pub enabled: bool,
+    pub subject: String,
+    pub message: String,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+/// The Gmail API's `VacationSettings`, times in epoch milliseconds as strings.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GmailVacation {
+    enable_auto_reply: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response_subject: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response_body_plain_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end_time: Option<String>,
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("Invalid date: {}", date))
+}
+
+/// Local midnight starting the day, or ending it for `end`, which Gmail treats as exclusive.
+fn date_to_millis(date: &str, end: bool) -> Result<String, String> {
+    let day = parse_date(date)?;
+    let day = if end { day.succ_opt().ok_or_else(|| format!("Invalid date: {}", date))? } else { day };
+    day.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+        .map(|midnight| midnight.timestamp_millis().to_string())
+        .ok_or_else(|| format!("Invalid date: {}", date))
+}
+
+fn millis_to_date(millis: &str, end: bool) -> Option<String> {
+    let millis: i64 = millis.parse().ok()?;
+    let millis = if end { millis - 1 } else { millis };
+    chrono::DateTime::from_timestamp_millis(millis).map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+}
+
+fn from_gmail(vacation: GmailVacation) -> VacationResponder {
+    VacationResponder {
+        enabled: vacation.enable_auto_reply,
+        subject: vacation.response_subject.unwrap_or_default(),
+        message: vacation.response_body_plain_text.unwrap_or_default(),
+        start_date: vacation.start_time.as_deref().and_then(|t| millis_to_date(t, false)),
+        end_date: vacation.end_time.as_deref().and_then(|t| millis_to_date(t, true)),
+    }
+}
+
+fn to_gmail(responder: &VacationResponder) -> Result<GmailVacation, String> {
+    Ok(GmailVacation {
+        enable_auto_reply: responder.enabled,
+        response_subject: Some(responder.subject.clone()),
+        response_body_plain_text: Some(responder.message.clone()),
+        start_time: responder.start_date.as_deref().map(|d| date_to_millis(d, false)).transpose()?,
+        end_time: responder.end_date.as_deref().map(|d| date_to_millis(d, true)).transpose()?,
+    })
+}
+
+fn gmail_error(status: reqwest::StatusCode) -> String {
+    if status == reqwest::StatusCode::FORBIDDEN {
+        "Gmail didn't allow changing settings, sign in to the account again to grant access".to_string()
+    } else {
+        format!("Gmail returned {}", status)
+    }
+}
+
+async fn gmail_get(token: &str) -> Result<VacationResponder, String> {
+    let response = proxy::http_client()?
+        .get(GMAIL_VACATION_URL)
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(gmail_error(response.status()));
+    }
+    let vacation: GmailVacation = response.json().await.map_err(|e| e.to_string())?;
+    Ok(from_gmail(vacation))
+}
+
+async fn gmail_set(token: &str, responder: &VacationResponder) -> Result<(), String> {
+    let response = proxy::http_client()?
+        .put(GMAIL_VACATION_URL)
+        .bearer_auth(token)
+        .json(&to_gmail(responder)?)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(gmail_error(response.status()));
+    }
+    Ok(())
+}
+
+/// The Sieve commands replying at most once a day to each sender within the dates, and the
+/// extensions they need.
+pub(crate) fn to_sieve(responder: &VacationResponder) -> (Vec<&'static str>, String) {
+    let mut conditions = Vec::new();
+    if let Some(start) = &responder.start_date {
+        conditions.push(format!("currentdate :value \"ge\" \"date\" {}", managesieve::quote(start)));
+    }
+    if let Some(end) = &responder.end_date {
+        conditions.push(format!("currentdate :value \"le\" \"date\" {}", managesieve::quote(end)));
+    }
+
+    // Scripts use CRLF line breaks throughout
+    let message = responder.message.replace("\r\n", "\n").replace('\n', "\r\n");
+    let action = format!(
+        "vacation :days 1 :subject {} {};",
+        managesieve::quote(&responder.subject),
+        managesieve::quote(&message)
+    );
+    if conditions.is_empty() {
+        (vec!["vacation"], action)
+    } else {
+        (vec!["vacation", "date", "relational"], format!("if allof({}) {{\r\n    {}\r\n}}", conditions.join(", "), action))
+    }
+}
+
+async fn sieve_get(client: &mut SieveClient) -> Result<VacationResponder, String> {
+    let (managed, active) = sieve::read_managed(client).await?;
+    let mut responder = managed.vacation.unwrap_or_default();
+    responder.enabled &= active;
+    Ok(responder)
+}
+
+async fn sieve_set(client: &mut SieveClient, responder: &VacationResponder) -> Result<(), String> {
+    if !client.extensions.iter().any(|e| e == "vacation") {
+        return Err("The mail server's Sieve doesn't support vacation replies".to_string());
+    }
+    if (responder.start_date.is_some() || responder.end_date.is_some()) && !client.extensions.iter().any(|e| e == "date") {
+        return Err("The mail server's Sieve can't limit replies to dates".to_string());
+    }
+    sieve::update_managed(client, |managed| managed.vacation = Some(responder.clone())).await
+}
+
+fn check(responder: &VacationResponder) -> Result<(), String> {
+    let start = responder.start_date.as_deref().map(parse_date).transpose()?;
+    let end = responder.end_date.as_deref().map(parse_date).transpose()?;
+    if let (Some(start), Some(end)) = (start, end) {
+        if end < start {
+            return Err("The vacation reply ends before it starts".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn unsupported(account: &Account) -> String {
+    format!("Vacation replies can't be managed for {} yet", account.email())
+}
+
+#[tauri::command]
+pub async fn get_vacation_responder<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64) -> Result<VacationResponder, String> {
+    let manager = AccountManager::new(&app_handle).await?;
+    let account = manager.get_account_by_id(account_id).await?;
+
+    match &account {
+        Account::Google(google) if google.app_password.is_none() => {
+            let token = manager.refresh_access_token(&google.email).await?;
+            gmail_get(&token).await
+        }
+        Account::ImapSmtp(imap_smtp) => {
+            let mut client = sieve::connect(imap_smtp).await?;
+            let responder = sieve_get(&mut client).await;
+            client.logout().await;
+            responder
+        }
+        _ => Err(unsupported(&account)),
+    }
+}
+
+/// Sets the auto-reply on the server: through the Gmail API for Google accounts, as a Sieve
+/// script for servers offering ManageSieve.
+#[tauri::command]
+pub async fn set_vacation_responder<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, responder: VacationResponder) -> Result<(), String> {
+    check(&responder)?;
+    let manager = AccountManager::new(&app_handle).await?;
+    let account = manager.get_account_by_id(account_id).await?;
+
+    match &account {
+        Account::Google(google) if google.app_password.is_none() => {
+            let token = manager.refresh_access_token(&google.email).await?;
+            gmail_set(&token, &responder).await
+        }
+        Account::ImapSmtp(imap_smtp) => {
+            let mut client = sieve::connect(imap_smtp).await?;
+            let result = sieve_set(&mut client, &responder).await;
+            client.logout().await;
+            result
+        }
+        _ => Err(unsupported(&account)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sieve_vacation_dates() {
+        let responder = VacationResponder {
+            enabled: true,
+            subject: "Away \"until\" Monday".to_string(),
+            message: "Back soon.\nFor urgent matters call the office.".to_string(),
+            start_date: Some("2024-07-01".to_string()),
+            end_date: Some("2024-07-14".to_string()),
+        };
+
+        let (requires, action) = to_sieve(&responder);
+        assert_eq!(requires, vec!["vacation", "date", "relational"]);
+        assert!(action.contains("currentdate :value \"le\" \"date\" \"2024-07-14\""));
+        assert!(action.contains(":subject \"Away \\\"until\\\" Monday\""));
+        assert!(action.contains("Back soon.\r\nFor urgent"));
+
+        let (requires, action) = to_sieve(&VacationResponder::default());
+        assert_eq!(requires, vec!["vacation"]);
+        assert!(!action.contains("currentdate"));
+    }
+
+    #[test]
+    fn test_gmail_dates_are_inclusive_days() {
+        let responder = VacationResponder {
+            enabled: true,
+            start_date: Some("2024-07-01".to_string()),
+            end_date: Some("2024-07-14".to_string()),
+            ..Default::default()
+        };
+        let gmail = to_gmail(&responder).unwrap();
+        assert_eq!(from_gmail(gmail), responder);
+
+        assert!(check(&VacationResponder { start_date: Some("2024-07-14".to_string()), end_date: Some("2024-07-01".to_string()), ..Default::default() }).is_err());
+        assert!(to_gmail(&VacationResponder { start_date: Some("July 1st".to_string()), ..Default::default() }).is_err());
+    }
+}
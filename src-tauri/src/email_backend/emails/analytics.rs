@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DailyVolume {
+    /// `YYYY-MM-DD`, local time
+    pub day: String,
+    pub received: i64,
+    pub sent: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SenderVolume {
+    pub address: String,
+    pub name: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct HourlyVolume {
+    /// 0-23, local time
+    pub hour: i64,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MailboxAnalytics {
+    pub period: String,
+    pub daily: Vec<DailyVolume>,
+    pub top_senders: Vec<SenderVolume>,
+    pub busiest_hours: Vec<HourlyVolume>,
+    /// Average time between receiving an email and sending a reply to it
+    pub avg_response_minutes: Option<f64>,
+}
+
+fn period_days(period: &str) -> Result<i64, String> {
+    match period {
+        "week" => Ok(7),
+        "month" => Ok(30),
+        "quarter" => Ok(90),
+        "year" => Ok(365),
+        other => Err(format!("Unknown period: {}", other)),
+    }
+}
+
+/// Messages of the period, one row per Message-ID so copies in several folders count once.
+/// `direction` is 'sent' for the sent folder and 'received' for everything that isn't sent,
+/// drafts, spam or trash.
+const PERIOD_MESSAGES_CTE: &str = "WITH period_messages AS (
+        SELECT e.message_id, MIN(e.sender_address) as sender_address, MAX(e.sender_name) as sender_name,
+               MIN(e.date) as date, MIN(e.in_reply_to) as in_reply_to,
+               MAX(CASE WHEN f.role = 'sent' THEN 'sent' ELSE 'received' END) as direction
+        FROM emails e JOIN folders f ON e.folder_id = f.id
+        WHERE COALESCE(f.role, '') NOT IN ('drafts', 'spam', 'trash')
+          AND datetime(e.date) > datetime('now', ?)
+          AND (? IS NULL OR e.account_id = ?)
+        GROUP BY e.account_id, COALESCE(e.message_id, CAST(e.id AS TEXT))
+    ) ";
+
+#[tauri::command]
+pub async fn get_mailbox_analytics<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    period: String,
+    account_id: Option<i64>,
+) -> Result<MailboxAnalytics, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let since = format!("-{} days", period_days(&period)?);
+
+    let daily: Vec<DailyVolume> = sqlx::query_as(&format!(
+        "{}SELECT date(date, 'localtime') as day,
+                  SUM(direction = 'received') as received,
+                  SUM(direction = 'sent') as sent
+           FROM period_messages GROUP BY day ORDER BY day",
+        PERIOD_MESSAGES_CTE
+    ))
+    .bind(&since)
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Addresses merged into one contact count as that contact's primary address
+    let top_senders: Vec<SenderVolume> = sqlx::query_as(&format!(
+        "{}SELECT COALESCE(LOWER(sa.primary_address), LOWER(m.sender_address)) as address, MAX(m.sender_name) as name, COUNT(*) as count
+           FROM period_messages m
+           LEFT JOIN sender_aliases sa ON LOWER(sa.alias_address) = LOWER(m.sender_address)
+           WHERE m.direction = 'received'
+           GROUP BY 1 ORDER BY count DESC LIMIT 10",
+        PERIOD_MESSAGES_CTE
+    ))
+    .bind(&since)
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let busiest_hours: Vec<HourlyVolume> = sqlx::query_as(&format!(
+        "{}SELECT CAST(strftime('%H', date, 'localtime') AS INTEGER) as hour, COUNT(*) as count
+           FROM period_messages WHERE direction = 'received'
+           GROUP BY hour ORDER BY count DESC",
+        PERIOD_MESSAGES_CTE
+    ))
+    .bind(&since)
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Replies are matched to what they answer through In-Reply-To, ignoring replies to our own mail
+    let avg_response_minutes: Option<f64> = sqlx::query_scalar(&format!(
+        "{}SELECT AVG((julianday(r.date) - julianday(o.date)) * 24 * 60)
+           FROM period_messages r JOIN period_messages o ON o.message_id = r.in_reply_to
+           WHERE r.direction = 'sent' AND o.direction = 'received' AND julianday(r.date) > julianday(o.date)",
+        PERIOD_MESSAGES_CTE
+    ))
+    .bind(&since)
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(MailboxAnalytics { period, daily, top_senders, busiest_hours, avg_response_minutes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_app, setup_test_db};
+    use chrono::{Duration, SecondsFormat, Utc};
+
+    #[tokio::test]
+    async fn test_analytics_count_each_message_once() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let mut folders = Vec::new();
+        for role in ["inbox", "archive", "sent", "spam"] {
+            let folder_id = insert_test_folder(&pool, account_id, role, Some(role)).await;
+            folders.push(folder_id);
+        }
+
+        let hours_ago = |hours: i64| (Utc::now() - Duration::hours(hours)).to_rfc3339_opts(SecondsFormat::Secs, true);
+        let emails = [
+            // The same message in the inbox and the archive
+            (folders[0], "<a1@example.org>", None, "alice@example.org", hours_ago(3)),
+            (folders[1], "<a1@example.org>", None, "alice@example.org", hours_ago(3)),
+            (folders[0], "<b1@example.org>", None, "bob@example.org", hours_ago(5)),
+            (folders[2], "<r1@example.com>", Some("<a1@example.org>"), "me@example.com", hours_ago(1)),
+            (folders[3], "<s1@example.org>", None, "spam@example.org", hours_ago(2)),
+            (folders[0], "<old@example.org>", None, "alice@example.org", hours_ago(24 * 60)),
+        ];
+        for (i, (folder_id, message_id, in_reply_to, sender, date)) in emails.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, in_reply_to, subject, sender_address, date, flags)
+                 VALUES (?, ?, ?, ?, ?, 'Hello', ?, ?, '[]')"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(i.to_string())
+            .bind(message_id)
+            .bind(in_reply_to)
+            .bind(sender)
+            .bind(date)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let (app, _dir) = setup_test_app(pool).await;
+
+        let analytics = get_mailbox_analytics(app.handle().clone(), "month".to_string(), None).await.unwrap();
+        assert_eq!(analytics.daily.iter().map(|d| d.received).sum::<i64>(), 2);
+        assert_eq!(analytics.daily.iter().map(|d| d.sent).sum::<i64>(), 1);
+
+        let mut senders: Vec<(String, i64)> = analytics.top_senders.into_iter().map(|s| (s.address, s.count)).collect();
+        senders.sort();
+        assert_eq!(senders, vec![("alice@example.org".to_string(), 1), ("bob@example.org".to_string(), 1)]);
+        assert_eq!(analytics.busiest_hours.iter().map(|h| h.count).sum::<i64>(), 2);
+
+        let minutes = analytics.avg_response_minutes.unwrap();
+        assert!((minutes - 120.0).abs() < 1.0, "{}", minutes);
+
+        assert!(get_mailbox_analytics(app.handle().clone(), "decade".to_string(), None).await.is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use sqlx::SqlitePool;
+use crate::db::settings::Settings;
+
+/// A body as it is cached and indexed, cut down when it went over the size limits.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct CachedBody {
+    pub body_text: Option<String>,
+    pub body_html: Option<String>,
+    pub truncated: bool,
+}
+
+/// The byte limits for cached HTML and text, `usize::MAX` when the setting is 0.
+pub(crate) async fn limits(pool: &SqlitePool) -> (usize, usize) {
+    let settings = Settings::load(pool).await.unwrap_or_default();
+    let bytes = |kb: u32| if kb == 0 { usize::MAX } else { kb as usize * 1024 };
+    (bytes(settings.cached_html_limit_kb), bytes(settings.cached_text_limit_kb))
+}
+
+fn floor_char_boundary(text: &str, max: usize) -> usize {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    end
+}
+
+/// Drops comments and inlined `data:` images, which make up most of an oversized newsletter
+/// without being anything to read or search.
+fn slim_html(html: &str) -> String {
+    // ASCII lowercasing keeps byte offsets identical between `lower` and `html`
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    loop {
+        let comment = lower[pos..].find("<!--").map(|i| pos + i);
+        let data = ["\"data:", "'data:"].iter().filter_map(|p| lower[pos..].find(p).map(|i| pos + i)).min();
+        match (comment, data) {
+            (Some(start), d) if d.is_none_or(|d| start < d) => {
+                out.push_str(&html[pos..start]);
+                pos = lower[start..].find("-->").map(|end| start + end + 3).unwrap_or(html.len());
+            }
+            (_, Some(start)) => {
+                // Keeps the quotes, the attribute is left empty
+                let quote = &html[start..start + 1];
+                out.push_str(&html[pos..=start]);
+                pos = html[start + 1..].find(quote).map(|end| start + 1 + end).unwrap_or(html.len());
+            }
+            _ => break,
+        }
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+/// Cuts the HTML before the tag crossing `max`, browsers close what is left open.
+fn cut_html(html: &str, max: usize) -> &str {
+    let end = floor_char_boundary(html, max);
+    let end = html[..end].rfind('<').unwrap_or(end);
+    &html[..end]
+}
+
+/// Cuts the text at the last whitespace before `max`.
+fn cut_text(text: &str, max: usize) -> &str {
+    let end = floor_char_boundary(text, max);
+    let end = text[..end].rfind(char::is_whitespace).unwrap_or(end);
+    &text[..end]
+}
+
+/// What of a downloaded body goes into the cache and the search index. Bodies within the
+/// limits are kept as they are.
+pub(crate) fn cap(body_text: Option<&str>, body_html: Option<&str>, html_limit: usize, text_limit: usize) -> CachedBody {
+    let mut truncated = false;
+
+    let body_html = body_html.map(|html| {
+        if html.len() <= html_limit {
+            return html.to_string();
+        }
+        truncated = true;
+        let slim = slim_html(html);
+        if slim.len() <= html_limit { slim } else { cut_html(&slim, html_limit).to_string() }
+    });
+    let body_text = body_text.map(|text| {
+        if text.len() <= text_limit {
+            return text.to_string();
+        }
+        truncated = true;
+        cut_text(text, text_limit).to_string()
+    });
+
+    CachedBody { body_text, body_html, truncated }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cap_slims_then_cuts() {
+        let image = format!("<img src=\"data:image/png;base64,{}\" alt=\"logo\">", "A".repeat(4000));
+        let html = format!("<p>Hello</p><!-- {} -->{}<p>Bye</p>", "x".repeat(2000), image);
+
+        let slimmed = cap(None, Some(&html), 1000, 1000);
+        assert!(slimmed.truncated);
+        assert_eq!(slimmed.body_html.as_deref(), Some("<p>Hello</p><img src=\"\" alt=\"logo\"><p>Bye</p>"));
+
+        let cut = cap(Some("Grüße aus Berlin"), Some(&html), 20, 8);
+        assert_eq!(cut.body_html.as_deref(), Some("<p>Hello</p>"));
+        assert_eq!(cut.body_text.as_deref(), Some("Grüße"));
+
+        let whole = cap(Some("short"), Some("<p>short</p>"), 1000, 1000);
+        assert_eq!(whole, CachedBody { body_text: Some("short".to_string()), body_html: Some("<p>short</p>".to_string()), truncated: false });
+    }
+}
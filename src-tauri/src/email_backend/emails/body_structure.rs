@@ -0,0 +1,248 @@
+use imap_client::imap_next::imap_types::body::{Body, BodyStructure, Disposition, SpecificFields};
+use imap_client::imap_next::imap_types::core::{IString, Vec1};
+use imap_client::imap_next::imap_types::fetch::{Part, Section};
+use std::num::NonZeroU32;
+use crate::email_backend::emails::charset;
+
+/// A leaf part of a message as described by BODYSTRUCTURE, addressable by its IMAP section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessagePart {
+    pub section: String,
+    pub mime_type: String,
+    pub charset: Option<String>,
+    pub encoding: String,
+    pub filename: Option<String>,
+    pub size: i64,
+    /// Content-ID without the angle brackets, how the HTML part refers to inline images
+    pub content_id: Option<String>,
+    /// Shown in the body rather than sent along, see `is_inline`
+    pub is_inline: bool,
+}
+
+impl MessagePart {
+    /// BODYSTRUCTURE reports the encoded size, base64 inflates by a third.
+    pub fn decoded_size(&self) -> i64 {
+        if self.encoding == "base64" {
+            self.size * 3 / 4
+        } else {
+            self.size
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MessageLayout {
+    pub text: Option<MessagePart>,
+    pub html: Option<MessagePart>,
+    /// The first `text/calendar` part, inline or attached, of an invite
+    pub calendar: Option<MessagePart>,
+    pub attachments: Vec<MessagePart>,
+    /// RFC822.SIZE of the whole message, when fetched along with the structure
+    pub size: Option<i64>,
+}
+
+fn istring(s: &IString) -> String {
+    String::from_utf8_lossy(s.as_ref()).to_string()
+}
+
+/// Parameters with their values as sent, for `charset::mime_param` to decode.
+fn raw_params(params: &[(IString, IString)]) -> Vec<(String, Vec<u8>)> {
+    params.iter().map(|(k, v)| (istring(k), v.as_ref().to_vec())).collect()
+}
+
+fn param(params: &[(IString, IString)], name: &str) -> Option<String> {
+    params
+        .iter()
+        .find(|(k, _)| istring(k).eq_ignore_ascii_case(name))
+        .map(|(_, v)| istring(v))
+}
+
+/// Signature images and logos the HTML shows in place, as opposed to files sent along: images
+/// marked inline, or carrying a Content-ID without being marked as an attachment.
+pub fn is_inline(mime_type: &str, disposition: Option<&str>, has_content_id: bool) -> bool {
+    if !mime_type.to_ascii_lowercase().starts_with("image/") {
+        return false;
+    }
+    match disposition {
+        Some(kind) if kind.eq_ignore_ascii_case("attachment") => false,
+        Some(kind) if kind.eq_ignore_ascii_case("inline") => true,
+        _ => has_content_id,
+    }
+}
+
+fn leaf(body: &Body, disposition: Option<&Disposition>, section: String) -> (MessagePart, bool) {
+    let (mime_type, is_message) = match &body.specific {
+        SpecificFields::Basic { r#type, subtype } => (format!("{}/{}", istring(r#type), istring(subtype)), false),
+        SpecificFields::Text { subtype, .. } => (format!("text/{}", istring(subtype)), false),
+        SpecificFields::Message { .. } => ("message/rfc822".to_string(), true),
+    };
+
+    let disposition = disposition.and_then(|d| d.disposition.as_ref());
+    let disposition_kind = disposition.map(|(kind, _)| istring(kind));
+    let is_attachment_disposition = disposition_kind.as_deref().is_some_and(|kind| kind.eq_ignore_ascii_case("attachment"));
+    let filename = disposition
+        .and_then(|(_, params)| charset::mime_param(&raw_params(params), "filename"))
+        .or_else(|| charset::mime_param(&raw_params(&body.basic.parameter_list), "name"));
+
+    let content_id = body.basic.id.0.as_ref().map(|id| istring(id).trim_matches(['<', '>']).to_string());
+
+    let part = MessagePart {
+        section,
+        is_inline: is_inline(&mime_type, disposition_kind.as_deref(), content_id.is_some()),
+        mime_type: mime_type.to_lowercase(),
+        charset: param(&body.basic.parameter_list, "charset"),
+        encoding: istring(&body.basic.content_transfer_encoding).to_lowercase(),
+        filename,
+        size: body.basic.size as i64,
+        content_id,
+    };
+    let is_attachment = is_message || is_attachment_disposition || part.filename.is_some();
+    (part, is_attachment)
+}
+
+fn walk(body: &BodyStructure, path: &mut Vec<u32>, layout: &mut MessageLayout) {
+    match body {
+        BodyStructure::Single { body, extension_data } => {
+            // A non-multipart message still has a part "1"
+            let section = if path.is_empty() {
+                "1".to_string()
+            } else {
+                path.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(".")
+            };
+            let disposition = extension_data.as_ref().and_then(|data| data.tail.as_ref());
+            let (part, is_attachment) = leaf(body, disposition, section);
+
+            if layout.calendar.is_none() && matches!(part.mime_type.as_str(), "text/calendar" | "application/ics") {
+                layout.calendar = Some(part.clone());
+            }
+            if is_attachment {
+                layout.attachments.push(part);
+            } else if part.mime_type == "text/plain" && layout.text.is_none() {
+                layout.text = Some(part);
+            } else if part.mime_type == "text/html" && layout.html.is_none() {
+                layout.html = Some(part);
+            } else if !part.mime_type.starts_with("text/") {
+                // Inline images and the like without a name
+                layout.attachments.push(part);
+            }
+        }
+        BodyStructure::Multi { bodies, .. } => {
+            for (i, child) in bodies.as_ref().iter().enumerate() {
+                path.push(i as u32 + 1);
+                walk(child, path, layout);
+                path.pop();
+            }
+        }
+    }
+}
+
+pub fn layout(body: &BodyStructure) -> MessageLayout {
+    let mut layout = MessageLayout::default();
+    walk(body, &mut Vec::new(), &mut layout);
+    layout
+}
+
+/// Turns a section like `1.2` into the IMAP `BODY[1.2]` section.
+pub fn section(path: &str) -> Result<Section<'static>, String> {
+    let parts = path
+        .split('.')
+        .map(|n| n.parse::<u32>().ok().and_then(NonZeroU32::new).ok_or_else(|| format!("Invalid section: {}", path)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let parts = Vec1::try_from(parts).map_err(|_| format!("Invalid section: {}", path))?;
+    Ok(Section::Part(Part(parts)))
+}
+
+fn parse(header: String, body: &[u8]) -> mail_parser::Message<'static> {
+    let mut bytes = header.into_bytes();
+    bytes.extend_from_slice(body);
+    mail_parser::MessageParser::default()
+        .parse(&bytes)
+        .map(|m| m.into_owned())
+        .unwrap_or_default()
+}
+
+/// Undoes the transfer encoding and the charset of a raw text section, then lets mail_parser
+/// read the UTF-8 result behind a minimal header. HTML without a charset label may declare
+/// one in a `<meta>` tag.
+pub fn decode(part: &MessagePart, raw: &[u8]) -> mail_parser::Message<'static> {
+    let bytes = decode_bytes(&part.encoding, raw);
+    let label = match &part.charset {
+        Some(label) => Some(label.clone()),
+        None if part.mime_type == "text/html" => charset::sniff_html_charset(&bytes),
+        None => None,
+    };
+    let text = charset::decode_bytes(&bytes, label.as_deref());
+    parse(
+        format!("Content-Type: {}; charset=\"utf-8\"\r\nContent-Transfer-Encoding: 8bit\r\n\r\n", part.mime_type),
+        text.as_bytes(),
+    )
+}
+
+/// Decoded content of a non-text part.
+pub fn decode_bytes(encoding: &str, raw: &[u8]) -> Vec<u8> {
+    parse(format!("Content-Type: application/octet-stream\r\nContent-Transfer-Encoding: {}\r\n\r\n", encoding), raw)
+        .parts
+        .first()
+        .map(|p| p.contents().to_vec())
+        .unwrap_or_else(|| raw.to_vec())
+}
+
+/// The whole lines of a section fetched only up to `requested` bytes, so a base64 group, a
+/// quoted-printable escape or a multibyte character isn't cut in half.
+pub fn complete_lines(raw: &[u8], requested: usize) -> &[u8] {
+    if raw.len() < requested {
+        return raw;
+    }
+    match raw.iter().rposition(|b| *b == b'\n') {
+        Some(end) => &raw[..=end],
+        None => raw,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_bytes_base64() {
+        assert_eq!(decode_bytes("base64", b"aGVsbG8gd29ybGQ="), b"hello world");
+        assert_eq!(decode_bytes("7bit", b"plain"), b"plain");
+    }
+
+    #[test]
+    fn test_decode_legacy_charsets() {
+        let part = |mime_type: &str, charset: Option<&str>, encoding: &str| MessagePart {
+            section: "1".to_string(),
+            mime_type: mime_type.to_string(),
+            charset: charset.map(str::to_string),
+            encoding: encoding.to_string(),
+            filename: None,
+            size: 0,
+            content_id: None,
+            is_inline: false,
+        };
+
+        let cyrillic = decode(&part("text/plain", Some("windows-1251"), "base64"), b"z/Do4uXyLCDs6PA=");
+        assert_eq!(cyrillic.body_text(0).as_deref(), Some("Привет, мир"));
+
+        let japanese = decode(&part("text/plain", Some("ISO-2022-JP"), "7bit"), b"\x1b$B%F%9%H\x1b(B");
+        assert_eq!(japanese.body_text(0).as_deref(), Some("テスト"));
+
+        let chinese = decode(&part("text/html", None, "8bit"), b"<meta charset=\"gb18030\"><p>\xc4\xe3\xba\xc3</p>");
+        assert!(chinese.body_html(0).is_some_and(|html| html.contains("你好")));
+
+        // A base64 body cut off mid-line by a partial fetch
+        let raw = b"z/Do4uXyLCDs6PA=\r\nz/Do4uXy";
+        assert_eq!(complete_lines(raw, raw.len()), b"z/Do4uXyLCDs6PA=\r\n");
+        assert_eq!(complete_lines(raw, raw.len() + 1), raw);
+    }
+
+    #[test]
+    fn test_is_inline() {
+        assert!(is_inline("image/png", None, true));
+        assert!(is_inline("IMAGE/GIF", Some("inline"), false));
+        assert!(!is_inline("image/png", None, false));
+        assert!(!is_inline("image/jpeg", Some("attachment"), true));
+        assert!(!is_inline("application/pdf", Some("inline"), true));
+    }
+}
@@ -0,0 +1,398 @@
+use crate::db::writer::WritePool;
+use crate::email_backend::emails::commands::view_role_filter;
+use crate::email_backend::emails::events::EmailEvent;
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::i18n;
+use imap_client::imap_next::imap_types::error::ValidationError;
+use imap_client::imap_next::imap_types::flag::Flag;
+use imap_client::imap_next::imap_types::sequence::{Sequence, SequenceSet};
+use log::{info, warn};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroU32;
+use tauri::{Emitter, Manager};
+
+/// UIDs per STORE/MOVE command, keeps command lines well below server limits.
+const BULK_BATCH_SIZE: usize = 500;
+
+/// Payload of the `bulk-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkProgress {
+    pub operation: String,
+    pub processed: usize,
+    pub total: usize,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BulkAction {
+    MarkRead,
+    MoveToRole(&'static str),
+    DeletePermanently,
+}
+
+/// (account_id, folder_id, folder path) -> [(email_id, uid, is_unread)]
+pub(crate) type EmailsByFolder = BTreeMap<(i64, i64, String), Vec<(i64, NonZeroU32, bool)>>;
+
+/// Rows are (email_id, account_id, folder_id, folder path, remote_id, is_unread).
+pub(crate) fn group_by_folder(rows: Vec<(i64, i64, i64, String, String, bool)>) -> EmailsByFolder {
+    let mut groups = EmailsByFolder::new();
+    for (email_id, account_id, folder_id, path, remote_id, is_unread) in rows {
+        if let Some(uid) = remote_id.parse::<u32>().ok().and_then(NonZeroU32::new) {
+            groups.entry((account_id, folder_id, path)).or_default().push((email_id, uid, is_unread));
+        }
+    }
+    groups
+}
+
+async fn target_folder(pool: &SqlitePool, account_id: i64, role: &str) -> Result<(i64, String), String> {
+    sqlx::query_as("SELECT id, path FROM folders WHERE account_id = ? AND role = ?")
+        .bind(account_id)
+        .bind(role)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} folder not found for account {}", role, account_id))
+}
+
+/// Closes an `id IN (` list with the ids of the batch.
+fn push_ids(query: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>, batch: &[(i64, NonZeroU32, bool)]) {
+    let mut separated = query.separated(", ");
+    for (id, _, _) in batch {
+        separated.push_bind(*id);
+    }
+    query.push(")");
+}
+
+/// Applies one batch locally once the server accepted it.
+async fn apply_local(writer: &WritePool, action: BulkAction, source_folder_id: i64, target_folder_id: Option<i64>, batch: &[(i64, NonZeroU32, bool)]) -> Result<(), String> {
+    let unread = batch.iter().filter(|(_, _, unread)| *unread).count() as i64;
+    let mut tx = writer.begin().await?;
+
+    match action {
+        BulkAction::MarkRead => {
+            let mut query = sqlx::QueryBuilder::new("UPDATE emails SET flags = json_insert(COALESCE(flags, '[]'), '$[#]', 'seen') WHERE flags NOT LIKE '%\"seen\"%' AND id IN (");
+            push_ids(&mut query, batch);
+            query.build().execute(&mut *tx).await.map_err(|e| e.to_string())?;
+
+            sqlx::query("UPDATE folders SET unread_count = MAX(0, unread_count - ?) WHERE id = ?")
+                .bind(unread)
+                .bind(source_folder_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        BulkAction::MoveToRole(_) | BulkAction::DeletePermanently => {
+            let mut query = match target_folder_id {
+                Some(target) => {
+                    // The UID belonged to the source folder, see `apply_local_move`
+                    let mut q = sqlx::QueryBuilder::new("UPDATE emails SET folder_id = ");
+                    q.push_bind(target);
+                    q.push(", remote_id = 'moved:' || id WHERE id IN (");
+                    q
+                }
+                None => sqlx::QueryBuilder::new("DELETE FROM emails WHERE id IN ("),
+            };
+            push_ids(&mut query, batch);
+            query.build().execute(&mut *tx).await.map_err(|e| e.to_string())?;
+
+            sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - ?), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
+                .bind(batch.len() as i64)
+                .bind(unread)
+                .bind(source_folder_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            if let Some(target) = target_folder_id {
+                sqlx::query("UPDATE folders SET total_count = total_count + ?, unread_count = unread_count + ? WHERE id = ?")
+                    .bind(batch.len() as i64)
+                    .bind(unread)
+                    .bind(target)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Runs `action` over the selected emails folder by folder, one UID set per batch. Returns the ids processed.
+pub(crate) async fn run_bulk<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, operation: &str, action: BulkAction, groups: EmailsByFolder) -> Result<Vec<i64>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let engine = app_handle.state::<SyncEngine<R>>();
+    let total: usize = groups.values().map(|v| v.len()).sum();
+    let mut processed = Vec::new();
+
+    let emit = |processed: usize, done: bool| {
+        let _ = app_handle.emit("bulk-progress", BulkProgress { operation: operation.to_string(), processed, total, done });
+    };
+    emit(0, total == 0);
+
+    for ((account_id, folder_id, path), emails) in groups {
+        let target = match action {
+            BulkAction::MoveToRole(role) => Some(target_folder(&pool, account_id, role).await?),
+            _ => None,
+        };
+        if target.as_ref().map(|(id, _)| *id == folder_id).unwrap_or(false) {
+            continue;
+        }
+
+        let context = engine.get_context(account_id).await?;
+
+        for batch in emails.chunks(BULK_BATCH_SIZE) {
+            let uids: SequenceSet = batch
+                .iter()
+                .map(|(_, uid, _)| Sequence::from(*uid))
+                .collect::<Vec<_>>()
+                .try_into()
+                .map_err(|e: ValidationError| e.to_string())?;
+
+            let mut client = context.client().await;
+            engine.with_timeout(account_id, async {
+                client.select_mailbox(&path).await.map_err(|e| e.to_string())?;
+                match (action, &target) {
+                    (BulkAction::MarkRead, _) => {
+                        client.add_flags_silently(uids, [Flag::Seen]).await.map_err(|e| e.to_string())?;
+                    }
+                    (BulkAction::MoveToRole(_), Some((_, target_path))) => {
+                        client.move_messages(uids, target_path).await.map_err(|e| e.to_string())?;
+                    }
+                    (BulkAction::DeletePermanently, _) => {
+                        // A plain EXPUNGE would also remove whatever else is flagged \Deleted in the folder
+                        if !client.ext_uidplus_supported() {
+                            return Err(i18n::t("error.uidplus_missing", &[]));
+                        }
+                        client.add_deleted_flag_silently(uids.clone()).await.map_err(|e| e.to_string())?;
+                        client.expunge_messages(uids).await.map_err(|e| e.to_string())?;
+                    }
+                    _ => {}
+                }
+                Ok::<_, String>(())
+            }).await??;
+            drop(client);
+
+            apply_local(&app_handle.state::<WritePool>(), action, folder_id, target.as_ref().map(|(id, _)| *id), batch).await?;
+
+            let ids: Vec<i64> = batch.iter().map(|(id, _, _)| *id).collect();
+            processed.extend_from_slice(&ids);
+            let _ = app_handle.emit("emails-updated", match action {
+                BulkAction::MarkRead => EmailEvent::UpdatedBulk { ids, flags: None },
+                _ => EmailEvent::RemovedBulk { ids },
+            });
+
+            emit(processed.len(), false);
+        }
+    }
+
+    emit(processed.len(), true);
+    info!("{}: processed {}/{} emails", operation, processed.len(), total);
+    Ok(processed)
+}
+
+pub(crate) async fn emails_by_id(pool: &SqlitePool, ids: &[i64]) -> Result<EmailsByFolder, String> {
+    let mut rows = Vec::new();
+    for chunk in ids.chunks(BULK_BATCH_SIZE) {
+        let mut query = sqlx::QueryBuilder::new(
+            "SELECT e.id, e.account_id, e.folder_id, f.path, e.remote_id, e.flags NOT LIKE '%\"seen\"%' as is_unread
+             FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id IN ("
+        );
+        let mut separated = query.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        query.push(")");
+        let chunk_rows: Vec<(i64, i64, i64, String, String, bool)> = query
+            .build_query_as()
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        rows.extend(chunk_rows);
+    }
+    Ok(group_by_folder(rows))
+}
+
+/// Drops the emails from the local database only, leaving the server untouched. Returns the ids removed.
+pub(crate) async fn delete_local(writer: &WritePool, groups: EmailsByFolder) -> Result<Vec<i64>, String> {
+    let mut removed = Vec::new();
+    for ((_, folder_id, _), emails) in groups {
+        for batch in emails.chunks(BULK_BATCH_SIZE) {
+            apply_local(writer, BulkAction::DeletePermanently, folder_id, None, batch).await?;
+            removed.extend(batch.iter().map(|(id, _, _)| *id));
+        }
+    }
+    Ok(removed)
+}
+
+/// Swaps the `moved:<id>` placeholders of emails moved in the app since their folder was last
+/// synced for the UIDs a refresh of the folder finds, rather than leaving them out of the batch.
+async fn resolve_moved<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, mut rows: Vec<(i64, i64, i64, String, String, bool)>) -> Result<Vec<(i64, i64, i64, String, String, bool)>, String> {
+    let folders: BTreeSet<(i64, i64)> = rows.iter()
+        .filter(|(_, _, _, _, remote_id, _)| remote_id.starts_with("moved:"))
+        .map(|(_, account_id, folder_id, _, _, _)| (*account_id, *folder_id))
+        .collect();
+    if folders.is_empty() {
+        return Ok(rows);
+    }
+    for (account_id, folder_id) in folders {
+        if let Err(e) = SyncEngine::refresh_folder(app_handle, account_id, folder_id).await {
+            warn!("Failed to refresh folder {} for moved emails: {}", folder_id, e);
+        }
+    }
+
+    let pool = app_handle.state::<SqlitePool>();
+    for (email_id, _, _, _, remote_id, _) in rows.iter_mut().filter(|row| row.4.starts_with("moved:")) {
+        if let Some(current) = sqlx::query_scalar::<_, String>("SELECT remote_id FROM emails WHERE id = ?")
+            .bind(*email_id)
+            .fetch_optional(&*pool)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            *remote_id = current;
+        }
+    }
+    Ok(rows)
+}
+
+async fn emails_in_view<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, view: &str, account_id: Option<i64>, only_read: bool) -> Result<EmailsByFolder, String> {
+    let condition = view_role_filter(Some(view), "f.role", "e").ok_or_else(|| format!("Unknown view: {}", view))?;
+
+    let mut query = sqlx::QueryBuilder::new(
+        "SELECT e.id, e.account_id, e.folder_id, f.path, e.remote_id, e.flags NOT LIKE '%\"seen\"%' as is_unread
+         FROM emails e JOIN folders f ON e.folder_id = f.id WHERE "
+    );
+    query.push(condition);
+    if let Some(aid) = account_id {
+        query.push(" AND e.account_id = ");
+        query.push_bind(aid);
+    }
+    if only_read {
+        query.push(" AND e.flags LIKE '%\"seen\"%'");
+    }
+
+    let rows: Vec<(i64, i64, i64, String, String, bool)> = query
+        .build_query_as()
+        .fetch_all(&*app_handle.state::<SqlitePool>())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(group_by_folder(resolve_moved(app_handle, rows).await?))
+}
+
+#[tauri::command]
+pub async fn archive_all_read<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, view: String, account_id: Option<i64>) -> Result<usize, String> {
+    let groups = emails_in_view(&app_handle, &view, account_id, true).await?;
+    Ok(run_bulk(&app_handle, "archive_all_read", BulkAction::MoveToRole("archive"), groups).await?.len())
+}
+
+#[tauri::command]
+pub async fn mark_folder_read<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, folder_id: i64) -> Result<usize, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+
+    let rows: Vec<(i64, i64, i64, String, String, bool)> = sqlx::query_as(
+        "SELECT e.id, e.account_id, e.folder_id, f.path, e.remote_id, 1 as is_unread
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE e.folder_id = ? AND e.flags NOT LIKE '%\"seen\"%'"
+    )
+    .bind(folder_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let rows = resolve_moved(&app_handle, rows).await?;
+    Ok(run_bulk(&app_handle, "mark_folder_read", BulkAction::MarkRead, group_by_folder(rows)).await?.len())
+}
+
+/// Moves everything in the view to the trash, or deletes it for good when the view is the trash itself.
+#[tauri::command]
+pub async fn delete_all_in_view<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, view: String, account_id: Option<i64>) -> Result<usize, String> {
+    let groups = emails_in_view(&app_handle, &view, account_id, false).await?;
+    let action = if view == "trash" { BulkAction::DeletePermanently } else { BulkAction::MoveToRole("trash") };
+    Ok(run_bulk(&app_handle, "delete_all_in_view", action, groups).await?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{add_mock_account, mock_message, setup_test_app, setup_test_db, MockMailServer};
+
+    async fn email_id(pool: &SqlitePool, message_id: &str) -> i64 {
+        sqlx::query_scalar("SELECT id FROM emails WHERE message_id = ?")
+            .bind(message_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delete_permanently_leaves_other_deleted_mail() {
+        let server = MockMailServer::start().await;
+        let doomed = server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Delete me", "<doomed@example.com>", "Bye"), &[]);
+        // Flagged by another client that hasn't expunged yet
+        let pending = server.add_message("INBOX", &mock_message("Bob <bob@example.com>", "Deleted elsewhere", "<pending@example.com>", "Hi"), &["\\Deleted"]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let pool = app.state::<SqlitePool>();
+        let id = email_id(&pool, "<doomed@example.com>").await;
+        let groups = emails_by_id(&pool, &[id]).await.unwrap();
+        assert_eq!(run_bulk(app.handle(), "test", BulkAction::DeletePermanently, groups).await.unwrap(), vec![id]);
+
+        let uids: Vec<u32> = server.state.lock().unwrap().mailbox("INBOX").unwrap().messages.iter().map(|m| m.uid).collect();
+        assert!(!uids.contains(&doomed));
+        assert_eq!(uids, vec![pending]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_permanently_needs_uidplus() {
+        let server = MockMailServer::start().await;
+        server.state.lock().unwrap().without_uidplus = true;
+        let uid = server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Keep me", "<kept@example.com>", "Hi"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let pool = app.state::<SqlitePool>();
+        let id = email_id(&pool, "<kept@example.com>").await;
+        let groups = emails_by_id(&pool, &[id]).await.unwrap();
+        assert!(run_bulk(app.handle(), "test", BulkAction::DeletePermanently, groups).await.is_err());
+
+        // Nothing was flagged or dropped, on the server or locally
+        assert!(server.flags("INBOX", uid).is_empty());
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE id = ?").bind(id).fetch_one(&*pool).await.unwrap();
+        assert_eq!(left, 1);
+    }
+
+    #[tokio::test]
+    async fn test_moved_mail_gets_its_new_uid_from_the_next_sync() {
+        let server = MockMailServer::start().await;
+        server.add_mailbox("Trash", Some("\\Trash"));
+        // Holds UID 1 in the trash, the same UID the moved message had in the inbox
+        server.add_message("Trash", &mock_message("Bob <bob@example.com>", "Old trash", "<old@example.com>", "Hi"), &[]);
+        server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Move me", "<moved@example.com>", "Hi"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let pool = app.state::<SqlitePool>();
+        let id = email_id(&pool, "<moved@example.com>").await;
+        let groups = emails_by_id(&pool, &[id]).await.unwrap();
+        run_bulk(app.handle(), "test", BulkAction::MoveToRole("trash"), groups).await.expect("Failed to move");
+
+        let remote_id: String = sqlx::query_scalar("SELECT remote_id FROM emails WHERE id = ?").bind(id).fetch_one(&*pool).await.unwrap();
+        assert_eq!(remote_id, format!("moved:{}", id));
+
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+        let rows: Vec<(i64, String)> = sqlx::query_as("SELECT id, remote_id FROM emails WHERE message_id = '<moved@example.com>'")
+            .fetch_all(&*pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, vec![(id, "2".to_string())]);
+    }
+}
@@ -0,0 +1,721 @@
+use crate::email_backend::sync::links;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use mail_parser::MimeHeaders;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use tauri::Manager;
+
+/// Video meeting services whose links are offered as the join link of an event.
+const MEETING_HOSTS: &[&str] = &[
+    "zoom.us", "meet.google.com", "teams.microsoft.com", "teams.live.com", "webex.com",
+    "whereby.com", "gotomeeting.com", "meet.jit.si", "chime.aws", "bluejeans.com",
+];
+
+/// Outlook names zones the Windows way, these are the ones invites come with most.
+const WINDOWS_ZONES: &[(&str, &str)] = &[
+    ("UTC", "UTC"),
+    ("GMT Standard Time", "Europe/London"),
+    ("W. Europe Standard Time", "Europe/Berlin"),
+    ("Romance Standard Time", "Europe/Paris"),
+    ("Central Europe Standard Time", "Europe/Budapest"),
+    ("Central European Standard Time", "Europe/Warsaw"),
+    ("E. Europe Standard Time", "Europe/Chisinau"),
+    ("FLE Standard Time", "Europe/Kiev"),
+    ("Eastern Standard Time", "America/New_York"),
+    ("Central Standard Time", "America/Chicago"),
+    ("Mountain Standard Time", "America/Denver"),
+    ("Pacific Standard Time", "America/Los_Angeles"),
+    ("India Standard Time", "Asia/Kolkata"),
+    ("China Standard Time", "Asia/Shanghai"),
+    ("Tokyo Standard Time", "Asia/Tokyo"),
+    ("AUS Eastern Standard Time", "Australia/Sydney"),
+];
+
+/// Repetitions of a series walked through before giving up, bounds a daily series started long ago.
+const MAX_PERIODS: i64 = 100_000;
+/// Largest INTERVAL taken as given, far beyond any date a series could reach.
+const MAX_INTERVAL: i64 = 1_000_000;
+
+/// One VEVENT of an invite, times in UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InviteEvent {
+    pub uid: String,
+    /// Empty for an event or a whole series, the original start of a single changed occurrence
+    pub recurrence_id: String,
+    pub sequence: i64,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub organizer: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub all_day: bool,
+    pub status: &'static str,
+    pub rrule: Option<String>,
+    /// The zone of the start, which the series repeats in
+    pub tzid: Option<String>,
+    pub exdates: Vec<DateTime<Utc>>,
+    pub join_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct AgendaEvent {
+    pub id: i64,
+    /// The invite the event came from
+    pub email_id: Option<i64>,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub organizer: Option<String>,
+    /// This occurrence's start, for a recurring event
+    pub start_at: String,
+    pub end_at: Option<String>,
+    pub all_day: bool,
+    pub status: String,
+    pub join_url: Option<String>,
+    pub recurring: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct EventRow {
+    id: i64,
+    email_id: Option<i64>,
+    uid: String,
+    recurrence_id: String,
+    summary: Option<String>,
+    location: Option<String>,
+    organizer: Option<String>,
+    start_at: String,
+    end_at: Option<String>,
+    all_day: bool,
+    status: String,
+    rrule: Option<String>,
+    tzid: Option<String>,
+    exdates: Option<String>,
+    join_url: Option<String>,
+}
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_stored(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time).ok().map(|time| time.with_timezone(&Utc))
+}
+
+/// Joins folded lines, a line starting with a space or a tab continues the one before.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        if let (Some(rest), Some(last)) = (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            last.push_str(rest);
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    lines
+}
+
+/// Splits on `separator` outside of double quotes, parameter values may hold `:` and `;`.
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == separator && !in_quotes {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// `NAME;PARAM=value;...:value`
+fn parse_property(line: &str) -> Option<Property> {
+    let mut parts = split_unquoted(line, ':');
+    if parts.len() < 2 {
+        return None;
+    }
+    let head = parts.remove(0);
+    let value = parts.join(":");
+
+    let mut head = split_unquoted(head, ';').into_iter();
+    let name = head.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = head
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Some(Property { name, params, value })
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => {}
+        }
+    }
+    text
+}
+
+/// The zone of a TZID, also for Windows names and path-like ids such as
+/// `/mozilla.org/20050126_1/Europe/Berlin`.
+fn zone(tzid: &str) -> Option<Tz> {
+    let tzid = tzid.trim();
+    if let Some((_, iana)) = WINDOWS_ZONES.iter().find(|(windows, _)| windows.eq_ignore_ascii_case(tzid)) {
+        return iana.parse().ok();
+    }
+    std::iter::once(tzid)
+        .chain(tzid.match_indices('/').map(|(i, _)| &tzid[i + 1..]))
+        .find_map(|name| name.parse::<Tz>().ok())
+}
+
+/// A DATE or DATE-TIME value in UTC, and whether it was a date alone.
+fn parse_time(value: &str, tzid: Option<&str>) -> Option<(DateTime<Utc>, bool)> {
+    let value = value.trim();
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((date.and_hms_opt(0, 0, 0)?.and_utc(), true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        return Some((NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?.and_utc(), false));
+    }
+
+    let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    // Floating times, and zones nothing is known about, are taken as the user's own
+    let time = match tzid.and_then(zone) {
+        Some(tz) => tz.from_local_datetime(&local).earliest()?.with_timezone(&Utc),
+        None => Local.from_local_datetime(&local).earliest()?.with_timezone(&Utc),
+    };
+    Some((time, false))
+}
+
+/// A DURATION like `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match c {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    _ => Duration::seconds(n),
+                };
+            }
+            _ => return None,
+        }
+    }
+    Some(if negative { -total } else { total })
+}
+
+/// The first link to a video meeting in `text`.
+pub fn join_url(text: &str) -> Option<String> {
+    links::extract_links(Some(text), None)
+        .into_iter()
+        .find(|link| MEETING_HOSTS.iter().any(|host| link.domain == *host || link.domain.ends_with(&format!(".{}", host))))
+        .map(|link| link.url)
+}
+
+fn event_from(props: &[Property], method: &str) -> Option<InviteEvent> {
+    let get = |name: &str| props.iter().find(|p| p.name == name);
+    let text = |name: &str| get(name).map(|p| unescape(&p.value).trim().to_string()).filter(|v| !v.is_empty());
+    let time = |p: &Property| parse_time(&p.value, p.param("TZID"));
+
+    let start_prop = get("DTSTART")?;
+    let (start, all_day) = time(start_prop)?;
+    let end = match get("DTEND") {
+        Some(p) => time(p).map(|(end, _)| end),
+        None => get("DURATION").and_then(|p| parse_duration(&p.value)).map(|length| start + length),
+    };
+
+    let status = match (method, text("STATUS").map(|s| s.to_ascii_uppercase()).as_deref()) {
+        ("CANCEL", _) | (_, Some("CANCELLED")) => "cancelled",
+        (_, Some("TENTATIVE")) => "tentative",
+        _ => "confirmed",
+    };
+
+    let exdates = props.iter()
+        .filter(|p| p.name == "EXDATE")
+        .flat_map(|p| p.value.split(',').filter_map(|v| parse_time(v, p.param("TZID"))).map(|(t, _)| t).collect::<Vec<_>>())
+        .collect();
+
+    // Conferencing properties first, then wherever organizers paste the link
+    let join_url = ["X-GOOGLE-CONFERENCE", "X-MICROSOFT-SKYPETEAMSMEETINGURL", "URL", "LOCATION", "DESCRIPTION"]
+        .iter()
+        .filter_map(|name| text(name))
+        .find_map(|value| join_url(&value));
+
+    Some(InviteEvent {
+        uid: text("UID")?,
+        recurrence_id: get("RECURRENCE-ID").and_then(time).map(|(t, _)| format_time(t)).unwrap_or_default(),
+        sequence: text("SEQUENCE").and_then(|s| s.parse().ok()).unwrap_or(0),
+        summary: text("SUMMARY"),
+        location: text("LOCATION"),
+        description: text("DESCRIPTION"),
+        organizer: get("ORGANIZER").map(|p| {
+            p.param("CN").map(str::to_string).unwrap_or_else(|| {
+                let value = p.value.trim();
+                value.get(..7).filter(|s| s.eq_ignore_ascii_case("mailto:")).map(|_| value[7..].to_string()).unwrap_or_else(|| value.to_string())
+            })
+        }),
+        start,
+        end,
+        all_day,
+        status,
+        rrule: text("RRULE"),
+        tzid: start_prop.param("TZID").and_then(zone).map(|tz| tz.name().to_string()),
+        exdates,
+        join_url,
+    })
+}
+
+/// The events of an iCalendar invite. Replies from attendees carry no event of their own.
+pub fn parse_invite(ics: &str) -> Vec<InviteEvent> {
+    let mut method = String::new();
+    let mut events = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+    // Components inside the VEVENT, such as VALARM, whose properties are not the event's
+    let mut nested = 0usize;
+
+    for line in unfold(ics) {
+        let Some(prop) = parse_property(&line) else { continue };
+        let (name, value) = (prop.name.clone(), prop.value.trim().to_ascii_uppercase());
+
+        match (name.as_str(), value.as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(Vec::new()),
+            ("END", "VEVENT") => events.extend(current.take().and_then(|props| event_from(&props, &method))),
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", _) if current.is_some() => nested = nested.saturating_sub(1),
+            ("METHOD", method_value) if current.is_none() => method = method_value.to_string(),
+            _ => {
+                if let (Some(props), 0) = (current.as_mut(), nested) {
+                    props.push(prop);
+                }
+            }
+        }
+    }
+
+    if method == "REPLY" {
+        return Vec::new();
+    }
+    events
+}
+
+/// The calendar part of a fully fetched message.
+pub fn invite_in(message: &mail_parser::Message) -> Option<String> {
+    message.parts.iter()
+        .find(|part| {
+            part.content_type()
+                .map(|ct| {
+                    let subtype = ct.subtype().unwrap_or_default();
+                    (ct.ctype().eq_ignore_ascii_case("text") && subtype.eq_ignore_ascii_case("calendar"))
+                        || (ct.ctype().eq_ignore_ascii_case("application") && subtype.eq_ignore_ascii_case("ics"))
+                })
+                .unwrap_or(false)
+        })
+        .map(|part| String::from_utf8_lossy(part.contents()).to_string())
+}
+
+/// Stores the events of an invite received in `email_id`. An update only replaces what is known
+/// when its SEQUENCE is at least as recent; `body_text` is searched for a join link the invite lacks.
+pub async fn save_invite(pool: &SqlitePool, email_id: i64, ics: &str, body_text: Option<&str>) -> Result<usize, String> {
+    let events = parse_invite(ics);
+    if events.is_empty() {
+        return Ok(0);
+    }
+    let account_id: i64 = sqlx::query_scalar("SELECT account_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let body_join_url = body_text.and_then(join_url);
+
+    for event in &events {
+        let exdates = (!event.exdates.is_empty())
+            .then(|| event.exdates.iter().map(|t| format_time(*t)).collect::<Vec<_>>().join(","));
+
+        sqlx::query(
+            "INSERT INTO events (account_id, email_id, uid, recurrence_id, sequence, summary, location, description, organizer,
+                                 start_at, end_at, all_day, status, rrule, tzid, exdates, join_url)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(account_id, uid, recurrence_id) DO UPDATE SET
+                email_id = excluded.email_id,
+                sequence = excluded.sequence,
+                summary = COALESCE(excluded.summary, events.summary),
+                location = COALESCE(excluded.location, events.location),
+                description = COALESCE(excluded.description, events.description),
+                organizer = COALESCE(excluded.organizer, events.organizer),
+                start_at = excluded.start_at,
+                end_at = COALESCE(excluded.end_at, events.end_at),
+                all_day = excluded.all_day,
+                status = excluded.status,
+                rrule = COALESCE(excluded.rrule, events.rrule),
+                tzid = excluded.tzid,
+                exdates = COALESCE(excluded.exdates, events.exdates),
+                join_url = COALESCE(excluded.join_url, events.join_url),
+                updated_at = CURRENT_TIMESTAMP
+             WHERE excluded.sequence >= events.sequence"
+        )
+        .bind(account_id)
+        .bind(email_id)
+        .bind(&event.uid)
+        .bind(&event.recurrence_id)
+        .bind(event.sequence)
+        .bind(&event.summary)
+        .bind(&event.location)
+        .bind(&event.description)
+        .bind(&event.organizer)
+        .bind(format_time(event.start))
+        .bind(event.end.map(format_time))
+        .bind(event.all_day)
+        .bind(event.status)
+        .bind(&event.rrule)
+        .bind(&event.tzid)
+        .bind(exdates)
+        .bind(event.join_url.clone().or_else(|| body_join_url.clone()))
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(events.len())
+}
+
+struct Rule {
+    freq: String,
+    interval: i64,
+    count: Option<i64>,
+    until: Option<DateTime<Utc>>,
+    /// (ordinal, weekday), the ordinal is 0 for every such weekday of the period
+    by_day: Vec<(i64, Weekday)>,
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    Some(match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn parse_rule(rule: &str) -> Option<Rule> {
+    let mut parsed = Rule { freq: String::new(), interval: 1, count: None, until: None, by_day: Vec::new() };
+    for part in rule.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.trim().to_ascii_uppercase().as_str() {
+            "FREQ" => parsed.freq = value.trim().to_ascii_uppercase(),
+            "INTERVAL" => parsed.interval = value.trim().parse::<i64>().ok()?.clamp(1, MAX_INTERVAL),
+            "COUNT" => parsed.count = value.trim().parse().ok(),
+            "UNTIL" => parsed.until = parse_time(value, None).map(|(t, _)| t),
+            "BYDAY" => {
+                parsed.by_day = value.split(',')
+                    .filter_map(|day| {
+                        let day = day.trim().to_ascii_uppercase();
+                        if !day.is_ascii() {
+                            return None;
+                        }
+                        let (ordinal, weekday) = day.split_at(day.len().checked_sub(2)?);
+                        Some((ordinal.parse().unwrap_or(0), parse_weekday(weekday)?))
+                    })
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+    matches!(parsed.freq.as_str(), "DAILY" | "WEEKLY" | "MONTHLY" | "YEARLY").then_some(parsed)
+}
+
+/// The `n`th (from the end when negative) `weekday` of a month.
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: i64) -> Option<NaiveDate> {
+    if n > 0 {
+        return NaiveDate::from_weekday_of_month_opt(year, month, weekday, u8::try_from(n).ok()?);
+    }
+    let (next_year, next_month) = if month == 12 { (year.checked_add(1)?, 1) } else { (year, month + 1) };
+    let last = NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()?;
+    let back = (7 + last.weekday().num_days_from_monday() as i64 - weekday.num_days_from_monday() as i64) % 7;
+    let date = last - Duration::days(back + 7 * (-n - 1));
+    (date.month() == month).then_some(date)
+}
+
+/// Starts of the `period`th repetition of a series starting at `first`, in order. None once the
+/// repetition lies beyond the dates that can be represented, which ends the series.
+fn period_starts(first: NaiveDateTime, rule: &Rule, period: i64) -> Option<Vec<NaiveDateTime>> {
+    let step = period.checked_mul(rule.interval)?;
+    let time = first.time();
+    let date = first.date();
+
+    let dates: Vec<NaiveDate> = match rule.freq.as_str() {
+        "DAILY" => vec![date.checked_add_signed(Duration::try_days(step)?)?],
+        "WEEKLY" if !rule.by_day.is_empty() => {
+            let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+            let week = monday.checked_add_signed(Duration::try_weeks(step)?)?;
+            let mut days: Vec<NaiveDate> = rule.by_day.iter()
+                .filter_map(|(_, day)| week.checked_add_signed(Duration::days(day.num_days_from_monday() as i64)))
+                .collect();
+            days.sort();
+            days
+        }
+        "WEEKLY" => vec![date.checked_add_signed(Duration::try_weeks(step)?)?],
+        "MONTHLY" => {
+            let months = (date.year() as i64 * 12 + date.month0() as i64).checked_add(step)?;
+            let (year, month) = (i32::try_from(months / 12).ok()?, (months % 12) as u32 + 1);
+            let mut days: Vec<NaiveDate> = if rule.by_day.is_empty() {
+                NaiveDate::from_ymd_opt(year, month, date.day()).into_iter().collect()
+            } else {
+                rule.by_day.iter()
+                    .filter_map(|(n, day)| nth_weekday(year, month, *day, if *n == 0 { 1 } else { *n }))
+                    .collect()
+            };
+            days.sort();
+            days
+        }
+        _ => {
+            let year = date.year().checked_add(i32::try_from(step).ok()?)?;
+            NaiveDate::from_ymd_opt(year, date.month(), date.day()).into_iter().collect()
+        }
+    };
+
+    Some(dates.into_iter().map(|d| d.and_time(time)).filter(|start| *start >= first).collect())
+}
+
+/// Starts of a series' occurrences between `from` and `to`, repeating in `zone` so the local time
+/// stays put across daylight saving changes.
+fn occurrences<Z: TimeZone>(start: DateTime<Utc>, rule: &str, exdates: &[DateTime<Utc>], from: DateTime<Utc>, to: DateTime<Utc>, zone: &Z) -> Vec<DateTime<Utc>> {
+    let Some(rule) = parse_rule(rule) else { return vec![start] };
+    let first = start.with_timezone(zone).naive_local();
+    let mut found = Vec::new();
+    let mut seen = 0;
+
+    for period in 0..MAX_PERIODS {
+        let Some(starts) = period_starts(first, &rule, period) else { break };
+        for local in starts {
+            let Some(occurrence) = zone.from_local_datetime(&local).earliest().map(|t| t.with_timezone(&Utc)) else { continue };
+            seen += 1;
+            if occurrence > to || rule.until.is_some_and(|until| occurrence > until) || rule.count.is_some_and(|count| seen > count) {
+                return found;
+            }
+            if occurrence >= from && !exdates.contains(&occurrence) {
+                found.push(occurrence);
+            }
+        }
+    }
+    found
+}
+
+/// Expands stored events into the occurrences overlapping `from..to`, changed and cancelled
+/// occurrences of a series replacing the series' own.
+fn agenda(rows: Vec<EventRow>, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<AgendaEvent> {
+    let changed: HashSet<(String, String)> = rows.iter()
+        .filter(|row| !row.recurrence_id.is_empty())
+        .map(|row| (row.uid.clone(), row.recurrence_id.clone()))
+        .collect();
+    let mut agenda = Vec::new();
+
+    for row in rows {
+        let Some(start) = parse_stored(&row.start_at) else { continue };
+        if row.status == "cancelled" {
+            continue;
+        }
+        let length = row.end_at.as_deref().and_then(parse_stored).map(|end| end - start).unwrap_or_else(Duration::zero);
+        let exdates: Vec<DateTime<Utc>> = row.exdates.as_deref().unwrap_or_default().split(',').filter_map(parse_stored).collect();
+
+        let series = row.recurrence_id.is_empty() && row.rrule.is_some();
+        let starts = match &row.rrule {
+            Some(rule) if series && row.all_day => occurrences(start, rule, &exdates, from - length, to, &Utc),
+            Some(rule) if series => match row.tzid.as_deref().and_then(zone) {
+                Some(tz) => occurrences(start, rule, &exdates, from - length, to, &tz),
+                None => occurrences(start, rule, &exdates, from - length, to, &Local),
+            },
+            _ => vec![start],
+        };
+
+        for start in starts {
+            if series && changed.contains(&(row.uid.clone(), format_time(start))) {
+                continue;
+            }
+            let end = start + length;
+            if start >= to || (end <= from && start < from) {
+                continue;
+            }
+            agenda.push(AgendaEvent {
+                id: row.id,
+                email_id: row.email_id,
+                summary: row.summary.clone(),
+                location: row.location.clone(),
+                organizer: row.organizer.clone(),
+                start_at: format_time(start),
+                end_at: row.end_at.as_ref().map(|_| format_time(end)),
+                all_day: row.all_day,
+                status: row.status.clone(),
+                join_url: row.join_url.clone(),
+                recurring: row.rrule.is_some() || !row.recurrence_id.is_empty(),
+            });
+        }
+    }
+
+    agenda.sort_by(|a, b| a.start_at.cmp(&b.start_at));
+    agenda
+}
+
+/// Events from received invites happening within the next `range_days` days (7 by default),
+/// including those under way, for the agenda sidebar.
+#[tauri::command]
+pub async fn get_upcoming_events<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, range_days: Option<i64>) -> Result<Vec<AgendaEvent>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let from = Utc::now();
+    let to = from + Duration::days(range_days.unwrap_or(7).clamp(1, 366));
+
+    let rows: Vec<EventRow> = sqlx::query_as(
+        "SELECT id, email_id, uid, recurrence_id, summary, location, organizer, start_at, end_at, all_day, status, rrule, tzid, exdates, join_url
+         FROM events
+         WHERE start_at < ? OR recurrence_id != ''"
+    )
+    .bind(format_time(to))
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(agenda(rows, from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        parse_stored(time).unwrap()
+    }
+
+    #[test]
+    fn test_parse_invite() {
+        let ics = "BEGIN:VCALENDAR\r\nMETHOD:REQUEST\r\nBEGIN:VTIMEZONE\r\nTZID:W. Europe Standard Time\r\nEND:VTIMEZONE\r\n\
+BEGIN:VEVENT\r\nUID:abc-123\r\nSEQUENCE:2\r\nSUMMARY:Planning\\, Q3\r\n\
+DTSTART;TZID=\"W. Europe Standard Time\":20240703T100000\r\nDURATION:PT1H30M\r\n\
+ORGANIZER;CN=\"Doe, Jane\":mailto:jane@example.com\r\n\
+DESCRIPTION:Join: https://example.zoom.us/j/123?pwd=x\\nAgenda in the do\r\n c\r\n\
+BEGIN:VALARM\r\nDESCRIPTION:Reminder\r\nEND:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+        let events = parse_invite(ics);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.uid, "abc-123");
+        assert_eq!(event.sequence, 2);
+        assert_eq!(event.summary.as_deref(), Some("Planning, Q3"));
+        assert_eq!(event.organizer.as_deref(), Some("Doe, Jane"));
+        assert_eq!(event.start, utc("2024-07-03T08:00:00Z"));
+        assert_eq!(event.end, Some(utc("2024-07-03T09:30:00Z")));
+        assert_eq!(event.description.as_deref(), Some("Join: https://example.zoom.us/j/123?pwd=x\nAgenda in the doc"));
+        assert_eq!(event.join_url.as_deref(), Some("https://example.zoom.us/j/123?pwd=x"));
+        assert_eq!(event.status, "confirmed");
+        assert_eq!(event.tzid.as_deref(), Some("Europe/Berlin"));
+
+        let cancel = ics.replace("METHOD:REQUEST", "METHOD:CANCEL");
+        assert_eq!(parse_invite(&cancel)[0].status, "cancelled");
+        assert!(parse_invite(&ics.replace("METHOD:REQUEST", "METHOD:REPLY")).is_empty());
+    }
+
+    #[test]
+    fn test_recurring_events_expand_within_range() {
+        let start = utc("2024-01-01T09:00:00Z"); // a Monday
+        let from = utc("2024-03-01T00:00:00Z");
+        let to = utc("2024-03-15T00:00:00Z");
+
+        let weekly = occurrences(start, "FREQ=WEEKLY;BYDAY=MO,TH", &[utc("2024-03-04T09:00:00Z")], from, to, &Utc);
+        assert_eq!(weekly, vec![utc("2024-03-07T09:00:00Z"), utc("2024-03-11T09:00:00Z"), utc("2024-03-14T09:00:00Z")]);
+
+        let monthly = occurrences(start, "FREQ=MONTHLY;BYDAY=-1FR", &[], from, utc("2024-05-01T00:00:00Z"), &Utc);
+        assert_eq!(monthly, vec![utc("2024-03-29T09:00:00Z"), utc("2024-04-26T09:00:00Z")]);
+
+        assert!(occurrences(start, "FREQ=DAILY;COUNT=10", &[], from, to, &Utc).is_empty());
+    }
+
+    #[test]
+    fn test_series_repeat_in_their_zone_and_stop_at_the_end_of_time() {
+        let new_york = zone("America/New_York").unwrap();
+        let start = utc("2024-03-04T14:00:00Z"); // 09:00 EST
+        let weekly = occurrences(start, "FREQ=WEEKLY", &[], start, utc("2024-03-12T00:00:00Z"), &new_york);
+        assert_eq!(weekly, vec![start, utc("2024-03-11T13:00:00Z")]);
+
+        for rule in ["FREQ=DAILY;INTERVAL=1000000000", "FREQ=WEEKLY;BYDAY=MO;INTERVAL=1000000000", "FREQ=MONTHLY;INTERVAL=1000000000", "FREQ=YEARLY;INTERVAL=1000000000"] {
+            assert_eq!(occurrences(start, rule, &[], start, utc("2100-01-01T00:00:00Z"), &Utc), vec![start], "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_agenda_replaces_changed_occurrences() {
+        let row = |id: i64, recurrence_id: &str, start_at: &str, status: &str, rrule: Option<&str>| EventRow {
+            id,
+            email_id: None,
+            uid: "standup".to_string(),
+            recurrence_id: recurrence_id.to_string(),
+            summary: Some("Standup".to_string()),
+            location: None,
+            organizer: None,
+            start_at: start_at.to_string(),
+            end_at: None,
+            all_day: true,
+            status: status.to_string(),
+            rrule: rrule.map(str::to_string),
+            tzid: None,
+            exdates: None,
+            join_url: None,
+        };
+        let rows = vec![
+            row(1, "", "2024-03-01T00:00:00Z", "confirmed", Some("FREQ=DAILY")),
+            row(2, "2024-03-02T00:00:00Z", "2024-03-02T00:00:00Z", "cancelled", None),
+            row(3, "2024-03-03T00:00:00Z", "2024-03-03T12:00:00Z", "confirmed", None),
+        ];
+
+        let starts: Vec<(i64, String)> = agenda(rows, utc("2024-03-01T00:00:00Z"), utc("2024-03-04T00:00:00Z"))
+            .into_iter()
+            .map(|event| (event.id, event.start_at))
+            .collect();
+        assert_eq!(starts, vec![
+            (1, "2024-03-01T00:00:00Z".to_string()),
+            (3, "2024-03-03T12:00:00Z".to_string()),
+        ]);
+    }
+}
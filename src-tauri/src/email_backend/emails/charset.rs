@@ -0,0 +1,203 @@
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine as _;
+use encoding_rs::Encoding;
+
+/// The encoding a MIME charset label names, aliases like `cp1251` or `gb2312` included.
+pub fn encoding(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().trim_matches('"').as_bytes())
+}
+
+/// Text in the given charset. Without a label encoding_rs knows, UTF-8 is assumed, and bytes
+/// that aren't valid UTF-8 are read as Windows-1252, the usual unlabeled legacy charset.
+pub fn decode_bytes(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset.and_then(encoding) {
+        Some(encoding) => encoding.decode(bytes).0.into_owned(),
+        None => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => encoding_rs::WINDOWS_1252.decode(bytes).0.into_owned(),
+        },
+    }
+}
+
+/// The charset an HTML body declares in a `<meta>` tag near its start.
+pub fn sniff_html_charset(html: &[u8]) -> Option<String> {
+    let head = String::from_utf8_lossy(&html[..html.len().min(1024)]).to_ascii_lowercase();
+    let start = head.find("charset=")? + "charset=".len();
+    let label: String = head[start..]
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| !matches!(c, '"' | '\'' | ';' | '>' | '/') && !c.is_whitespace())
+        .collect();
+    encoding(&label).map(|encoding| encoding.name().to_string())
+}
+
+/// `%XX` (or `=XX`) escapes to bytes, `_` to a space in encoded-words.
+fn unescape(text: &str, escape: u8, underscore_is_space: bool) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b if b == escape => {
+                if let Some(byte) = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    out.push(byte);
+                    i += 3;
+                    continue;
+                }
+                out.push(b);
+            }
+            b'_' if underscore_is_space => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Decodes the RFC 2047 encoded-word at the start of `s`, returning its text and length.
+fn encoded_word(s: &str) -> Option<(String, usize)> {
+    let inner = s.strip_prefix("=?")?;
+    let charset_end = inner.find('?')?;
+    let kind = inner.get(charset_end + 1..charset_end + 2)?;
+    inner.get(charset_end + 2..charset_end + 3).filter(|q| *q == "?")?;
+    let text_start = charset_end + 3;
+    let text_end = text_start + inner[text_start..].find("?=")?;
+
+    // RFC 2231 allows a language after the charset: `utf-8*en`
+    let charset = inner[..charset_end].split('*').next().unwrap_or_default();
+    let text = &inner[text_start..text_end];
+    let bytes = match kind {
+        "B" | "b" => STANDARD_NO_PAD.decode(text.trim_end_matches('=')).ok()?,
+        "Q" | "q" => unescape(text, b'=', true),
+        _ => return None,
+    };
+    Some((decode_bytes(&bytes, Some(charset)), "=?".len() + text_end + "?=".len()))
+}
+
+/// Decodes the RFC 2047 encoded-words (`=?charset?B?...?=`) in a header value, the rest is
+/// kept as it is.
+pub fn decode_header(value: &str) -> String {
+    if !value.contains("=?") {
+        return value.to_string();
+    }
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        match encoded_word(&rest[start..]) {
+            Some((word, len)) => {
+                // Whitespace between two encoded-words only folds the header
+                let between = &rest[..start];
+                if !(after_word && between.trim().is_empty()) {
+                    out.push_str(between);
+                }
+                out.push_str(&word);
+                rest = &rest[start + len..];
+                after_word = true;
+            }
+            None => {
+                out.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A MIME parameter such as `filename`, joining RFC 2231 continuations (`filename*0*=`,
+/// `filename*1*=`) and decoding `charset'language'%XX` values. A plain value may still carry
+/// encoded-words, as many mailers put them there.
+pub fn mime_param(params: &[(String, Vec<u8>)], name: &str) -> Option<String> {
+    let mut plain = None;
+    let mut sections: Vec<(u32, bool, &[u8])> = Vec::new();
+
+    for (key, value) in params {
+        let key = key.to_ascii_lowercase();
+        let Some(rest) = key.strip_prefix(name) else { continue };
+        match rest {
+            "" => plain = Some(value.as_slice()),
+            "*" => sections.push((0, true, value.as_slice())),
+            _ => {
+                let Some(section) = rest.strip_prefix('*') else { continue };
+                let encoded = section.ends_with('*');
+                if let Ok(index) = section.trim_end_matches('*').parse() {
+                    sections.push((index, encoded, value.as_slice()));
+                }
+            }
+        }
+    }
+
+    if sections.is_empty() {
+        return plain.map(|value| decode_header(&decode_bytes(value, None)));
+    }
+    sections.sort_by_key(|(index, _, _)| *index);
+
+    let mut charset = None;
+    let mut bytes = Vec::new();
+    for (i, (_, encoded, raw)) in sections.into_iter().enumerate() {
+        if !encoded {
+            bytes.extend_from_slice(raw);
+            continue;
+        }
+        let decoded = String::from_utf8_lossy(raw);
+        let mut value: &str = &decoded;
+        if i == 0 {
+            // charset'language'value, the language is of no use here
+            let mut fields = value.splitn(3, '\'');
+            if let (Some(label), Some(_), Some(rest)) = (fields.next(), fields.next(), fields.next()) {
+                charset = Some(label.to_string()).filter(|label| !label.is_empty());
+                value = rest;
+            }
+        }
+        bytes.extend(unescape(value, b'%', false));
+    }
+    Some(decode_bytes(&bytes, charset.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_header_samples() {
+        // A Japanese quote request, an ISO-2022-JP subject as sent by many Japanese mailers
+        assert_eq!(decode_header("=?ISO-2022-JP?B?GyRCOCtAUT1xGyhC?="), "見積書");
+        assert_eq!(decode_header("Re: =?windows-1251?B?z/Do4uXyLCDs6PA=?= (2)"), "Re: Привет, мир (2)");
+        assert_eq!(decode_header("=?iso-8859-1?Q?Caf=E9_cr=E8me?="), "Café crème");
+        assert_eq!(decode_header("=?GB18030?B?xOO6ww==?="), "你好");
+        // Split across two encoded-words, folded onto a second line
+        assert_eq!(decode_header("=?UTF-8?B?0J7RgtGH0ZHRgg==?=\r\n =?UTF-8?B?LnBkZg==?="), "Отчёт.pdf");
+        assert_eq!(decode_header("50% off =?bogus"), "50% off =?bogus");
+        assert_eq!(decode_header("plain"), "plain");
+    }
+
+    #[test]
+    fn test_decode_bytes_with_and_without_labels() {
+        assert_eq!(decode_bytes(b"\xcf\xf0\xe8\xe2\xe5\xf2", Some("cp1251")), "Привет");
+        assert_eq!(decode_bytes(b"\xc4\xe3\xba\xc3", Some("gb2312")), "你好");
+        assert_eq!(decode_bytes(b"\x1b$B%F%9%H\x1b(B", Some("\"iso-2022-jp\"")), "テスト");
+        assert_eq!(decode_bytes(b"caf\xe9", None), "café");
+        assert_eq!(decode_bytes("café".as_bytes(), Some("x-unknown")), "café");
+        assert_eq!(sniff_html_charset(b"<html><head><meta charset=\"Windows-1251\">").as_deref(), Some("windows-1251"));
+    }
+
+    #[test]
+    fn test_mime_param_rfc2231_and_encoded_words() {
+        let param = |pairs: &[(&str, &str)]| -> Vec<(String, Vec<u8>)> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.as_bytes().to_vec())).collect()
+        };
+        assert_eq!(
+            mime_param(&param(&[("filename*", "UTF-8''%E6%97%A5%E6%9C%AC%E8%AA%9E.pdf")]), "filename").as_deref(),
+            Some("日本語.pdf")
+        );
+        assert_eq!(
+            mime_param(&param(&[("filename*1*", "%E6%9C%AC"), ("filename*0*", "utf-8'ja'%E6%97%A5"), ("filename*2", ".pdf")]), "filename").as_deref(),
+            Some("日本.pdf")
+        );
+        assert_eq!(mime_param(&param(&[("NAME", "=?ISO-2022-JP?B?GyRCOCtAUT1xGyhC?=.pdf")]), "name").as_deref(), Some("見積書.pdf"));
+        assert_eq!(mime_param(&param(&[("filename", "a.txt")]), "name"), None);
+    }
+}
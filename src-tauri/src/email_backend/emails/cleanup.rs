@@ -0,0 +1,163 @@
+use crate::email_backend::emails::bulk::{emails_by_id, run_bulk, BulkAction};
+use crate::email_backend::emails::newsletters::newsletter_condition;
+use crate::utils::attachments::remove_attachment_file;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+const LARGE_ATTACHMENT_BYTES: i64 = 5 * 1024 * 1024;
+const STALE_NEWSLETTER_DAYS: i64 = 30;
+const HUGE_THREAD_MESSAGES: i64 = 50;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CleanupSuggestion {
+    /// `large_attachments`, `old_unread_newsletters` or `huge_thread`
+    pub kind: String,
+    pub title: String,
+    pub email_ids: Vec<i64>,
+    /// Bytes the suggestion would free, roughly
+    pub estimated_bytes: i64,
+    /// What `apply_cleanup_action` accepts for it
+    pub actions: Vec<String>,
+}
+
+/// Approximate size of an email `e`, body plus attachments, for mail without a stored RFC822.SIZE.
+pub(crate) const ESTIMATED_SIZE: &str = "COALESCE(LENGTH(e.body_text), 0) + COALESCE(LENGTH(e.body_html), 0)
+    + COALESCE((SELECT SUM(a.size) FROM attachments a WHERE a.email_id = e.id), 0)";
+
+fn actions(names: &[&str]) -> Vec<String> {
+    names.iter().map(|a| a.to_string()).collect()
+}
+
+fn parse_ids(ids: &str) -> Vec<i64> {
+    ids.split(',').filter_map(|id| id.parse().ok()).collect()
+}
+
+#[tauri::command]
+pub async fn get_cleanup_suggestions<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: Option<i64>) -> Result<Vec<CleanupSuggestion>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let mut suggestions = Vec::new();
+
+    let (ids, bytes): (Option<String>, Option<i64>) = sqlx::query_as(
+        "SELECT GROUP_CONCAT(DISTINCT e.id), SUM(a.size)
+         FROM attachments a JOIN emails e ON a.email_id = e.id JOIN folders f ON e.folder_id = f.id
+         WHERE a.size >= ? AND f.role != 'trash' AND (? IS NULL OR e.account_id = ?)"
+    )
+    .bind(LARGE_ATTACHMENT_BYTES)
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if let Some(ids) = ids {
+        let email_ids = parse_ids(&ids);
+        suggestions.push(CleanupSuggestion {
+            kind: "large_attachments".to_string(),
+            title: format!("{} emails with attachments over 5 MB", email_ids.len()),
+            email_ids,
+            estimated_bytes: bytes.unwrap_or(0),
+            actions: actions(&["strip_attachments", "archive", "delete"]),
+        });
+    }
+
+    let (ids, bytes): (Option<String>, Option<i64>) = sqlx::query_as(&format!(
+        "SELECT GROUP_CONCAT(e.id), SUM({})
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE f.role = 'inbox' AND e.flags NOT LIKE '%\"seen\"%' AND datetime(e.date) < datetime('now', ?)
+           AND {} AND (? IS NULL OR e.account_id = ?)",
+        ESTIMATED_SIZE,
+        newsletter_condition("e")
+    ))
+    .bind(format!("-{} days", STALE_NEWSLETTER_DAYS))
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if let Some(ids) = ids {
+        let email_ids = parse_ids(&ids);
+        suggestions.push(CleanupSuggestion {
+            kind: "old_unread_newsletters".to_string(),
+            title: format!("{} unread newsletters older than a month", email_ids.len()),
+            email_ids,
+            estimated_bytes: bytes.unwrap_or(0),
+            actions: actions(&["archive", "delete"]),
+        });
+    }
+
+    let threads: Vec<(Option<String>, String, i64, i64)> = sqlx::query_as(&format!(
+        "SELECT MAX(e.subject), GROUP_CONCAT(e.id), COUNT(*) as messages, SUM({})
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE e.thread_id IS NOT NULL AND f.role NOT IN ('trash', 'sent', 'drafts') AND (? IS NULL OR e.account_id = ?)
+         GROUP BY e.account_id, e.thread_id
+         HAVING messages >= ?
+         ORDER BY messages DESC LIMIT 5",
+        ESTIMATED_SIZE
+    ))
+    .bind(account_id)
+    .bind(account_id)
+    .bind(HUGE_THREAD_MESSAGES)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for (subject, ids, messages, bytes) in threads {
+        suggestions.push(CleanupSuggestion {
+            kind: "huge_thread".to_string(),
+            title: format!("\"{}\" has {} messages", subject.unwrap_or_else(|| "(No subject)".to_string()), messages),
+            email_ids: parse_ids(&ids),
+            estimated_bytes: bytes,
+            actions: actions(&["archive", "delete"]),
+        });
+    }
+
+    Ok(suggestions)
+}
+
+/// Forgets the downloaded copies of the emails' attachments. They stay listed and are fetched
+/// again from the server when opened. Returns the bytes freed on disk.
+pub(crate) async fn strip_attachments<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, pool: &SqlitePool, email_ids: &[i64]) -> Result<u64, String> {
+    let mut freed = 0;
+    for chunk in email_ids.chunks(500) {
+        let mut query = sqlx::QueryBuilder::new("UPDATE attachments SET file_hash = NULL WHERE file_hash IS NOT NULL AND email_id IN (");
+        let mut separated = query.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        query.push(") RETURNING file_hash");
+        let hashes: Vec<String> = query.build_query_scalar().fetch_all(pool).await.map_err(|e| e.to_string())?;
+
+        for hash in hashes {
+            // Files are content addressed, another attachment may still point at the same one
+            let still_used: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM attachments WHERE file_hash = ?)")
+                .bind(&hash)
+                .fetch_one(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            if !still_used {
+                freed += remove_attachment_file(app_handle, &hash)?;
+            }
+        }
+    }
+    Ok(freed)
+}
+
+/// Runs one of a suggestion's actions on its emails: `archive`, `delete` (to trash) or `strip_attachments`.
+#[tauri::command]
+pub async fn apply_cleanup_action<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, action: String, email_ids: Vec<i64>) -> Result<usize, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+
+    let role = match action.as_str() {
+        "archive" => "archive",
+        "delete" => "trash",
+        "strip_attachments" => {
+            let freed = strip_attachments(&app_handle, &pool, &email_ids).await?;
+            info!("Cleanup freed {} bytes of attachments from {} email(s)", freed, email_ids.len());
+            return Ok(email_ids.len());
+        }
+        other => return Err(format!("Unknown cleanup action: {}", other)),
+    };
+
+    let groups = emails_by_id(&pool, &email_ids).await?;
+    Ok(run_bulk(&app_handle, "cleanup", BulkAction::MoveToRole(role), groups).await?.len())
+}
@@ -1,15 +1,21 @@
-use crate::email_backend::emails::events::EmailEvent;
+use crate::email_backend::emails::{body_limits, body_structure, cleanup, compose, confidential, fts, newsletters, quotes, remote_content, spam_signals, undo};
+use crate::email_backend::emails::remote_content::SenderContentRules;
+use crate::email_backend::emails::events::{AttachmentDownloadProgress, EmailEvent, SendProgress, SendStage};
 use tauri::{Manager, Emitter};
-use log::info;
+use log::{info, warn};
 use sqlx::SqlitePool;
 use serde::{Deserialize, Serialize};
 use crate::email_backend::accounts::manager::AccountManager;
-use crate::email_backend::sync::SyncEngine;
-use crate::utils::attachments::{save_attachment_data, read_attachment_data};
-use email::backend::BackendBuilder;
-use email::smtp::SmtpContextBuilder;
-use email::message::send::SendMessage;
+use crate::email_backend::sync::{links, SyncEngine, SyncWorker};
+use crate::email_backend::sync::scheduler::JobScheduler;
+use crate::db::profiling;
+use crate::db::settings::Settings;
+use crate::db::writer::WritePool;
+use crate::email_backend::errors::{is_auth_error, report_error, BackendError, ErrorCategory, ErrorSeverity};
+use crate::utils::attachments::{save_attachment_data, read_attachment_data, get_partial_download_path};
+use crate::utils::i18n;
 use email::envelope::Id;
+use email::imap::ImapClient;
 use email::flag::add::AddFlags;
 use email::flag::Flag;
 use email::flag::Flags;
@@ -17,6 +23,13 @@ use email::message::add::AddMessage;
 use mail_builder::MessageBuilder;
 use imap_client::imap_next::imap_types::sequence::Sequence;
 use imap_client::imap_next::imap_types::error::ValidationError;
+use imap_client::imap_next::imap_types::fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Email {
@@ -40,12 +53,56 @@ pub struct Email {
     pub has_attachments: bool,
     pub is_reply: bool,
     pub is_forward: bool,
+    /// Set on sent emails once a bounce or DSN for them has been seen
+    #[sqlx(default)]
+    pub delivery_status: Option<String>,
+    #[sqlx(default)]
+    pub delivery_error: Option<String>,
+    /// `reply_later` or `set_aside` while the email sits in one of those stacks
+    #[sqlx(default)]
+    pub stack: Option<String>,
+    /// JSON array of the ids of the local tags on the email
+    #[sqlx(default)]
+    pub tag_ids: Option<String>,
+    /// The user's private note, only loaded for a single email
+    #[sqlx(default)]
+    pub note: Option<String>,
+    /// Whether any message in the conversation is unread
+    #[sqlx(default)]
+    pub thread_unread: bool,
+    /// Whether any message in the conversation has attachments
+    #[sqlx(default)]
+    pub thread_has_attachments: bool,
+    /// JSON array of the conversation's senders as `{name, address}`, oldest first
+    #[sqlx(default)]
+    pub participants: Option<String>,
+    /// The color the user gave the email's account, for telling accounts apart in a unified list
+    #[sqlx(default)]
+    pub account_color: Option<String>,
+    /// Sent from one of the user's accounts or their aliases
+    #[sqlx(default)]
+    pub is_from_me: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, sqlx::FromRow, Clone)]
 pub struct EmailContent {
     pub body_text: Option<String>,
     pub body_html: Option<String>,
+    /// Remote images or styles were taken out of `body_html`, the viewer offers to load them
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub remote_content_blocked: bool,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub sender_rules: SenderContentRules,
+    /// Only the cut-down cached copy could be shown, the full body couldn't be fetched
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub body_truncated: bool,
+    /// Quoted messages and signatures in the bodies, for the reader to collapse
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub quotes: quotes::QuoteRanges,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -76,6 +133,10 @@ pub struct Draft {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UnifiedCounts {
     pub primary: i32,
+    /// Unread mail from first-time senders waiting in the screener
+    pub screened: i32,
+    pub reply_later: i32,
+    pub set_aside: i32,
     pub sent: i32,
     pub spam: i32,
     pub drafts: i32,
@@ -90,7 +151,125 @@ pub async fn refresh_folder<R: tauri::Runtime>(
     SyncEngine::refresh_folder(&app_handle, account_id, folder_id).await
 }
 
+/// SQL condition selecting the emails a view shows, defaulting to the inbox. `None` for unknown views.
+/// The inbox is split between the primary view, the screener (`{alias}.screening`) and the
+/// reply later / set aside stacks (`{alias}.stack`).
+pub(crate) fn view_role_filter(view: Option<&str>, role_column: &str, email_alias: &str) -> Option<String> {
+    let role = match view.unwrap_or("primary") {
+        "primary" => return Some(format!("{0} = 'inbox' AND {1}.screening IS NULL AND {1}.stack IS NULL", role_column, email_alias)),
+        "screened" => return Some(format!("{} = 'inbox' AND {}.screening = 'pending'", role_column, email_alias)),
+        "screened_out" => return Some(format!("{} = 'inbox' AND {}.screening = 'screened_out'", role_column, email_alias)),
+        stack @ ("reply_later" | "set_aside") => {
+            return Some(format!("{}.stack = '{}' AND {} != 'trash'", email_alias, stack, role_column));
+        }
+        "spam" => "spam",
+        "sent" => "sent",
+        "drafts" => "drafts",
+        "trash" => "trash",
+        "archive" => "archive",
+        "others" => {
+            return Some(format!(
+                "({0} IS NULL OR {0} = '' OR {0} NOT IN ('inbox', 'spam', 'sent', 'drafts', 'trash', 'archive'))",
+                role_column
+            ));
+        }
+        _ => return None,
+    };
+    Some(format!("{} = '{}'", role_column, role))
+}
+
+/// The `attachments.kind` a quick filter word stands for, so `doc` and `images` work like
+/// `document` and `image`. Kinds are derived from the MIME type by a trigger.
+pub fn attachment_kind(word: &str) -> String {
+    let word = word.trim().to_lowercase();
+    match word.as_str() {
+        "img" | "images" | "photo" | "photos" => "image".to_string(),
+        "doc" | "docs" | "documents" => "document".to_string(),
+        "sheet" | "sheets" | "spreadsheets" => "spreadsheet".to_string(),
+        "slides" | "presentations" => "presentation".to_string(),
+        "zip" | "archives" => "archive".to_string(),
+        _ => word,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AttachmentFacet {
+    pub kind: String,
+    pub count: i64,
+}
+
+/// Number of emails in the view with each kind of attachment.
+#[tauri::command]
+pub async fn get_attachment_facets<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    account_id: Option<i64>,
+    view: Option<String>,
+) -> Result<Vec<AttachmentFacet>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let condition = view_role_filter(view.as_deref(), "f.role", "e").ok_or("Unknown view")?;
+
+    let mut query_builder = sqlx::QueryBuilder::new(format!(
+        "SELECT a.kind as kind, COUNT(DISTINCT e.id) as count
+         FROM attachments a JOIN emails e ON a.email_id = e.id JOIN folders f ON e.folder_id = f.id
+         WHERE NOT a.is_inline AND {}",
+        condition
+    ));
+    if let Some(aid) = account_id {
+        query_builder.push(" AND e.account_id = ");
+        query_builder.push_bind(aid);
+    }
+    query_builder.push(" GROUP BY kind ORDER BY count DESC");
+
+    query_builder
+        .build_query_as::<AttachmentFacet>()
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Filters of the thread listing shared by `get_emails` and the per-contact listings.
+#[derive(Debug, Default)]
+pub(crate) struct EmailListing {
+    pub account_id: Option<i64>,
+    /// `None` lists every folder except spam, trash and drafts
+    pub view: Option<String>,
+    pub filter: Option<String>,
+    pub limit: Option<u32>,
+    pub before_date: Option<String>,
+    pub before_id: Option<i64>,
+    pub attachment_type: Option<String>,
+    /// Only mail carrying this local tag
+    pub tag_id: Option<i64>,
+    /// Only mail from, or sent to, these (lowercase) addresses
+    pub correspondents: Option<Vec<String>>,
+    /// `limit` applies per account, with results ordered by account
+    pub grouped: bool,
+    /// Per account keyset cursors of a grouped listing
+    pub account_cursors: Vec<AccountCursor>,
+}
+
+/// Where an account's section of the unified inbox left off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountCursor {
+    pub account_id: i64,
+    pub before_date: String,
+    pub before_id: i64,
+}
+
+/// One account's threads in the unified inbox.
+#[derive(Debug, Serialize)]
+pub struct AccountEmails {
+    pub account_id: i64,
+    pub email: String,
+    pub emails: Vec<Email>,
+    /// `None` once the account has no more threads in the view
+    pub next_cursor: Option<AccountCursor>,
+}
+
+const GROUPED_LIMIT: u32 = 20;
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_emails<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
     account_id: Option<i64>,
@@ -99,47 +278,168 @@ pub async fn get_emails<R: tauri::Runtime>(
     limit: Option<u32>,
     before_date: Option<String>,
     before_id: Option<i64>,
+    attachment_type: Option<String>,
+    tag_id: Option<i64>,
 ) -> Result<Vec<Email>, String> {
     let pool = app_handle.state::<SqlitePool>();
-    
-    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+    list_emails(&pool, EmailListing {
+        account_id,
+        view: Some(view.unwrap_or_else(|| "primary".to_string())),
+        filter,
+        limit,
+        before_date,
+        before_id,
+        attachment_type,
+        tag_id,
+        ..Default::default()
+    })
+    .await
+}
+
+/// The unified inbox as one section per account, each with its own cursor, from a single query.
+/// `cursors` only needs the accounts being paged, `account_id` narrows to one section.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_emails_by_account<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    account_id: Option<i64>,
+    view: Option<String>,
+    filter: Option<String>,
+    limit: Option<u32>,
+    cursors: Option<Vec<AccountCursor>>,
+    attachment_type: Option<String>,
+    tag_id: Option<i64>,
+) -> Result<Vec<AccountEmails>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let limit = limit.unwrap_or(GROUPED_LIMIT);
+    let emails = list_emails(&pool, EmailListing {
+        account_id,
+        view: Some(view.unwrap_or_else(|| "primary".to_string())),
+        filter,
+        limit: Some(limit),
+        attachment_type,
+        tag_id,
+        grouped: true,
+        account_cursors: cursors.unwrap_or_default(),
+        ..Default::default()
+    })
+    .await?;
+
+    let accounts: Vec<(i64, String)> = sqlx::query_as("SELECT id, email FROM accounts ORDER BY id")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut by_account: HashMap<i64, Vec<Email>> = HashMap::new();
+    for email in emails {
+        by_account.entry(email.account_id).or_default().push(email);
+    }
+
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(account_id, email)| {
+            let emails = by_account.remove(&account_id)?;
+            let next_cursor = match emails.last() {
+                Some(last) if emails.len() as u32 >= limit => Some(AccountCursor {
+                    account_id,
+                    before_date: last.date.clone(),
+                    before_id: last.id,
+                }),
+                _ => None,
+            };
+            Some(AccountEmails { account_id, email, emails, next_cursor })
+        })
+        .collect())
+}
+
+/// `e.recipient_to` lowercased with a comma around every address, so matching `%,<address>,%`
+/// finds whole addresses only and bob@example.com doesn't match rebob@example.com.
+pub(crate) const DELIMITED_RECIPIENTS: &str =
+    "',' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(LOWER(COALESCE(e.recipient_to, '')), ' ', ','), '<', ','), '>', ','), ';', ','), '\"', ',') || ','";
+
+/// Mail from any of `addresses` or sent to them, for an `emails e` joined with `folders f`.
+pub(crate) fn push_correspondents_condition(query_builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>, addresses: &[String]) {
+    query_builder.push("(LOWER(e.sender_address) IN (");
+    let mut separated = query_builder.separated(", ");
+    for address in addresses {
+        separated.push_bind(address.clone());
+    }
+    query_builder.push(") OR (f.role = 'sent' AND (0");
+    for address in addresses {
+        query_builder.push(" OR ");
+        query_builder.push(DELIMITED_RECIPIENTS);
+        query_builder.push(" LIKE ");
+        query_builder.push_bind(format!("%,{},%", address));
+    }
+    query_builder.push(")))");
+}
+
+/// One row per thread, newest first, drafts included unless limited to correspondents.
+pub(crate) async fn list_emails(pool: &SqlitePool, listing: EmailListing) -> Result<Vec<Email>, String> {
+    let EmailListing { account_id, view, filter, limit, before_date, before_id, attachment_type, tag_id, correspondents, grouped, account_cursors } = listing;
+
+    // Grouped listings number the threads of each account and keep the first `limit`
+    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(if grouped { "SELECT * FROM (" } else { "" });
+    query_builder.push(
         "WITH unique_messages AS (
             SELECT 
                 e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, 
                 e.in_reply_to, e.references_header, e.subject, e.normalized_subject, 
                 e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, 
-                e.snippet, e.summary, e.has_attachments, f.role as folder_role,
-                ROW_NUMBER() OVER (
-                    PARTITION BY e.account_id, e.message_id 
-                    ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC
-                ) as msg_rn
+                e.snippet, e.summary, e.has_attachments, f.role as folder_role, e.list_id, e.screening, e.stack
             FROM emails e
             JOIN folders f ON e.folder_id = f.id
+            JOIN messages m ON m.email_id = e.id"
+    );
+
+    if let Some(addresses) = &correspondents {
+        query_builder.push(" WHERE ");
+        push_correspondents_condition(&mut query_builder, addresses);
+    }
+
+    query_builder.push(
+        "
             UNION ALL
             SELECT 
                 -d.id as id, d.account_id, -1 as folder_id, 'local-draft-' || d.id as remote_id, NULL as message_id, NULL as thread_id, 
                 NULL as in_reply_to, NULL as references_header, d.subject, LOWER(COALESCE(d.subject, '')) as normalized_subject, 
                 NULL as sender_name, COALESCE(d.to_address, '(No Recipient)') as sender_address, d.to_address as recipient_to, strftime('%Y-%m-%dT%H:%M:%SZ', d.updated_at) as date, '[]' as flags, 
                 d.body_html as snippet, NULL as summary, EXISTS(SELECT 1 FROM attachments WHERE draft_id = d.id) as has_attachments, 
-                'drafts' as folder_role,
-                1 as msg_rn
-            FROM drafts d
+                'drafts' as folder_role, NULL as list_id, NULL as screening, NULL as stack
+            FROM drafts d"
+    );
+    if correspondents.is_some() {
+        query_builder.push(" WHERE 0");
+    }
+
+    query_builder.push(
+        "
          ),
           latest_threads AS (
             SELECT *,
-            ROW_NUMBER() OVER (
-                PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id) 
-                ORDER BY date DESC, id DESC
-            ) as thread_rn,
-            COUNT(*) OVER (
-                PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id)
-            ) as t_count
+            ROW_NUMBER() OVER (thread ORDER BY date DESC, id DESC) as thread_rn,
+            COUNT(*) OVER thread as t_count,
+            MAX(flags NOT LIKE '%\"seen\"%') OVER thread as t_unread,
+            MAX(has_attachments) OVER thread as t_attachments,
+            json_group_array(json_array(sender_name, sender_address)) OVER (thread ORDER BY date, id ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING) as t_participants
             FROM unique_messages
-            WHERE msg_rn = 1
+            WINDOW thread AS (
+                PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id)
+            )
          )
-         SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, e.t_count as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments,
+         SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, e.t_count as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments, e.stack,
+         e.t_unread as thread_unread, e.t_attachments as thread_has_attachments, e.t_participants as participants,
+         (SELECT ac.color FROM accounts ac WHERE ac.id = e.account_id) as account_color,
+         (SELECT json_group_array(et.tag_id) FROM email_tags et WHERE et.account_id = e.account_id AND et.message_id = e.message_id) as tag_ids,
          (e.subject LIKE 'Re:%' OR e.subject LIKE 're:%' OR e.in_reply_to IS NOT NULL) as is_reply,
-         (e.subject LIKE 'Fwd:%' OR e.subject LIKE 'fwd:%' OR e.subject LIKE 'Fw:%' OR e.subject LIKE 'fw:%') as is_forward
+         (e.subject LIKE 'Fwd:%' OR e.subject LIKE 'fwd:%' OR e.subject LIKE 'Fw:%' OR e.subject LIKE 'fw:%') as is_forward,
+         (LOWER(e.sender_address) IN (SELECT address FROM own_addresses)) as is_from_me"
+    );
+    if grouped {
+        query_builder.push(", ROW_NUMBER() OVER (PARTITION BY e.account_id ORDER BY e.date DESC, e.id DESC) as account_rn");
+    }
+    query_builder.push(
+        "
          FROM latest_threads e 
          WHERE e.thread_rn = 1 "
     );
@@ -152,45 +452,46 @@ pub async fn get_emails<R: tauri::Runtime>(
         query_builder.push_bind(aid);
     }
 
-    if let Some(v) = view {
-        match v.as_str() {
-            "primary" => {
-                query_builder.push(" AND e.folder_role = 'inbox'");
-            }
-            "spam" => {
-                query_builder.push(" AND e.folder_role = 'spam'");
+    match view.as_deref() {
+        Some(view) => {
+            if let Some(condition) = view_role_filter(Some(view), "e.folder_role", "e") {
+                query_builder.push(" AND ");
+                query_builder.push(condition);
             }
-            "sent" => {
-                query_builder.push(" AND e.folder_role = 'sent'");
-            }
-            "drafts" => {
-                query_builder.push(" AND e.folder_role = 'drafts'");
-            }
-            "trash" => {
-                query_builder.push(" AND e.folder_role = 'trash'");
-            }
-            "archive" => {
-                query_builder.push(" AND e.folder_role = 'archive'");
-            }
-            "others" => {
-                query_builder.push(" AND (e.folder_role IS NULL OR e.folder_role = '' OR e.folder_role NOT IN ('inbox', 'spam', 'sent', 'drafts', 'trash', 'archive'))");
+
+            // Newsletters are listed through their daily roll-up instead
+            if view == "primary" && newsletters::rollup_enabled(pool).await {
+                query_builder.push(" AND NOT ");
+                query_builder.push(newsletters::newsletter_condition("e"));
             }
-            _ => {}
-        };
-    } else {
-        // Default to primary if no view specified
-        query_builder.push(" AND e.folder_role = 'inbox'");
+        }
+        None => {
+            query_builder.push(" AND COALESCE(e.folder_role, '') NOT IN ('spam', 'trash', 'drafts')");
+        }
     }
 
     if let Some(f) = filter {
         if !has_where { query_builder.push(" WHERE "); } else { query_builder.push(" AND "); }
         match f.as_str() {
-            "unread" => query_builder.push(" e.flags NOT LIKE '%seen%'"),
-            "flagged" => query_builder.push(" e.flags LIKE '%flagged%'"),
+            "unread" => query_builder.push(" e.flags NOT LIKE '%\"seen\"%'"),
+            "flagged" => query_builder.push(" e.flags LIKE '%\"flagged\"%'"),
+            "attachments" => query_builder.push(" e.has_attachments = 1"),
             _ => &mut query_builder,
         };
     }
 
+    if let Some(kind) = attachment_type {
+        query_builder.push(" AND EXISTS (SELECT 1 FROM attachments a WHERE a.email_id = e.id AND NOT a.is_inline AND a.kind = ");
+        query_builder.push_bind(attachment_kind(&kind));
+        query_builder.push(")");
+    }
+
+    if let Some(tag_id) = tag_id {
+        query_builder.push(" AND EXISTS (SELECT 1 FROM email_tags et WHERE et.account_id = e.account_id AND et.message_id = e.message_id AND et.tag_id = ");
+        query_builder.push_bind(tag_id);
+        query_builder.push(")");
+    }
+
     // Keyset Pagination
     if let (Some(date), Some(id)) = (before_date, before_id) {
         if !has_where { query_builder.push(" WHERE "); } else { query_builder.push(" AND "); }
@@ -203,18 +504,66 @@ pub async fn get_emails<R: tauri::Runtime>(
         query_builder.push("))");
     }
 
-    query_builder.push(" ORDER BY e.date DESC, e.id DESC LIMIT ");
-    query_builder.push_bind(limit.unwrap_or(100) as i64);
+    if !account_cursors.is_empty() {
+        query_builder.push(" AND CASE e.account_id");
+        for cursor in account_cursors {
+            query_builder.push(" WHEN ");
+            query_builder.push_bind(cursor.account_id);
+            query_builder.push(" THEN (e.date < ");
+            query_builder.push_bind(cursor.before_date.clone());
+            query_builder.push(" OR (e.date = ");
+            query_builder.push_bind(cursor.before_date);
+            query_builder.push(" AND e.id < ");
+            query_builder.push_bind(cursor.before_id);
+            query_builder.push("))");
+        }
+        query_builder.push(" ELSE 1 END");
+    }
 
-    let emails = query_builder
-        .build_query_as::<Email>()
-        .fetch_all(&*pool)
+    if grouped {
+        query_builder.push(") WHERE account_rn <= ");
+        query_builder.push_bind(limit.unwrap_or(GROUPED_LIMIT) as i64);
+        query_builder.push(" ORDER BY account_id, date DESC, id DESC");
+    } else {
+        query_builder.push(" ORDER BY e.date DESC, e.id DESC LIMIT ");
+        query_builder.push_bind(limit.unwrap_or(100) as i64);
+    }
+
+    let sql = query_builder.sql().to_string();
+    let mut emails = profiling::timed(pool, "list_emails", &sql, query_builder.build_query_as::<Email>().fetch_all(pool))
         .await
         .map_err(|e| e.to_string())?;
+    collapse_participants(&mut emails);
 
     Ok(emails)
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct Participant {
+    name: Option<String>,
+    address: String,
+}
+
+/// The query lists the sender of every message in the conversation, this keeps each address once,
+/// in the order they first wrote, with the latest name they used.
+fn collapse_participants(emails: &mut [Email]) {
+    for email in emails {
+        let Some(raw) = &email.participants else { continue };
+        let senders: Vec<(Option<String>, Option<String>)> = serde_json::from_str(raw).unwrap_or_default();
+
+        let mut participants: Vec<Participant> = Vec::new();
+        for (name, address) in senders {
+            let Some(address) = address else { continue };
+            let name = name.filter(|n| !n.trim().is_empty());
+            match participants.iter_mut().find(|p| p.address.eq_ignore_ascii_case(&address)) {
+                Some(existing) => existing.name = name.or(existing.name.take()),
+                None => participants.push(Participant { name, address }),
+            }
+        }
+        email.participants = serde_json::to_string(&participants).ok();
+    }
+}
+
 #[tauri::command]
 pub async fn get_unified_counts<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<UnifiedCounts, String> {
     let pool = app_handle.state::<SqlitePool>();
@@ -236,8 +585,32 @@ pub async fn get_unified_counts<R: tauri::Runtime>(app_handle: tauri::AppHandle<
         .await
         .map_err(|e| e.to_string())?;
 
+    // Held back and stacked mail is part of the inbox folder's count but not of the primary view
+    let (screened, held_back): (i32, i32) = sqlx::query_as(
+        "SELECT COALESCE(SUM(e.screening = 'pending'), 0), COUNT(*)
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE f.role = 'inbox' AND (e.screening IS NOT NULL OR e.stack IS NOT NULL) AND e.flags NOT LIKE '%\"seen\"%'"
+    )
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Stacks hold whole threads, count those rather than messages
+    let (reply_later, set_aside): (i32, i32) = sqlx::query_as(
+        "SELECT COUNT(DISTINCT CASE WHEN e.stack = 'reply_later' THEN COALESCE(e.thread_id, e.id) END),
+                COUNT(DISTINCT CASE WHEN e.stack = 'set_aside' THEN COALESCE(e.thread_id, e.id) END)
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE e.stack IS NOT NULL AND f.role != 'trash'"
+    )
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
     Ok(UnifiedCounts {
-        primary: row.0,
+        primary: (row.0 - held_back).max(0),
+        screened,
+        reply_later,
+        set_aside,
         sent: row.1,
         spam: row.2,
         drafts: row.3 + local_drafts_count.0,
@@ -248,9 +621,10 @@ pub async fn get_unified_counts<R: tauri::Runtime>(app_handle: tauri::AppHandle<
     pub async fn get_email_by_id<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<Email, String> {
     let pool = app_handle.state::<SqlitePool>();
     let email = sqlx::query_as::<_, Email>(
-        "SELECT id, account_id, folder_id, remote_id, message_id, thread_id, 1 as thread_count, in_reply_to, references_header, subject, sender_name, sender_address, recipient_to, date, flags, snippet, summary, has_attachments,
+        "SELECT id, account_id, folder_id, remote_id, message_id, thread_id, 1 as thread_count, in_reply_to, references_header, subject, sender_name, sender_address, recipient_to, date, flags, snippet, summary, has_attachments, delivery_status, delivery_error, stack, note,
          (subject LIKE 'Re:%' OR subject LIKE 're:%' OR in_reply_to IS NOT NULL) as is_reply,
-         (subject LIKE 'Fwd:%' OR subject LIKE 'fwd:%' OR subject LIKE 'Fw:%' OR subject LIKE 'fw:%') as is_forward
+         (subject LIKE 'Fwd:%' OR subject LIKE 'fwd:%' OR subject LIKE 'Fw:%' OR subject LIKE 'fw:%') as is_forward,
+         (LOWER(sender_address) IN (SELECT address FROM own_addresses)) as is_from_me
          FROM emails WHERE id = ?"
     )
     .bind(email_id)
@@ -322,9 +696,10 @@ pub async fn get_thread_emails<R: tauri::Runtime>(
     
     query_builder.push(")
         )
-        SELECT id, account_id, folder_id, remote_id, message_id, thread_id, 1 as thread_count, in_reply_to, references_header, subject, sender_name, sender_address, recipient_to, date, flags, snippet, summary, has_attachments,
+        SELECT id, account_id, folder_id, remote_id, message_id, thread_id, 1 as thread_count, in_reply_to, references_header, subject, sender_name, sender_address, recipient_to, date, flags, snippet, summary, has_attachments, delivery_status, delivery_error, stack,
         (subject LIKE 'Re:%' OR subject LIKE 're:%' OR in_reply_to IS NOT NULL) as is_reply,
-        (subject LIKE 'Fwd:%' OR subject LIKE 'fwd:%' OR subject LIKE 'Fw:%' OR subject LIKE 'fw:%') as is_forward
+        (subject LIKE 'Fwd:%' OR subject LIKE 'fwd:%' OR subject LIKE 'Fw:%' OR subject LIKE 'fw:%') as is_forward,
+        (LOWER(sender_address) IN (SELECT address FROM own_addresses)) as is_from_me
         FROM thread_emails
         WHERE message_rn = 1
         ORDER BY date DESC, id DESC LIMIT ");
@@ -341,20 +716,36 @@ pub async fn get_thread_emails<R: tauri::Runtime>(
     Ok(emails)
 }
 
+/// The email's body, with remote content blocked unless the sender is always allowed or
+/// `load_remote` allows it this once.
 #[tauri::command]
-pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<EmailContent, String> {
+pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, load_remote: Option<bool>) -> Result<EmailContent, String> {
+    let content = load_email_content(app_handle.clone(), email_id).await?;
+    let pool = app_handle.state::<SqlitePool>();
+    let mut content = remote_content::apply_sender_rules(&pool, email_id, content, load_remote.unwrap_or(false)).await?;
+    // Found on the bodies as shown, blocking remote content changes the HTML
+    content.quotes = quotes::find(content.body_text.as_deref(), content.body_html.as_deref());
+    Ok(content)
+}
+
+async fn load_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<EmailContent, String> {
     let pool = app_handle.state::<SqlitePool>().inner().clone();
     
-    let cached_info: Option<(Option<String>, Option<String>, Option<String>, bool, i64)> = sqlx::query_as(
-        "SELECT body_text, body_html, summary, has_attachments, account_id FROM emails WHERE id = ?"
+    let cached_info: Option<(Option<String>, Option<String>, Option<String>, bool, i64, bool)> = sqlx::query_as(
+        "SELECT body_text, body_html, summary, has_attachments, account_id, body_truncated FROM emails WHERE id = ?"
     )
     .bind(email_id)
     .fetch_optional(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-    if let Some((body_text, body_html, summary, has_attachments, _account_id)) = cached_info {
-        if body_text.is_some() || body_html.is_some() {
+    // Shown when the cached copy is cut down and the full body can't be fetched
+    let mut truncated_copy = None;
+
+    if let Some((body_text, body_html, summary, has_attachments, _account_id, body_truncated)) = cached_info {
+        if body_truncated {
+            truncated_copy = Some(EmailContent { body_text, body_html, body_truncated: true, ..Default::default() });
+        } else if body_text.is_some() || body_html.is_some() {
             // Check if we have attachments if we expect them
              let attachment_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE email_id = ?")
                  .bind(email_id)
@@ -370,17 +761,9 @@ pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R
                     let pool_clone = pool.clone();
                     
                     tauri::async_runtime::spawn(async move {
-                        let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-                            .fetch_one(&pool_clone)
-                            .await
-                            .unwrap_or(("false".to_string(),));
-                        
-                        let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-                            .fetch_one(&pool_clone)
-                            .await
-                            .unwrap_or(("false".to_string(),));
-
-                        if ai_enabled.0 == "true" && ai_summarization_enabled.0 == "true" {
+                        let settings = Settings::load(&pool_clone).await.unwrap_or_default();
+
+                        if settings.ai_enabled && settings.ai_summarization_enabled {
                             // Check folder role
                             let role: Option<String> = sqlx::query_scalar("SELECT f.role FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?")
                                 .bind(email_id)
@@ -417,81 +800,36 @@ pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R
                 return Ok(EmailContent {
                     body_text,
                     body_html,
+                    ..Default::default()
                 });
             }
         }
     }
 
-    let email_info: (i64, String, String) = sqlx::query_as(
-        "SELECT e.account_id, e.remote_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
-    )
-    .bind(email_id)
-    .fetch_one(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    let (account_id, remote_id, _folder_path) = email_info;
-
-    // Get folder role to check for spam/trash
-    let folder_role: Option<String> = sqlx::query_scalar("SELECT role FROM folders WHERE path = ? AND account_id = ?")
-        .bind(&_folder_path)
-        .bind(account_id)
-        .fetch_one(&pool)
-        .await
-        .unwrap_or(None);
-
-    let engine = app_handle.state::<SyncEngine<R>>();
-    let context = engine.get_context(account_id).await?;
-
-    let mut client = context.client().await;
-    
-    let id = Id::single(remote_id);
-    use imap_client::imap_next::imap_types::fetch::MessageDataItemName;
-    use imap_client::imap_next::imap_types::fetch::MacroOrMessageDataItemNames;
-    let fetch_items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
-        MessageDataItemName::BodyExt {
-            section: None,
-            partial: None,
-            peek: true,
+    let content = match (cache_email_content(&app_handle, email_id).await, truncated_copy) {
+        (Ok(content), _) => content,
+        (Err(e), Some(copy)) => {
+            warn!("Showing the cached part of email {}, the full body couldn't be fetched: {}", email_id, e);
+            return Ok(copy);
         }
-    ]);
-    
-    // Select the mailbox first
-    client.examine_mailbox(&_folder_path).await.map_err(|e| e.to_string())?;
-
-    use std::num::NonZeroU32;
-    let uids: imap_client::imap_next::imap_types::sequence::SequenceSet = id.iter()
-        .filter_map(|s| s.parse::<u32>().ok())
-        .filter_map(|n| NonZeroU32::new(n))
-        .map(Sequence::from)
-        .collect::<Vec<_>>()
-        .try_into()
-        .map_err(|e: ValidationError| e.to_string())?;
-
-    let messages = client.fetch_messages_with_items(uids, fetch_items).await.map_err(|e| e.to_string())?;
-    let message = messages.first().ok_or("Email not found on server")?;
-
-    let parsed = message.parsed().map_err(|e: email::Error| e.to_string())?;
-    let body_text: Option<String> = parsed.body_text(0).map(|b| b.to_string());
-    let body_html: Option<String> = parsed.body_html(0).map(|b| b.to_string());
+        (Err(e), None) => return Err(e),
+    };
 
-    // Trigger AI Summarization in background if enabled
-    if let Some(text) = body_text.clone() {
+    // Trigger AI Summarization in background if enabled, confidential mail keeps no summary either
+    let uncached = confidential::is_uncached(&pool, email_id).await.unwrap_or(true);
+    if let Some(text) = content.body_text.clone().filter(|_| !uncached) {
+        // Get folder role to check for spam/trash
+        let folder_role: Option<String> = sqlx::query_scalar("SELECT f.role FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?")
+            .bind(email_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap_or(None);
         let handle = app_handle.clone();
         let pool_clone = pool.clone();
-        let folder_role_clone = folder_role.clone();
         tauri::async_runtime::spawn(async move {
-            let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-                .fetch_one(&pool_clone)
-                .await
-                .unwrap_or(("false".to_string(),));
-            
-            let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-                .fetch_one(&pool_clone)
-                .await
-                .unwrap_or(("false".to_string(),));
+            let settings = Settings::load(&pool_clone).await.unwrap_or_default();
 
-            if ai_enabled.0 == "true" && ai_summarization_enabled.0 == "true" && folder_role_clone.as_deref() != Some("spam") && folder_role_clone.as_deref() != Some("trash") {
+            if settings.ai_enabled && settings.ai_summarization_enabled && folder_role.as_deref() != Some("spam") && folder_role.as_deref() != Some("trash") {
                 if let Ok(s) = crate::email_backend::llm::summarization::summarize_email_with_ai(&handle, email_id, &text, false).await {
                     let sender_address: Option<String> = sqlx::query_scalar("SELECT sender_address FROM emails WHERE id = ?")
                         .bind(email_id)
@@ -516,55 +854,129 @@ pub async fn get_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R
         });
     }
 
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    Ok(content)
+}
+
+/// Emails prefetched per call, the next few rows on screen.
+const MAX_PREFETCH: usize = 10;
+
+/// Queues the bodies of emails the user is likely to open next. The indexer fetches them
+/// ahead of its snippet backlog, so opening them needs no round trip.
+#[tauri::command]
+pub async fn prefetch_email_content<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+
+    // Confidential mail would only be fetched to be thrown away
+    let sql = format!(
+        "SELECT e.body_text IS NOT NULL OR e.body_html IS NOT NULL OR {} FROM emails e WHERE e.id = ?",
+        confidential::uncached_condition("e")
+    );
+    let mut uncached = Vec::new();
+    for email_id in email_ids.into_iter().take(MAX_PREFETCH) {
+        let cached: Option<bool> = sqlx::query_scalar(&sql)
+            .bind(email_id)
+            .fetch_optional(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        if cached == Some(false) {
+            uncached.push(email_id);
+        }
+    }
+
+    if uncached.is_empty() {
+        return Ok(());
+    }
+    app_handle.state::<SyncEngine<R>>().queue_prefetch(uncached).await;
+    if let Some(scheduler) = app_handle.try_state::<JobScheduler>() {
+        scheduler.wake("indexing");
+    }
+    Ok(())
+}
+
+/// Downloads the text and HTML parts of an email and caches them, with its attachment list and links.
+pub(crate) async fn cache_email_content<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_id: i64) -> Result<EmailContent, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+
+    let email_info: (i64, String, i64, String) = sqlx::query_as(
+        "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+    )
+    .bind(email_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (account_id, remote_id, folder_id, folder_path) = email_info;
+    let remote_id = server_remote_id(app_handle, email_id, account_id, folder_id, remote_id).await?;
+
+    let uid = remote_id.parse::<u32>().ok().and_then(NonZeroU32::new).ok_or("Invalid message UID")?;
+
+    let engine = app_handle.state::<SyncEngine<R>>();
+    let context = engine.get_context(account_id).await?;
+
+    // Waiting for a free pooled connection isn't a hung server, only the exchange is timed
+    let mut client = context.client().await;
+    let (layout, text_message, html_message) = engine.with_timeout(account_id, async {
+        client.examine_mailbox(&folder_path).await.map_err(|e| e.to_string())?;
+
+        // Only the structure up front, attachment bodies are left for download_attachment
+        let layout = fetch_layout(&mut client, uid).await?;
+
+        let text_message = match &layout.text {
+            Some(part) => Some(body_structure::decode(part, &fetch_section(&mut client, uid, &part.section, None).await?)),
+            None => None,
+        };
+        let html_message = match &layout.html {
+            Some(part) => Some(body_structure::decode(part, &fetch_section(&mut client, uid, &part.section, None).await?)),
+            None => None,
+        };
+        Ok::<_, String>((layout, text_message, html_message))
+    }).await??;
+    drop(client);
+
+    // mail_parser converts between text and html when only one of them exists
+    let body_text: Option<String> = text_message.as_ref().or(html_message.as_ref()).and_then(|m| m.body_text(0)).map(|b| b.to_string());
+    let body_html: Option<String> = html_message.as_ref().or(text_message.as_ref()).and_then(|m| m.body_html(0)).map(|b| b.to_string());
+
+    // Confidential mail only gets its attachment list recorded, the body stays in memory
+    let uncached = confidential::is_uncached(&pool, email_id).await?;
+
+    let mut tx = app_handle.state::<WritePool>().begin().await?;
+
+    if !uncached {
+        // The cache and the search index get a cut-down copy of oversized bodies, the caller the whole body
+        let (html_limit, text_limit) = body_limits::limits(&pool).await;
+        let cached = body_limits::cap(body_text.as_deref(), body_html.as_deref(), html_limit, text_limit);
 
-    sqlx::query("UPDATE emails SET body_text = ?, body_html = ? WHERE id = ?")
-        .bind(&body_text)
-        .bind(&body_html)
+        sqlx::query("UPDATE emails SET body_text = ?, body_html = ?, body_truncated = ? WHERE id = ?")
+            .bind(&cached.body_text)
+            .bind(&cached.body_html)
+            .bind(cached.truncated)
+            .bind(email_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    sqlx::query("UPDATE emails SET has_attachments = ? WHERE id = ?")
+        .bind(layout.attachments.iter().any(|part| !part.is_inline))
         .bind(email_id)
         .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
 
-    if let Ok(attachments) = message.attachments() {
-        if attachments.is_empty() {
-             // If we expected attachments but found none (and we are here because of that), 
-             // update the flag to avoid re-fetching loop.
-             // We only want to do this if we were expecting attachments. 
-             // But checking "has_attachments" here from the initial SELECT is hard as variables are in different scope.
-             // However, it's safe to set it to false if we found none.
-             let _ = sqlx::query("UPDATE emails SET has_attachments = false WHERE id = ?")
-                 .bind(email_id)
-                 .execute(&mut *tx)
-                 .await;
-        } else {
-            sqlx::query("UPDATE emails SET has_attachments = true WHERE id = ?")
-                .bind(email_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| e.to_string())?;
-
-            for att in attachments {
-                sqlx::query(
-                    "INSERT INTO attachments (email_id, filename, mime_type, size)
-                     VALUES (?, ?, ?, ?)"
-                )
-                .bind(email_id)
-                .bind(&att.filename)
-                .bind(&att.mime)
-                .bind(att.body.len() as i64)
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| e.to_string())?;
-            }
-        }
-    }
+    record_attachments(&mut *tx, email_id, &layout.attachments).await?;
 
     tx.commit().await.map_err(|e| e.to_string())?;
 
+    // Background indexing only fetches snippets now, links are found once the body is here
+    if !uncached {
+        SyncWorker::<R>::save_links(&pool, email_id, links::extract_links(body_text.as_deref(), body_html.as_deref())).await;
+    }
+
     Ok(EmailContent {
         body_text,
         body_html,
+        ..Default::default()
     })
 }
 
@@ -712,7 +1124,7 @@ pub async fn get_draft_by_id<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>,
 
     draft.id = -draft.id; // Return negative ID
 
-    let attachments = sqlx::query_as::<_, Attachment>("SELECT id, email_id, draft_id, filename, mime_type, size, file_hash FROM attachments WHERE draft_id = ?")
+    let attachments = sqlx::query_as::<_, Attachment>("SELECT id, email_id, draft_id, filename, mime_type, size, file_hash, is_inline FROM attachments WHERE draft_id = ?")
         .bind(actual_id)
         .fetch_all(&*pool)
         .await
@@ -751,30 +1163,40 @@ pub async fn mark_as_read<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, em
     let mut final_flags = String::new();
     
     for &email_id in &email_ids {
-        let email_info: Option<(i64, String, String, String, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, f.path, e.flags, e.sender_address FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+        let email_info: Option<(i64, String, i64, String, String, String)> = sqlx::query_as(
+            "SELECT e.account_id, e.remote_id, e.folder_id, f.path, e.flags, e.sender_address FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
         )
         .bind(email_id)
         .fetch_optional(&*pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (account_id, remote_id, folder_path, current_flags, _sender_address) = match email_info {
+        let (account_id, remote_id, folder_id, folder_path, current_flags, _sender_address) = match email_info {
             Some(info) => info,
             None => continue,
         };
 
-        if current_flags.contains("\"seen\"") || current_flags.contains("seen") {
+        if current_flags.contains("\"seen\"") {
             continue;
         }
 
         let engine = app_handle.state::<SyncEngine<R>>();
-        if let Ok(backend) = engine.get_backend(account_id).await {
-            let id = Id::single(remote_id);
-            let _ = backend.add_flag(&folder_path, &id, Flag::Seen).await;
+        match engine.get_backend(account_id).await {
+            Ok(backend) => {
+                let result = match server_remote_id(&app_handle, email_id, account_id, folder_id, remote_id).await {
+                    Ok(remote_id) => backend.add_flag(&folder_path, &Id::single(remote_id), Flag::Seen).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    report_error(&app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.mark_read_server", &[("error", &e)])).retryable());
+                }
+            }
+            Err(e) => {
+                report_error(&app_handle, BackendError::new(ErrorCategory::Network, ErrorSeverity::Warning, i18n::t("error.mark_read_offline", &[("error", &e)])).retryable());
+            }
         }
 
-        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+        let mut tx = app_handle.state::<WritePool>().begin().await?;
 
         let mut flags: Vec<String> = serde_json::from_str(&current_flags).unwrap_or_default();
         if !flags.contains(&"seen".to_string()) {
@@ -810,264 +1232,187 @@ pub async fn mark_as_read<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, em
     Ok(())
 }
 
-#[tauri::command]
-pub async fn move_to_inbox<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
-    let pool = app_handle.state::<SqlitePool>();
+/// An email moved by `move_emails_to_role`, with what `undo_action` needs to put it back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedEmail {
+    pub email_id: i64,
+    pub account_id: i64,
+    pub message_id: Option<String>,
+    pub from_folder_id: i64,
+    pub to_folder_id: i64,
+}
 
-    for &email_id in &email_ids {
-        let email_info: Option<(i64, String, i64, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
-        )
+/// Moves an email between folders in the local DB and keeps both folders' counts right.
+/// Without the `remote_id` it got in the target folder, the old UID is swapped for a
+/// `moved:<id>` placeholder until the next sync of that folder finds the message again,
+/// so nothing acts on a UID that now points at another message.
+pub(crate) async fn apply_local_move(writer: &WritePool, email_id: i64, source_folder_id: i64, target_folder_id: i64, remote_id: Option<&str>) -> Result<(), String> {
+    let mut tx = writer.begin().await?;
+
+    // Check if seen to update counts
+    let is_unread: bool = sqlx::query_scalar("SELECT flags NOT LIKE '%\"seen\"%' FROM emails WHERE id = ?")
         .bind(email_id)
-        .fetch_optional(&*pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (account_id, remote_id, source_folder_id, source_folder_path) = match email_info {
-            Some(info) => info,
-            None => continue,
-        };
+    sqlx::query("UPDATE emails SET folder_id = ?, remote_id = COALESCE(?, 'moved:' || id) WHERE id = ?")
+        .bind(target_folder_id)
+        .bind(remote_id)
+        .bind(email_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
 
-        // Find inbox folder for this account
-        let inbox_folder_info: Option<(i64, String)> = sqlx::query_as(
-            "SELECT id, path FROM folders WHERE account_id = ? AND role = 'inbox'"
-        )
-        .bind(account_id)
-        .fetch_optional(&*pool)
+    // Update counts
+    sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - 1), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
+        .bind(if is_unread { 1 } else { 0 })
+        .bind(source_folder_id)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (inbox_folder_id, inbox_folder_path) = match inbox_folder_info {
-            Some(info) => info,
-            None => return Err(format!("Inbox folder not found for account {}", account_id)),
-        };
-        
-        if source_folder_id == inbox_folder_id {
-            continue;
-        }
-
-        // Perform move on server
-        let engine = app_handle.state::<SyncEngine<R>>();
-        if let Ok(backend) = engine.get_backend(account_id).await {
-            let id = email::envelope::Id::single(remote_id);
-            use email::message::r#move::MoveMessages;
-            let _ = backend.move_messages(&source_folder_path, &inbox_folder_path, &id).await.map_err(|e| e.to_string())?;
-        }
-
-        // Update local DB
-        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
-
-        // Check if seen to update counts
-        let is_unread: bool = sqlx::query_scalar("SELECT flags NOT LIKE '%seen%' FROM emails WHERE id = ?")
-            .bind(email_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE emails SET folder_id = ? WHERE id = ?")
-            .bind(inbox_folder_id)
-            .bind(email_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        // Update counts
-        sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - 1), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(source_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE folders SET total_count = total_count + 1, unread_count = unread_count + ? WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(inbox_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        tx.commit().await.map_err(|e| e.to_string())?;
-    }
-
-    if !email_ids.is_empty() {
-        let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids: email_ids });
-    }
-
-    Ok(())
-}
-
-#[tauri::command]
-pub async fn archive_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
-    let pool = app_handle.state::<SqlitePool>();
-
-    for &email_id in &email_ids {
-        let email_info: Option<(i64, String, i64, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
-        )
-        .bind(email_id)
-        .fetch_optional(&*pool)
+    sqlx::query("UPDATE folders SET total_count = total_count + 1, unread_count = unread_count + ? WHERE id = ?")
+        .bind(if is_unread { 1 } else { 0 })
+        .bind(target_folder_id)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (account_id, remote_id, source_folder_id, source_folder_path) = match email_info {
-            Some(info) => info,
-            None => continue,
-        };
+    tx.commit().await.map_err(|e| e.to_string())
+}
 
-        // Find archive folder for this account
-        let archive_folder_info: Option<(i64, String)> = sqlx::query_as(
-            "SELECT id, path FROM folders WHERE account_id = ? AND role = 'archive'"
-        )
-        .bind(account_id)
-        .fetch_optional(&*pool)
+/// The UID to use for an email on the server. An email moved in the app carries the
+/// `moved:<id>` placeholder of `apply_local_move`, a refresh of its folder finds its new UID.
+pub(crate) async fn server_remote_id<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_id: i64, account_id: i64, folder_id: i64, remote_id: String) -> Result<String, String> {
+    if !remote_id.starts_with("moved:") {
+        return Ok(remote_id);
+    }
+    SyncEngine::refresh_folder(app_handle, account_id, folder_id).await?;
+    let remote_id: String = sqlx::query_scalar("SELECT remote_id FROM emails WHERE id = ?")
+        .bind(email_id)
+        .fetch_one(&*app_handle.state::<SqlitePool>())
         .await
         .map_err(|e| e.to_string())?;
-
-        let (archive_folder_id, archive_folder_path) = match archive_folder_info {
-            Some(info) => info,
-            None => return Err(format!("Archive folder not found for account {}", account_id)),
-        };
-        
-        if source_folder_id == archive_folder_id {
-            continue;
-        }
-
-        // Perform move on server
-        let engine = app_handle.state::<SyncEngine<R>>();
-        if let Ok(backend) = engine.get_backend(account_id).await {
-            let id = email::envelope::Id::single(remote_id);
-            use email::message::r#move::MoveMessages;
-            let _ = backend.move_messages(&source_folder_path, &archive_folder_path, &id).await.map_err(|e| e.to_string())?;
-        }
-
-        // Update local DB
-        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
-
-        // Check if seen to update counts
-        let is_unread: bool = sqlx::query_scalar("SELECT flags NOT LIKE '%seen%' FROM emails WHERE id = ?")
-            .bind(email_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE emails SET folder_id = ? WHERE id = ?")
-            .bind(archive_folder_id)
-            .bind(email_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        // Update counts
-        sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - 1), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(source_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE folders SET total_count = total_count + 1, unread_count = unread_count + ? WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(archive_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        tx.commit().await.map_err(|e| e.to_string())?;
+    if remote_id.starts_with("moved:") {
+        return Err(format!("Email {} is not in its folder on the server yet", email_id));
     }
+    Ok(remote_id)
+}
 
-    if !email_ids.is_empty() {
-        let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids: email_ids });
-    }
+/// Moves emails to the account's folder with the given role, on the server and locally.
+/// Stops at the first error, what was moved before it is returned along with it so it can
+/// still be undone.
+async fn move_emails_to_role<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_ids: &[i64], role: &str) -> (Vec<MovedEmail>, Result<(), String>) {
+    let mut moved = Vec::new();
+    let result = move_each_to_role(app_handle, email_ids, role, &mut moved).await;
 
-    Ok(())
+    let removed: Vec<i64> = match result {
+        Ok(()) => email_ids.to_vec(),
+        Err(_) => moved.iter().map(|m| m.email_id).collect(),
+    };
+    if !removed.is_empty() {
+        let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids: removed });
+    }
+    (moved, result)
 }
 
-#[tauri::command]
-pub async fn move_to_trash<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
+async fn move_each_to_role<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_ids: &[i64], role: &str, moved: &mut Vec<MovedEmail>) -> Result<(), String> {
     let pool = app_handle.state::<SqlitePool>();
 
-    for &email_id in &email_ids {
-        let email_info: Option<(i64, String, i64, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+    for &email_id in email_ids {
+        let email_info: Option<(i64, String, Option<String>, i64, String)> = sqlx::query_as(
+            "SELECT e.account_id, e.remote_id, e.message_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
         )
         .bind(email_id)
         .fetch_optional(&*pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (account_id, remote_id, source_folder_id, source_folder_path) = match email_info {
+        let (account_id, remote_id, message_id, source_folder_id, source_folder_path) = match email_info {
             Some(info) => info,
             None => continue,
         };
 
-        // Find trash folder for this account
-        let trash_folder_info: Option<(i64, String)> = sqlx::query_as(
-            "SELECT id, path FROM folders WHERE account_id = ? AND role = 'trash'"
+        // Find target folder for this account
+        let target_folder_info: Option<(i64, String)> = sqlx::query_as(
+            "SELECT id, path FROM folders WHERE account_id = ? AND role = ?"
         )
         .bind(account_id)
+        .bind(role)
         .fetch_optional(&*pool)
         .await
         .map_err(|e| e.to_string())?;
 
-        let (trash_folder_id, trash_folder_path) = match trash_folder_info {
+        let (target_folder_id, target_folder_path) = match target_folder_info {
             Some(info) => info,
-            None => return Err(format!("Trash folder not found for account {}", account_id)),
+            None => return Err(format!("{} folder not found for account {}", role, account_id)),
         };
-        
-        if source_folder_id == trash_folder_id {
-            // Already in trash, maybe we should permanently delete?
-            // For now, let's just skip.
+
+        if source_folder_id == target_folder_id {
             continue;
         }
 
         // Perform move on server
         let engine = app_handle.state::<SyncEngine<R>>();
-        if let Ok(backend) = engine.get_backend(account_id).await {
-            let id = email::envelope::Id::single(remote_id);
-            use email::message::r#move::MoveMessages;
-            let _ = backend.move_messages(&source_folder_path, &trash_folder_path, &id).await.map_err(|e| e.to_string())?;
+        match engine.get_backend(account_id).await {
+            Ok(backend) => {
+                use email::message::r#move::MoveMessages;
+                let result = match server_remote_id(app_handle, email_id, account_id, source_folder_id, remote_id).await {
+                    Ok(remote_id) => backend.move_messages(&source_folder_path, &target_folder_path, &Id::single(remote_id)).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e),
+                };
+                result.map_err(|e| report_error(app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Error, i18n::t("error.move_server", &[("folder", &i18n::folder(role)), ("error", &e)])).retryable()))?;
+            }
+            Err(e) => {
+                report_error(app_handle, BackendError::new(ErrorCategory::Network, ErrorSeverity::Warning, i18n::t("error.move_offline", &[("folder", &i18n::folder(role)), ("error", &e)])).retryable());
+            }
         }
 
-        // Update local DB
-        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+        apply_local_move(&app_handle.state::<WritePool>(), email_id, source_folder_id, target_folder_id, None).await?;
 
-        // Check if seen to update counts
-        let is_unread: bool = sqlx::query_scalar("SELECT flags NOT LIKE '%seen%' FROM emails WHERE id = ?")
-            .bind(email_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        sqlx::query("UPDATE emails SET folder_id = ? WHERE id = ?")
-            .bind(trash_folder_id)
-            .bind(email_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        // Update counts
-        sqlx::query("UPDATE folders SET total_count = MAX(0, total_count - 1), unread_count = MAX(0, unread_count - ?) WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(source_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+        moved.push(MovedEmail {
+            email_id,
+            account_id,
+            message_id,
+            from_folder_id: source_folder_id,
+            to_folder_id: target_folder_id,
+        });
+    }
+    Ok(())
+}
 
-        sqlx::query("UPDATE folders SET total_count = total_count + 1, unread_count = unread_count + ? WHERE id = ?")
-            .bind(if is_unread { 1 } else { 0 })
-            .bind(trash_folder_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+#[tauri::command]
+pub async fn move_to_inbox<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<(), String> {
+    let (moved, result) = move_emails_to_role(&app_handle, &email_ids, "inbox").await;
+    spam_signals::record_moves(&app_handle.state::<SqlitePool>(), &moved, "inbox", 1).await?;
+    result
+}
 
-        tx.commit().await.map_err(|e| e.to_string())?;
-    }
+/// Returns an undo token, `None` when nothing was moved.
+#[tauri::command]
+pub async fn archive_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<Option<String>, String> {
+    let (moved, result) = move_emails_to_role(&app_handle, &email_ids, "archive").await;
+    let token = undo::record(&app_handle.state::<SqlitePool>(), "archive", &moved).await?;
+    result.map(|()| token)
+}
 
-    if !email_ids.is_empty() {
-        let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids: email_ids });
-    }
+/// Returns an undo token, `None` when nothing was moved.
+#[tauri::command]
+pub async fn move_to_trash<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<Option<String>, String> {
+    // Emails already in the trash are skipped rather than deleted for good
+    let (moved, result) = move_emails_to_role(&app_handle, &email_ids, "trash").await;
+    let token = undo::record(&app_handle.state::<SqlitePool>(), "trash", &moved).await?;
+    result.map(|()| token)
+}
 
-    Ok(())
+/// Returns an undo token, `None` when nothing was moved.
+#[tauri::command]
+pub async fn report_spam<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>) -> Result<Option<String>, String> {
+    let (moved, result) = move_emails_to_role(&app_handle, &email_ids, "spam").await;
+    spam_signals::record_moves(&app_handle.state::<SqlitePool>(), &moved, "spam", 1).await?;
+    let token = undo::record(&app_handle.state::<SqlitePool>(), "spam", &moved).await?;
+    result.map(|()| token)
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -1079,32 +1424,37 @@ pub struct Attachment {
     pub mime_type: Option<String>,
     pub size: i64,
     pub file_hash: Option<String>,
+    /// An image shown in the body, like a signature logo, rather than a file sent along
+    #[serde(default)]
+    pub is_inline: bool,
 }
 
+/// The files sent along with the email, `include_inline` adds the images shown in its body.
 #[tauri::command]
-pub async fn get_attachments<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<Vec<Attachment>, String> {
+pub async fn get_attachments<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, include_inline: Option<bool>) -> Result<Vec<Attachment>, String> {
     let pool = app_handle.state::<SqlitePool>();
-    let attachments = sqlx::query_as::<_, Attachment>("SELECT id, email_id, draft_id, filename, mime_type, size, file_hash FROM attachments WHERE email_id = ?")
+    let attachments = sqlx::query_as::<_, Attachment>("SELECT id, email_id, draft_id, filename, mime_type, size, file_hash, is_inline FROM attachments WHERE email_id = ? AND (? OR NOT is_inline)")
         .bind(email_id)
+        .bind(include_inline.unwrap_or(false))
         .fetch_all(&*pool)
         .await
         .map_err(|e| e.to_string())?;
     Ok(attachments)
 }
 
-async fn fetch_attachment_data_internal<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, attachment_id: i64) -> Result<Vec<u8>, String> {
+pub(crate) async fn fetch_attachment_data_internal<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, attachment_id: i64) -> Result<Vec<u8>, String> {
     let pool = app_handle.state::<SqlitePool>().inner().clone();
     
     // 1. Try to get cached data from file
-    let row: Option<(Option<String>, Option<i64>, Option<String>, Option<String>, i64)> = sqlx::query_as(
-        "SELECT file_hash, email_id, filename, mime_type, size FROM attachments WHERE id = ?"
+    let row: Option<(Option<String>, Option<i64>, Option<String>, Option<String>, i64, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT file_hash, email_id, filename, mime_type, size, section, encoding FROM attachments WHERE id = ?"
     )
     .bind(attachment_id)
     .fetch_optional(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-    let (file_hash, email_id, filename, mime_type, size) = match row {
+    let (file_hash, email_id, filename, mime_type, size, section, encoding) = match row {
         Some(r) => r,
         None => return Err("Attachment not found".to_string()),
     };
@@ -1116,25 +1466,50 @@ async fn fetch_attachment_data_internal<R: tauri::Runtime>(app_handle: &tauri::A
     }
 
     let email_id = email_id.ok_or("Attachment has no associated email (might be a draft)")?;
+    // Attachments of confidential mail are handed over without being saved
+    let uncached = confidential::is_uncached(&pool, email_id).await?;
 
     // 2. Data is missing, fetch from server
-    let email_info: (i64, String, String) = sqlx::query_as(
-        "SELECT e.account_id, e.remote_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+    let email_info: (i64, String, i64, String) = sqlx::query_as(
+        "SELECT e.account_id, e.remote_id, e.folder_id, f.path FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
     )
     .bind(email_id)
     .fetch_one(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-    let (account_id, remote_id, folder_path) = email_info;
+    let (account_id, remote_id, folder_id, folder_path) = email_info;
+    let remote_id = server_remote_id(app_handle, email_id, account_id, folder_id, remote_id).await?;
     let engine = app_handle.state::<SyncEngine<R>>();
     let context = engine.get_context(account_id).await?;
 
     let mut client = context.client().await;
+    engine.with_timeout(account_id, client.examine_mailbox(&folder_path)).await?.map_err(|e| e.to_string())?;
+
+    // Attachments recorded from BODYSTRUCTURE can be fetched on their own
+    if let Some(section) = section {
+        let uid = remote_id.parse::<u32>().ok().and_then(NonZeroU32::new).ok_or("Invalid message UID")?;
+        let encoding = encoding.as_deref().unwrap_or("7bit");
+        let total = if encoding == "base64" { size * 4 / 3 } else { size };
+        let raw = fetch_section_chunked(app_handle, &mut client, account_id, attachment_id, uid, &section, total).await?;
+        let data = body_structure::decode_bytes(encoding, &raw);
+        if uncached {
+            return Ok(data);
+        }
+
+        let hash = save_attachment_data(app_handle, &data)?;
+        sqlx::query("UPDATE attachments SET file_hash = ?, size = ? WHERE id = ?")
+            .bind(&hash)
+            .bind(data.len() as i64)
+            .bind(attachment_id)
+            .execute(&pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        return Ok(data);
+    }
+
     let id = Id::single(remote_id);
-    
-    use imap_client::imap_next::imap_types::fetch::MessageDataItemName;
-    use imap_client::imap_next::imap_types::fetch::MacroOrMessageDataItemNames;
     let fetch_items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
         MessageDataItemName::BodyExt {
             section: None,
@@ -1142,20 +1517,16 @@ async fn fetch_attachment_data_internal<R: tauri::Runtime>(app_handle: &tauri::A
             peek: true,
         }
     ]);
-    
-    client.examine_mailbox(&folder_path).await.map_err(|e| e.to_string())?;
 
-    use std::num::NonZeroU32;
-    use imap_client::imap_next::imap_types::sequence::Sequence;
     let uids: imap_client::imap_next::imap_types::sequence::SequenceSet = id.iter()
         .filter_map(|s| s.parse::<u32>().ok())
-        .filter_map(|n| NonZeroU32::new(n))
+        .filter_map(NonZeroU32::new)
         .map(Sequence::from)
         .collect::<Vec<_>>()
         .try_into()
-        .map_err(|e: imap_client::imap_next::imap_types::error::ValidationError| e.to_string())?;
+        .map_err(|e: ValidationError| e.to_string())?;
 
-    let messages = client.fetch_messages_with_items(uids, fetch_items).await.map_err(|e| e.to_string())?;
+    let messages = engine.with_timeout(account_id, client.fetch_messages_with_items(uids, fetch_items)).await?.map_err(|e| e.to_string())?;
     let message = messages.first().ok_or("Email not found on server")?;
 
     if let Ok(attachments) = message.attachments() {
@@ -1164,7 +1535,10 @@ async fn fetch_attachment_data_internal<R: tauri::Runtime>(app_handle: &tauri::A
             if att.filename == filename && 
                (mime_type.is_none() || att.mime == mime_type.as_ref().unwrap().as_str()) &&
                att.body.len() as i64 == size {
-                
+                if uncached {
+                    return Ok(att.body);
+                }
+
                 // Save to file system and get hash
                 let hash = save_attachment_data(app_handle, &att.body)?;
                 
@@ -1184,6 +1558,205 @@ async fn fetch_attachment_data_internal<R: tauri::Runtime>(app_handle: &tauri::A
     Err("Attachment data not found in email message".to_string())
 }
 
+/// The parts of a message, from its BODYSTRUCTURE.
+pub(crate) async fn fetch_layout(client: &mut ImapClient, uid: NonZeroU32) -> Result<body_structure::MessageLayout, String> {
+    let items = client
+        .fetch_first_items(uid, MacroOrMessageDataItemNames::MessageDataItemNames(vec![MessageDataItemName::BodyStructure]))
+        .await
+        .map_err(|e| e.to_string())?;
+    items
+        .as_ref()
+        .iter()
+        .find_map(|item| match item {
+            MessageDataItem::BodyStructure(body) => Some(body_structure::layout(body)),
+            _ => None,
+        })
+        .ok_or_else(|| "Email not found on server".to_string())
+}
+
+/// Records the attachments found in a BODYSTRUCTURE, unless the email already has them.
+pub(crate) async fn record_attachments(
+    conn: &mut sqlx::SqliteConnection,
+    email_id: i64,
+    parts: &[body_structure::MessagePart],
+) -> Result<(), String> {
+    // The background indexer may have stored them already
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE email_id = ?")
+        .bind(email_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if existing == 0 {
+        for part in parts {
+            sqlx::query(
+                "INSERT INTO attachments (email_id, filename, mime_type, size, section, encoding, content_id, is_inline)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(email_id)
+            .bind(&part.filename)
+            .bind(&part.mime_type)
+            .bind(part.decoded_size())
+            .bind(&part.section)
+            .bind(&part.encoding)
+            .bind(&part.content_id)
+            .bind(part.is_inline)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the raw (still transfer-encoded) bytes of one body section, `partial` being an (offset, length) range.
+pub(crate) async fn fetch_section(client: &mut ImapClient, uid: NonZeroU32, section: &str, partial: Option<(u32, NonZeroU32)>) -> Result<Vec<u8>, String> {
+    let items = client
+        .fetch_first_items(uid, MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+            MessageDataItemName::BodyExt {
+                section: Some(body_structure::section(section)?),
+                partial,
+                peek: true,
+            }
+        ]))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    items
+        .as_ref()
+        .iter()
+        .find_map(|item| match item {
+            MessageDataItem::BodyExt { data, .. } => Some(data.0.as_ref().map(|d| d.as_ref().to_vec()).unwrap_or_default()),
+            _ => None,
+        })
+        .ok_or_else(|| format!("Section {} not found on server", section))
+}
+
+const ATTACHMENT_CHUNK_SIZE: u32 = 512 * 1024;
+
+/// Cancellation flags of the downloads currently running, by attachment id.
+fn active_downloads() -> &'static Mutex<HashMap<i64, Arc<AtomicBool>>> {
+    static DOWNLOADS: OnceLock<Mutex<HashMap<i64, Arc<AtomicBool>>>> = OnceLock::new();
+    DOWNLOADS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Fetches a section in `ATTACHMENT_CHUNK_SIZE` ranges. Chunks are appended to a partial file as they
+/// arrive, so a cancelled or failed download picks up where it stopped next time.
+async fn fetch_section_chunked<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    client: &mut ImapClient,
+    account_id: i64,
+    attachment_id: i64,
+    uid: NonZeroU32,
+    section: &str,
+    total: i64,
+) -> Result<Vec<u8>, String> {
+    let partial_path = get_partial_download_path(app_handle, attachment_id)?;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let mut downloads = active_downloads().lock().map_err(|e| e.to_string())?;
+        // Two writers appending to the same partial file would corrupt it
+        if downloads.contains_key(&attachment_id) {
+            return Err(i18n::t("error.download_in_progress", &[]));
+        }
+        downloads.insert(attachment_id, cancelled.clone());
+    }
+
+    let result = async {
+        let mut raw = tokio::fs::read(&partial_path).await.unwrap_or_default();
+        if !raw.is_empty() {
+            info!("Resuming download of attachment {} at {} bytes", attachment_id, raw.len());
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&partial_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        let chunk_size = NonZeroU32::new(ATTACHMENT_CHUNK_SIZE).unwrap();
+
+        loop {
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(i18n::t("error.download_cancelled", &[]));
+            }
+
+            // Each chunk gets the whole timeout, a large attachment takes many of them
+            let chunk = app_handle
+                .state::<SyncEngine<R>>()
+                .with_timeout(account_id, fetch_section(client, uid, section, Some((raw.len() as u32, chunk_size))))
+                .await??;
+            file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+            raw.extend_from_slice(&chunk);
+
+            let _ = app_handle.emit("attachment-download-progress", AttachmentDownloadProgress {
+                attachment_id,
+                downloaded: raw.len() as i64,
+                total: total.max(raw.len() as i64),
+                done: false,
+            });
+
+            // The server answers with less than asked once the end of the section is reached
+            if (chunk.len() as u32) < ATTACHMENT_CHUNK_SIZE {
+                break;
+            }
+        }
+
+        Ok(raw)
+    }
+    .await;
+
+    if let Ok(mut downloads) = active_downloads().lock() {
+        downloads.remove(&attachment_id);
+    }
+    if result.is_ok() {
+        let _ = tokio::fs::remove_file(&partial_path).await;
+    }
+    result
+}
+
+#[tauri::command]
+pub async fn download_attachment<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, attachment_id: i64) -> Result<Attachment, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+
+    let size: i64 = sqlx::query_scalar("SELECT size FROM attachments WHERE id = ?")
+        .bind(attachment_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Attachment not found")?;
+
+    let _ = app_handle.emit("attachment-download-progress", AttachmentDownloadProgress {
+        attachment_id,
+        downloaded: 0,
+        total: size,
+        done: false,
+    });
+
+    let data = fetch_attachment_data_internal(&app_handle, attachment_id).await?;
+
+    let _ = app_handle.emit("attachment-download-progress", AttachmentDownloadProgress {
+        attachment_id,
+        downloaded: data.len() as i64,
+        total: data.len() as i64,
+        done: true,
+    });
+
+    sqlx::query_as::<_, Attachment>("SELECT id, email_id, draft_id, filename, mime_type, size, file_hash, is_inline FROM attachments WHERE id = ?")
+        .bind(attachment_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_attachment_download(attachment_id: i64) -> Result<(), String> {
+    if let Some(cancelled) = active_downloads().lock().map_err(|e| e.to_string())?.get(&attachment_id) {
+        cancelled.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_attachment_data<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, attachment_id: i64) -> Result<Vec<u8>, String> {
     fetch_attachment_data_internal(&app_handle, attachment_id).await
@@ -1218,16 +1791,122 @@ pub async fn open_attachment<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>,
     
     // 4. Open with system handler
     #[cfg(target_os = "windows")]
-    let _ = std::process::Command::new("cmd").args(["/C", "start", "", file_path.to_str().unwrap()]).spawn();
+    let spawned = std::process::Command::new("cmd").args(["/C", "start", "", file_path.to_str().unwrap()]).spawn();
     #[cfg(target_os = "macos")]
-    let _ = std::process::Command::new("open").arg(file_path).spawn();
+    let spawned = std::process::Command::new("open").arg(file_path).spawn();
     #[cfg(target_os = "linux")]
-    let _ = std::process::Command::new("xdg-open").arg(file_path).spawn();
-    
+    let spawned = std::process::Command::new("xdg-open").arg(file_path).spawn();
+
+    spawned.map_err(|e| report_error(&app_handle, BackendError::new(ErrorCategory::Storage, ErrorSeverity::Error, i18n::t("error.open_attachment", &[("error", &e.to_string())]))))?;
+
     Ok(())
 }
 
+/// Cancellation switches of the sends in flight, by the request id the frontend passed in.
+fn active_sends() -> &'static Mutex<HashMap<String, watch::Sender<bool>>> {
+    static SENDS: OnceLock<Mutex<HashMap<String, watch::Sender<bool>>>> = OnceLock::new();
+    SENDS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Unregisters a send from `active_sends` however `send_email` returns.
+struct ActiveSend(Option<String>);
+
+impl ActiveSend {
+    fn register(request_id: Option<&String>) -> Result<(Self, Option<watch::Receiver<bool>>), String> {
+        let Some(id) = request_id else { return Ok((Self(None), None)) };
+        let (tx, rx) = watch::channel(false);
+        active_sends().lock().map_err(|e| e.to_string())?.insert(id.clone(), tx);
+        Ok((Self(Some(id.clone())), Some(rx)))
+    }
+}
+
+impl Drop for ActiveSend {
+    fn drop(&mut self) {
+        if let (Some(id), Ok(mut sends)) = (&self.0, active_sends().lock()) {
+            sends.remove(id);
+        }
+    }
+}
+
+/// Runs `fut` unless the send is cancelled first. Dropping an SMTP send before DATA completes aborts it.
+async fn unless_cancelled<T>(cancel: &mut Option<watch::Receiver<bool>>, fut: impl std::future::Future<Output = T>) -> Result<T, String> {
+    match cancel {
+        Some(rx) => tokio::select! {
+            res = fut => Ok(res),
+            Ok(_) = rx.wait_for(|cancelled| *cancelled) => Err(i18n::t("error.send_cancelled", &[])),
+        },
+        None => Ok(fut.await),
+    }
+}
+
 #[tauri::command]
+pub async fn cancel_send(request_id: String) -> Result<(), String> {
+    if let Some(cancel) = active_sends().lock().map_err(|e| e.to_string())?.get(&request_id) {
+        let _ = cancel.send(true);
+    }
+    Ok(())
+}
+
+const FORWARDED_FLAG: &str = "$Forwarded";
+
+/// Sets `\Answered` / `$Forwarded` on the message that was replied to or forwarded, on the server and locally.
+async fn add_flag_to_original<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_id: i64, flag: Flag) {
+    let pool = app_handle.state::<SqlitePool>();
+    let email_info: Option<(i64, String, i64, String, String)> = sqlx::query_as(
+        "SELECT e.account_id, e.remote_id, e.folder_id, f.path, e.flags FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(&*pool)
+    .await
+    .unwrap_or(None);
+
+    let Some((account_id, remote_id, folder_id, folder_path, current_flags)) = email_info else { return };
+
+    let engine = app_handle.state::<SyncEngine<R>>();
+    match engine.get_backend(account_id).await {
+        Ok(backend) => {
+            let result = match server_remote_id(app_handle, email_id, account_id, folder_id, remote_id).await {
+                Ok(remote_id) => backend.add_flag(&folder_path, &Id::single(remote_id), flag.clone()).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                report_error(app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.flag_original_server", &[("error", &e)])).retryable());
+            }
+        }
+        Err(e) => {
+            report_error(app_handle, BackendError::new(ErrorCategory::Network, ErrorSeverity::Warning, i18n::t("error.flag_original_offline", &[("error", &e)])).retryable());
+        }
+    }
+
+    let mut flags: Vec<String> = serde_json::from_str(&current_flags).unwrap_or_default();
+    let flag = flag.to_string();
+    if flags.contains(&flag) {
+        return;
+    }
+    flags.push(flag);
+    let flags = serde_json::to_string(&flags).unwrap_or_default();
+
+    if let Err(e) = sqlx::query("UPDATE emails SET flags = ? WHERE id = ?")
+        .bind(&flags)
+        .bind(email_id)
+        .execute(&*pool)
+        .await
+    {
+        report_error(app_handle, BackendError::new(ErrorCategory::Storage, ErrorSeverity::Warning, i18n::t("error.update_flags", &[("id", &email_id.to_string()), ("error", &e.to_string())])));
+        return;
+    }
+
+    let _ = app_handle.emit("emails-updated", EmailEvent::Updated {
+        id: email_id,
+        address: None,
+        flags: Some(flags),
+        summary: None,
+        thread_count: None,
+    });
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn send_email<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
     account_id: i64,
@@ -1237,26 +1916,49 @@ pub async fn send_email<R: tauri::Runtime>(
     subject: String,
     body: String,
     attachment_ids: Vec<i64>,
+    request_id: Option<String>,
+    reply_to_email_id: Option<i64>,
+    forward_of_email_id: Option<i64>,
 ) -> Result<(), String> {
     let manager = AccountManager::new(&app_handle).await?;
     let account = manager.get_account_by_id(account_id).await?;
-    let (account_config, _, smtp_config) = account.get_configs()?;
     let pool = app_handle.state::<SqlitePool>();
 
+    let emit_progress = |stage: SendStage| {
+        let _ = app_handle.emit("send-progress", SendProgress { request_id: request_id.clone(), stage });
+    };
+    let (active_send, mut cancel) = ActiveSend::register(request_id.as_ref())?;
+
+    // Validate everything before touching the network, mail-builder takes care of the encoding
+    let to_list = compose::parse_recipients(&to, "To")?;
+    let cc_list = compose::parse_recipients(cc.as_deref().unwrap_or(""), "Cc")?;
+    let bcc_list = compose::parse_recipients(bcc.as_deref().unwrap_or(""), "Bcc")?;
+    compose::validate_subject(&subject)?;
+
+    if to_list.is_empty() && cc_list.is_empty() && bcc_list.is_empty() {
+        return Err(i18n::t("error.no_recipients", &[]));
+    }
+
+    // Known up front to tell whether the server already filed the message in Sent
+    let message_id = format!(
+        "{}.{:016x}@{}",
+        chrono::Utc::now().timestamp_millis(),
+        rand::random::<u64>(),
+        account.email().rsplit('@').next().unwrap_or("localhost")
+    );
+
     let mut builder = MessageBuilder::new();
     builder = builder.from(account.email());
-    builder = builder.to(to.clone());
+    builder = builder.message_id(message_id.as_str());
 
-    if let Some(ref cc_val) = cc {
-        if !cc_val.trim().is_empty() {
-            builder = builder.cc(cc_val.clone());
-        }
+    if !to_list.is_empty() {
+        builder = builder.to(compose::to_address_list(&to_list));
     }
-
-    if let Some(ref bcc_val) = bcc {
-        if !bcc_val.trim().is_empty() {
-            builder = builder.bcc(bcc_val.clone());
-        }
+    if !cc_list.is_empty() {
+        builder = builder.cc(compose::to_address_list(&cc_list));
+    }
+    if !bcc_list.is_empty() {
+        builder = builder.bcc(compose::to_address_list(&bcc_list));
     }
 
     builder = builder.subject(subject);
@@ -1271,61 +1973,61 @@ pub async fn send_email<R: tauri::Runtime>(
         
         let data = fetch_attachment_data_internal(&app_handle, id).await?;
 
+        let mime_type = att_info.1
+            .filter(|m| m.contains('/') && !m.chars().any(|c| c.is_whitespace() || c.is_control()))
+            .unwrap_or_else(|| "application/octet-stream".to_string());
         builder = builder.attachment(
-            att_info.1.unwrap_or_else(|| "application/octet-stream".to_string()),
-            att_info.0.unwrap_or_else(|| "attachment".to_string()),
+            mime_type,
+            compose::sanitize_filename(att_info.0.as_deref().unwrap_or("attachment")),
             data
         );
     }
 
     let message = builder.write_to_vec().map_err(|e| e.to_string())?;
 
-    let backend_builder = BackendBuilder::new(
-        account_config.clone(),
-        SmtpContextBuilder::new(account_config, smtp_config),
-    );
+    let engine = app_handle.state::<SyncEngine<R>>();
+    let sent = async {
+        emit_progress(SendStage::Connecting);
 
-    let backend = match backend_builder.build().await {
-        Ok(b) => b,
-        Err(e) => {
+        let context = unless_cancelled(&mut cancel, engine.get_smtp_context(account_id)).await??;
+
+        emit_progress(SendStage::Sending);
+
+        let result = unless_cancelled(&mut cancel, async { context.lock().await.send(&message).await }).await?;
+        if let Err(e) = result {
             let err_str = e.to_string();
-            if err_str.contains("auth") || err_str.contains("Unauthorized") || err_str.contains("token") || err_str.contains("credentials") {
-                info!("Refreshing token for account {} due to build error: {}", account.email(), err_str);
+            if is_auth_error(&err_str) {
+                // Refreshing the token also closes the pooled session
+                info!("Refreshing token for account {} due to send error: {}", account.email(), err_str);
                 manager.refresh_access_token(account.email()).await?;
-                let account = manager.get_account_by_id(account_id).await?;
-                let (account_config, _, smtp_config) = account.get_configs()?;
-                let backend_builder = BackendBuilder::new(
-                    account_config.clone(),
-                    SmtpContextBuilder::new(account_config, smtp_config),
-                );
-                backend_builder.build().await.map_err(|e| e.to_string())?
+                let context = unless_cancelled(&mut cancel, engine.get_smtp_context(account_id)).await??;
+                unless_cancelled(&mut cancel, async { context.lock().await.send(&message).await }).await?.map_err(|e| e.to_string())?;
             } else {
                 return Err(err_str);
             }
         }
-    };
 
-    if let Err(e) = backend.send_message(&message).await {
-        let err_str = e.to_string();
-        if err_str.contains("auth") || err_str.contains("Unauthorized") || err_str.contains("token") || err_str.contains("credentials") {
-            info!("Refreshing token for account {} due to send error: {}", account.email(), err_str);
-            manager.refresh_access_token(account.email()).await?;
-            let account = manager.get_account_by_id(account_id).await?;
-            let (account_config, _, smtp_config) = account.get_configs()?;
-            let backend_builder = BackendBuilder::new(
-                account_config.clone(),
-                SmtpContextBuilder::new(account_config, smtp_config),
-            );
-            let backend = backend_builder.build().await.map_err(|e| e.to_string())?;
-            backend.send_message(&message).await.map_err(|e| e.to_string())?;
-        } else {
-            return Err(err_str);
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = sent {
+        // The session is in an unknown state after a failed or interrupted send, the next one starts over
+        engine.invalidate_smtp_context(account_id).await;
+        if cancel.as_ref().map(|rx| *rx.borrow()).unwrap_or(false) {
+            info!("Sending cancelled for account {}", account_id);
+            emit_progress(SendStage::Cancelled);
+        } else if is_auth_error(&e) {
+            return Err(report_error(&app_handle, BackendError::new(ErrorCategory::Auth, ErrorSeverity::Error, i18n::t("error.auth_failed", &[("account", account.email()), ("error", &e)]))));
         }
+        return Err(e);
     }
 
-    // Append to Sent Folder
-    let engine = app_handle.state::<SyncEngine<R>>();
+    // The message is out, cancelling no longer applies
+    drop(active_send);
+    emit_progress(SendStage::SavingToSent);
 
+    // Append to Sent Folder
     let sent_folder: Option<(i64, String)> = sqlx::query_as("SELECT id, path FROM folders WHERE account_id = ? AND role = 'sent'")
         .bind(account_id)
         .fetch_optional(&*pool)
@@ -1333,37 +2035,61 @@ pub async fn send_email<R: tauri::Runtime>(
         .map_err(|e| e.to_string())?;
 
     if let Some((folder_id, path)) = sent_folder {
-         if let Ok(backend) = engine.get_backend(account_id).await {
-            let flags = Flags::from_iter([Flag::Seen]);
-            let _ = backend.add_message_with_flags(&path, &message, &flags).await;
-            
-            // Trigger refresh
-            let _ = SyncEngine::refresh_folder(&app_handle, account_id, folder_id).await;
-         }
+        // Gmail and Outlook file mail sent over SMTP themselves, appending would make a second copy
+        let already_filed = SyncEngine::refresh_folder(&app_handle, account_id, folder_id).await.is_ok()
+            && sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM emails WHERE folder_id = ? AND message_id = ?")
+                .bind(folder_id)
+                .bind(format!("<{}>", message_id))
+                .fetch_one(&*pool)
+                .await
+                .unwrap_or(0) > 0;
+
+        if already_filed {
+            info!("Server filed sent message {} itself, not appending it", message_id);
+        } else {
+            match engine.get_backend(account_id).await {
+                Ok(backend) => {
+                    let flags = Flags::from_iter([Flag::Seen]);
+                    if let Err(e) = backend.add_message_with_flags(&path, &message, &flags).await {
+                        report_error(&app_handle, BackendError::new(ErrorCategory::Send, ErrorSeverity::Warning, i18n::t("error.save_sent", &[("error", &e.to_string())])));
+                    }
+
+                    // Trigger refresh
+                    if let Err(e) = SyncEngine::refresh_folder(&app_handle, account_id, folder_id).await {
+                        report_error(&app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.refresh_sent", &[("error", &e)])).retryable());
+                    }
+                }
+                Err(e) => {
+                    report_error(&app_handle, BackendError::new(ErrorCategory::Network, ErrorSeverity::Warning, i18n::t("error.save_sent", &[("error", &e)])).retryable());
+                }
+            }
+        }
     }
 
     // Save recipients as contacts
-    let mut all_recipients = Vec::new();
-    all_recipients.push(to);
-    if let Some(cc_val) = cc {
-        all_recipients.push(cc_val);
-    }
-    if let Some(bcc_val) = bcc {
-        all_recipients.push(bcc_val);
-    }
-    
-    // Flatten comma separated lists
-    let flat_recipients: Vec<String> = all_recipients.iter()
-        .flat_map(|s| s.split(',').map(|p| p.trim().to_string()))
-        .filter(|s| !s.is_empty())
+    let flat_recipients: Vec<String> = to_list.iter()
+        .chain(cc_list.iter())
+        .chain(bcc_list.iter())
+        .map(|r| r.address.clone())
         .collect();
 
-    let _ = crate::email_backend::enrichment::commands::save_recipients_as_contacts(&app_handle, flat_recipients).await;
+    if let Err(e) = crate::email_backend::enrichment::commands::save_recipients_as_contacts(&app_handle, flat_recipients).await {
+        report_error(&app_handle, BackendError::new(ErrorCategory::Storage, ErrorSeverity::Warning, i18n::t("error.save_contacts", &[("error", &e)])));
+    }
+
+    if let Some(original_id) = reply_to_email_id {
+        add_flag_to_original(&app_handle, original_id, Flag::Answered).await;
+    }
+    if let Some(original_id) = forward_of_email_id {
+        add_flag_to_original(&app_handle, original_id, Flag::custom(FORWARDED_FLAG)).await;
+    }
 
+    emit_progress(SendStage::Done);
     Ok(())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn search_emails<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
     query_text: String,
@@ -1372,62 +2098,114 @@ pub async fn search_emails<R: tauri::Runtime>(
     limit: Option<u32>,
     before_date: Option<String>,
     before_id: Option<i64>,
+    thread_id: Option<String>,
+    email_id: Option<i64>,
+    date_from: Option<String>,
+    date_to: Option<String>,
+    min_size: Option<i64>,
+    has_attachment: Option<bool>,
 ) -> Result<Vec<Email>, String> {
     let pool = app_handle.state::<SqlitePool>();
-    
-    if query_text.trim().is_empty() {
+
+    let (query_text, attachment_type) = fts::take_attachment_type(&query_text);
+    // Without any text the filters alone make the search, only a search for nothing is empty
+    let filtered = attachment_type.is_some() || date_from.is_some() || date_to.is_some() || min_size.is_some() || has_attachment.is_some();
+    if query_text.is_empty() && !filtered {
         return Ok(Vec::new());
     }
 
-    // FTS5 works better with a '*' for prefix matching if the user is typing
-    // We wrap the term in double quotes for phrase matching and add * for prefix matching
-    // Example: \"query\"*
-    let fts_query = query_text.trim().replace("\"", "\"\"");
-    let fts_query = if fts_query.contains(' ') {
-        format!("\"{}\"", fts_query)
-    } else {
-        format!("{}*", fts_query)
+    // Scoped to one conversation: every matching message is returned instead of one per thread
+    let thread_scope = match (thread_id, email_id) {
+        (Some(tid), _) => Some(tid),
+        (None, Some(eid)) => {
+            let tid: Option<String> = sqlx::query_scalar("SELECT COALESCE(thread_id, message_id) FROM emails WHERE id = ?")
+                .bind(eid)
+                .fetch_optional(&*pool)
+                .await
+                .map_err(|e| e.to_string())?
+                .flatten();
+            Some(tid.ok_or("Email has no thread to search in")?)
+        }
+        (None, None) => None,
     };
 
+    // A bare `type:pdf` or a filter alone lists every email matching it
+    let fts_query = (!query_text.is_empty()).then(|| fts::build(&query_text));
+
     let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
         "WITH unique_messages AS (
-            SELECT e.*, f.role as folder_role,
-            ROW_NUMBER() OVER (
-                PARTITION BY e.account_id, e.message_id 
-                ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, e.date DESC
-            ) as msg_rn
+            SELECT e.*, f.role as folder_role
             FROM emails e
             JOIN folders f ON e.folder_id = f.id
-            JOIN emails_fts fts ON e.id = fts.rowid 
-            WHERE emails_fts MATCH "
+            JOIN messages m ON m.email_id = e.id"
     );
-    
-    query_builder.push_bind(fts_query);
+    match &fts_query {
+        Some(fts_query) => fts_query.push_match(&mut query_builder),
+        None => {
+            query_builder.push(" WHERE 1 = 1");
+        }
+    }
+    if let Some(kind) = attachment_type {
+        query_builder.push(" AND EXISTS (SELECT 1 FROM attachments a WHERE a.email_id = e.id AND NOT a.is_inline AND a.kind = ");
+        query_builder.push_bind(attachment_kind(&kind));
+        query_builder.push(")");
+    }
+    if let Some(tid) = &thread_scope {
+        query_builder.push(" AND (e.thread_id = ");
+        query_builder.push_bind(tid.clone());
+        query_builder.push(" OR e.message_id = ");
+        query_builder.push_bind(tid.clone());
+        query_builder.push(")");
+    }
+    if let Some(from) = date_from {
+        query_builder.push(" AND datetime(e.date) >= datetime(");
+        query_builder.push_bind(from);
+        query_builder.push(")");
+    }
+    if let Some(to) = date_to {
+        // The whole day of `date_to` is in range, whatever the time of the mail
+        query_builder.push(" AND datetime(e.date) < date(");
+        query_builder.push_bind(to);
+        query_builder.push(", '+1 day')");
+    }
+    if let Some(has_attachment) = has_attachment {
+        query_builder.push(" AND e.has_attachments = ");
+        query_builder.push_bind(has_attachment);
+    }
+    if let Some(min_size) = min_size {
+        // Mail synced before sizes were stored falls back to the estimate
+        query_builder.push(format!(" AND COALESCE(e.size, {}) >= ", cleanup::ESTIMATED_SIZE));
+        query_builder.push_bind(min_size);
+    }
     query_builder.push("),
           latest_threads AS (
             SELECT *,
-            ROW_NUMBER() OVER (
-                PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id) 
-                ORDER BY date DESC, id DESC
-            ) as thread_rn,
-            COUNT(*) OVER (
-                PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id)
-            ) as t_count
+            ROW_NUMBER() OVER (thread ORDER BY date DESC, id DESC) as thread_rn,
+            COUNT(*) OVER thread as t_count,
+            MAX(flags NOT LIKE '%\"seen\"%') OVER thread as t_unread,
+            MAX(has_attachments) OVER thread as t_attachments,
+            json_group_array(json_array(sender_name, sender_address)) OVER (thread ORDER BY date, id ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING) as t_participants
             FROM unique_messages
-            WHERE msg_rn = 1
+            WINDOW thread AS (
+                PARTITION BY account_id, COALESCE(NULLIF(thread_id, message_id), normalized_subject || '-' || sender_address || '-' || COALESCE(recipient_to, ''), message_id)
+            )
          )
-         SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, e.t_count as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments,
+         SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, e.t_count as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments, e.stack,
+         e.t_unread as thread_unread, e.t_attachments as thread_has_attachments, e.t_participants as participants,
+         (SELECT ac.color FROM accounts ac WHERE ac.id = e.account_id) as account_color,
          (e.subject LIKE 'Re:%' OR e.subject LIKE 're:%' OR e.in_reply_to IS NOT NULL) as is_reply,
-         (e.subject LIKE 'Fwd:%' OR e.subject LIKE 'fwd:%' OR e.subject LIKE 'Fw:%' OR e.subject LIKE 'fw:%') as is_forward
+         (e.subject LIKE 'Fwd:%' OR e.subject LIKE 'fwd:%' OR e.subject LIKE 'Fw:%' OR e.subject LIKE 'fw:%') as is_forward,
+         (LOWER(e.sender_address) IN (SELECT address FROM own_addresses)) as is_from_me
          FROM latest_threads e 
-         WHERE e.thread_rn = 1 ");
+         WHERE ");
+    query_builder.push(if thread_scope.is_some() { "1 = 1 " } else { "e.thread_rn = 1 " });
 
     if let Some(aid) = account_id {
         query_builder.push(" AND e.account_id = ");
         query_builder.push_bind(aid);
     }
 
-    if let Some(v) = view {
+    if let Some(v) = view.filter(|_| thread_scope.is_none()) {
         match v.as_str() {
             "primary" => query_builder.push(" AND e.folder_role = 'inbox'"),
             "spam" => query_builder.push(" AND e.folder_role = 'spam'"),
@@ -1454,11 +2232,11 @@ pub async fn search_emails<R: tauri::Runtime>(
     query_builder.push(" ORDER BY e.date DESC, e.id DESC LIMIT ");
     query_builder.push_bind(limit.unwrap_or(100) as i64);
 
-    let emails = query_builder
-        .build_query_as::<Email>()
-        .fetch_all(&*pool)
+    let sql = query_builder.sql().to_string();
+    let mut emails = profiling::timed(&pool, "search_emails", &sql, query_builder.build_query_as::<Email>().fetch_all(&*pool))
         .await
         .map_err(|e| e.to_string())?;
+    collapse_participants(&mut emails);
 
     Ok(emails)
 }
@@ -1477,7 +2255,7 @@ pub async fn get_folders<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, acc
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::test_utils::setup_test_db;
+    use crate::utils::test_utils::{add_mock_account, mock_message, setup_test_app, setup_test_db, MockMailServer};
     use tauri::test::mock_builder;
     use chrono::Utc;
 
@@ -1529,16 +2307,77 @@ mod tests {
         use tauri::Manager;
         let pool = setup_test_db().await;
         let (account_id, _, _) = seed_test_data(&pool).await;
+        sqlx::query("UPDATE accounts SET color = '#346ecd' WHERE id = ?")
+            .bind(account_id)
+            .execute(&pool)
+            .await
+            .unwrap();
         
         let app = mock_builder().build(tauri::generate_context!()).unwrap();
         app.manage(pool);
 
-        let emails = get_emails(app.handle().clone(), Some(account_id), Some("primary".to_string()), None, None, None)
+        let emails = get_emails(app.handle().clone(), Some(account_id), Some("primary".to_string()), None, None, None, None, None, None)
             .await
             .expect("Failed to get emails");
 
         assert_eq!(emails.len(), 1);
         assert_eq!(emails[0].subject, Some("Test Subject".to_string()));
+        assert_eq!(emails[0].account_color.as_deref(), Some("#346ecd"));
+    }
+
+    #[tokio::test]
+    async fn test_get_emails_by_account_pages_each_account() {
+        use tauri::Manager;
+        let pool = setup_test_db().await;
+        let (account_id, folder_id, _) = seed_test_data(&pool).await;
+        let (other_account,): (i64,) = sqlx::query_as("INSERT INTO accounts (email, account_type) VALUES ('other@example.com', 'google') RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let (other_folder,): (i64,) = sqlx::query_as("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'Inbox', 'INBOX', 'inbox') RETURNING id")
+            .bind(other_account)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        for (account, folder, n) in [(account_id, folder_id, 2), (account_id, folder_id, 3), (other_account, other_folder, 4)] {
+            sqlx::query(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, sender_address, date, flags)
+                 VALUES (?, ?, ?, ?, ?, ?, 'sender@example.com', ?, '[]')"
+            )
+            .bind(account)
+            .bind(folder)
+            .bind(format!("remote-{}", n))
+            .bind(format!("msg-{}", n))
+            .bind(format!("msg-{}", n))
+            .bind(format!("Subject {}", n))
+            .bind(format!("2024-01-0{}T00:00:00Z", n))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool);
+
+        let sections = get_emails_by_account(app.handle().clone(), None, None, None, Some(2), None, None, None)
+            .await
+            .expect("Failed to get sections");
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].account_id, account_id);
+        assert_eq!(sections[0].emails.len(), 2);
+        assert_eq!(sections[1].email, "other@example.com");
+        assert_eq!(sections[1].emails.len(), 1);
+        assert!(sections[1].next_cursor.is_none());
+
+        let cursor = sections[0].next_cursor.clone().expect("First account has more");
+        let next = get_emails_by_account(app.handle().clone(), Some(account_id), None, None, Some(2), Some(vec![cursor]), None, None)
+            .await
+            .expect("Failed to get next page");
+        assert_eq!(next.len(), 1);
+        assert_eq!(next[0].emails.len(), 1);
+        assert_eq!(next[0].emails[0].remote_id, "remote-2");
+        assert!(next[0].next_cursor.is_none());
     }
 
     #[tokio::test]
@@ -1581,12 +2420,116 @@ mod tests {
             .await
             .unwrap();
 
-        let emails = get_emails(app.handle().clone(), Some(account_id), Some("primary".to_string()), None, None, None)
+        let emails = get_emails(app.handle().clone(), Some(account_id), Some("primary".to_string()), None, None, None, None, None, None)
             .await
             .expect("Failed to get emails");
 
         assert_eq!(emails.len(), 1);
         assert_eq!(emails[0].thread_count, Some(2));
+        assert!(emails[0].thread_unread);
+        assert!(!emails[0].thread_has_attachments);
+
+        sqlx::query("UPDATE emails SET sender_name = 'Alice', has_attachments = 1 WHERE remote_id = 'remote-1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let emails = get_emails(app.handle().clone(), Some(account_id), Some("primary".to_string()), None, None, None, None, None, None)
+            .await
+            .expect("Failed to get emails");
+        assert!(emails[0].thread_has_attachments);
+        assert_eq!(emails[0].participants.as_deref(), Some(r#"[{"name":"Alice","address":"sender@example.com"}]"#));
+    }
+
+    #[tokio::test]
+    async fn test_copies_in_several_folders_list_once() {
+        use tauri::Manager;
+        let pool = setup_test_db().await;
+        let (account_id, inbox_id, inbox_copy) = seed_test_data(&pool).await;
+
+        // Gmail's All Mail holds a second copy of the inbox message
+        let (archive_id,): (i64,) = sqlx::query_as("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'All Mail', '[Gmail]/All Mail', 'archive') RETURNING id")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, sender_address, recipient_to, date, flags, has_attachments)
+             SELECT account_id, ?, 'remote-99', message_id, thread_id, subject, sender_address, recipient_to, date, flags, has_attachments FROM emails WHERE id = ?"
+        )
+        .bind(archive_id)
+        .bind(inbox_copy)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let (messages, listed): (i64, i64) = sqlx::query_as("SELECT COUNT(*), MAX(email_id) FROM messages WHERE account_id = ?")
+            .bind(account_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((messages, listed), (1, inbox_copy));
+
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool.clone());
+        let emails = get_emails(app.handle().clone(), Some(account_id), None, None, None, None, None, None, None).await.unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].folder_id, inbox_id);
+
+        // With the inbox copy gone, the archived one takes over
+        sqlx::query("DELETE FROM emails WHERE id = ?").bind(inbox_copy).execute(&pool).await.unwrap();
+        let emails = get_emails(app.handle().clone(), Some(account_id), Some("archive".to_string()), None, None, None, None, None, None).await.unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].folder_id, archive_id);
+    }
+
+    #[tokio::test]
+    async fn test_attachment_type_filters_list_and_search() {
+        use tauri::Manager;
+        let pool = setup_test_db().await;
+        let (account_id, _, email_id) = seed_test_data(&pool).await;
+        sqlx::query("INSERT INTO attachments (email_id, filename, mime_type, size) VALUES (?, 'report.pdf', 'application/pdf', 100)")
+            .bind(email_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool);
+
+        let pdfs = get_emails(app.handle().clone(), Some(account_id), None, None, None, None, None, Some("pdf".to_string()), None).await.unwrap();
+        assert_eq!(pdfs.len(), 1);
+        let docs = get_emails(app.handle().clone(), Some(account_id), None, None, None, None, None, Some("doc".to_string()), None).await.unwrap();
+        assert!(docs.is_empty());
+
+        let found = search_emails(app.handle().clone(), "type:pdf".to_string(), None, None, None, None, None, None, None, None, None, None, None).await.unwrap();
+        assert_eq!(found.len(), 1);
+        let found = search_emails(app.handle().clone(), "Test type:image".to_string(), None, None, None, None, None, None, None, None, None, None, None).await.unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_by_filters_alone() {
+        use tauri::Manager;
+        let pool = setup_test_db().await;
+        let (_, _, email_id) = seed_test_data(&pool).await;
+        sqlx::query("UPDATE emails SET date = '2024-03-05T18:30:00Z', size = 2000000 WHERE id = ?")
+            .bind(email_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let app = mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool);
+        let search = |date_to: &str, min_size: i64| {
+            search_emails(app.handle().clone(), String::new(), None, None, None, None, None, None, None, None, Some(date_to.to_string()), Some(min_size), None)
+        };
+
+        // The mail came in the evening of the last day of the range
+        assert_eq!(search("2024-03-05", 1_000_000).await.unwrap().len(), 1);
+        assert!(search("2024-03-04", 1_000_000).await.unwrap().is_empty());
+        assert!(search("2024-03-05", 3_000_000).await.unwrap().is_empty());
+        let nothing = search_emails(app.handle().clone(), String::new(), None, None, None, None, None, None, None, None, None, None, None).await.unwrap();
+        assert!(nothing.is_empty());
     }
 
     #[tokio::test]
@@ -1598,10 +2541,212 @@ mod tests {
         let app = mock_builder().build(tauri::generate_context!()).unwrap();
         app.manage(pool);
 
-        let content = get_email_content(app.handle().clone(), email_id)
+        let content = get_email_content(app.handle().clone(), email_id, None)
             .await
             .expect("Failed to get email content");
 
         assert_eq!(content.body_text, Some("Hello content".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_prefetch_queues_only_uncached_emails() {
+        use tauri::Manager;
+        let pool = setup_test_db().await;
+        let (account_id, folder_id, cached_id) = seed_test_data(&pool).await;
+        let (uncached_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, subject, sender_address, date, flags)
+             VALUES (?, ?, 'remote-2', 'msg-2', 'No body yet', 'sender@example.com', '2024-01-01T00:00:00Z', '[]') RETURNING id"
+        )
+        .bind(account_id)
+        .bind(folder_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let (app, _dir) = setup_test_app(pool).await;
+
+        prefetch_email_content(app.handle().clone(), vec![cached_id, uncached_id, -1]).await.unwrap();
+
+        let engine = app.state::<SyncEngine<tauri::test::MockRuntime>>();
+        assert_eq!(engine.next_prefetch().await, Some(uncached_id));
+        assert_eq!(engine.next_prefetch().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_mark_as_read_sets_seen_on_server() {
+        let server = MockMailServer::start().await;
+        let uid = server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Unread", "<unread@example.com>", "Hello"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let pool = app.state::<SqlitePool>();
+        let (email_id,): (i64,) = sqlx::query_as("SELECT id FROM emails WHERE remote_id = ?")
+            .bind(uid.to_string())
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+
+        mark_as_read(app.handle().clone(), vec![email_id]).await.expect("Failed to mark as read");
+
+        assert!(server.flags("INBOX", uid).iter().any(|f| f == "\\Seen"));
+        let (flags,): (String,) = sqlx::query_as("SELECT flags FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+        assert!(flags.contains("seen"));
+    }
+
+    #[tokio::test]
+    async fn test_moves_before_an_error_can_be_undone() {
+        let server = MockMailServer::start().await;
+        server.add_mailbox("Trash", Some("\\Trash"));
+        server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Old news", "<old@example.com>", "Hi"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let pool = app.state::<SqlitePool>();
+        let moved_id: i64 = sqlx::query_scalar("SELECT id FROM emails WHERE message_id = '<old@example.com>'").fetch_one(&*pool).await.unwrap();
+        // An account without a trash folder stops the batch at its email
+        let (other_account,): (i64,) = sqlx::query_as("INSERT INTO accounts (email, account_type) VALUES ('other@example.com', 'imap_smtp') RETURNING id")
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+        let (other_folder,): (i64,) = sqlx::query_as("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'INBOX', 'INBOX', 'inbox') RETURNING id")
+            .bind(other_account)
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+        let (stuck_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, date, flags) VALUES (?, ?, '1', 'Stuck', 'bob@example.com', '2024-01-01T00:00:00Z', '[]') RETURNING id"
+        )
+        .bind(other_account)
+        .bind(other_folder)
+        .fetch_one(&*pool)
+        .await
+        .unwrap();
+
+        assert!(move_to_trash(app.handle().clone(), vec![moved_id, stuck_id]).await.is_err());
+        assert_eq!(server.state.lock().unwrap().mailbox("Trash").unwrap().messages.len(), 1);
+        let entries: String = sqlx::query_scalar("SELECT entries FROM undo_journal WHERE action = 'trash'").fetch_one(&*pool).await.unwrap();
+        let entries: Vec<MovedEmail> = serde_json::from_str(&entries).unwrap();
+        assert_eq!(entries.iter().map(|e| e.email_id).collect::<Vec<_>>(), vec![moved_id]);
+    }
+
+    #[tokio::test]
+    async fn test_send_email_delivers_over_smtp() {
+        let server = MockMailServer::start().await;
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+
+        send_email(
+            app.handle().clone(),
+            account.id().unwrap(),
+            "Alice <alice@example.com>".to_string(),
+            Some("carol@example.com".to_string()),
+            Some("dave@example.com".to_string()),
+            "Hello".to_string(),
+            "<p>Hi Alice</p>".to_string(),
+            vec![],
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("Failed to send");
+
+        let sent = server.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].from, "me@example.com");
+        assert_eq!(sent[0].recipients, vec!["alice@example.com", "carol@example.com", "dave@example.com"]);
+
+        let data = String::from_utf8_lossy(&sent[0].data);
+        assert!(data.contains("Subject: Hello"));
+        // Bcc recipients get the mail without being listed
+        assert!(!data.contains("dave@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_smtp_session_is_reused_between_sends() {
+        let server = MockMailServer::start().await;
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account_id = add_mock_account(&app, &server).await.id().unwrap();
+        let send = |subject: &str| send_email(
+            app.handle().clone(),
+            account_id,
+            "alice@example.com".to_string(),
+            None,
+            None,
+            subject.to_string(),
+            "<p>Hi</p>".to_string(),
+            vec![],
+            None,
+            None,
+            None,
+        );
+
+        send("First").await.expect("Failed to send");
+        send("Second").await.expect("Failed to send");
+        assert_eq!(server.sent().len(), 2);
+        assert_eq!(server.state.lock().unwrap().smtp_sessions, 1);
+
+        // A dropped session signs in again on the next send
+        app.state::<SyncEngine<tauri::test::MockRuntime>>().invalidate_smtp_context(account_id).await;
+        send("Third").await.expect("Failed to send");
+        assert_eq!(server.sent().len(), 3);
+        assert_eq!(server.state.lock().unwrap().smtp_sessions, 2);
+    }
+
+    #[tokio::test]
+    async fn test_hung_imap_server_times_out_and_reconnects() {
+        let server = MockMailServer::start().await;
+        let uid = server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Slow", "<slow@example.com>", "Finally here"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        crate::db::settings::update_setting(app.handle().clone(), "imapTimeoutSecs".to_string(), "1".to_string()).await.unwrap();
+        let pool = app.state::<SqlitePool>();
+        let email_id: i64 = sqlx::query_scalar("SELECT id FROM emails WHERE remote_id = ?")
+            .bind(uid.to_string())
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+
+        server.state.lock().unwrap().stalled = true;
+        let err = cache_email_content(app.handle(), email_id).await.err();
+        assert_eq!(err, Some(i18n::t("error.imap_timeout", &[("seconds", "1")])));
+
+        // The hung connection was dropped, the next request connects again
+        server.state.lock().unwrap().stalled = false;
+        let content = cache_email_content(app.handle(), email_id).await.expect("Failed to fetch after the timeout");
+        assert!(content.body_text.unwrap_or_default().contains("Finally here"));
+    }
+
+    #[tokio::test]
+    async fn test_send_email_does_not_append_what_the_server_filed() {
+        use tauri::Manager;
+        let server = MockMailServer::start().await;
+        server.add_mailbox("Sent", Some("\\Sent"));
+        server.state.lock().unwrap().files_sent_in = Some("Sent".to_string());
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        send_email(app.handle().clone(), account.id().unwrap(), "alice@example.com".to_string(), None, None, "Hello".to_string(), "<p>Hi</p>".to_string(), vec![], None, None, None)
+            .await
+            .expect("Failed to send");
+
+        assert_eq!(server.state.lock().unwrap().mailbox("Sent").unwrap().messages.len(), 1);
+        let pool = app.state::<SqlitePool>();
+        let copies: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails e JOIN folders f ON e.folder_id = f.id WHERE f.role = 'sent'")
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+        assert_eq!(copies, 1);
+    }
 }
@@ -0,0 +1,136 @@
+use mail_builder::headers::address::Address;
+use crate::utils::i18n;
+
+/// A single recipient as typed in the composer, e.g. `"Doe, John" <john@example.com>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipient {
+    pub name: Option<String>,
+    pub address: String,
+}
+
+impl Recipient {
+    pub fn to_address(&self) -> Address<'static> {
+        Address::new_address(self.name.clone(), self.address.clone())
+    }
+}
+
+/// Header values end up on a single line, anything that could start a new header is rejected.
+fn check_header_value(value: &str, field: &str) -> Result<(), String> {
+    if value.chars().any(|c| c == '\r' || c == '\n' || c == '\0') {
+        return Err(format!("{} must not contain line breaks", field));
+    }
+    Ok(())
+}
+
+fn is_valid_address(address: &str) -> bool {
+    match address.rsplit_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.is_empty()
+                && domain.contains('.')
+                && !address.chars().any(|c| c.is_whitespace() || "<>()[],;:\"".contains(c))
+        }
+        None => false,
+    }
+}
+
+/// Splits on commas that aren't inside a quoted display name.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for c in value.chars() {
+        match c {
+            _ if escaped => {
+                escaped = false;
+                current.push(c);
+            }
+            '\\' if in_quotes => {
+                escaped = true;
+                current.push(c);
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ',' | ';' if !in_quotes => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+
+    items.into_iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+}
+
+fn parse_recipient(value: &str) -> Result<Recipient, String> {
+    let (name, address) = match (value.rfind('<'), value.ends_with('>')) {
+        (Some(start), true) => {
+            let name = value[..start].trim();
+            let name = name
+                .strip_prefix('"')
+                .and_then(|n| n.strip_suffix('"'))
+                .map(|n| n.replace("\\\"", "\"").replace("\\\\", "\\"))
+                .unwrap_or_else(|| name.to_string());
+            (Some(name).filter(|n| !n.is_empty()), value[start + 1..value.len() - 1].trim())
+        }
+        _ => (None, value),
+    };
+
+    if !is_valid_address(address) {
+        return Err(i18n::t("error.invalid_address", &[("address", value)]));
+    }
+
+    Ok(Recipient { name, address: address.to_string() })
+}
+
+/// Parses a comma separated recipient field, validating every address in it.
+pub fn parse_recipients(value: &str, field: &str) -> Result<Vec<Recipient>, String> {
+    check_header_value(value, field)?;
+    split_list(value).iter().map(|item| parse_recipient(item)).collect()
+}
+
+pub fn to_address_list(recipients: &[Recipient]) -> Address<'static> {
+    Address::new_list(recipients.iter().map(Recipient::to_address).collect())
+}
+
+pub fn validate_subject(subject: &str) -> Result<(), String> {
+    check_header_value(subject, "Subject")
+}
+
+/// Attachment names go into MIME parameters, keep them to one line and without path separators.
+pub fn sanitize_filename(filename: &str) -> String {
+    let cleaned: String = filename
+        .chars()
+        .map(|c| if c.is_control() || c == '/' || c == '\\' { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() { "attachment".to_string() } else { cleaned.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipients() {
+        let recipients = parse_recipients("\"Doe, John\" <john@example.com>, jane@example.org; Bob <bob@example.net>", "To").unwrap();
+        assert_eq!(recipients, vec![
+            Recipient { name: Some("Doe, John".to_string()), address: "john@example.com".to_string() },
+            Recipient { name: None, address: "jane@example.org".to_string() },
+            Recipient { name: Some("Bob".to_string()), address: "bob@example.net".to_string() },
+        ]);
+
+        assert!(parse_recipients("not an address", "To").is_err());
+        assert!(parse_recipients("a@example.com\r\nBcc: victim@example.com", "To").is_err());
+        assert!(parse_recipients("", "Cc").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_subject_and_filename() {
+        assert!(validate_subject("Café – “quoted” 日本語").is_ok());
+        assert!(validate_subject("Hi\r\nBcc: x@example.com").is_err());
+        assert_eq!(sanitize_filename("../report\n.pdf"), ".._report_.pdf");
+    }
+}
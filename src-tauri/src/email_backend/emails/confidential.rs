@@ -0,0 +1,199 @@
+use crate::db::writer::WritePool;
+use crate::email_backend::emails::cleanup;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+
+/// A sender whose mail is never cached locally: an address, or `@domain` for a whole domain.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NoCacheSender {
+    pub pattern: String,
+    pub created_at: Option<String>,
+}
+
+fn normalize_pattern(pattern: &str) -> Result<String, String> {
+    let pattern = pattern.trim().to_lowercase();
+    let pattern = if pattern.contains('@') { pattern } else { format!("@{}", pattern) };
+    let (local, domain) = pattern.split_once('@').unwrap_or_default();
+    if domain.is_empty() || !domain.contains('.') || domain.contains(['@', ' ']) || local.contains(' ') {
+        return Err(format!("Not an address or domain: {}", pattern));
+    }
+    Ok(pattern)
+}
+
+/// SQL condition for emails whose bodies and attachments stay off the disk, flagged one by
+/// one or covered by a sender pattern. `alias` is the emails table.
+pub(crate) fn uncached_condition(alias: &str) -> String {
+    format!(
+        "({0}.no_cache OR EXISTS (SELECT 1 FROM no_cache_senders n WHERE n.pattern = LOWER({0}.sender_address)
+             OR (n.pattern LIKE '@%' AND LOWER({0}.sender_address) LIKE '%' || n.pattern)))",
+        alias
+    )
+}
+
+/// Whether the email's content must only ever be held in memory.
+pub(crate) async fn is_uncached(pool: &SqlitePool, email_id: i64) -> Result<bool, String> {
+    let uncached: Option<bool> = sqlx::query_scalar(&format!("SELECT {} FROM emails e WHERE e.id = ?", uncached_condition("e")))
+        .bind(email_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(uncached.unwrap_or(false))
+}
+
+/// Drops what was cached of the emails before they became confidential: bodies, snippets,
+/// summaries, links, events from invites and downloaded attachments. The attachment list itself stays.
+async fn purge_local_copies<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_ids: &[i64]) -> Result<(), String> {
+    if email_ids.is_empty() {
+        return Ok(());
+    }
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+
+    let mut tx = app_handle.state::<WritePool>().begin().await?;
+    for chunk in email_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        for sql in [
+            format!("UPDATE emails SET body_text = NULL, body_html = NULL, body_truncated = 0, snippet = NULL, summary = NULL WHERE id IN ({})", placeholders),
+            format!("DELETE FROM links WHERE email_id IN ({})", placeholders),
+            format!("DELETE FROM events WHERE email_id IN ({})", placeholders),
+        ] {
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id);
+            }
+            query.execute(&mut *tx).await.map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    cleanup::strip_attachments(app_handle, &pool, email_ids).await?;
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
+/// Marks emails as confidential, or back to normal. Marking them drops their local copies
+/// right away, clearing the flag lets them be cached again the next time they are opened.
+#[tauri::command]
+pub async fn set_email_no_cache<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, no_cache: bool) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+    for chunk in email_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!("UPDATE emails SET no_cache = ? WHERE id IN ({})", placeholders);
+        let mut query = sqlx::query(&sql).bind(no_cache);
+        for id in chunk {
+            query = query.bind(id);
+        }
+        query.execute(&pool).await.map_err(|e| e.to_string())?;
+    }
+
+    if no_cache {
+        purge_local_copies(&app_handle, &email_ids).await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_no_cache_senders<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<NoCacheSender>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as("SELECT pattern, created_at FROM no_cache_senders ORDER BY pattern")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Stops caching mail from an address or a domain (`@example.com` or `example.com`), what
+/// is already cached from them is dropped.
+#[tauri::command]
+pub async fn add_no_cache_sender<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, pattern: String) -> Result<NoCacheSender, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+    let pattern = normalize_pattern(&pattern)?;
+
+    let sender: NoCacheSender = sqlx::query_as(
+        "INSERT INTO no_cache_senders (pattern) VALUES (?)
+         ON CONFLICT(pattern) DO UPDATE SET pattern = excluded.pattern
+         RETURNING pattern, created_at"
+    )
+    .bind(&pattern)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let email_ids: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM emails WHERE LOWER(sender_address) = ? OR (? LIKE '@%' AND LOWER(sender_address) LIKE '%' || ?)"
+    )
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&pattern)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    purge_local_copies(&app_handle, &email_ids).await?;
+
+    Ok(sender)
+}
+
+/// New mail from the sender is cached as usual again, emails flagged one by one stay confidential.
+#[tauri::command]
+pub async fn remove_no_cache_sender<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, pattern: String) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("DELETE FROM no_cache_senders WHERE pattern = ?")
+        .bind(normalize_pattern(&pattern)?)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_app, setup_test_db};
+
+    #[tokio::test]
+    async fn test_sender_patterns_purge_and_match() {
+        let pool = setup_test_db().await;
+        let (app, _dir) = setup_test_app(pool.clone()).await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+
+        let mut ids = Vec::new();
+        for (i, sender) in ["Counsel@Legal.example.com", "alice@example.org", "bob@example.org"].iter().enumerate() {
+            let (id,): (i64,) = sqlx::query_as(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, subject, sender_address, date, flags, body_text, snippet)
+                 VALUES (?, ?, ?, ?, 'Contract', ?, '2024-01-01T00:00:00Z', '[]', 'Privileged', 'Privileged') RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(i.to_string())
+            .bind(format!("msg-{}", i))
+            .bind(sender)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        sqlx::query("INSERT INTO events (account_id, email_id, uid, summary, start_at) VALUES (?, ?, 'offsite', 'Offsite', '2024-02-01T09:00:00Z')")
+            .bind(account_id)
+            .bind(ids[1])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let sender = add_no_cache_sender(app.handle().clone(), "Legal.Example.com".to_string()).await.unwrap();
+        assert_eq!(sender.pattern, "@legal.example.com");
+        set_email_no_cache(app.handle().clone(), vec![ids[1]], true).await.unwrap();
+
+        let bodies: Vec<Option<String>> = sqlx::query_scalar("SELECT body_text FROM emails ORDER BY id").fetch_all(&pool).await.unwrap();
+        assert_eq!(bodies, vec![None, None, Some("Privileged".to_string())]);
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events").fetch_one(&pool).await.unwrap();
+        assert_eq!(events, 0);
+        assert!(is_uncached(&pool, ids[0]).await.unwrap());
+        assert!(is_uncached(&pool, ids[1]).await.unwrap());
+        assert!(!is_uncached(&pool, ids[2]).await.unwrap());
+
+        remove_no_cache_sender(app.handle().clone(), "@legal.example.com".to_string()).await.unwrap();
+        assert!(!is_uncached(&pool, ids[0]).await.unwrap());
+        assert!(normalize_pattern("not an address").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+/// How far back sender stats go when looking for noisy domains.
+const NOISY_WINDOW_DAYS: i64 = 30;
+/// Inbox emails from a domain in the window before it counts as noisy.
+const NOISY_MIN_COUNT: i64 = 20;
+/// Share of a domain's mail that has to look automated, so colleagues' domains stay out.
+const NOISY_AUTOMATED_SHARE: f64 = 0.8;
+
+/// Local parts of addresses that only ever send machine-generated mail.
+const AUTOMATED_LOCAL_PARTS: &[&str] = &[
+    "noreply", "no-reply", "donotreply", "do-not-reply", "notifications", "notification",
+    "alerts", "builds", "ci", "jira", "jenkins", "bot",
+];
+
+/// What happens to new mail from a domain and its subdomains.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DomainPolicy {
+    pub domain: String,
+    pub mute_notifications: bool,
+    /// Moved to the archive as it arrives, unread
+    pub auto_archive: bool,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// A domain sending lots of mail that looks automated, CI systems and issue trackers mostly.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NoisyDomain {
+    pub domain: String,
+    pub count: i64,
+    pub sender_count: i64,
+    /// 0 to 1
+    pub automated_share: f64,
+    pub has_policy: bool,
+}
+
+fn normalize_domain(domain: &str) -> Result<String, String> {
+    let domain = domain.trim().trim_start_matches('@').to_lowercase();
+    if domain.is_empty() || !domain.contains('.') || domain.contains(['@', ' ']) {
+        return Err(format!("Not a domain: {}", domain));
+    }
+    Ok(domain)
+}
+
+/// SQL condition for mail that looks automated: list traffic, senders enrichment flagged as
+/// automated mailers, and no-reply style addresses. Needs `e` for emails and `s` for senders.
+fn automated_condition() -> String {
+    let local_parts: Vec<String> = AUTOMATED_LOCAL_PARTS
+        .iter()
+        .map(|part| format!("LOWER(e.sender_address) LIKE '{}@%'", part))
+        .collect();
+    format!("(e.list_id IS NOT NULL OR COALESCE(s.is_automated_mailer, 0) = 1 OR {})", local_parts.join(" OR "))
+}
+
+async fn noisy_domains(pool: &SqlitePool, min_count: i64) -> Result<Vec<NoisyDomain>, String> {
+    sqlx::query_as(&format!(
+        "WITH received AS (
+            SELECT LOWER(SUBSTR(e.sender_address, INSTR(e.sender_address, '@') + 1)) as domain,
+                   LOWER(e.sender_address) as address,
+                   {} as automated
+            FROM emails e
+            JOIN folders f ON e.folder_id = f.id
+            LEFT JOIN senders s ON s.address = LOWER(e.sender_address)
+            WHERE f.role = 'inbox' AND INSTR(e.sender_address, '@') > 0 AND datetime(e.date) > datetime('now', ?)
+        )
+        SELECT r.domain, COUNT(*) as count, COUNT(DISTINCT r.address) as sender_count,
+               AVG(r.automated) as automated_share, MAX(p.domain IS NOT NULL) as has_policy
+        FROM received r LEFT JOIN domain_policies p ON p.domain = r.domain
+        GROUP BY r.domain
+        HAVING count >= ? AND automated_share >= ?
+        ORDER BY count DESC",
+        automated_condition()
+    ))
+    .bind(format!("-{} days", NOISY_WINDOW_DAYS))
+    .bind(min_count)
+    .bind(NOISY_AUTOMATED_SHARE)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// The policy covering the sender, the most specific domain winning.
+pub(crate) async fn policy_for_sender(pool: &SqlitePool, sender_address: &str) -> Result<Option<DomainPolicy>, String> {
+    let Some((_, domain)) = sender_address.trim().rsplit_once('@') else {
+        return Ok(None);
+    };
+    let domain = domain.to_lowercase();
+    sqlx::query_as(
+        "SELECT domain, mute_notifications, auto_archive, updated_at FROM domain_policies
+         WHERE domain = ? OR ? LIKE '%.' || domain
+         ORDER BY LENGTH(domain) DESC LIMIT 1"
+    )
+    .bind(&domain)
+    .bind(&domain)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Domains that sent at least `min_count` inbox emails in the last 30 days, nearly all of
+/// them automated, busiest first.
+#[tauri::command]
+pub async fn get_noisy_domains<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, min_count: Option<i64>) -> Result<Vec<NoisyDomain>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    noisy_domains(&pool, min_count.unwrap_or(NOISY_MIN_COUNT)).await
+}
+
+#[tauri::command]
+pub async fn get_domain_policies<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<DomainPolicy>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as("SELECT domain, mute_notifications, auto_archive, updated_at FROM domain_policies ORDER BY domain")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Applies to mail arriving from now on, what is already in the inbox stays.
+#[tauri::command]
+pub async fn set_domain_policy<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, domain: String, mute_notifications: bool, auto_archive: bool) -> Result<DomainPolicy, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as(
+        "INSERT INTO domain_policies (domain, mute_notifications, auto_archive) VALUES (?, ?, ?)
+         ON CONFLICT(domain) DO UPDATE SET mute_notifications = excluded.mute_notifications, auto_archive = excluded.auto_archive, updated_at = CURRENT_TIMESTAMP
+         RETURNING domain, mute_notifications, auto_archive, updated_at"
+    )
+    .bind(normalize_domain(&domain)?)
+    .bind(mute_notifications)
+    .bind(auto_archive)
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_domain_policy<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, domain: String) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("DELETE FROM domain_policies WHERE domain = ?")
+        .bind(normalize_domain(&domain)?)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[tokio::test]
+    async fn test_noisy_domains_and_subdomain_policies() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+
+        let date = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let senders = (0..3).map(|_| "builds@ci.example.com").chain((0..3).map(|_| "alice@example.org")).chain(["jira@ci.example.com"]);
+        for (i, sender) in senders.enumerate() {
+            sqlx::query(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, subject, sender_address, date, flags)
+                 VALUES (?, ?, ?, ?, 'Update', ?, ?, '[]')"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(i.to_string())
+            .bind(format!("msg-{}", i))
+            .bind(sender)
+            .bind(&date)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        // A colleague's domain isn't noisy however much they write
+        let noisy = noisy_domains(&pool, 3).await.unwrap();
+        assert_eq!(noisy.len(), 1);
+        assert_eq!((noisy[0].domain.as_str(), noisy[0].count, noisy[0].sender_count), ("ci.example.com", 4, 2));
+
+        sqlx::query("INSERT INTO domain_policies (domain, mute_notifications, auto_archive) VALUES ('example.com', 1, 0), ('ci.example.com', 1, 1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let policy = policy_for_sender(&pool, "Builds@CI.example.com").await.unwrap().unwrap();
+        assert_eq!(policy.domain, "ci.example.com");
+        assert!(policy.auto_archive);
+        assert_eq!(policy_for_sender(&pool, "bob@mail.example.com").await.unwrap().unwrap().domain, "example.com");
+        assert!(policy_for_sender(&pool, "bob@notexample.com").await.unwrap().is_none());
+
+        assert_eq!(normalize_domain(" @CI.Example.com").unwrap(), "ci.example.com");
+        assert!(normalize_domain("me@example.com").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::db::writer::WritePool;
+use crate::email_backend::emails::bulk::{delete_local, group_by_folder, run_bulk, BulkAction};
+use crate::email_backend::emails::events::EmailEvent;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DuplicateCopy {
+    pub email_id: i64,
+    pub folder_id: i64,
+    pub folder_name: String,
+    pub role: Option<String>,
+    /// The copy `remove_duplicates` keeps
+    pub canonical: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub account_id: i64,
+    pub message_id: String,
+    pub subject: Option<String>,
+    pub copies: Vec<DuplicateCopy>,
+}
+
+/// The copy to keep sorts first: inbox, then sent, archive, other folders, and spam/trash last.
+const CANONICAL_ORDER: &str = "CASE f.role WHEN 'inbox' THEN 0 WHEN 'sent' THEN 1 WHEN 'archive' THEN 2 WHEN 'spam' THEN 4 WHEN 'trash' THEN 4 ELSE 3 END, e.id";
+
+/// Every copy of a message that exists more than once. `same_folder` restricts duplicates to
+/// copies within one folder, what imports leave behind, as opposed to Gmail-style labels.
+fn duplicates_query(same_folder: bool) -> String {
+    let partition = if same_folder { "e.account_id, e.message_id, e.folder_id" } else { "e.account_id, e.message_id" };
+    format!(
+        "WITH copies AS (
+            SELECT e.id, e.account_id, e.folder_id, e.message_id, e.subject, e.remote_id, e.flags, f.name as folder_name, f.path, f.role,
+                   a.account_type,
+                   ROW_NUMBER() OVER (PARTITION BY {0} ORDER BY {1}) as rn,
+                   COUNT(*) OVER (PARTITION BY {0}) as copies
+            FROM emails e
+            JOIN folders f ON e.folder_id = f.id
+            JOIN accounts a ON e.account_id = a.id
+            WHERE e.message_id IS NOT NULL AND e.message_id != '' AND (? IS NULL OR e.account_id = ?)
+         )
+         SELECT id, account_id, folder_id, message_id, subject, remote_id, flags, folder_name, path, role, account_type, rn
+         FROM copies WHERE copies > 1
+         ORDER BY account_id, message_id, rn",
+        partition, CANONICAL_ORDER
+    )
+}
+
+type CopyRow = (i64, i64, i64, String, Option<String>, String, String, String, String, Option<String>, String, i64);
+
+async fn fetch_copies(pool: &SqlitePool, same_folder: bool, account_id: Option<i64>) -> Result<Vec<CopyRow>, String> {
+    sqlx::query_as(&duplicates_query(same_folder))
+        .bind(account_id)
+        .bind(account_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn find_duplicates<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: Option<i64>) -> Result<Vec<DuplicateGroup>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let rows = fetch_copies(&pool, false, account_id).await?;
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for (id, account_id, folder_id, message_id, subject, _, _, folder_name, _, role, _, rn) in rows {
+        let copy = DuplicateCopy { email_id: id, folder_id, folder_name, role, canonical: rn == 1 };
+        match groups.last_mut() {
+            Some(group) if group.account_id == account_id && group.message_id == message_id => group.copies.push(copy),
+            _ => groups.push(DuplicateGroup { account_id, message_id, subject, copies: vec![copy] }),
+        }
+    }
+    Ok(groups)
+}
+
+/// Removes every copy but the canonical one.
+///
+/// `strategy` is `same_folder` (only repeated copies inside a folder) or `across_folders`.
+/// With `remove_on_server` the extra copies are also expunged from the server, except for Gmail
+/// accounts across folders, where the "copies" are labels of a single message.
+#[tauri::command]
+pub async fn remove_duplicates<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    strategy: String,
+    remove_on_server: bool,
+    account_id: Option<i64>,
+) -> Result<usize, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+    let same_folder = match strategy.as_str() {
+        "same_folder" => true,
+        "across_folders" => false,
+        other => return Err(format!("Unknown strategy: {}", other)),
+    };
+
+    let mut on_server = Vec::new();
+    let mut local_only = Vec::new();
+    for (id, account_id, folder_id, _, _, remote_id, flags, _, path, _, account_type, rn) in fetch_copies(&pool, same_folder, account_id).await? {
+        if rn == 1 {
+            continue;
+        }
+        let row = (id, account_id, folder_id, path, remote_id, !flags.contains("\"seen\""));
+        if remove_on_server && (same_folder || account_type != "google") {
+            on_server.push(row);
+        } else {
+            local_only.push(row);
+        }
+    }
+
+    let mut removed = run_bulk(&app_handle, "remove_duplicates", BulkAction::DeletePermanently, group_by_folder(on_server)).await?;
+
+    let local = delete_local(&app_handle.state::<WritePool>(), group_by_folder(local_only)).await?;
+    if !local.is_empty() {
+        let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids: local.clone() });
+    }
+    removed.extend(local);
+
+    info!("Removed {} duplicate email(s) ({})", removed.len(), strategy);
+    Ok(removed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email_backend::sync::SyncEngine;
+    use crate::utils::test_utils::{add_mock_account, mock_message, setup_test_app, setup_test_db, MockMailServer};
+
+    #[tokio::test]
+    async fn test_remove_duplicates_expunges_extra_copies_on_server() {
+        let server = MockMailServer::start().await;
+        server.add_mailbox("Archive", Some("\\Archive"));
+        let raw = mock_message("Alice <alice@example.com>", "Twice", "<twice@example.com>", "Hi");
+        let kept = server.add_message("INBOX", &raw, &[]);
+        server.add_message("Archive", &raw, &["\\Seen"]);
+        let other = server.add_message("Archive", &mock_message("Bob <bob@example.com>", "Once", "<once@example.com>", "Hi"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let groups = find_duplicates(app.handle().clone(), None).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        let canonical: Vec<Option<String>> = groups[0].copies.iter().filter(|c| c.canonical).map(|c| c.role.clone()).collect();
+        assert_eq!(canonical, vec![Some("inbox".to_string())]);
+
+        assert_eq!(remove_duplicates(app.handle().clone(), "across_folders".to_string(), true, None).await.unwrap(), 1);
+
+        // The inbox copy stays, the archive keeps its other mail
+        let state = server.state.lock().unwrap();
+        let uids = |name: &str| state.mailbox(name).unwrap().messages.iter().map(|m| m.uid).collect::<Vec<u32>>();
+        assert_eq!(uids("INBOX"), vec![kept]);
+        assert_eq!(uids("Archive"), vec![other]);
+        drop(state);
+
+        let pool = app.state::<SqlitePool>();
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE message_id = '<twice@example.com>'").fetch_one(&*pool).await.unwrap();
+        assert_eq!(left, 1);
+        assert!(find_duplicates(app.handle().clone(), None).await.unwrap().is_empty());
+    }
+}
@@ -29,3 +29,29 @@ pub enum EmailEvent {
     #[serde(rename = "emails-removed-bulk")]
     RemovedBulk { ids: Vec<i64> },
 }
+
+/// Payload of the `attachment-download-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachmentDownloadProgress {
+    pub attachment_id: i64,
+    pub downloaded: i64,
+    pub total: i64,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SendStage {
+    Connecting,
+    Sending,
+    SavingToSent,
+    Done,
+    Cancelled,
+}
+
+/// Payload of the `send-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct SendProgress {
+    pub request_id: Option<String>,
+    pub stage: SendStage,
+}
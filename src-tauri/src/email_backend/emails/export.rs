@@ -0,0 +1,159 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::emails::commands::{get_email_content, get_thread_emails, Email};
+use crate::email_backend::emails::print::format_date;
+use crate::email_backend::emails::quotes::strip_quoted;
+use crate::utils::i18n;
+
+#[derive(Debug, Serialize)]
+pub struct ExportedThread {
+    pub subject: String,
+    /// Oldest first
+    pub messages: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedMessage {
+    pub message_id: Option<String>,
+    pub in_reply_to: Option<String>,
+    pub sender_name: Option<String>,
+    pub sender_address: String,
+    pub recipient_to: Option<String>,
+    /// RFC 3339, as stored
+    pub date: String,
+    /// Plain text, without the quoted message it answers
+    pub body: String,
+    pub attachments: Vec<String>,
+}
+
+async fn export_message<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email: Email) -> Result<ExportedMessage, String> {
+    let content = get_email_content(app_handle.clone(), email.id, None).await?;
+    let text = match (content.body_text, content.body_html) {
+        (Some(text), _) => text,
+        (None, Some(html)) => mail_parser::decoders::html::html_to_text(&html),
+        (None, None) => String::new(),
+    };
+
+    let pool = app_handle.state::<SqlitePool>();
+    let attachments: Vec<String> = sqlx::query_scalar(
+        "SELECT COALESCE(filename, 'attachment') FROM attachments WHERE email_id = ? AND NOT is_inline ORDER BY id"
+    )
+    .bind(email.id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(ExportedMessage {
+        message_id: email.message_id,
+        in_reply_to: email.in_reply_to,
+        sender_name: email.sender_name.filter(|name| !name.is_empty()),
+        sender_address: email.sender_address,
+        recipient_to: email.recipient_to.filter(|to| !to.is_empty()),
+        date: email.date,
+        body: strip_quoted(&text),
+        attachments,
+    })
+}
+
+fn to_markdown(thread: &ExportedThread) -> String {
+    let mut out = format!("# {}\n", thread.subject);
+    for message in &thread.messages {
+        let from = match &message.sender_name {
+            Some(name) => format!("{} <{}>", name, message.sender_address),
+            None => message.sender_address.clone(),
+        };
+        out.push_str(&format!("\n---\n\n**{}**: {}  \n", i18n::t("print.from", &[]), from));
+        if let Some(to) = &message.recipient_to {
+            out.push_str(&format!("**{}**: {}  \n", i18n::t("print.to", &[]), to));
+        }
+        out.push_str(&format!("**{}**: {}\n", i18n::t("print.date", &[]), format_date(&message.date)));
+        if !message.body.is_empty() {
+            out.push_str(&format!("\n{}\n", message.body));
+        }
+        if !message.attachments.is_empty() {
+            out.push_str(&format!("\n*{}: {}*\n", i18n::t("print.attachments", &[]), message.attachments.join(", ")));
+        }
+    }
+    out
+}
+
+/// The whole conversation of `email_id`, oldest first, as `markdown` for pasting into a
+/// ticket or as `json` for other tools. Quoted text is left out of every message.
+#[tauri::command]
+pub async fn export_thread<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, format: String) -> Result<String, String> {
+    if !matches!(format.as_str(), "markdown" | "json") {
+        return Err(format!("Unknown export format: {}", format));
+    }
+
+    let mut emails = get_thread_emails(app_handle.clone(), email_id, None, None).await?;
+    emails.reverse();
+    let subject = emails
+        .first()
+        .and_then(|e| e.subject.clone())
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| i18n::t("email.no_subject", &[]));
+
+    let mut messages = Vec::with_capacity(emails.len());
+    for email in emails {
+        messages.push(export_message(&app_handle, email).await?);
+    }
+    let thread = ExportedThread { subject, messages };
+
+    match format.as_str() {
+        "json" => serde_json::to_string_pretty(&thread).map_err(|e| e.to_string()),
+        _ => Ok(to_markdown(&thread)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[tokio::test]
+    async fn test_export_thread_as_markdown_and_json() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+        let messages = [
+            ("msg-1", "Alice", "alice@example.com", "2024-01-01T09:00:00Z", "Lunch on Friday?"),
+            ("msg-2", "Bob", "bob@example.com", "2024-01-01T10:00:00Z", "Yes!\n\nOn Mon, Alice wrote:\n> Lunch on Friday?"),
+        ];
+        let mut ids = Vec::new();
+        for (message_id, name, address, date, body) in messages {
+            let (id,): (i64,) = sqlx::query_as(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, normalized_subject, sender_name, sender_address, recipient_to, date, flags, body_text)
+                 VALUES (?, ?, ?, ?, 'msg-1', 'Lunch', 'lunch', ?, ?, 'me@example.com', ?, '[]', ?) RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(message_id)
+            .bind(message_id)
+            .bind(name)
+            .bind(address)
+            .bind(date)
+            .bind(body)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        let app = tauri::test::mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool);
+
+        let json = export_thread(app.handle().clone(), ids[1], "json".to_string()).await.unwrap();
+        let thread: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(thread["subject"], "Lunch");
+        assert_eq!(thread["messages"][0]["sender_address"], "alice@example.com");
+        assert_eq!(thread["messages"][1]["body"], "Yes!");
+
+        let markdown = export_thread(app.handle().clone(), ids[0], "markdown".to_string()).await.unwrap();
+        assert!(markdown.starts_with("# Lunch\n"));
+        assert!(markdown.contains("Bob <bob@example.com>"));
+        assert!(!markdown.contains("> Lunch"));
+
+        assert!(export_thread(app.handle().clone(), ids[0], "pdf".to_string()).await.is_err());
+    }
+}
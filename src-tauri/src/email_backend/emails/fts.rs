@@ -0,0 +1,147 @@
+/// How a search box query is run against the two full text indexes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FtsQuery {
+    /// Word based match on `emails_fts`
+    Words(String),
+    /// Substring match on `emails_fts_trigram`, for text without word boundaries
+    Trigram(String),
+    /// Trigrams need three characters, shorter CJK queries fall back to LIKE
+    Substring(String),
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF   // Hiragana, Katakana
+        | 0x3400..=0x4DBF // CJK Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF // Hangul
+        | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+        | 0xFF66..=0xFF9F // Half-width Katakana
+    )
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Splits `type:pdf` style attachment filters out of a search box query, returning the rest
+/// of the text and the last type given.
+pub fn take_attachment_type(query_text: &str) -> (String, Option<String>) {
+    let mut kind = None;
+    let mut words = Vec::new();
+    for word in query_text.split_whitespace() {
+        match word.split_once(':') {
+            Some((key, value)) if key.eq_ignore_ascii_case("type") && !value.is_empty() => kind = Some(value.to_string()),
+            _ => words.push(word),
+        }
+    }
+    (words.join(" "), kind)
+}
+
+pub fn build(query_text: &str) -> FtsQuery {
+    let text = query_text.trim();
+
+    if text.chars().any(is_cjk) {
+        return if text.chars().count() >= 3 {
+            FtsQuery::Trigram(quote(text))
+        } else {
+            FtsQuery::Substring(text.to_string())
+        };
+    }
+
+    // Phrase match for several words, prefix match while the user is still typing a single one
+    if text.contains(' ') {
+        FtsQuery::Words(quote(text))
+    } else {
+        FtsQuery::Words(format!("{}*", quote(text)))
+    }
+}
+
+impl FtsQuery {
+    /// Pushes the join against the index and the matching condition, for an `emails e` alias.
+    pub fn push_match(&self, query: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>) {
+        match self {
+            FtsQuery::Words(q) => {
+                query.push(" JOIN emails_fts fts ON e.id = fts.rowid WHERE emails_fts MATCH ");
+                query.push_bind(q.clone());
+            }
+            FtsQuery::Trigram(q) => {
+                query.push(" JOIN emails_fts_trigram fts ON e.id = fts.rowid WHERE emails_fts_trigram MATCH ");
+                query.push_bind(q.clone());
+            }
+            FtsQuery::Substring(text) => {
+                let pattern = format!("%{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+                query.push(" WHERE (e.subject LIKE ");
+                query.push_bind(pattern.clone());
+                query.push(" ESCAPE '\\' OR e.body_text LIKE ");
+                query.push_bind(pattern.clone());
+                query.push(" ESCAPE '\\' OR e.sender_name LIKE ");
+                query.push_bind(pattern.clone());
+                query.push(" ESCAPE '\\' OR e.note LIKE ");
+                query.push_bind(pattern.clone());
+                query.push(" ESCAPE '\\' OR e.snippet LIKE ");
+                query.push_bind(pattern.clone());
+                query.push(" ESCAPE '\\' OR e.summary LIKE ");
+                query.push_bind(pattern);
+                query.push(" ESCAPE '\\')");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[test]
+    fn test_build_picks_index_by_script() {
+        assert_eq!(build("invoice"), FtsQuery::Words("\"invoice\"*".to_string()));
+        assert_eq!(build(" quarterly report "), FtsQuery::Words("\"quarterly report\"".to_string()));
+        assert_eq!(build("会議の議事録"), FtsQuery::Trigram("\"会議の議事録\"".to_string()));
+        assert_eq!(build("会議"), FtsQuery::Substring("会議".to_string()));
+    }
+
+    #[test]
+    fn test_take_attachment_type() {
+        assert_eq!(take_attachment_type("type:pdf invoice"), ("invoice".to_string(), Some("pdf".to_string())));
+        assert_eq!(take_attachment_type("quarterly  Type:doc report"), ("quarterly report".to_string(), Some("doc".to_string())));
+        assert_eq!(take_attachment_type("type: ratio 16:9"), ("type: ratio 16:9".to_string(), None));
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_accents_and_cjk() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+        for (remote_id, subject) in [("1", "Réunion au café"), ("2", "明日の会議の議事録")] {
+            sqlx::query("INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, date, flags) VALUES (?, ?, ?, ?, 'a@example.com', '2024-01-01T00:00:00Z', '[]')")
+                .bind(account_id)
+                .bind(folder_id)
+                .bind(remote_id)
+                .bind(subject)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // Only the preview and the summary of the third one are known, its body was never fetched
+        sqlx::query("INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, date, flags) VALUES (?, ?, '3', 'Hello', 'a@example.com', '2024-01-01T00:00:00Z', '[]')")
+            .bind(account_id)
+            .bind(folder_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE emails SET snippet = 'The invoice for March is attached', summary = '見積書の確認依頼' WHERE remote_id = '3'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for (text, expected) in [("cafe", "1"), ("REUNION", "1"), ("会議の議事", "2"), ("議事", "2"), ("invoice", "3"), ("見積書の確認", "3"), ("見積", "3")] {
+            let mut query = sqlx::QueryBuilder::new("SELECT e.remote_id FROM emails e");
+            build(text).push_match(&mut query);
+            let found: Vec<String> = query.build_query_scalar().fetch_all(&pool).await.unwrap();
+            assert_eq!(found, vec![expected.to_string()], "searching {}", text);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::email_backend::emails::commands::server_remote_id;
+use crate::email_backend::emails::events::EmailEvent;
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::i18n;
+use email::envelope::Id;
+use email::flag::add::AddFlags;
+use email::flag::remove::RemoveFlags;
+use email::flag::Flag;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+
+/// System flags and well-known keywords clients set for their own bookkeeping, none of them a tag.
+const NON_TAG_FLAGS: &[&str] = &[
+    "seen", "answered", "flagged", "deleted", "draft",
+    "$forwarded", "$mdnsent", "$junk", "$notjunk", "junk", "nonjunk", "$phishing",
+    "$submitpending", "$submitted",
+];
+
+/// Whether a stored flag is a keyword the user put on the message, shown as a tag.
+pub fn is_tag(flag: &str) -> bool {
+    !flag.is_empty() && !flag.starts_with('\\') && !NON_TAG_FLAGS.contains(&flag.to_lowercase().as_str())
+}
+
+/// Keywords are IMAP atoms: no spaces, controls or the characters IMAP gives a meaning to.
+pub(crate) fn validate_keyword(keyword: &str) -> Result<(), String> {
+    let valid = !keyword.is_empty()
+        && keyword.chars().all(|c| c.is_ascii_graphic() && !"(){%*\"\\]".contains(c));
+    if !valid || !is_tag(keyword) {
+        return Err(format!("Invalid tag: {}", keyword));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeywordTag {
+    pub name: String,
+    pub count: i64,
+}
+
+/// Adds or removes `keyword` on the server first, then locally, like the other flag changes.
+pub(crate) async fn set_keyword<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_ids: Vec<i64>, keyword: &str, add: bool) -> Result<(), String> {
+    validate_keyword(keyword)?;
+    let pool = app_handle.state::<SqlitePool>();
+    let engine = app_handle.state::<SyncEngine<R>>();
+    let mut updated_ids = Vec::new();
+    let mut final_flags = String::new();
+    let mut failed = None;
+
+    for email_id in email_ids {
+        let email_info: Option<(i64, String, i64, String, String)> = sqlx::query_as(
+            "SELECT e.account_id, e.remote_id, e.folder_id, f.path, e.flags FROM emails e JOIN folders f ON e.folder_id = f.id WHERE e.id = ?"
+        )
+        .bind(email_id)
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let Some((account_id, remote_id, folder_id, folder_path, current_flags)) = email_info else { continue };
+
+        let mut flags: Vec<String> = serde_json::from_str(&current_flags).unwrap_or_default();
+        if flags.iter().any(|f| f == keyword) == add {
+            continue;
+        }
+
+        let result = match engine.get_backend(account_id).await {
+            Ok(backend) => match server_remote_id(app_handle, email_id, account_id, folder_id, remote_id).await {
+                Ok(remote_id) => {
+                    let id = Id::single(remote_id);
+                    let flag = Flag::custom(keyword);
+                    let result = if add {
+                        backend.add_flag(&folder_path, &id, flag).await
+                    } else {
+                        backend.remove_flag(&folder_path, &id, flag).await
+                    };
+                    result.map_err(|e| i18n::t("error.tag_server", &[("error", &e.to_string())]))
+                }
+                Err(e) => Err(i18n::t("error.tag_server", &[("error", &e)])),
+            },
+            Err(e) => Err(i18n::t("error.tag_offline", &[("error", &e)])),
+        };
+        // Servers without `\*` in PERMANENTFLAGS refuse new keywords. A tag kept only in the
+        // local flags would be gone after the next sync, so the change is refused instead
+        if let Err(e) = result {
+            failed = Some(e);
+            break;
+        }
+
+        if add {
+            flags.push(keyword.to_string());
+        } else {
+            flags.retain(|f| f != keyword);
+        }
+        final_flags = serde_json::to_string(&flags).unwrap_or_default();
+
+        sqlx::query("UPDATE emails SET flags = ? WHERE id = ?")
+            .bind(&final_flags)
+            .bind(email_id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        updated_ids.push(email_id);
+    }
+
+    if !updated_ids.is_empty() {
+        let _ = app_handle.emit("emails-updated", EmailEvent::UpdatedBulk {
+            ids: updated_ids,
+            flags: Some(final_flags),
+        });
+    }
+    match failed {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub async fn add_keyword<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, keyword: String) -> Result<(), String> {
+    set_keyword(&app_handle, email_ids, keyword.trim(), true).await
+}
+
+#[tauri::command]
+pub async fn remove_keyword<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, keyword: String) -> Result<(), String> {
+    set_keyword(&app_handle, email_ids, keyword.trim(), false).await
+}
+
+/// Tags in use, from the keywords synced with the messages, most used first.
+#[tauri::command]
+pub async fn get_keyword_tags<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: Option<i64>) -> Result<Vec<KeywordTag>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let rows: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT j.value, COUNT(*) FROM emails e, json_each(e.flags) j
+         WHERE json_valid(e.flags) AND (? IS NULL OR e.account_id = ?)
+         GROUP BY j.value
+         ORDER BY COUNT(*) DESC, j.value"
+    )
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter()
+        .filter(|(name, _)| is_tag(name))
+        .map(|(name, count)| KeywordTag { name, count })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_user_keywords_are_tags() {
+        assert!(is_tag("Work"));
+        assert!(is_tag("$label1"));
+        assert!(!is_tag("seen"));
+        assert!(!is_tag("$Forwarded"));
+        assert!(!is_tag("NonJunk"));
+
+        assert!(validate_keyword("Project-X").is_ok());
+        assert!(validate_keyword("two words").is_err());
+        assert!(validate_keyword("\\Seen").is_err());
+        assert!(validate_keyword("flagged").is_err());
+    }
+}
@@ -1,2 +1,30 @@
+pub mod analytics;
+pub mod body_limits;
+pub mod body_structure;
+pub mod bulk;
+pub mod calendar;
+pub mod charset;
+pub mod cleanup;
 pub mod commands;
+pub mod compose;
+pub mod confidential;
+pub mod domain_policies;
+pub mod duplicates;
 pub mod events;
+pub mod export;
+pub mod fts;
+pub mod keywords;
+pub mod needs_reply;
+pub mod newsletters;
+pub mod notes;
+pub mod print;
+pub mod quotes;
+pub mod reading;
+pub mod remote_content;
+pub mod retention;
+pub mod screener;
+pub mod spam_signals;
+pub mod stacks;
+pub mod tags;
+pub mod tasks;
+pub mod undo;
@@ -0,0 +1,280 @@
+use crate::db::settings::Settings;
+use crate::email_backend::emails::commands::DELIMITED_RECIPIENTS;
+use crate::email_backend::emails::quotes::strip_quoted;
+use crate::email_backend::llm::needs_reply::needs_reply_with_ai;
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::i18n;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+/// Received mail older than this is taken as settled, answered or not.
+const NEEDS_REPLY_WINDOW_DAYS: u32 = 30;
+/// Emails the AI is asked about per run, the rest wait for the next one.
+const AI_BATCH: usize = 10;
+
+/// Phrases that ask the reader for something, matched on lowercased text.
+const REQUEST_PHRASES: &[&str] = &[
+    "can you", "could you", "would you", "will you", "let me know", "please confirm",
+    "please advise", "please review", "please reply", "get back to me", "your thoughts",
+    "what do you think", "are you available", "any update",
+];
+
+/// A received email that looks like it is waiting on the user.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NeedsReply {
+    pub id: i64,
+    pub account_id: i64,
+    pub subject: Option<String>,
+    pub sender_name: Option<String>,
+    pub sender_address: String,
+    pub date: String,
+    pub snippet: Option<String>,
+    pub days_waiting: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Candidate {
+    id: i64,
+    account_id: i64,
+    subject: Option<String>,
+    sender_name: Option<String>,
+    sender_address: String,
+    date: String,
+    snippet: Option<String>,
+    body_text: Option<String>,
+    needs_reply_ai: Option<bool>,
+    nudged: bool,
+    days_waiting: i64,
+}
+
+impl Candidate {
+    fn text(&self) -> &str {
+        self.body_text.as_deref().or(self.snippet.as_deref()).unwrap_or_default()
+    }
+
+    /// The AI's answer when there is one and it may be used, the heuristics otherwise.
+    fn needs_reply(&self, use_ai: bool) -> bool {
+        match self.needs_reply_ai {
+            Some(answer) if use_ai => answer,
+            _ => asks_for_reply(self.subject.as_deref().unwrap_or_default(), self.text()),
+        }
+    }
+}
+
+/// Whether an email asks its reader something: a question, or one of the usual request
+/// phrases. Question marks in links don't count.
+fn asks_for_reply(subject: &str, text: &str) -> bool {
+    let text = strip_quoted(text).to_lowercase();
+    let has_question = |text: &str| text.split_whitespace().any(|word| word.contains('?') && !word.contains("://"));
+    has_question(subject) || has_question(&text) || REQUEST_PHRASES.iter().any(|phrase| text.contains(phrase))
+}
+
+/// Inbox mail sent straight to the user more than `days` days ago, with nothing after it in
+/// its thread. Lists, automated senders, unscreened senders and reply later threads are left out.
+async fn candidates(pool: &SqlitePool, days: u32, account_id: Option<i64>) -> Result<Vec<Candidate>, String> {
+    sqlx::query_as(&format!(
+        "SELECT e.id, e.account_id, e.subject, e.sender_name, e.sender_address, e.date, e.snippet, e.body_text,
+                e.needs_reply_ai, e.reply_nudged_at IS NOT NULL as nudged,
+                CAST(julianday('now') - julianday(e.date) AS INTEGER) as days_waiting
+         FROM emails e
+         JOIN folders f ON e.folder_id = f.id
+         LEFT JOIN senders s ON s.address = LOWER(e.sender_address)
+         WHERE f.role = 'inbox'
+           AND (? IS NULL OR e.account_id = ?)
+           AND NOT e.needs_reply_dismissed
+           AND e.list_id IS NULL AND e.screening IS NULL AND COALESCE(e.stack, '') != 'reply_later'
+           AND e.flags NOT LIKE '%\"answered\"%'
+           AND COALESCE(s.is_automated_mailer, 0) = 0
+           AND datetime(e.date) < datetime('now', ?) AND datetime(e.date) > datetime('now', ?)
+           AND LOWER(e.sender_address) NOT IN (SELECT address FROM own_addresses)
+           AND EXISTS (SELECT 1 FROM own_addresses o WHERE o.account_id = e.account_id AND {} LIKE '%,' || o.address || ',%')
+           AND NOT EXISTS (
+               SELECT 1 FROM emails r
+               WHERE r.account_id = e.account_id AND r.id != e.id AND datetime(r.date) > datetime(e.date)
+                 AND ((e.thread_id IS NOT NULL AND r.thread_id = e.thread_id) OR r.in_reply_to = e.message_id)
+           )
+         ORDER BY e.date ASC, e.id ASC",
+        DELIMITED_RECIPIENTS
+    ))
+    .bind(account_id)
+    .bind(account_id)
+    .bind(format!("-{} days", days))
+    .bind(format!("-{} days", NEEDS_REPLY_WINDOW_DAYS))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Lets the AI judge the candidates it hasn't seen yet, a few per run.
+async fn classify_with_ai<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, candidates: &mut [Candidate]) {
+    let pool = app_handle.state::<SqlitePool>();
+    for candidate in candidates.iter_mut().filter(|c| c.needs_reply_ai.is_none()).take(AI_BATCH) {
+        let answer = match needs_reply_with_ai(app_handle, candidate.id, candidate.subject.as_deref().unwrap_or_default(), candidate.text()).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                // Most likely the same for the rest, the heuristics stand in until the next run
+                warn!("Failed to ask the AI whether email {} needs a reply: {}", candidate.id, e);
+                return;
+            }
+        };
+        if let Err(e) = sqlx::query("UPDATE emails SET needs_reply_ai = ? WHERE id = ?")
+            .bind(answer)
+            .bind(candidate.id)
+            .execute(&*pool)
+            .await
+        {
+            warn!("Failed to save the AI's answer about email {}: {}", candidate.id, e);
+        }
+        candidate.needs_reply_ai = Some(answer);
+    }
+}
+
+/// Received emails that seem to be waiting on a reply for longer than `needsReplyDays`,
+/// longest waiting first.
+#[tauri::command]
+pub async fn get_needs_reply<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: Option<i64>) -> Result<Vec<NeedsReply>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let settings = Settings::load(&pool).await?;
+    let use_ai = settings.ai_enabled && settings.ai_needs_reply_enabled;
+
+    Ok(candidates(&pool, settings.needs_reply_days, account_id)
+        .await?
+        .into_iter()
+        .filter(|c| c.needs_reply(use_ai))
+        .map(|c| NeedsReply {
+            id: c.id,
+            account_id: c.account_id,
+            subject: c.subject,
+            sender_name: c.sender_name,
+            sender_address: c.sender_address,
+            date: c.date,
+            snippet: c.snippet,
+            days_waiting: c.days_waiting,
+        })
+        .collect())
+}
+
+/// Takes an email off the list for good, it needs no reply or got one elsewhere.
+#[tauri::command]
+pub async fn dismiss_needs_reply<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("UPDATE emails SET needs_reply_dismissed = 1 WHERE id = ?")
+        .bind(email_id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Has the AI look at new candidates when it is enabled for this, then reminds about emails
+/// still waiting on a reply when `needsReplyNudgesEnabled` is on, once per email.
+pub async fn nudge_unanswered<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let settings = Settings::load(&pool).await?;
+    let use_ai = settings.ai_enabled && settings.ai_needs_reply_enabled;
+    let nudge = settings.needs_reply_nudges_enabled && settings.notifications_enabled;
+    if !use_ai && !nudge {
+        return Ok(());
+    }
+
+    let mut candidates = candidates(&pool, settings.needs_reply_days, None).await?;
+    if use_ai {
+        classify_with_ai(app_handle, &mut candidates).await;
+    }
+    if !nudge {
+        return Ok(());
+    }
+
+    let due: Vec<&Candidate> = candidates.iter().filter(|c| !c.nudged && c.needs_reply(use_ai)).collect();
+    let days = settings.needs_reply_days.to_string();
+    let (title, body) = match due.as_slice() {
+        [] => return Ok(()),
+        [email] => (
+            i18n::t("notification.needs_reply", &[("subject", &email.subject.clone().unwrap_or_else(|| i18n::t("email.no_subject", &[])))]),
+            i18n::t("notification.needs_reply_body", &[("sender", email.sender_name.as_deref().unwrap_or(&email.sender_address)), ("days", &days)]),
+        ),
+        emails => (
+            i18n::t("notification.needs_reply_many", &[("count", &emails.len().to_string())]),
+            i18n::t("notification.needs_reply_many_body", &[("days", &days)]),
+        ),
+    };
+    SyncEngine::<R>::show_notification(app_handle, title, body).await;
+
+    for email in &due {
+        sqlx::query("UPDATE emails SET reply_nudged_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(email.id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    info!("Nudged about {} unanswered email(s)", due.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+    use chrono::{Duration, SecondsFormat, Utc};
+
+    #[test]
+    fn test_asks_for_reply() {
+        assert!(asks_for_reply("Budget", "Hi,\nCould you send me the numbers by Friday"));
+        assert!(asks_for_reply("Lunch tomorrow?", "Thanks"));
+        assert!(!asks_for_reply("Invoice", "Thanks, see https://example.com/invoice?id=42 for details."));
+        assert!(!asks_for_reply("Re: Plans", "Sounds good.\n\nOn Mon, Alice wrote:\n> Can you make it?"));
+    }
+
+    #[tokio::test]
+    async fn test_candidates_skip_answered_and_recent_mail() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let mut folders = Vec::new();
+        for role in ["inbox", "sent"] {
+            let folder_id = insert_test_folder(&pool, account_id, role, Some(role)).await;
+            folders.push(folder_id);
+        }
+
+        let days_ago = |days: i64| (Utc::now() - Duration::days(days)).to_rfc3339_opts(SecondsFormat::Secs, true);
+        let emails = [
+            (folders[0], "alice@example.org", "t1", 5, "Can you send the report by Friday?"),
+            (folders[0], "bob@example.org", "t2", 5, "Thanks, all good."),
+            (folders[0], "carol@example.org", "t3", 5, "Are you coming on Monday?"),
+            (folders[1], "me@example.com", "t3", 4, "Yes, see you then."),
+            (folders[0], "dave@example.org", "t4", 1, "Could you have a look?"),
+            (folders[0], "erin@example.org", "t5", 5, "Can you all join the call?"),
+        ];
+        let mut ids = Vec::new();
+        for (i, (folder_id, sender, thread, days, snippet)) in emails.iter().enumerate() {
+            let (id,): (i64,) = sqlx::query_as(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, sender_address, recipient_to, date, flags, snippet)
+                 VALUES (?, ?, ?, ?, ?, 'Hello', ?, 'Me@Example.com', ?, '[]', ?) RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(i.to_string())
+            .bind(format!("msg-{}", i))
+            .bind(thread)
+            .bind(sender)
+            .bind(days_ago(*days))
+            .bind(snippet)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+        // Sent to an address that only ends in the user's, not to the user
+        sqlx::query("UPDATE emails SET recipient_to = 'Everyone <someme@example.com>' WHERE id = ?").bind(ids[5]).execute(&pool).await.unwrap();
+
+        let found: Vec<i64> = candidates(&pool, 3, None).await.unwrap().into_iter().filter(|c| c.needs_reply(false)).map(|c| c.id).collect();
+        assert_eq!(found, vec![ids[0]]);
+
+        // A stored AI answer only counts while the AI is in use
+        sqlx::query("UPDATE emails SET needs_reply_ai = 1 WHERE id = ?").bind(ids[1]).execute(&pool).await.unwrap();
+        let with_ai: Vec<i64> = candidates(&pool, 3, None).await.unwrap().into_iter().filter(|c| c.needs_reply(true)).map(|c| c.id).collect();
+        assert_eq!(with_ai, vec![ids[0], ids[1]]);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::db::settings::Settings;
+use crate::email_backend::emails::commands::Email;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+
+/// All newsletters that reached the inbox on one (local) day, shown as a single inbox entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsletterRollup {
+    /// `YYYY-MM-DD`
+    pub day: String,
+    /// Date of the newest newsletter, to place the entry among the regular inbox emails
+    pub date: String,
+    pub count: i64,
+    pub unread_count: i64,
+    pub senders: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NewsletterSender {
+    pub address: String,
+    pub rollup: bool,
+    pub updated_at: String,
+}
+
+/// SQL condition matching rolled-up emails: mailing list traffic unless the sender opted out,
+/// plus senders explicitly opted in. `alias` needs `sender_address` and `list_id` columns.
+pub(crate) fn newsletter_condition(alias: &str) -> String {
+    format!(
+        "(({0}.list_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM newsletter_senders ns WHERE ns.address = LOWER({0}.sender_address) AND ns.rollup = 0))
+          OR EXISTS (SELECT 1 FROM newsletter_senders ns WHERE ns.address = LOWER({0}.sender_address) AND ns.rollup = 1))",
+        alias
+    )
+}
+
+pub(crate) async fn rollup_enabled(pool: &SqlitePool) -> bool {
+    Settings::load(pool).await.map_or(true, |settings| settings.newsletter_rollup_enabled)
+}
+
+#[tauri::command]
+pub async fn get_newsletter_rollups<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    account_id: Option<i64>,
+    before_day: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<NewsletterRollup>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    if !rollup_enabled(&pool).await {
+        return Ok(Vec::new());
+    }
+
+    let mut query = sqlx::QueryBuilder::new(
+        "SELECT date(e.date, 'localtime') as day, MAX(e.date), COUNT(*), SUM(e.flags NOT LIKE '%\"seen\"%'),
+                json_group_array(DISTINCT COALESCE(NULLIF(e.sender_name, ''), e.sender_address))
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE f.role = 'inbox' AND e.screening IS NULL AND "
+    );
+    query.push(newsletter_condition("e"));
+    if let Some(aid) = account_id {
+        query.push(" AND e.account_id = ");
+        query.push_bind(aid);
+    }
+    if let Some(day) = before_day {
+        query.push(" AND date(e.date, 'localtime') < ");
+        query.push_bind(day);
+    }
+    query.push(" GROUP BY day ORDER BY day DESC LIMIT ");
+    query.push_bind(limit.unwrap_or(30) as i64);
+
+    let rows: Vec<(String, String, i64, i64, String)> = query
+        .build_query_as()
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(day, date, count, unread_count, senders)| NewsletterRollup {
+            day,
+            date,
+            count,
+            unread_count,
+            senders: serde_json::from_str(&senders).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// The newsletters behind one roll-up entry, newest first.
+#[tauri::command]
+pub async fn expand_newsletter_rollup<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    day: String,
+    account_id: Option<i64>,
+) -> Result<Vec<Email>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+
+    let mut query = sqlx::QueryBuilder::new(
+        "SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, 1 as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments,
+         (e.subject LIKE 'Re:%' OR e.subject LIKE 're:%' OR e.in_reply_to IS NOT NULL) as is_reply,
+         (e.subject LIKE 'Fwd:%' OR e.subject LIKE 'fwd:%' OR e.subject LIKE 'Fw:%' OR e.subject LIKE 'fw:%') as is_forward,
+         (LOWER(e.sender_address) IN (SELECT address FROM own_addresses)) as is_from_me
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE f.role = 'inbox' AND e.screening IS NULL AND date(e.date, 'localtime') = "
+    );
+    query.push_bind(day);
+    query.push(" AND ");
+    query.push(newsletter_condition("e"));
+    if let Some(aid) = account_id {
+        query.push(" AND e.account_id = ");
+        query.push_bind(aid);
+    }
+    query.push(" ORDER BY e.date DESC, e.id DESC");
+
+    query
+        .build_query_as::<Email>()
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_newsletter_senders<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<NewsletterSender>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as("SELECT address, rollup, updated_at FROM newsletter_senders ORDER BY address")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Opts a sender into (`Some(true)`) or out of (`Some(false)`) roll-ups, `None` goes back to detection.
+#[tauri::command]
+pub async fn set_newsletter_rollup<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    address: String,
+    rollup: Option<bool>,
+) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let address = address.trim().to_lowercase();
+
+    match rollup {
+        Some(rollup) => {
+            sqlx::query(
+                "INSERT INTO newsletter_senders (address, rollup) VALUES (?, ?)
+                 ON CONFLICT(address) DO UPDATE SET rollup = excluded.rollup, updated_at = CURRENT_TIMESTAMP"
+            )
+            .bind(&address)
+            .bind(rollup)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+        None => {
+            sqlx::query("DELETE FROM newsletter_senders WHERE address = ?")
+                .bind(&address)
+                .execute(&*pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let _ = app_handle.emit("newsletter-senders-changed", &address);
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+/// Keeps a private note on an email, never sent or synced to the server. Blank text removes it.
+#[tauri::command]
+pub async fn set_email_note<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, text: String) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    save_note(&pool, email_id, &text).await
+}
+
+async fn save_note(pool: &SqlitePool, email_id: i64, text: &str) -> Result<(), String> {
+    let text = text.trim();
+    let result = sqlx::query("UPDATE emails SET note = ? WHERE id = ?")
+        .bind((!text.is_empty()).then_some(text))
+        .bind(email_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if result.rows_affected() == 0 {
+        return Err("Email not found".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email_backend::emails::fts;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[tokio::test]
+    async fn test_notes_are_searchable() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+        let (email_id,): (i64,) = sqlx::query_as("INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, date, flags) VALUES (?, ?, '1', 'Your order', 'shop@example.com', '2024-01-01T00:00:00Z', '[]') RETURNING id")
+            .bind(account_id)
+            .bind(folder_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        let search = |text: &'static str| {
+            let pool = pool.clone();
+            async move {
+                let mut query = sqlx::QueryBuilder::new("SELECT e.id FROM emails e");
+                fts::build(text).push_match(&mut query);
+                query.build_query_scalar::<i64>().fetch_all(&pool).await.unwrap()
+            }
+        };
+
+        save_note(&pool, email_id, "Expense claim for the new monitor").await.unwrap();
+        assert_eq!(search("monitor").await, vec![email_id]);
+
+        save_note(&pool, email_id, "  ").await.unwrap();
+        assert!(search("monitor").await.is_empty());
+        assert!(save_note(&pool, email_id + 1, "missing").await.is_err());
+    }
+}
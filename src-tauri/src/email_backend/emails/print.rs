@@ -0,0 +1,232 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::emails::commands::{fetch_attachment_data_internal, get_email_by_id, get_email_content, get_thread_emails, Email};
+use crate::utils::i18n;
+
+/// Inline images larger than this are left out of the printout instead of being embedded.
+const MAX_INLINE_IMAGE_BYTES: i64 = 5 * 1024 * 1024;
+
+/// Elements dropped from bodies before printing, nothing in a printout needs to run or embed.
+const STRIPPED_ELEMENTS: &[&str] = &["script", "iframe", "object", "embed"];
+
+const PRINT_STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Roboto, Helvetica, Arial, sans-serif; font-size: 12pt; color: #000; margin: 0; }
+article + article { border-top: 1px solid #999; margin-top: 24pt; padding-top: 16pt; }
+h1 { font-size: 16pt; margin: 0 0 8pt; }
+table.headers { border-collapse: collapse; margin-bottom: 12pt; font-size: 10pt; }
+table.headers th { text-align: left; padding: 1pt 8pt 1pt 0; color: #555; font-weight: normal; vertical-align: top; }
+.body img { max-width: 100%; }
+.body pre { white-space: pre-wrap; font-family: inherit; }
+.attachments { margin-top: 12pt; font-size: 10pt; color: #555; }
+@media print { article { break-inside: avoid-page; } }
+";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Removes each `<name ...>...</name>` element, and lone opening tags of ones left unclosed.
+fn strip_element(html: &str, name: &str) -> String {
+    // ASCII lowercasing keeps byte offsets identical between `lower` and `html`
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find(&open) {
+        let start = pos + found;
+        let after = lower[start + open.len()..].chars().next();
+        if !matches!(after, Some(c) if c.is_whitespace() || c == '>' || c == '/') {
+            out.push_str(&html[pos..start + open.len()]);
+            pos = start + open.len();
+            continue;
+        }
+        out.push_str(&html[pos..start]);
+        pos = match lower[start..].find(&close) {
+            Some(end) => start + end + close.len(),
+            None => lower[start..].find('>').map(|end| start + end + 1).unwrap_or(html.len()),
+        };
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+/// What sits between `<body>` and `</body>`, or all of it for a fragment.
+fn body_of(html: &str) -> &str {
+    let lower = html.to_ascii_lowercase();
+    let start = lower
+        .find("<body")
+        .and_then(|open| lower[open..].find('>').map(|end| open + end + 1))
+        .unwrap_or(0);
+    let end = lower[start..].rfind("</body").map(|end| start + end).unwrap_or(html.len());
+    &html[start..end]
+}
+
+pub(crate) fn format_date(date: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(date)
+        .map(|d| d.with_timezone(&chrono::Local).format("%a, %d %b %Y %H:%M").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
+
+fn format_size(bytes: i64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{} KB", b / 1024),
+        b => format!("{} B", b),
+    }
+}
+
+/// Embeds the images the HTML refers to by `cid:` as data URLs, downloading them if needed.
+/// Returns the filenames and sizes of the files sent along, for the list under the body.
+async fn embed_inline_images<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_id: i64, html: &mut String) -> Result<Vec<(String, i64)>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let attachments: Vec<(i64, Option<String>, Option<String>, i64, Option<String>, bool)> = sqlx::query_as(
+        "SELECT id, filename, mime_type, size, content_id, is_inline FROM attachments WHERE email_id = ? ORDER BY id"
+    )
+    .bind(email_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut listed = Vec::new();
+    for (id, filename, mime_type, size, content_id, is_inline) in attachments {
+        let reference = content_id.map(|cid| format!("cid:{}", cid)).filter(|r| html.contains(r.as_str()));
+        let Some(reference) = reference else {
+            if !is_inline {
+                listed.push((filename.unwrap_or_else(|| "attachment".to_string()), size));
+            }
+            continue;
+        };
+        if size > MAX_INLINE_IMAGE_BYTES {
+            continue;
+        }
+        match fetch_attachment_data_internal(app_handle, id).await {
+            Ok(data) => {
+                let mime_type = mime_type.unwrap_or_else(|| "application/octet-stream".to_string());
+                *html = html.replace(&reference, &format!("data:{};base64,{}", mime_type, STANDARD.encode(data)));
+            }
+            Err(e) => log::warn!("Inline image {} left out of the printout: {}", id, e),
+        }
+    }
+    Ok(listed)
+}
+
+async fn render_email<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email: &Email, with_subject: bool) -> Result<String, String> {
+    let content = get_email_content(app_handle.clone(), email.id, None).await?;
+
+    let mut body = match (content.body_html, content.body_text) {
+        (Some(html), _) => STRIPPED_ELEMENTS.iter().fold(body_of(&html).to_string(), |html, name| strip_element(&html, name)),
+        (None, Some(text)) => format!("<pre>{}</pre>", escape_html(&text)),
+        (None, None) => String::new(),
+    };
+    let attachments = embed_inline_images(app_handle, email.id, &mut body).await?;
+
+    let subject = email.subject.clone().filter(|s| !s.trim().is_empty()).unwrap_or_else(|| i18n::t("email.no_subject", &[]));
+    let from = match &email.sender_name {
+        Some(name) if !name.is_empty() => format!("{} &lt;{}&gt;", escape_html(name), escape_html(&email.sender_address)),
+        _ => escape_html(&email.sender_address),
+    };
+
+    let mut out = String::from("<article>");
+    if with_subject {
+        out.push_str(&format!("<h1>{}</h1>", escape_html(&subject)));
+    }
+    out.push_str("<table class=\"headers\">");
+    out.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>", i18n::t("print.from", &[]), from));
+    if let Some(to) = email.recipient_to.as_deref().filter(|to| !to.is_empty()) {
+        out.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>", i18n::t("print.to", &[]), escape_html(to)));
+    }
+    out.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>", i18n::t("print.date", &[]), escape_html(&format_date(&email.date))));
+    out.push_str("</table>");
+    out.push_str(&format!("<div class=\"body\">{}</div>", body));
+
+    if !attachments.is_empty() {
+        let names: Vec<String> = attachments
+            .iter()
+            .map(|(name, size)| format!("<li>{} ({})</li>", escape_html(name), format_size(*size)))
+            .collect();
+        out.push_str(&format!("<div class=\"attachments\">{}<ul>{}</ul></div>", i18n::t("print.attachments", &[]), names.concat()));
+    }
+    out.push_str("</article>");
+    Ok(out)
+}
+
+/// A standalone HTML document for the print dialog: the headers above each body, remote
+/// content blocked as in the viewer, inline images embedded and other attachments listed.
+/// `include_thread` prints the whole conversation, oldest first.
+#[tauri::command]
+pub async fn get_printable_email<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, include_thread: Option<bool>) -> Result<String, String> {
+    let emails = if include_thread.unwrap_or(false) {
+        let mut emails = get_thread_emails(app_handle.clone(), email_id, None, None).await?;
+        emails.reverse();
+        emails
+    } else {
+        vec![get_email_by_id(app_handle.clone(), email_id).await?]
+    };
+    let title = emails
+        .first()
+        .and_then(|e| e.subject.clone())
+        .unwrap_or_else(|| i18n::t("email.no_subject", &[]));
+
+    let mut articles = Vec::with_capacity(emails.len());
+    for (i, email) in emails.iter().enumerate() {
+        // The conversation shares one subject, printed once at the top
+        articles.push(render_email(&app_handle, email, i == 0).await?);
+    }
+
+    Ok(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>{}</body></html>",
+        escape_html(&title),
+        PRINT_STYLE,
+        articles.concat()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[test]
+    fn test_strip_element_and_body_of() {
+        let html = "<html><head><title>x</title></head><body><p>Hi</p><script>alert(1)</script><scripted>ok</scripted><iframe src=\"a\"></body></html>";
+        let body = STRIPPED_ELEMENTS.iter().fold(body_of(html).to_string(), |html, name| strip_element(&html, name));
+        assert_eq!(body, "<p>Hi</p><scripted>ok</scripted>");
+        assert_eq!(body_of("<p>fragment</p>"), "<p>fragment</p>");
+    }
+
+    #[tokio::test]
+    async fn test_printable_email_has_headers_and_escaped_text() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", None).await;
+        let (email_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, subject, sender_name, sender_address, recipient_to, date, flags, body_text)
+             VALUES (?, ?, '1', 'msg-1', 'Plans', 'Alice', 'alice@example.com', 'me@example.com', '2024-01-01T00:00:00Z', '[]', 'a < b') RETURNING id"
+        )
+        .bind(account_id)
+        .bind(folder_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO attachments (email_id, filename, mime_type, size) VALUES (?, 'notes.pdf', 'application/pdf', 2048)")
+            .bind(email_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let app = tauri::test::mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool);
+
+        let document = get_printable_email(app.handle().clone(), email_id, None).await.unwrap();
+        assert!(document.contains("<title>Plans</title>"));
+        assert!(document.contains("Alice &lt;alice@example.com&gt;"));
+        assert!(document.contains("<pre>a &lt; b</pre>"));
+        assert!(document.contains("<li>notes.pdf (2 KB)</li>"));
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+
+/// Class or id values HTML mailers put on the element holding a quoted message.
+const HTML_QUOTE_MARKERS: &[&str] = &["gmail_quote", "moz-cite-prefix", "yahoo_quoted", "protonmail_quote"];
+const HTML_SIGNATURE_MARKERS: &[&str] = &["gmail_signature", "moz-signature", "protonmail_signature_block"];
+/// Outlook wraps nothing around the quoted message, it runs from one of these to the end.
+const HTML_REPLY_HEADERS: &[&str] = &["divrplyfwdmsg", "appendonsend", "stopspelling"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteKind {
+    Quote,
+    Signature,
+}
+
+/// A part of a body the reader can collapse. Offsets are in UTF-16 code units, the way
+/// JavaScript indexes strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteRange {
+    pub kind: QuoteKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The collapsible parts of `body_text` and of `body_html`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuoteRanges {
+    pub text: Vec<QuoteRange>,
+    pub html: Vec<QuoteRange>,
+}
+
+struct Line<'a> {
+    start: usize,
+    end: usize,
+    trimmed: &'a str,
+}
+
+impl Line<'_> {
+    fn is_quoted(&self) -> bool {
+        self.trimmed.starts_with('>')
+    }
+}
+
+/// "On Mon, 1 Jan 2024, Alice wrote:", which mailers often wrap onto a second line.
+fn is_attribution(lines: &[Line], i: usize) -> bool {
+    let line = lines[i].trimmed;
+    let next = lines.get(i + 1).map(|next| next.trimmed).unwrap_or_default();
+    line.starts_with("On ") && (line.ends_with("wrote:") || (next.ends_with("wrote:") && !next.starts_with("On ")))
+}
+
+/// An Outlook style header above an unprefixed quoted message.
+fn is_reply_header(lines: &[Line], i: usize) -> bool {
+    let line = lines[i].trimmed;
+    let next = lines.get(i + 1).map(|next| next.trimmed).unwrap_or_default();
+    line.starts_with("-----Original Message") || (line.starts_with("From:") && next.starts_with("Sent:"))
+}
+
+/// Index of the last `>` line of the block starting at `i`, blank lines inside it included.
+fn quoted_block_end(lines: &[Line], i: usize) -> usize {
+    let mut last = i;
+    for (j, line) in lines.iter().enumerate().skip(i) {
+        if line.is_quoted() {
+            last = j;
+        } else if !line.trimmed.is_empty() {
+            break;
+        }
+    }
+    last
+}
+
+/// Byte ranges of the quoted messages and signature in plain text.
+fn text_ranges(text: &str) -> Vec<(QuoteKind, usize, usize)> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push(Line { start, end: start + line.len(), trimmed: line.trim() });
+        start += line.len();
+    }
+
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if is_reply_header(&lines, i) {
+            ranges.push((QuoteKind::Quote, lines[i].start, text.len()));
+            break;
+        }
+        if is_attribution(&lines, i) {
+            let mut j = if lines[i].trimmed.ends_with("wrote:") { i + 1 } else { i + 2 };
+            while lines.get(j).is_some_and(|line| line.trimmed.is_empty()) {
+                j += 1;
+            }
+            if lines.get(j).is_some_and(Line::is_quoted) {
+                let end = quoted_block_end(&lines, j);
+                ranges.push((QuoteKind::Quote, lines[i].start, lines[end].end));
+                i = end + 1;
+                continue;
+            }
+            // Quoted without prefixes, the rest is the earlier message
+            ranges.push((QuoteKind::Quote, lines[i].start, text.len()));
+            break;
+        }
+        if lines[i].is_quoted() {
+            let end = quoted_block_end(&lines, i);
+            ranges.push((QuoteKind::Quote, lines[i].start, lines[end].end));
+            i = end + 1;
+            continue;
+        }
+        if lines[i].trimmed == "--" {
+            // The signature ends where a quoted message starts, if one follows
+            let end = (i + 1..lines.len())
+                .find(|&j| lines[j].is_quoted() || is_attribution(&lines, j) || is_reply_header(&lines, j))
+                .unwrap_or(lines.len());
+            ranges.push((QuoteKind::Signature, lines[i].start, lines.get(end).map(|line| line.start).unwrap_or(text.len())));
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    ranges
+}
+
+/// Where the element opening at `start` ends, past its closing tag, with nested elements of
+/// the same name counted. The end of the document when it is never closed.
+fn element_end(lower: &str, start: usize, name: &str) -> usize {
+    let open = format!("<{}", name);
+    let close = format!("</{}", name);
+    let mut depth = 0;
+    let mut pos = start;
+    loop {
+        let next_open = lower[pos..].find(&open).map(|i| pos + i);
+        let next_close = lower[pos..].find(&close).map(|i| pos + i);
+        match (next_open, next_close) {
+            (Some(open_at), Some(close_at)) if open_at < close_at => {
+                depth += 1;
+                pos = open_at + open.len();
+            }
+            (_, Some(close_at)) => {
+                depth -= 1;
+                pos = lower[close_at..].find('>').map(|i| close_at + i + 1).unwrap_or(lower.len());
+                if depth <= 0 {
+                    return pos;
+                }
+            }
+            _ => return lower.len(),
+        }
+    }
+}
+
+/// Byte ranges of the quoted messages and signatures in HTML: blockquotes, and the wrappers
+/// Gmail, Thunderbird, Yahoo, Proton and Outlook put around them.
+fn html_ranges(html: &str) -> Vec<(QuoteKind, usize, usize)> {
+    // ASCII lowercasing keeps byte offsets identical between `lower` and `html`
+    let lower = html.to_ascii_lowercase();
+    let body_end = lower.rfind("</body").unwrap_or(lower.len());
+
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find('<') {
+        let start = pos + found;
+        let Some(tag_len) = lower[start..].find('>') else { break };
+        let tag = &lower[start..start + tag_len + 1];
+        let name: String = tag[1..].chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        pos = start + tag_len + 1;
+        if name.is_empty() {
+            continue;
+        }
+
+        if HTML_REPLY_HEADERS.iter().any(|marker| tag.contains(marker)) {
+            if start < body_end {
+                ranges.push((QuoteKind::Quote, start, body_end));
+            }
+            break;
+        }
+        let kind = if name == "blockquote" || HTML_QUOTE_MARKERS.iter().any(|marker| tag.contains(marker)) {
+            QuoteKind::Quote
+        } else if HTML_SIGNATURE_MARKERS.iter().any(|marker| tag.contains(marker)) {
+            QuoteKind::Signature
+        } else {
+            continue;
+        };
+        // Nested quotes go with the outermost one
+        let end = element_end(&lower, start, &name);
+        ranges.push((kind, start, end));
+        pos = end;
+    }
+    ranges
+}
+
+fn to_utf16(body: &str, ranges: Vec<(QuoteKind, usize, usize)>) -> Vec<QuoteRange> {
+    let offset = |byte: usize| body[..byte].encode_utf16().count();
+    ranges
+        .into_iter()
+        .map(|(kind, start, end)| QuoteRange { kind, start: offset(start), end: offset(end) })
+        .collect()
+}
+
+/// The quoted messages and signatures in the bodies of an email, for the reader to collapse.
+pub fn find(body_text: Option<&str>, body_html: Option<&str>) -> QuoteRanges {
+    QuoteRanges {
+        text: body_text.map(|text| to_utf16(text, text_ranges(text))).unwrap_or_default(),
+        html: body_html.map(|html| to_utf16(html, html_ranges(html))).unwrap_or_default(),
+    }
+}
+
+/// The text of a reply with the quoted messages and the signature taken out.
+pub(crate) fn strip_quoted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (_, start, end) in text_ranges(text) {
+        out.push_str(&text[pos..start]);
+        pos = end;
+    }
+    out.push_str(&text[pos..]);
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_quoted() {
+        assert_eq!(strip_quoted("Works for me.\n\n-- \nBob\n"), "Works for me.");
+        let gmail = "Sounds good\n> earlier\nsee you\n\nOn Mon, 1 Jan 2024 at 10:00, Alice <alice@example.com>\nwrote:\n> Lunch?";
+        assert_eq!(strip_quoted(gmail), "Sounds good\nsee you");
+        assert_eq!(strip_quoted("Approved.\n\nFrom: Alice\nSent: Monday\nSubject: Budget"), "Approved.");
+    }
+
+    #[test]
+    fn test_text_ranges_in_utf16() {
+        let text = "Grüße 👋\n-- \nBob\nOn Mon, Alice wrote:\n> Hi\n>\n> Bye\nInline answer\n";
+        let ranges = find(Some(text), None).text;
+        let slice = |range: &QuoteRange| String::from_utf16(&text.encode_utf16().collect::<Vec<_>>()[range.start..range.end]).unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!((ranges[0].kind, slice(&ranges[0]).as_str()), (QuoteKind::Signature, "-- \nBob\n"));
+        assert_eq!((ranges[1].kind, slice(&ranges[1]).as_str()), (QuoteKind::Quote, "On Mon, Alice wrote:\n> Hi\n>\n> Bye\n"));
+    }
+
+    #[test]
+    fn test_html_ranges() {
+        let html = "<div dir=\"ltr\">Thanks<div class=\"gmail_signature\">Bob</div></div>\
+                    <div class=\"gmail_quote\"><div>On Mon, Alice wrote:</div><blockquote><div>Hi</div><blockquote>Old</blockquote></blockquote></div>\
+                    <p>After</p>";
+        let ranges = html_ranges(html);
+        let parts: Vec<(QuoteKind, &str)> = ranges.iter().map(|(kind, start, end)| (*kind, &html[*start..*end])).collect();
+        assert_eq!(parts, vec![
+            (QuoteKind::Signature, "<div class=\"gmail_signature\">Bob</div>"),
+            (QuoteKind::Quote, "<div class=\"gmail_quote\"><div>On Mon, Alice wrote:</div><blockquote><div>Hi</div><blockquote>Old</blockquote></blockquote></div>"),
+        ]);
+
+        let outlook = "<body><p>Sure</p><div id=\"divRplyFwdMsg\"><b>From:</b> Alice</div><p>Old</p></body>";
+        let ranges = html_ranges(outlook);
+        assert_eq!(&outlook[ranges[0].1..ranges[0].2], "<div id=\"divRplyFwdMsg\"><b>From:</b> Alice</div><p>Old</p>");
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::emails::commands::get_thread_emails;
+
+/// Where the user left off in one message of a thread.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MessageReadingState {
+    pub id: i64,
+    /// Share of the body scrolled past, from 0 to 1
+    pub read_position: Option<f64>,
+    pub last_opened_at: Option<String>,
+    /// `None` leaves it to the reader
+    pub expanded: Option<bool>,
+    #[sqlx(skip)]
+    pub unread: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThreadReadingState {
+    /// Oldest first
+    pub messages: Vec<MessageReadingState>,
+    /// Where reopening the thread should jump to: the first unread message, or else the one
+    /// opened last
+    pub resume_at: Option<i64>,
+}
+
+/// Every copy of the message in the account, its other folders included.
+const SAME_MESSAGE: &str =
+    "(id = ? OR (message_id IS NOT NULL
+        AND message_id = (SELECT message_id FROM emails WHERE id = ?)
+        AND account_id = (SELECT account_id FROM emails WHERE id = ?)))";
+
+async fn save_position(pool: &SqlitePool, email_id: i64, position: f64) -> Result<(), String> {
+    let result = sqlx::query(&format!(
+        "UPDATE emails SET read_position = ?, last_opened_at = CURRENT_TIMESTAMP WHERE {}",
+        SAME_MESSAGE
+    ))
+    .bind(position.clamp(0.0, 1.0))
+    .bind(email_id)
+    .bind(email_id)
+    .bind(email_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if result.rows_affected() == 0 {
+        return Err("Email not found".to_string());
+    }
+    Ok(())
+}
+
+/// Records how far the user got in an email and that it was just opened. `position` is the
+/// share of the body scrolled past, from 0 to 1.
+#[tauri::command]
+pub async fn set_reading_position<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, position: f64) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    save_position(&pool, email_id, position).await
+}
+
+/// Remembers messages of a thread as expanded or collapsed, `None` goes back to the reader's default.
+#[tauri::command]
+pub async fn set_messages_expanded<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, expanded: Option<bool>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    for email_id in email_ids {
+        sqlx::query(&format!("UPDATE emails SET expanded = ? WHERE {}", SAME_MESSAGE))
+            .bind(expanded)
+            .bind(email_id)
+            .bind(email_id)
+            .bind(email_id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// The reading position, last opened time and expanded state of each message in the thread
+/// of `email_id`, with the message to resume at.
+#[tauri::command]
+pub async fn get_thread_reading_state<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<ThreadReadingState, String> {
+    let mut emails = get_thread_emails(app_handle.clone(), email_id, None, None).await?;
+    emails.reverse();
+    if emails.is_empty() {
+        return Ok(ThreadReadingState { messages: Vec::new(), resume_at: None });
+    }
+
+    let pool = app_handle.state::<SqlitePool>();
+    let mut query = sqlx::QueryBuilder::new("SELECT id, read_position, last_opened_at, expanded FROM emails WHERE id IN (");
+    let mut separated = query.separated(", ");
+    for email in &emails {
+        separated.push_bind(email.id);
+    }
+    query.push(")");
+    let mut states: Vec<MessageReadingState> = query.build_query_as().fetch_all(&*pool).await.map_err(|e| e.to_string())?;
+
+    // Back in thread order, the flags say what is unread
+    let mut messages = Vec::with_capacity(emails.len());
+    for email in &emails {
+        if let Some(i) = states.iter().position(|state| state.id == email.id) {
+            let mut state = states.swap_remove(i);
+            state.unread = !email.flags.to_lowercase().contains("\"seen\"");
+            messages.push(state);
+        }
+    }
+
+    let resume_at = messages
+        .iter()
+        .find(|m| m.unread)
+        .or_else(|| messages.iter().filter(|m| m.last_opened_at.is_some()).max_by(|a, b| a.last_opened_at.cmp(&b.last_opened_at)))
+        .map(|m| m.id);
+    Ok(ThreadReadingState { messages, resume_at })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[tokio::test]
+    async fn test_thread_resumes_at_first_unread() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+        let messages = [("msg-1", "2024-01-01T09:00:00Z", "[\"Seen\"]"), ("msg-2", "2024-01-02T09:00:00Z", "[\"Seen\"]"), ("msg-3", "2024-01-03T09:00:00Z", "[]")];
+        let mut ids = Vec::new();
+        for (message_id, date, flags) in messages {
+            let (id,): (i64,) = sqlx::query_as(
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, subject, normalized_subject, sender_address, date, flags)
+                 VALUES (?, ?, ?, ?, 'msg-1', 'Plan', 'plan', 'alice@example.com', ?, ?) RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folder_id)
+            .bind(message_id)
+            .bind(message_id)
+            .bind(date)
+            .bind(flags)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        let app = tauri::test::mock_builder().build(tauri::generate_context!()).unwrap();
+        app.manage(pool.clone());
+
+        save_position(&pool, ids[1], 1.7).await.unwrap();
+        set_messages_expanded(app.handle().clone(), vec![ids[0]], Some(false)).await.unwrap();
+        assert!(save_position(&pool, ids[2] + 1, 0.5).await.is_err());
+
+        let state = get_thread_reading_state(app.handle().clone(), ids[0]).await.unwrap();
+        assert_eq!(state.messages.iter().map(|m| m.id).collect::<Vec<_>>(), ids);
+        assert_eq!(state.messages[1].read_position, Some(1.0));
+        assert_eq!(state.messages[0].expanded, Some(false));
+        assert_eq!(state.resume_at, Some(ids[2]));
+
+        // Once everything is read, the last opened message is where to resume
+        sqlx::query("UPDATE emails SET flags = '[\"Seen\"]'").execute(&pool).await.unwrap();
+        let state = get_thread_reading_state(app.handle().clone(), ids[0]).await.unwrap();
+        assert_eq!(state.resume_at, Some(ids[1]));
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::emails::commands::EmailContent;
+
+pub const ALWAYS: &str = "always";
+pub const NEVER: &str = "never";
+
+/// Attributes that make the viewer fetch something as soon as the email is shown.
+const FETCHING_ATTRIBUTES: &[&str] = &["src", "srcset", "background", "poster"];
+
+/// What the user decided for a sender, `None` where they haven't.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SenderContentRules {
+    pub remote_content: Option<String>,
+    pub auto_attachments: Option<String>,
+}
+
+fn is_remote(url: &str) -> bool {
+    let url = url.trim().trim_start_matches("&quot;").trim_matches(['"', '\'']).to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")
+}
+
+/// Byte range of the attribute value starting at `start`, quoted or not.
+fn attribute_value(tag: &str, start: usize) -> (usize, usize) {
+    let (value_start, end_char) = match tag[start..].chars().next() {
+        Some(q @ ('"' | '\'')) => (start + 1, q),
+        _ => (start, ' '),
+    };
+    let value_end = tag[value_start..]
+        .find(|c: char| c == end_char || c == '>' || (end_char == ' ' && c.is_whitespace()))
+        .map(|i| value_start + i)
+        .unwrap_or(tag.len());
+    (value_start, value_end)
+}
+
+/// Renames the remote fetching attributes of one tag to `data-blocked-*`, so nothing loads
+/// and the markup stays intact. `None` when the tag fetches nothing remote.
+fn block_tag(tag: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets identical between `lower` and `tag`
+    let lower = tag.to_ascii_lowercase();
+    let name = lower[1..].split(|c: char| c.is_whitespace() || c == '>' || c == '/').next().unwrap_or("");
+
+    let mut renames = Vec::new();
+    for attribute in FETCHING_ATTRIBUTES.iter().chain((name == "link").then_some(&"href")) {
+        let pattern = format!("{}=", attribute);
+        let mut pos = 0;
+        while let Some(found) = lower[pos..].find(&pattern) {
+            let at = pos + found;
+            pos = at + pattern.len();
+            if !lower[..at].ends_with(char::is_whitespace) {
+                continue;
+            }
+            let (value_start, value_end) = attribute_value(tag, pos);
+            let value = &tag[value_start..value_end];
+            let remote = if *attribute == "srcset" { value.split(',').any(is_remote) } else { is_remote(value) };
+            if remote {
+                renames.push(at);
+            }
+        }
+    }
+    if renames.is_empty() {
+        return None;
+    }
+
+    renames.sort_unstable();
+    let mut blocked = String::with_capacity(tag.len() + renames.len() * 13);
+    let mut last = 0;
+    for at in renames {
+        blocked.push_str(&tag[last..at]);
+        blocked.push_str("data-blocked-");
+        last = at;
+    }
+    blocked.push_str(&tag[last..]);
+    Some(blocked)
+}
+
+fn block_tags(html: &str) -> (String, bool) {
+    let mut out = String::with_capacity(html.len());
+    let mut blocked = false;
+    let mut pos = 0;
+    while let Some(open) = html[pos..].find('<') {
+        let start = pos + open;
+        let end = html[start..].find('>').map(|i| start + i + 1).unwrap_or(html.len());
+        out.push_str(&html[pos..start]);
+        match block_tag(&html[start..end]) {
+            Some(tag) => {
+                out.push_str(&tag);
+                blocked = true;
+            }
+            None => out.push_str(&html[start..end]),
+        }
+        pos = end;
+    }
+    out.push_str(&html[pos..]);
+    (out, blocked)
+}
+
+/// Remote `url(...)` in `<style>` blocks and style attributes, replaced by `none`.
+fn block_css_urls(html: &str) -> (String, bool) {
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    let mut blocked = false;
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find("url(") {
+        let start = pos + found;
+        let Some(close) = lower[start..].find(')') else { break };
+        let end = start + close + 1;
+        out.push_str(&html[pos..start]);
+        if is_remote(&html[start + 4..end - 1]) {
+            out.push_str("none");
+            blocked = true;
+        } else {
+            out.push_str(&html[start..end]);
+        }
+        pos = end;
+    }
+    out.push_str(&html[pos..]);
+    (out, blocked)
+}
+
+/// Keeps remote images, stylesheets and media from loading, which is how senders track opens.
+/// Returns the sanitized HTML and whether anything was blocked.
+pub fn block_remote_content(html: &str) -> (String, bool) {
+    let (html, in_tags) = block_tags(html);
+    let (html, in_css) = block_css_urls(&html);
+    (html, in_tags || in_css)
+}
+
+async fn sender_rules(pool: &SqlitePool, email_id: i64) -> Result<SenderContentRules, String> {
+    let rules = sqlx::query_as(
+        "SELECT s.remote_content, s.auto_attachments FROM emails e JOIN senders s ON s.address = LOWER(e.sender_address) WHERE e.id = ?"
+    )
+    .bind(email_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rules.unwrap_or_default())
+}
+
+/// Blocks remote content unless the sender is always allowed or `load_remote` allows it once,
+/// and attaches the sender's rules for the viewer.
+pub(crate) async fn apply_sender_rules(pool: &SqlitePool, email_id: i64, mut content: EmailContent, load_remote: bool) -> Result<EmailContent, String> {
+    let rules = sender_rules(pool, email_id).await?;
+
+    if !load_remote && rules.remote_content.as_deref() != Some(ALWAYS) {
+        if let Some(html) = content.body_html.take() {
+            let (html, blocked) = block_remote_content(&html);
+            content.body_html = Some(html);
+            content.remote_content_blocked = blocked;
+        }
+    }
+    content.sender_rules = rules;
+    Ok(content)
+}
+
+fn check_rule(rule: &Option<String>) -> Result<(), String> {
+    match rule.as_deref() {
+        None | Some(ALWAYS) | Some(NEVER) => Ok(()),
+        Some(other) => Err(format!("Unknown rule: {}", other)),
+    }
+}
+
+/// Remembers whether mail from `address` may load remote content and show its attachments
+/// right away. `None` goes back to asking each time.
+#[tauri::command]
+pub async fn set_sender_content_rules<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    address: String,
+    remote_content: Option<String>,
+    auto_attachments: Option<String>,
+) -> Result<(), String> {
+    check_rule(&remote_content)?;
+    check_rule(&auto_attachments)?;
+
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query(
+        "INSERT INTO senders (address, remote_content, auto_attachments) VALUES (?, ?, ?)
+         ON CONFLICT(address) DO UPDATE SET remote_content = excluded.remote_content, auto_attachments = excluded.auto_attachments, updated_at = CURRENT_TIMESTAMP"
+    )
+    .bind(address.trim().to_lowercase())
+    .bind(&remote_content)
+    .bind(&auto_attachments)
+    .execute(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[test]
+    fn test_block_remote_content() {
+        let html = r#"<img src="https://t.example.com/open.gif" alt="x"><img data-src="https://a/b.png" src="cid:logo">
+            <link rel="stylesheet" href="//cdn.example.com/a.css"><a href="https://example.com">link</a>
+            <div style="background: url(&quot;https://example.com/bg.png&quot;) no-repeat"></div>
+            <img srcset="small.png 1x, https://example.com/big.png 2x">"#;
+
+        let (blocked, changed) = block_remote_content(html);
+        assert!(changed);
+        assert!(blocked.contains(r#"<img data-blocked-src="https://t.example.com/open.gif""#));
+        assert!(blocked.contains(r#"data-src="https://a/b.png" src="cid:logo""#));
+        assert!(blocked.contains(r#"data-blocked-href="//cdn.example.com/a.css""#));
+        assert!(blocked.contains(r#"<a href="https://example.com">"#));
+        assert!(blocked.contains("background: none no-repeat"));
+        assert!(blocked.contains("data-blocked-srcset="));
+
+        let (_, changed) = block_remote_content(r#"<p>Hi</p><img src="cid:logo">"#);
+        assert!(!changed);
+    }
+
+    #[tokio::test]
+    async fn test_sender_rules_decide_blocking() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "INBOX", None).await;
+        let (email_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, subject, sender_address, date, flags)
+             VALUES (?, ?, '1', 'msg-1', 'News', 'News@Example.com', '2024-01-01T00:00:00Z', '[]') RETURNING id"
+        )
+        .bind(account_id)
+        .bind(folder_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let content = || EmailContent { body_html: Some(r#"<img src="https://example.com/a.png">"#.to_string()), ..Default::default() };
+
+        let shown = apply_sender_rules(&pool, email_id, content(), false).await.unwrap();
+        assert!(shown.remote_content_blocked);
+        let shown = apply_sender_rules(&pool, email_id, content(), true).await.unwrap();
+        assert!(!shown.remote_content_blocked);
+
+        sqlx::query("INSERT INTO senders (address, remote_content, auto_attachments) VALUES ('news@example.com', 'always', 'never')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let shown = apply_sender_rules(&pool, email_id, content(), false).await.unwrap();
+        assert!(!shown.remote_content_blocked);
+        assert_eq!(shown.body_html, content().body_html);
+        assert_eq!(shown.sender_rules.auto_attachments.as_deref(), Some(NEVER));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::db::settings::Settings;
+use crate::email_backend::emails::bulk::{group_by_folder, run_bulk, BulkAction};
+use crate::utils::i18n;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+/// Emails moved per rule and run, the rest is picked up on the next run.
+const RETENTION_BATCH_LIMIT: i64 = 2000;
+
+/// "Archive after 7 days" / "trash after 30 days" for a sender, a sender domain or a mailing list.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RetentionRule {
+    pub id: Option<i64>,
+    /// `sender` or `list`
+    pub match_type: String,
+    /// An address, `@domain` or a List-Id
+    pub pattern: String,
+    /// `archive` or `trash`
+    pub action: String,
+    pub after_days: i64,
+    pub enabled: bool,
+    /// Pushed to the server's Sieve filters, which archive matching mail as it arrives
+    #[serde(default)]
+    #[sqlx(default)]
+    pub file_on_delivery: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// An account's own trash retention, `None` when it follows the `trashRetentionDays` setting.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AccountTrashRetention {
+    pub account_id: i64,
+    pub email: String,
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RetentionLogEntry {
+    pub id: i64,
+    pub rule_id: Option<i64>,
+    pub email_id: i64,
+    pub action: String,
+    pub subject: Option<String>,
+    pub sender_address: Option<String>,
+    pub applied_at: String,
+}
+
+/// Extracts the identifier from a List-Id header, `Name <id>` or a bare id.
+pub fn parse_list_id(value: &str) -> Option<String> {
+    let value = value.trim();
+    let id = match (value.rfind('<'), value.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value,
+    };
+    let id = id.trim().to_lowercase();
+    (!id.is_empty()).then_some(id)
+}
+
+fn normalize_rule(mut rule: RetentionRule) -> Result<RetentionRule, String> {
+    rule.pattern = match rule.match_type.as_str() {
+        "sender" => rule.pattern.trim().to_lowercase(),
+        "list" => parse_list_id(&rule.pattern).unwrap_or_default(),
+        other => return Err(format!("Unknown match type: {}", other)),
+    };
+    if rule.pattern.is_empty() || (rule.match_type == "sender" && !rule.pattern.contains('@')) {
+        return Err(i18n::t("error.retention_pattern", &[]));
+    }
+    if rule.action != "archive" && rule.action != "trash" {
+        return Err(format!("Unknown action: {}", rule.action));
+    }
+    if rule.after_days < 1 {
+        return Err(i18n::t("error.retention_days", &[]));
+    }
+    // Sieve can't wait, it would throw away new mail the moment it arrives
+    if rule.file_on_delivery && rule.action == "trash" {
+        return Err(i18n::t("error.retention_trash_on_delivery", &[]));
+    }
+    Ok(rule)
+}
+
+#[tauri::command]
+pub async fn get_retention_rules<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<RetentionRule>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as("SELECT id, match_type, pattern, action, after_days, enabled, file_on_delivery, created_at FROM retention_rules ORDER BY match_type, pattern")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_retention_rule<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, rule: RetentionRule) -> Result<RetentionRule, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let rule = normalize_rule(rule)?;
+
+    let query = match rule.id {
+        Some(id) => sqlx::query_as(
+            "UPDATE retention_rules SET match_type = ?, pattern = ?, action = ?, after_days = ?, enabled = ?, file_on_delivery = ? WHERE id = ?
+             RETURNING id, match_type, pattern, action, after_days, enabled, file_on_delivery, created_at"
+        )
+        .bind(&rule.match_type)
+        .bind(&rule.pattern)
+        .bind(&rule.action)
+        .bind(rule.after_days)
+        .bind(rule.enabled)
+        .bind(rule.file_on_delivery)
+        .bind(id),
+        None => sqlx::query_as(
+            "INSERT INTO retention_rules (match_type, pattern, action, after_days, enabled, file_on_delivery) VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(match_type, pattern) DO UPDATE SET action = excluded.action, after_days = excluded.after_days, enabled = excluded.enabled,
+                file_on_delivery = excluded.file_on_delivery
+             RETURNING id, match_type, pattern, action, after_days, enabled, file_on_delivery, created_at"
+        )
+        .bind(&rule.match_type)
+        .bind(&rule.pattern)
+        .bind(&rule.action)
+        .bind(rule.after_days)
+        .bind(rule.enabled)
+        .bind(rule.file_on_delivery),
+    };
+
+    query
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Rule not found".to_string())
+}
+
+#[tauri::command]
+pub async fn delete_retention_rule<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, rule_id: i64) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("DELETE FROM retention_rules WHERE id = ?")
+        .bind(rule_id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_retention_log<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, limit: Option<i64>) -> Result<Vec<RetentionLogEntry>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as(
+        "SELECT id, rule_id, email_id, action, subject, sender_address, applied_at FROM retention_log ORDER BY applied_at DESC, id DESC LIMIT ?"
+    )
+    .bind(limit.unwrap_or(200))
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_trash_retention<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<AccountTrashRetention>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as("SELECT id as account_id, email, trash_retention_days as days FROM accounts ORDER BY id")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// `days` of 0 keeps the account's trash until emptied by hand, `None` goes back to the setting.
+#[tauri::command]
+pub async fn set_account_trash_retention<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: i64, days: Option<i64>) -> Result<(), String> {
+    if days.is_some_and(|d| d < 0) {
+        return Err(i18n::t("error.retention_days", &[]));
+    }
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("UPDATE accounts SET trash_retention_days = ? WHERE id = ?")
+        .bind(days)
+        .bind(account_id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Emails a rule applies to: old enough, not starred, and not already where the rule would put them.
+async fn matching_emails(pool: &SqlitePool, rule: &RetentionRule) -> Result<Vec<(i64, i64, i64, String, String, bool)>, String> {
+    let mut query = sqlx::QueryBuilder::new(
+        "SELECT e.id, e.account_id, e.folder_id, f.path, e.remote_id, e.flags NOT LIKE '%\"seen\"%' as is_unread
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE e.flags NOT LIKE '%\"flagged\"%' AND datetime(e.date) < datetime('now', "
+    );
+    query.push_bind(format!("-{} days", rule.after_days));
+    query.push(")");
+
+    match (rule.match_type.as_str(), rule.pattern.strip_prefix('@')) {
+        ("sender", Some(domain)) => {
+            query.push(" AND LOWER(e.sender_address) LIKE ");
+            query.push_bind(format!("%@{}", domain));
+        }
+        ("sender", None) => {
+            query.push(" AND LOWER(e.sender_address) = ");
+            query.push_bind(&rule.pattern);
+        }
+        _ => {
+            query.push(" AND e.list_id = ");
+            query.push_bind(&rule.pattern);
+        }
+    }
+
+    query.push(if rule.action == "archive" {
+        " AND f.role = 'inbox'"
+    } else {
+        " AND COALESCE(f.role, '') NOT IN ('trash', 'sent', 'drafts')"
+    });
+    query.push(" LIMIT ");
+    query.push_bind(RETENTION_BATCH_LIMIT);
+
+    query.build_query_as().fetch_all(pool).await.map_err(|e| e.to_string())
+}
+
+async fn log_applied(pool: &SqlitePool, rule: &RetentionRule, ids: &[i64]) -> Result<(), String> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let mut query = sqlx::QueryBuilder::new("INSERT INTO retention_log (rule_id, email_id, action, subject, sender_address) SELECT ");
+    query.push_bind(rule.id);
+    query.push(", id, ");
+    query.push_bind(&rule.action);
+    query.push(", subject, sender_address FROM emails WHERE id IN (");
+    let mut separated = query.separated(", ");
+    for id in ids {
+        separated.push_bind(*id);
+    }
+    query.push(")");
+    query.build().execute(pool).await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Maintenance pass run by the sync worker. A failing rule is logged and skipped.
+pub async fn apply_retention_rules<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<usize, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+
+    let rules: Vec<RetentionRule> = sqlx::query_as(
+        "SELECT id, match_type, pattern, action, after_days, enabled, created_at FROM retention_rules WHERE enabled = 1"
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Keep the review log to a sensible size
+    sqlx::query("DELETE FROM retention_log WHERE applied_at < datetime('now', '-90 days')")
+        .execute(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut applied = 0;
+    for rule in rules {
+        let rows = match matching_emails(&pool, &rule).await {
+            Ok(rows) if !rows.is_empty() => rows,
+            Ok(_) => continue,
+            Err(e) => {
+                error!("Retention rule {:?} failed: {}", rule.id, e);
+                continue;
+            }
+        };
+
+        let role = if rule.action == "archive" { "archive" } else { "trash" };
+        match run_bulk(app_handle, "retention", BulkAction::MoveToRole(role), group_by_folder(rows)).await {
+            Ok(ids) => {
+                info!("Retention rule {:?} ({} {}) moved {} email(s) to {}", rule.id, rule.match_type, rule.pattern, ids.len(), role);
+                log_applied(&pool, &rule, &ids).await?;
+                applied += ids.len();
+            }
+            Err(e) => error!("Retention rule {:?} failed: {}", rule.id, e),
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Trash that has been there longer than its account's retention, flagged or not.
+async fn expired_trash(pool: &SqlitePool, default_days: u32) -> Result<Vec<(i64, i64, i64, String, String, bool)>, String> {
+    sqlx::query_as(
+        "SELECT e.id, e.account_id, e.folder_id, f.path, e.remote_id, e.flags NOT LIKE '%\"seen\"%' as is_unread
+         FROM emails e
+         JOIN folders f ON e.folder_id = f.id
+         JOIN accounts a ON e.account_id = a.id
+         WHERE f.role = 'trash' AND e.remote_id NOT LIKE 'moved:%'
+           AND COALESCE(a.trash_retention_days, ?1) > 0
+           AND e.trashed_at < datetime('now', '-' || COALESCE(a.trash_retention_days, ?1) || ' days')
+         LIMIT ?2"
+    )
+    .bind(default_days as i64)
+    .bind(RETENTION_BATCH_LIMIT)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Maintenance pass run by the sync worker, expunges old trash on the server and drops it locally.
+pub async fn empty_expired_trash<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<usize, String> {
+    let pool = app_handle.state::<SqlitePool>().inner().clone();
+    let settings = Settings::load(&pool).await?;
+
+    let rows = expired_trash(&pool, settings.trash_retention_days).await?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let ids = run_bulk(app_handle, "trash_retention", BulkAction::DeletePermanently, group_by_folder(rows)).await?;
+    info!("Trash retention deleted {} email(s)", ids.len());
+    Ok(ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(match_type: &str, pattern: &str) -> RetentionRule {
+        RetentionRule {
+            id: None,
+            match_type: match_type.to_string(),
+            pattern: pattern.to_string(),
+            action: "archive".to_string(),
+            after_days: 7,
+            enabled: true,
+            file_on_delivery: false,
+            created_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_trash_follows_account_override() {
+        let pool = crate::utils::test_utils::setup_test_db().await;
+        let mut trash_folders = Vec::new();
+        for (email, override_days) in [("default@example.com", None), ("keep@example.com", Some(0)), ("short@example.com", Some(1))] {
+            let (account_id,): (i64,) = sqlx::query_as("INSERT INTO accounts (email, account_type, trash_retention_days) VALUES (?, 'imap_smtp', ?) RETURNING id")
+                .bind(email)
+                .bind(override_days)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            let (folder_id,): (i64,) = sqlx::query_as("INSERT INTO folders (account_id, name, path, role) VALUES (?, 'Trash', 'Trash', 'trash') RETURNING id")
+                .bind(account_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO emails (account_id, folder_id, remote_id, message_id, subject, sender_address, date, flags) VALUES (?, ?, '1', ?, 'Old', 'a@example.com', '2024-01-01T00:00:00Z', '[]')")
+                .bind(account_id)
+                .bind(folder_id)
+                .bind(format!("<{}>", email))
+                .execute(&pool)
+                .await
+                .unwrap();
+            trash_folders.push(folder_id);
+        }
+        // Trashed five days ago
+        sqlx::query("UPDATE emails SET trashed_at = datetime('now', '-5 days')").execute(&pool).await.unwrap();
+
+        let folders = |rows: Vec<(i64, i64, i64, String, String, bool)>| rows.into_iter().map(|r| r.2).collect::<Vec<_>>();
+        assert_eq!(folders(expired_trash(&pool, 0).await.unwrap()), vec![trash_folders[2]]);
+        let mut with_default = folders(expired_trash(&pool, 3).await.unwrap());
+        with_default.sort();
+        assert_eq!(with_default, vec![trash_folders[0], trash_folders[2]]);
+        assert!(folders(expired_trash(&pool, 30).await.unwrap()).contains(&trash_folders[2]));
+    }
+
+    #[test]
+    fn test_normalize_rule() {
+        assert_eq!(parse_list_id("Rust Users <Rust-Users.Lists.Example.org>").as_deref(), Some("rust-users.lists.example.org"));
+        assert_eq!(normalize_rule(rule("list", "\"News\" <news.example.com>")).unwrap().pattern, "news.example.com");
+        assert_eq!(normalize_rule(rule("sender", " @Example.COM ")).unwrap().pattern, "@example.com");
+        assert!(normalize_rule(rule("sender", "example.com")).is_err());
+        assert!(normalize_rule(RetentionRule { after_days: 0, ..rule("sender", "a@example.com") }).is_err());
+        assert!(normalize_rule(RetentionRule { file_on_delivery: true, ..rule("sender", "a@example.com") }).is_ok());
+        let trash = RetentionRule { action: "trash".to_string(), file_on_delivery: true, ..rule("sender", "a@example.com") };
+        assert!(normalize_rule(trash).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::db::settings::Settings;
+use crate::email_backend::emails::commands::{get_email_by_id, DELIMITED_RECIPIENTS};
+use crate::email_backend::emails::events::EmailEvent;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ScreenedSender {
+    pub address: String,
+    pub status: String,
+    pub created_at: String,
+    pub decided_at: Option<String>,
+    pub email_count: i64,
+    pub latest_date: Option<String>,
+}
+
+pub(crate) async fn screener_enabled(pool: &SqlitePool) -> bool {
+    Settings::load(pool).await.is_ok_and(|settings| settings.screener_enabled)
+}
+
+/// Holds a freshly synced inbox email back when its sender hasn't been approved yet.
+/// Returns the screening status set on the email, `None` when it stays in the inbox.
+pub(crate) async fn screen_new_email(pool: &SqlitePool, email_id: i64, sender_address: &str) -> Result<Option<String>, String> {
+    let address = sender_address.trim().to_lowercase();
+
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM screened_senders WHERE address = ?")
+        .bind(&address)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let status = match status {
+        Some(status) => status,
+        None => {
+            // Anyone we already have mail from, or have written to, isn't a first-time sender
+            let known: bool = sqlx::query_scalar(&format!(
+                "SELECT EXISTS(SELECT 1 FROM emails WHERE LOWER(sender_address) = ? AND id != ?)
+                     OR EXISTS(SELECT 1 FROM emails e JOIN folders f ON e.folder_id = f.id
+                               WHERE f.role = 'sent' AND {} LIKE '%,' || ? || ',%')",
+                DELIMITED_RECIPIENTS
+            ))
+            .bind(&address)
+            .bind(email_id)
+            .bind(&address)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            if known {
+                return Ok(None);
+            }
+
+            sqlx::query("INSERT OR IGNORE INTO screened_senders (address) VALUES (?)")
+                .bind(&address)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            "pending".to_string()
+        }
+    };
+
+    if status == "approved" {
+        return Ok(None);
+    }
+
+    sqlx::query("UPDATE emails SET screening = ? WHERE id = ?")
+        .bind(&status)
+        .bind(email_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(status))
+}
+
+async fn decide(pool: &SqlitePool, address: &str, status: &str) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO screened_senders (address, status, decided_at) VALUES (?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(address) DO UPDATE SET status = excluded.status, decided_at = excluded.decided_at"
+    )
+    .bind(address)
+    .bind(status)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_screened_senders<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, status: Option<String>) -> Result<Vec<ScreenedSender>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as(
+        "SELECT s.address, s.status, s.created_at, s.decided_at, COUNT(e.id) as email_count, MAX(e.date) as latest_date
+         FROM screened_senders s
+         LEFT JOIN emails e ON LOWER(e.sender_address) = s.address
+         WHERE ? IS NULL OR s.status = ?
+         GROUP BY s.address
+         ORDER BY latest_date DESC"
+    )
+    .bind(&status)
+    .bind(&status)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Lets the sender into the inbox, including the mail already waiting in the screener.
+#[tauri::command]
+pub async fn approve_sender<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, address: String) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let address = address.trim().to_lowercase();
+    decide(&pool, &address, "approved").await?;
+
+    let ids: Vec<i64> = sqlx::query_scalar("UPDATE emails SET screening = NULL WHERE LOWER(sender_address) = ? AND screening IS NOT NULL RETURNING id")
+        .bind(&address)
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for email_id in ids {
+        if let Ok(email) = get_email_by_id(app_handle.clone(), email_id).await {
+            let _ = app_handle.emit("emails-updated", EmailEvent::Added(email));
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the sender's current and future mail out of the inbox.
+#[tauri::command]
+pub async fn screen_out_sender<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, address: String) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let address = address.trim().to_lowercase();
+    decide(&pool, &address, "screened_out").await?;
+
+    let ids: Vec<i64> = sqlx::query_scalar(
+        "UPDATE emails SET screening = 'screened_out'
+         WHERE LOWER(sender_address) = ? AND folder_id IN (SELECT id FROM folders WHERE role = 'inbox')
+         RETURNING id"
+    )
+    .bind(&address)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if !ids.is_empty() {
+        let _ = app_handle.emit("emails-updated", EmailEvent::RemovedBulk { ids });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[tokio::test]
+    async fn test_only_whole_addresses_written_to_are_known() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let sent_id = insert_test_folder(&pool, account_id, "Sent", Some("sent")).await;
+        let inbox_id = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+
+        let insert = |folder_id: i64, remote_id: &'static str, sender: &'static str, recipient_to: &'static str| {
+            let pool = pool.clone();
+            async move {
+                let (id,): (i64,) = sqlx::query_as(
+                    "INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, recipient_to, date, flags)
+                     VALUES (?, ?, ?, 'Hello', ?, ?, '2024-01-01T00:00:00Z', '[]') RETURNING id"
+                )
+                .bind(account_id)
+                .bind(folder_id)
+                .bind(remote_id)
+                .bind(sender)
+                .bind(recipient_to)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+                id
+            }
+        };
+
+        insert(sent_id, "1", "me@example.com", "Rebob <rebob@example.com>").await;
+        let first = insert(inbox_id, "1", "bob@example.com", "me@example.com").await;
+        assert_eq!(screen_new_email(&pool, first, "bob@example.com").await.unwrap().as_deref(), Some("pending"));
+
+        insert(sent_id, "2", "me@example.com", "Carol <carol@example.com>").await;
+        let second = insert(inbox_id, "2", "Carol@example.com", "me@example.com").await;
+        assert_eq!(screen_new_email(&pool, second, "Carol@example.com").await.unwrap(), None);
+    }
+}
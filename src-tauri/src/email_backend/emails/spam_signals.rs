@@ -0,0 +1,97 @@
+use crate::email_backend::emails::commands::MovedEmail;
+use sqlx::SqlitePool;
+
+/// How far reports have to outnumber rescues, or the other way round, before new mail from the
+/// sender is sorted by what the user did rather than by the server's spam filter.
+const LEARNED_THRESHOLD: i64 = 2;
+
+/// Counts moves to the spam folder as reports and moves from it to the inbox as rescues, per
+/// sender. `delta` is 1, or -1 when the move is undone.
+pub(crate) async fn record_moves(pool: &SqlitePool, moved: &[MovedEmail], role: &str, delta: i64) -> Result<(), String> {
+    for entry in moved {
+        let (spam, rescued) = match role {
+            "spam" => (delta, 0),
+            "inbox" => (0, delta),
+            _ => return Ok(()),
+        };
+        sqlx::query(
+            "INSERT INTO spam_signals (address, spam_count, rescued_count)
+             SELECT LOWER(e.sender_address), MAX(0, ?1), MAX(0, ?2) FROM emails e
+             WHERE e.id = ?3 AND (?4 = 'spam' OR (SELECT role FROM folders WHERE id = ?5) = 'spam')
+             ON CONFLICT(address) DO UPDATE SET
+                spam_count = MAX(0, spam_count + ?1),
+                rescued_count = MAX(0, rescued_count + ?2),
+                updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(spam)
+        .bind(rescued)
+        .bind(entry.email_id)
+        .bind(role)
+        .bind(entry.from_folder_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Where new mail from `address` belongs going by the user's past moves, `spam` or `inbox`,
+/// `None` while there is no clear pattern.
+pub(crate) async fn learned_role(pool: &SqlitePool, address: &str) -> Option<&'static str> {
+    let counts: Option<(i64, i64)> = sqlx::query_as("SELECT spam_count, rescued_count FROM spam_signals WHERE address = ?")
+        .bind(address.trim().to_lowercase())
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten();
+
+    match counts.map(|(spam, rescued)| spam - rescued) {
+        Some(score) if score >= LEARNED_THRESHOLD => Some("spam"),
+        Some(score) if score <= -LEARNED_THRESHOLD => Some("inbox"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_db};
+
+    #[tokio::test]
+    async fn test_learned_role_needs_repeated_moves() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let mut folders = Vec::new();
+        for role in ["inbox", "spam"] {
+            let folder_id = insert_test_folder(&pool, account_id, role, Some(role)).await;
+            folders.push(folder_id);
+        }
+        let mut moves = Vec::new();
+        for i in 0..3 {
+            let (email_id,): (i64,) = sqlx::query_as(
+                "INSERT INTO emails (account_id, folder_id, remote_id, subject, sender_address, date, flags) VALUES (?, ?, ?, 'Deal', 'Promo@Shop.example', '2024-01-01T00:00:00Z', '[]') RETURNING id"
+            )
+            .bind(account_id)
+            .bind(folders[1])
+            .bind(i.to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            moves.push(MovedEmail { email_id, account_id, message_id: None, from_folder_id: folders[0], to_folder_id: folders[1] });
+        }
+
+        record_moves(&pool, &moves[..1], "spam", 1).await.unwrap();
+        assert_eq!(learned_role(&pool, "promo@shop.example").await, None);
+        record_moves(&pool, &moves[1..], "spam", 1).await.unwrap();
+        assert_eq!(learned_role(&pool, "promo@shop.example").await, Some("spam"));
+
+        // Only mail taken out of the spam folder counts as rescued
+        let archived = MovedEmail { from_folder_id: folders[0], to_folder_id: folders[0], ..moves[0].clone() };
+        record_moves(&pool, &[archived], "inbox", 1).await.unwrap();
+        assert_eq!(learned_role(&pool, "promo@shop.example").await, Some("spam"));
+
+        let rescued = MovedEmail { from_folder_id: folders[1], to_folder_id: folders[0], ..moves[0].clone() };
+        record_moves(&pool, &[rescued], "inbox", 1).await.unwrap();
+        assert_eq!(learned_role(&pool, "promo@shop.example").await, None);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::db::settings::Settings;
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::i18n;
+use log::info;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+
+/// Payload of the `stack-changed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct StackChanged {
+    pub ids: Vec<i64>,
+    pub stack: Option<String>,
+}
+
+/// Puts the whole thread of `email_id` on a stack, or takes it off with `None`.
+async fn set_stack<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_id: i64, stack: Option<&str>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+
+    let ids: Vec<i64> = sqlx::query_scalar(
+        "UPDATE emails SET stack = ?, stacked_at = CASE WHEN ? IS NULL THEN NULL ELSE CURRENT_TIMESTAMP END, nudged_at = NULL
+         WHERE id = ?
+            OR (thread_id IS NOT NULL
+                AND thread_id = (SELECT thread_id FROM emails WHERE id = ?)
+                AND account_id = (SELECT account_id FROM emails WHERE id = ?))
+         RETURNING id"
+    )
+    .bind(stack)
+    .bind(stack)
+    .bind(email_id)
+    .bind(email_id)
+    .bind(email_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if ids.is_empty() {
+        return Err("Email not found".to_string());
+    }
+
+    let _ = app_handle.emit("stack-changed", StackChanged { ids, stack: stack.map(str::to_string) });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_reply_later<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
+    set_stack(&app_handle, email_id, Some("reply_later")).await
+}
+
+#[tauri::command]
+pub async fn set_aside<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
+    set_stack(&app_handle, email_id, Some("set_aside")).await
+}
+
+#[tauri::command]
+pub async fn clear_stack<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
+    set_stack(&app_handle, email_id, None).await
+}
+
+/// Reminds about reply later threads left alone for longer than `replyLaterNudgeDays`, once per thread.
+pub async fn nudge_stale_reply_later<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let settings = Settings::load(&pool).await?;
+    if settings.reply_later_nudge_days == 0 || !settings.notifications_enabled {
+        return Ok(());
+    }
+
+    let stale: Vec<(String, Option<String>)> = sqlx::query_as(
+        "SELECT COALESCE(e.thread_id, CAST(e.id AS TEXT)) as thread, MAX(e.subject)
+         FROM emails e JOIN folders f ON e.folder_id = f.id
+         WHERE e.stack = 'reply_later' AND e.nudged_at IS NULL AND f.role != 'trash'
+           AND e.stacked_at < datetime('now', ?)
+         GROUP BY thread"
+    )
+    .bind(format!("-{} days", settings.reply_later_nudge_days))
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let days = settings.reply_later_nudge_days.to_string();
+    let (title, body) = match stale.as_slice() {
+        [] => return Ok(()),
+        [(_, subject)] => (
+            i18n::t("notification.reply_later", &[("subject", &subject.clone().unwrap_or_else(|| i18n::t("email.no_subject", &[])))]),
+            i18n::t("notification.reply_later_body", &[("days", &days)]),
+        ),
+        threads => (
+            i18n::t("notification.reply_later_many", &[("count", &threads.len().to_string())]),
+            i18n::t("notification.reply_later_many_body", &[("days", &days)]),
+        ),
+    };
+    SyncEngine::<R>::show_notification(app_handle, title, body).await;
+
+    sqlx::query(
+        "UPDATE emails SET nudged_at = CURRENT_TIMESTAMP
+         WHERE stack = 'reply_later' AND nudged_at IS NULL AND stacked_at < datetime('now', ?)"
+    )
+    .bind(format!("-{} days", settings.reply_later_nudge_days))
+    .execute(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    info!("Nudged about {} reply later thread(s)", stale.len());
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use crate::email_backend::emails::bulk::{emails_by_id, run_bulk, BulkAction};
+use crate::email_backend::emails::keywords;
+use crate::email_backend::errors::{report_error, BackendError, ErrorCategory, ErrorSeverity};
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::i18n;
+use imap_client::imap_next::imap_types::sequence::Sequence;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::num::NonZeroU32;
+use tauri::{Emitter, Manager};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    /// `#rrggbb`
+    pub color: String,
+    pub sync_to_server: bool,
+    /// Tagged messages, in the account asked for when there is one
+    pub count: i64,
+}
+
+/// Payload of the `tags-changed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagsChanged {
+    pub ids: Vec<i64>,
+    pub tag_id: i64,
+    pub added: bool,
+}
+
+fn validate(name: &str, color: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Tag name cannot be empty".to_string());
+    }
+    let hex = color.strip_prefix('#').unwrap_or_default();
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid tag color: {}", color));
+    }
+    Ok(())
+}
+
+/// The IMAP keyword standing for a tag, its name with what an atom can't hold replaced.
+fn keyword_for(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_graphic() && !"(){%*\"\\]".contains(c) { c } else { '_' })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn get_tags<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, account_id: Option<i64>) -> Result<Vec<Tag>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as::<_, Tag>(
+        "SELECT t.id, t.name, t.color, t.sync_to_server, COUNT(et.tag_id) as count
+         FROM tags t LEFT JOIN email_tags et ON et.tag_id = t.id AND (? IS NULL OR et.account_id = ?)
+         GROUP BY t.id
+         ORDER BY t.name COLLATE NOCASE"
+    )
+    .bind(account_id)
+    .bind(account_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_tag<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, name: String, color: String, sync_to_server: bool) -> Result<Tag, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let name = name.trim();
+    validate(name, &color)?;
+
+    sqlx::query_as::<_, Tag>(
+        "INSERT INTO tags (name, color, sync_to_server) VALUES (?, ?, ?)
+         RETURNING id, name, color, sync_to_server, 0 as count"
+    )
+    .bind(name)
+    .bind(&color)
+    .bind(sync_to_server)
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Renaming does not follow on the server, messages tagged before keep the old keyword or label.
+#[tauri::command]
+pub async fn update_tag<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, id: i64, name: String, color: String, sync_to_server: bool) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let name = name.trim();
+    validate(name, &color)?;
+
+    let result = sqlx::query("UPDATE tags SET name = ?, color = ?, sync_to_server = ? WHERE id = ?")
+        .bind(name)
+        .bind(&color)
+        .bind(sync_to_server)
+        .bind(id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if result.rows_affected() == 0 {
+        return Err("Tag not found".to_string());
+    }
+    Ok(())
+}
+
+/// Deletes the tag locally, keywords and labels already on the server stay there.
+#[tauri::command]
+pub async fn delete_tag<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, id: i64) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("DELETE FROM tags WHERE id = ?")
+        .bind(id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Stores a tag change on the server: a Gmail label is a folder holding a copy of the message,
+/// everywhere else the tag is a keyword.
+async fn sync_to_server<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, name: &str, emails: Vec<(i64, i64, String, String, String, String)>, add: bool) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let engine = app_handle.state::<SyncEngine<R>>();
+    let mut keyword_ids = Vec::new();
+
+    for (email_id, account_id, message_id, remote_id, folder_path, account_type) in emails {
+        if account_type != "google" {
+            keyword_ids.push(email_id);
+            continue;
+        }
+
+        if add {
+            let Some(uid) = remote_id.parse::<NonZeroU32>().ok() else { continue };
+            let label_known: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM folders WHERE account_id = ? AND path = ?)")
+                .bind(account_id)
+                .bind(name)
+                .fetch_one(&*pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let result = async {
+                let context = engine.get_context(account_id).await?;
+                let mut client = context.client().await;
+                engine.with_timeout(account_id, async {
+                    if !label_known {
+                        client.create_mailbox(name).await.map_err(|e| e.to_string())?;
+                    }
+                    client.select_mailbox(&folder_path).await.map_err(|e| e.to_string())?;
+                    client.copy_messages(Sequence::from(uid).into(), name).await.map_err(|e| e.to_string())
+                }).await?
+            }.await;
+            if let Err(e) = result {
+                report_error(app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.tag_server", &[("error", &e)])).retryable());
+            }
+        } else {
+            // Deleting the copy in the label's folder only takes the label off on Gmail
+            let copies: Vec<i64> = sqlx::query_scalar(
+                "SELECT e.id FROM emails e JOIN folders f ON e.folder_id = f.id
+                 WHERE e.account_id = ? AND e.message_id = ? AND f.path = ?"
+            )
+            .bind(account_id)
+            .bind(&message_id)
+            .bind(name)
+            .fetch_all(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            if let Err(e) = run_bulk(app_handle, "untag", BulkAction::DeletePermanently, emails_by_id(&pool, &copies).await?).await {
+                report_error(app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.tag_server", &[("error", &e)])).retryable());
+            }
+        }
+    }
+
+    let keyword = keyword_for(name);
+    if !keyword_ids.is_empty() && keywords::validate_keyword(&keyword).is_ok() {
+        keywords::set_keyword(app_handle, keyword_ids, &keyword, add).await?;
+    }
+    Ok(())
+}
+
+async fn set_tag<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, email_ids: Vec<i64>, tag_id: i64, add: bool) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+
+    let (name, synced): (String, bool) = sqlx::query_as("SELECT name, sync_to_server FROM tags WHERE id = ?")
+        .bind(tag_id)
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Tag not found")?;
+
+    let mut changed = Vec::new();
+    for email_id in email_ids {
+        let email: Option<(i64, i64, String, String, String, String)> = sqlx::query_as(
+            "SELECT e.id, e.account_id, e.message_id, e.remote_id, f.path, a.account_type
+             FROM emails e JOIN folders f ON e.folder_id = f.id JOIN accounts a ON e.account_id = a.id
+             WHERE e.id = ? AND e.message_id IS NOT NULL"
+        )
+        .bind(email_id)
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let Some(email) = email else { continue };
+
+        let query = if add {
+            "INSERT OR IGNORE INTO email_tags (account_id, message_id, tag_id) VALUES (?, ?, ?)"
+        } else {
+            "DELETE FROM email_tags WHERE account_id = ? AND message_id = ? AND tag_id = ?"
+        };
+        let result = sqlx::query(query)
+            .bind(email.1)
+            .bind(&email.2)
+            .bind(tag_id)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if result.rows_affected() > 0 {
+            changed.push(email);
+        }
+    }
+
+    if changed.is_empty() {
+        return Ok(());
+    }
+    let _ = app_handle.emit("tags-changed", TagsChanged {
+        ids: changed.iter().map(|email| email.0).collect(),
+        tag_id,
+        added: add,
+    });
+
+    if synced {
+        sync_to_server(app_handle, &name, changed, add).await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn tag_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, tag_id: i64) -> Result<(), String> {
+    set_tag(&app_handle, email_ids, tag_id, true).await
+}
+
+#[tauri::command]
+pub async fn untag_emails<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_ids: Vec<i64>, tag_id: i64) -> Result<(), String> {
+    set_tag(&app_handle, email_ids, tag_id, false).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, setup_test_db};
+
+    #[test]
+    fn test_tag_names_and_colors() {
+        assert!(validate("Work", "#1a2B3c").is_ok());
+        assert!(validate("Work", "red").is_err());
+        assert!(validate("", "#000000").is_err());
+        assert_eq!(keyword_for("Follow up (urgent)"), "Follow_up__urgent_");
+    }
+
+    #[tokio::test]
+    async fn test_email_tags_follow_message_id_and_cascade() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let (tag_id,): (i64,) = sqlx::query_as("INSERT INTO tags (name, color) VALUES ('Work', '#ff0000') RETURNING id")
+            .fetch_one(&pool).await.unwrap();
+        sqlx::query("INSERT INTO email_tags (account_id, message_id, tag_id) VALUES (?, '<a@example.com>', ?)")
+            .bind(account_id).bind(tag_id).execute(&pool).await.unwrap();
+
+        assert!(sqlx::query("INSERT INTO tags (name, color) VALUES ('work', '#00ff00')").execute(&pool).await.is_err());
+
+        sqlx::query("DELETE FROM tags WHERE id = ?").bind(tag_id).execute(&pool).await.unwrap();
+        let (left,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM email_tags").fetch_one(&pool).await.unwrap();
+        assert_eq!(left, 0);
+    }
+}
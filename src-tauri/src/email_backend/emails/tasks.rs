@@ -0,0 +1,205 @@
+use crate::db::settings::Settings;
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::i18n;
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Task {
+    pub id: i64,
+    pub account_id: i64,
+    /// The email the task was made from, `None` once it is gone from every folder
+    pub email_id: Option<i64>,
+    pub title: String,
+    pub note: Option<String>,
+    pub due_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub sender_address: Option<String>,
+}
+
+const TASK_COLUMNS: &str =
+    "t.id, t.account_id, t.title, t.note, t.due_at, t.completed_at,
+     COALESCE(t.email_id, (SELECT e.id FROM emails e WHERE e.account_id = t.account_id AND e.message_id = t.message_id LIMIT 1)) as email_id,
+     (SELECT e.sender_address FROM emails e WHERE e.account_id = t.account_id AND (e.id = t.email_id OR e.message_id = t.message_id) LIMIT 1) as sender_address";
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Due dates come from the UI in any RFC 3339 offset and are stored in UTC so they compare as text.
+fn parse_due(due_at: Option<String>) -> Result<Option<String>, String> {
+    due_at
+        .filter(|due| !due.trim().is_empty())
+        .map(|due| {
+            DateTime::parse_from_rfc3339(due.trim())
+                .map(|due| format_time(due.with_timezone(&Utc)))
+                .map_err(|e| format!("Invalid due date {}: {}", due, e))
+        })
+        .transpose()
+}
+
+#[tauri::command]
+pub async fn create_task_from_email<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, email_id: i64, due_at: Option<String>, note: Option<String>) -> Result<Task, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let due_at = parse_due(due_at)?;
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+
+    let (account_id, message_id, subject): (i64, Option<String>, Option<String>) =
+        sqlx::query_as("SELECT account_id, message_id, subject FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_optional(&*pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("Email not found")?;
+    let title = subject
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| i18n::t("email.no_subject", &[]));
+
+    let (id,): (i64,) = sqlx::query_as(
+        "INSERT INTO tasks (account_id, email_id, message_id, title, note, due_at) VALUES (?, ?, ?, ?, ?, ?) RETURNING id"
+    )
+    .bind(account_id)
+    .bind(email_id)
+    .bind(message_id)
+    .bind(title)
+    .bind(note)
+    .bind(due_at)
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query_as::<_, Task>(&format!("SELECT {} FROM tasks t WHERE t.id = ?", TASK_COLUMNS))
+        .bind(id)
+        .fetch_one(&*pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Open tasks by due date, undated ones last, then the completed ones when asked for.
+#[tauri::command]
+pub async fn get_tasks<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, include_completed: Option<bool>) -> Result<Vec<Task>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query_as::<_, Task>(&format!(
+        "SELECT {} FROM tasks t
+         WHERE t.completed_at IS NULL OR ?
+         ORDER BY t.completed_at IS NOT NULL, t.due_at IS NULL, t.due_at, t.completed_at DESC, t.id",
+        TASK_COLUMNS
+    ))
+    .bind(include_completed.unwrap_or(false))
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Marks a task done, or open again with `completed` false.
+#[tauri::command]
+pub async fn complete_task<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, id: i64, completed: Option<bool>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let completed_at = completed.unwrap_or(true).then(|| format_time(Utc::now()));
+
+    let result = sqlx::query("UPDATE tasks SET completed_at = ? WHERE id = ?")
+        .bind(completed_at)
+        .bind(id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if result.rows_affected() == 0 {
+        return Err("Task not found".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_task<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, id: i64) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    sqlx::query("DELETE FROM tasks WHERE id = ?")
+        .bind(id)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Open tasks past their due date that were not reminded of yet.
+async fn take_due(pool: &SqlitePool, now: DateTime<Utc>) -> Result<Vec<(String, Option<String>)>, String> {
+    let now = format_time(now);
+    let due: Vec<(String, Option<String>)> = sqlx::query_as(
+        "UPDATE tasks SET notified_at = ?
+         WHERE completed_at IS NULL AND notified_at IS NULL AND due_at IS NOT NULL AND due_at <= ?
+         RETURNING title, note"
+    )
+    .bind(&now)
+    .bind(&now)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(due)
+}
+
+/// Reminds about tasks that became due, once each. Changing nothing when notifications are off
+/// would fire every missed reminder at once when they are turned back on, so those are used up too.
+pub async fn notify_due_tasks<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let due = take_due(&pool, Utc::now()).await?;
+    if due.is_empty() || !Settings::load(&pool).await?.notifications_enabled {
+        return Ok(());
+    }
+
+    let (title, body) = match due.as_slice() {
+        [(title, note)] => (
+            i18n::t("notification.task_due", &[("title", title)]),
+            note.clone().unwrap_or_default(),
+        ),
+        tasks => (
+            i18n::t("notification.tasks_due_many", &[("count", &tasks.len().to_string())]),
+            tasks.iter().map(|(title, _)| title.as_str()).collect::<Vec<_>>().join(", "),
+        ),
+    };
+    SyncEngine::<R>::show_notification(app_handle, title, body).await;
+
+    info!("Reminded about {} due task(s)", due.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, setup_test_db};
+
+    #[test]
+    fn test_due_dates_are_stored_in_utc() {
+        assert_eq!(parse_due(Some("2024-03-01T09:30:00+02:00".to_string())).unwrap(), Some("2024-03-01T07:30:00Z".to_string()));
+        assert_eq!(parse_due(Some(" ".to_string())).unwrap(), None);
+        assert!(parse_due(Some("tomorrow".to_string())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_due_tasks_are_reminded_once() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        for (title, due_at, completed_at) in [
+            ("Pay invoice", Some("2024-03-01T07:30:00Z"), None),
+            ("Later", Some("2024-03-02T07:30:00Z"), None),
+            ("Done", Some("2024-03-01T07:00:00Z"), Some("2024-03-01T06:00:00Z")),
+            ("Someday", None, None),
+        ] {
+            sqlx::query("INSERT INTO tasks (account_id, title, due_at, completed_at) VALUES (?, ?, ?, ?)")
+                .bind(account_id)
+                .bind(title)
+                .bind(due_at)
+                .bind(completed_at)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let now = DateTime::parse_from_rfc3339("2024-03-01T08:00:00Z").unwrap().with_timezone(&Utc);
+        let due = take_due(&pool, now).await.unwrap();
+        assert_eq!(due, vec![("Pay invoice".to_string(), None)]);
+        assert!(take_due(&pool, now).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::db::writer::WritePool;
+use crate::email_backend::emails::commands::{apply_local_move, get_email_by_id, MovedEmail};
+use crate::email_backend::emails::events::EmailEvent;
+use crate::email_backend::emails::spam_signals;
+use crate::email_backend::errors::{report_error, BackendError, ErrorCategory, ErrorSeverity};
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::i18n;
+use imap_client::imap_next::imap_types::core::AString;
+use imap_client::imap_next::imap_types::error::ValidationError;
+use imap_client::imap_next::imap_types::search::SearchKey;
+use imap_client::imap_next::imap_types::sequence::{Sequence, SequenceSet};
+use log::info;
+use rand::RngCore;
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+
+const UNDO_WINDOW_SECS: i64 = 60;
+
+/// Journals a batch of moves and hands back the token that reverses them.
+pub async fn record(pool: &SqlitePool, action: &str, moved: &[MovedEmail]) -> Result<Option<String>, String> {
+    if moved.is_empty() {
+        return Ok(None);
+    }
+
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    // Nothing older than the window can be undone anyway
+    sqlx::query("DELETE FROM undo_journal WHERE created_at < datetime('now', '-1 day')")
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("INSERT INTO undo_journal (token, action, entries) VALUES (?, ?, ?)")
+        .bind(&token)
+        .bind(action)
+        .bind(serde_json::to_string(moved).map_err(|e| e.to_string())?)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(token))
+}
+
+fn message_id_search(message_id: &str) -> Result<SearchKey<'static>, String> {
+    let name = AString::try_from("Message-ID".to_string()).map_err(|e: ValidationError| e.to_string())?;
+    let value = AString::try_from(message_id.to_string()).map_err(|e: ValidationError| e.to_string())?;
+    Ok(SearchKey::Header(name, value))
+}
+
+/// Moves the message back on the server. Its UID changed with the first move, so it is found by
+/// Message-ID, and the UID it gets back in the original folder is returned.
+async fn move_back_on_server<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    account_id: i64,
+    message_id: &str,
+    current_path: &str,
+    original_path: &str,
+) -> Result<Option<String>, String> {
+    let engine = app_handle.state::<SyncEngine<R>>();
+    let context = engine.get_context(account_id).await?;
+    let search = message_id_search(message_id)?;
+
+    let mut client = context.client().await;
+    engine.with_timeout(account_id, async {
+        client.select_mailbox(current_path).await.map_err(|e| e.to_string())?;
+        let uids = client.search_uids([search.clone()]).await.map_err(|e| e.to_string())?;
+        if uids.is_empty() {
+            return Err(format!("Message {} not found in {}", message_id, current_path));
+        }
+
+        let uids: SequenceSet = uids
+            .into_iter()
+            .map(Sequence::from)
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|e: ValidationError| e.to_string())?;
+        client.move_messages(uids, original_path).await.map_err(|e| e.to_string())?;
+
+        client.examine_mailbox(original_path).await.map_err(|e| e.to_string())?;
+        let uids = client.search_uids([search]).await.map_err(|e| e.to_string())?;
+        Ok::<_, String>(uids.last().map(|uid| uid.to_string()))
+    }).await?
+}
+
+#[tauri::command]
+pub async fn undo_action<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, token: String) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+
+    let row: Option<(String, String, i64)> = sqlx::query_as(
+        "SELECT action, entries, CAST(strftime('%s', 'now') - strftime('%s', created_at) AS INTEGER) FROM undo_journal WHERE token = ? AND undone = 0"
+    )
+    .bind(&token)
+    .fetch_optional(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (action, entries, age) = row.ok_or_else(|| i18n::t("error.nothing_to_undo", &[]))?;
+    if age > UNDO_WINDOW_SECS {
+        return Err(i18n::t("error.undo_too_late", &[]));
+    }
+
+    // Claim the entry first so a double click can't move the emails back twice
+    let claimed = sqlx::query("UPDATE undo_journal SET undone = 1 WHERE token = ? AND undone = 0")
+        .bind(&token)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if claimed.rows_affected() == 0 {
+        return Err(i18n::t("error.nothing_to_undo", &[]));
+    }
+
+    let entries: Vec<MovedEmail> = serde_json::from_str(&entries).map_err(|e| e.to_string())?;
+    let mut restored = Vec::new();
+
+    // A spam report taken back shouldn't count against the sender
+    if action == "spam" {
+        spam_signals::record_moves(&pool, &entries, "spam", -1).await?;
+    }
+
+    for entry in entries {
+        let paths: Option<(i64, String, String)> = sqlx::query_as(
+            "SELECT e.folder_id, cur.path, orig.path FROM emails e
+             JOIN folders cur ON e.folder_id = cur.id
+             JOIN folders orig ON orig.id = ?
+             WHERE e.id = ?"
+        )
+        .bind(entry.from_folder_id)
+        .bind(entry.email_id)
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        // Gone or moved again since, leave it where it is
+        let Some((folder_id, current_path, original_path)) = paths else { continue };
+        if folder_id != entry.to_folder_id {
+            continue;
+        }
+
+        let mut remote_id = None;
+        match entry.message_id.as_deref().filter(|m| !m.is_empty()) {
+            Some(message_id) => match move_back_on_server(&app_handle, entry.account_id, message_id, &current_path, &original_path).await {
+                Ok(uid) => remote_id = uid,
+                Err(e) => {
+                    report_error(&app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.undo_local", &[("action", &action), ("error", &e)])).retryable());
+                }
+            },
+            None => {
+                report_error(&app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.undo_no_message_id", &[("action", &action)])));
+            }
+        }
+
+        apply_local_move(&app_handle.state::<WritePool>(), entry.email_id, entry.to_folder_id, entry.from_folder_id, remote_id.as_deref()).await?;
+        restored.push(entry.email_id);
+    }
+
+    info!("Undid {} for {} email(s)", action, restored.len());
+
+    for email_id in restored {
+        if let Ok(email) = get_email_by_id(app_handle.clone(), email_id).await {
+            let _ = app_handle.emit("emails-updated", EmailEvent::Added(email));
+        }
+    }
+
+    Ok(())
+}
@@ -2,11 +2,30 @@ use tauri::{Manager, Emitter};
 use sqlx::SqlitePool;
 use chrono::Utc;
 use std::collections::HashMap;
-use crate::email_backend::enrichment::types::{Sender, Domain};
+use crate::email_backend::enrichment::types::{Sender, Domain, SenderTimeline, SharedFile, SharedItems, SharedLink, TimelineItem};
 use crate::email_backend::enrichment::providers::*;
 use crate::email_backend::enrichment::people::*;
+use crate::email_backend::enrichment::throttle;
 use crate::email_backend::accounts::manager::{AccountManager, Account};
-use crate::email_backend::emails::commands::Email;
+use crate::email_backend::emails::commands::{list_emails, push_correspondents_condition, Attachment, Email, EmailListing};
+use crate::db::settings::Settings;
+use crate::db::writer::WritePool;
+use crate::utils::{i18n, proxy};
+
+/// Whether external enrichment is allowed at all, and whether network-backed
+/// providers should be skipped because the user is offline.
+struct EnrichmentPolicy {
+    enabled: bool,
+    offline: bool,
+}
+
+async fn get_enrichment_policy(pool: &SqlitePool) -> EnrichmentPolicy {
+    let settings = Settings::load(pool).await.unwrap_or_default();
+    EnrichmentPolicy {
+        enabled: settings.enrichment_enabled,
+        offline: settings.offline_mode,
+    }
+}
 
 #[tauri::command]
 pub async fn search_contacts<R: tauri::Runtime>(
@@ -95,7 +114,11 @@ pub async fn sync_contacts_internal<R: tauri::Runtime>(
     let pool = app_handle.state::<SqlitePool>();
 
     for account in registry.accounts {
+        // The People API needs OAuth, app password accounts only have IMAP/SMTP access
         if let Account::Google(google) = account {
+            if google.app_password.is_some() {
+                continue;
+            }
             let email = google.email.clone();
             let token = match manager.refresh_access_token(&email).await {
                 Ok(t) => t,
@@ -119,7 +142,7 @@ async fn sync_google_contacts<R: tauri::Runtime>(
     account_email: &str,
     token: &str,
 ) -> Result<(), String> {
-    let client = reqwest::Client::new();
+    let client = proxy::http_client()?;
     let pool = app_handle.state::<SqlitePool>();
     
     let mut next_page_token: Option<String> = None;
@@ -223,30 +246,255 @@ pub async fn save_recipients_as_contacts<R: tauri::Runtime>(
     Ok(())
 }
 
+/// Resolves an address to the primary address of the contact it has been merged into.
+pub(crate) async fn resolve_primary_address(pool: &SqlitePool, address: &str) -> Result<String, String> {
+    let primary: Option<String> = sqlx::query_scalar("SELECT primary_address FROM sender_aliases WHERE LOWER(alias_address) = LOWER(?)")
+        .bind(address)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(primary.unwrap_or_else(|| address.to_string()))
+}
+
+#[tauri::command]
+pub async fn merge_senders<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    primary: String,
+    others: Vec<String>,
+) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+
+    // Merging into an address that is itself an alias would create chains, follow it instead
+    let primary = resolve_primary_address(&pool, &primary).await?;
+
+    let mut tx = app_handle.state::<WritePool>().begin().await?;
+    for other in others.iter().filter(|o| **o != primary) {
+        // Anything previously merged into `other` now belongs to the new primary
+        sqlx::query("UPDATE sender_aliases SET primary_address = ? WHERE primary_address = ?")
+            .bind(&primary)
+            .bind(other)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        sqlx::query(
+            "INSERT INTO sender_aliases (alias_address, primary_address) VALUES (?, ?)
+             ON CONFLICT(alias_address) DO UPDATE SET primary_address = excluded.primary_address"
+        )
+        .bind(other)
+        .bind(&primary)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    // The primary can never be an alias of something else
+    sqlx::query("DELETE FROM sender_aliases WHERE alias_address = ?")
+        .bind(&primary)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let _ = app_handle.emit("sender-updated", &primary);
+    Ok(())
+}
+
+/// A contact's primary address and all of its aliases, lowercased.
+async fn correspondent_addresses(pool: &SqlitePool, address: &str) -> Result<Vec<String>, String> {
+    let primary = resolve_primary_address(pool, address).await?;
+
+    let mut addresses: Vec<String> = sqlx::query_scalar("SELECT LOWER(alias_address) FROM sender_aliases WHERE primary_address = ?")
+        .bind(&primary)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    addresses.push(primary.to_lowercase());
+    Ok(addresses)
+}
+
+/// Threads with this person, mail from any of their addresses and mail we sent them, newest first.
 #[tauri::command]
 pub async fn get_emails_by_sender<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
     address: String,
     limit: u32,
+    before_date: Option<String>,
+    before_id: Option<i64>,
 ) -> Result<Vec<Email>, String> {
     let pool = app_handle.state::<SqlitePool>();
-
-    let emails = sqlx::query_as::<_, Email>(
-        "SELECT id, account_id, folder_id, remote_id, message_id, thread_id, 1 as thread_count, in_reply_to, references_header, subject, sender_name, sender_address, recipient_to, date, flags, snippet, has_attachments,
-         (subject LIKE 'Re:%' OR subject LIKE 're:%' OR in_reply_to IS NOT NULL) as is_reply,
-         (subject LIKE 'Fwd:%' OR subject LIKE 'fwd:%' OR subject LIKE 'Fw:%' OR subject LIKE 'fw:%') as is_forward
-         FROM emails
-         WHERE sender_address = ?
-         ORDER BY date DESC
-         LIMIT ?"
-    )
-    .bind(&address)
-    .bind(limit as i64)
-    .fetch_all(&*pool)
+    let correspondents = correspondent_addresses(&pool, &address).await?;
+
+    list_emails(&pool, EmailListing {
+        limit: Some(limit),
+        before_date,
+        before_id,
+        correspondents: Some(correspondents),
+        ..Default::default()
+    })
     .await
-    .map_err(|e| e.to_string())?;
+}
+
+const SHARED_ITEMS_LIMIT: i64 = 200;
+
+/// Links and files exchanged with a contact in either direction, newest first.
+#[tauri::command]
+pub async fn get_sender_shared_items<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    address: String,
+    limit: Option<i64>,
+) -> Result<SharedItems, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let correspondents = correspondent_addresses(&pool, &address).await?;
+    let limit = limit.unwrap_or(SHARED_ITEMS_LIMIT);
+
+    // The same link is usually repeated across a thread, only its latest mention is kept
+    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+        "SELECT url, domain, title, email_id, subject, date, from_me FROM (
+            SELECT l.url, l.domain, l.title, e.id as email_id, e.subject, e.date, COALESCE(f.role = 'sent', 0) as from_me,
+                   ROW_NUMBER() OVER (PARTITION BY l.url ORDER BY e.date DESC, e.id DESC) as rn
+            FROM links l
+            JOIN emails e ON l.email_id = e.id
+            JOIN folders f ON e.folder_id = f.id
+            WHERE COALESCE(f.role, '') NOT IN ('spam', 'trash') AND "
+    );
+    push_correspondents_condition(&mut query_builder, &correspondents);
+    query_builder.push(") WHERE rn = 1 ORDER BY date DESC, email_id DESC LIMIT ");
+    query_builder.push_bind(limit);
+    let links: Vec<SharedLink> = query_builder.build_query_as().fetch_all(&*pool).await.map_err(|e| e.to_string())?;
+
+    // Copies of one message in several folders would list its files twice
+    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+        "SELECT attachment_id, filename, mime_type, size, email_id, subject, date, from_me FROM (
+            SELECT a.id as attachment_id, a.filename, a.mime_type, a.size, e.id as email_id, e.subject, e.date, COALESCE(f.role = 'sent', 0) as from_me,
+                   ROW_NUMBER() OVER (
+                       PARTITION BY COALESCE(e.message_id, CAST(e.id AS TEXT)), a.filename, a.size
+                       ORDER BY CASE WHEN f.role = 'inbox' THEN 0 WHEN f.role = 'sent' THEN 1 ELSE 2 END, a.id
+                   ) as rn
+            FROM attachments a
+            JOIN emails e ON a.email_id = e.id
+            JOIN folders f ON e.folder_id = f.id
+            WHERE NOT a.is_inline AND COALESCE(f.role, '') NOT IN ('spam', 'trash') AND "
+    );
+    push_correspondents_condition(&mut query_builder, &correspondents);
+    query_builder.push(") WHERE rn = 1 ORDER BY date DESC, attachment_id DESC LIMIT ");
+    query_builder.push_bind(limit);
+    let files: Vec<SharedFile> = query_builder.build_query_as().fetch_all(&*pool).await.map_err(|e| e.to_string())?;
+
+    Ok(SharedItems { links, files })
+}
+
+const TIMELINE_PAGE_SIZE: i64 = 50;
+
+#[tauri::command]
+pub async fn get_sender_timeline<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    address: String,
+    cursor: Option<String>,
+) -> Result<SenderTimeline, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let correspondents = correspondent_addresses(&pool, &address).await?;
+    let (before_date, before_id) = match cursor.as_deref() {
+        Some(cursor) => {
+            let (date, id) = parse_timeline_cursor(cursor)?;
+            (Some(date), Some(id))
+        }
+        None => (None, None),
+    };
+
+    // Both directions: mail from any of their addresses, and mail we sent to them
+    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+        "SELECT e.id, e.account_id, e.folder_id, e.remote_id, e.message_id, e.thread_id, 1 as thread_count, e.in_reply_to, e.references_header, e.subject, e.sender_name, e.sender_address, e.recipient_to, e.date, e.flags, e.snippet, e.summary, e.has_attachments,
+         (e.subject LIKE 'Re:%' OR e.subject LIKE 're:%' OR e.in_reply_to IS NOT NULL) as is_reply,
+         (e.subject LIKE 'Fwd:%' OR e.subject LIKE 'fwd:%' OR e.subject LIKE 'Fw:%' OR e.subject LIKE 'fw:%') as is_forward,
+         (LOWER(e.sender_address) IN (SELECT address FROM own_addresses)) as is_from_me
+         FROM emails e
+         JOIN folders f ON e.folder_id = f.id
+         WHERE "
+    );
+    push_correspondents_condition(&mut query_builder, &correspondents);
+    if let (Some(date), Some(id)) = (&before_date, before_id) {
+        query_builder.push(" AND (e.date < ");
+        query_builder.push_bind(date.clone());
+        query_builder.push(" OR (e.date = ");
+        query_builder.push_bind(date.clone());
+        query_builder.push(" AND e.id < ");
+        query_builder.push_bind(id);
+        query_builder.push("))");
+    }
+    query_builder.push(" ORDER BY e.date DESC, e.id DESC LIMIT ");
+    query_builder.push_bind(TIMELINE_PAGE_SIZE);
+    let emails: Vec<Email> = query_builder.build_query_as().fetch_all(&*pool).await.map_err(|e| e.to_string())?;
+
+    let next_cursor = if emails.len() as i64 == TIMELINE_PAGE_SIZE {
+        emails.last().map(|e| format!("{}|{}", e.date, e.id))
+    } else {
+        None
+    };
+    if emails.is_empty() {
+        return Ok(SenderTimeline { items: Vec::new(), next_cursor });
+    }
+
+    // The page's attachments and links in one query each rather than two per email
+    let mut attachments_by_email: HashMap<i64, Vec<Attachment>> = HashMap::new();
+    let with_attachments: Vec<i64> = emails.iter().filter(|e| e.has_attachments).map(|e| e.id).collect();
+    if !with_attachments.is_empty() {
+        let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> = sqlx::QueryBuilder::new(
+            "SELECT id, email_id, draft_id, filename, mime_type, size, file_hash, is_inline FROM attachments WHERE NOT is_inline AND email_id IN ("
+        );
+        let mut separated = query_builder.separated(", ");
+        for id in &with_attachments {
+            separated.push_bind(*id);
+        }
+        query_builder.push(") ORDER BY id");
+        let attachments: Vec<Attachment> = query_builder.build_query_as().fetch_all(&*pool).await.map_err(|e| e.to_string())?;
+        for attachment in attachments {
+            if let Some(email_id) = attachment.email_id {
+                attachments_by_email.entry(email_id).or_default().push(attachment);
+            }
+        }
+    }
+
+    // Links are saved as the emails are indexed, in the order they appear
+    let mut query_builder: sqlx::QueryBuilder<sqlx::Sqlite> =
+        sqlx::QueryBuilder::new("SELECT email_id, url FROM links WHERE email_id IN (");
+    let mut separated = query_builder.separated(", ");
+    for email in &emails {
+        separated.push_bind(email.id);
+    }
+    query_builder.push(") ORDER BY id");
+    let mut links_by_email: HashMap<i64, Vec<String>> = HashMap::new();
+    let links: Vec<(i64, String)> = query_builder.build_query_as().fetch_all(&*pool).await.map_err(|e| e.to_string())?;
+    for (email_id, url) in links {
+        links_by_email.entry(email_id).or_default().push(url);
+    }
+
+    let mut items = Vec::new();
+    for email in emails {
+        let email_id = email.id;
+        let date = email.date.clone();
+        items.push(TimelineItem::Email(email));
+
+        for attachment in attachments_by_email.remove(&email_id).unwrap_or_default() {
+            items.push(TimelineItem::Attachment { date: date.clone(), attachment });
+        }
+        for url in links_by_email.remove(&email_id).unwrap_or_default() {
+            items.push(TimelineItem::Link { email_id, date: date.clone(), url });
+        }
+    }
 
-    Ok(emails)
+    Ok(SenderTimeline { items, next_cursor })
+}
+
+/// Cursor is "<date>|<email id>" of the last email on the previous page.
+fn parse_timeline_cursor(cursor: &str) -> Result<(String, i64), String> {
+    cursor
+        .rsplit_once('|')
+        .filter(|(date, _)| !date.is_empty())
+        .and_then(|(date, id)| Some((date.to_string(), id.parse::<i64>().ok()?)))
+        .ok_or_else(|| format!("Invalid timeline cursor: {}", cursor))
 }
 
 #[tauri::command]
@@ -265,6 +513,12 @@ pub async fn get_sender_info<R: tauri::Runtime>(
         .await
         .map_err(|e| e.to_string())?;
 
+    // Privacy switch: never reach out to external providers, just return what we have
+    if !get_enrichment_policy(&pool).await.enabled {
+        log::info!("Enrichment disabled, returning stored sender info for {}", address);
+        return Ok(sender);
+    }
+
     if let Some(s) = sender {
         // If we have an avatar and it's not super old, return it
         // Otherwise, if avatar is missing or it's been more than 30 days, re-enrich
@@ -296,6 +550,11 @@ pub async fn regenerate_sender_info<R: tauri::Runtime>(
     address: String,
 ) -> Result<Sender, String> {
     log::info!("regenerate_sender_info called for {}", address);
+    let pool = app_handle.state::<SqlitePool>();
+    if !get_enrichment_policy(&pool).await.enabled {
+        return Err(i18n::t("error.enrichment_disabled", &[]));
+    }
+
     // Passing true for manual_trigger forces re-enrichment
     let enriched = enrich_sender_internal(&app_handle, address, true).await?;
     Ok(enriched)
@@ -344,6 +603,87 @@ pub async fn update_sender_info<R: tauri::Runtime>(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn refresh_sender_enrichment<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    address: String,
+    force: bool,
+) -> Result<Sender, String> {
+    log::info!("refresh_sender_enrichment called for {} (force={})", address, force);
+    let pool = app_handle.state::<SqlitePool>();
+    if !get_enrichment_policy(&pool).await.enabled {
+        return Err(i18n::t("error.enrichment_disabled", &[]));
+    }
+
+    if force {
+        // Drop previously harvested profile fields, otherwise the COALESCE in the
+        // upsert and the AI sparsity check would keep the old job title around
+        sqlx::query(
+            "UPDATE senders SET
+                job_title = NULL,
+                company = NULL,
+                bio = NULL,
+                location = NULL,
+                ai_last_enriched_at = NULL,
+                last_enriched_at = NULL,
+                updated_at = CURRENT_TIMESTAMP
+             WHERE address = ?"
+        )
+        .bind(&address)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    enrich_sender_internal(&app_handle, address, true).await
+}
+
+#[tauri::command]
+pub async fn clear_enrichment_data<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+) -> Result<(), String> {
+    log::info!("Clearing all harvested enrichment data");
+    let mut tx = app_handle.state::<WritePool>().begin().await?;
+
+    // Keep the sender rows themselves (contacts, names seen in headers), only wipe profile data
+    sqlx::query(
+        "UPDATE senders SET
+            avatar_url = NULL,
+            job_title = NULL,
+            company = NULL,
+            bio = NULL,
+            location = NULL,
+            github_handle = NULL,
+            linkedin_handle = NULL,
+            twitter_handle = NULL,
+            website_url = NULL,
+            is_verified = 0,
+            is_personal_email = NULL,
+            is_automated_mailer = NULL,
+            ai_last_enriched_at = NULL,
+            last_enriched_at = NULL,
+            updated_at = CURRENT_TIMESTAMP"
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM domains")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    sqlx::query("DELETE FROM enrichment_misses")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let _ = app_handle.emit("enrichment-cleared", ());
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_domain_info<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
@@ -368,6 +708,12 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
     log::info!("Starting enrichment for {} (manual={})", address, manual_trigger);
     let pool = app_handle.state::<SqlitePool>();
 
+    // In offline mode every network-backed provider is a no-op; we only use local data
+    let offline = get_enrichment_policy(&pool).await.offline;
+    if offline {
+        log::info!("Offline mode active, skipping external providers for {}", address);
+    }
+
     let domain_name = extract_domain(&address);
     let mut avatar_url = None;
     let mut company = None;
@@ -413,6 +759,9 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
                     crate::email_backend::accounts::manager::Account::ImapSmtp(i) => {
                         own_info.insert(i.email.to_lowercase(), (i.name.clone(), None));
                     }
+                    crate::email_backend::accounts::manager::Account::OAuth(o) => {
+                        own_info.insert(o.email.to_lowercase(), (o.name.clone(), o.picture.clone()));
+                    }
                 }
             }
         }
@@ -440,11 +789,14 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
 
     // 1. People API Enrichment (Google, Microsoft, etc.)
     // We try this first because it's highly accurate for people we actually interact with.
-    if !is_system_address(&address) && !google_accounts.is_empty() {
+    if !offline && !is_system_address(&address) && !google_accounts.is_empty()
+        && throttle::may_request(&pool, throttle::GOOGLE_PEOPLE, &address, manual_trigger).await
+    {
         let google_provider = GooglePeopleProvider { accounts: google_accounts.clone() };
         match google_provider.enrich(&address).await {
             Ok(Some(people_data)) => {
                 log::info!("Enriched {} using Google People API", address);
+                throttle::record_success(&pool, throttle::GOOGLE_PEOPLE).await;
                 if let Some(n) = people_data.name { name = Some(n); }
                 if let Some(av) = people_data.avatar_url { avatar_url = Some(av); }
                 if let Some(jt) = people_data.job_title { job_title = Some(jt); }
@@ -455,15 +807,17 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
             }
             Ok(None) => {
                 log::info!("Google People API returned no results for {}", address);
+                throttle::record_miss(&pool, throttle::GOOGLE_PEOPLE, &address).await;
             }
             Err(e) => {
                 log::error!("Google People API enrichment failed for {}: {}", address, e);
+                throttle::record_failure(&pool, throttle::GOOGLE_PEOPLE).await;
             }
         }
     }
 
     // 1b. Google-specific profile photo fallback for Gmail addresses
-    if avatar_url.is_none() {
+    if avatar_url.is_none() && !offline {
         if let Some(d) = &domain_name {
             if (d == "gmail.com" || d == "googlemail.com") && !google_accounts.is_empty() {
                 log::info!("Using Google People API photo fallback for {}", address);
@@ -473,12 +827,38 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
     }
 
     // 2. Fetch Gravatar Profile for advanced metadata
-    let client = reqwest::Client::builder()
+    let client = proxy::http_client_builder()
         .user_agent("Dueam/0.1.0")
         .build()
         .map_err(|e| e.to_string())?;
 
-    if let Ok(resp) = client.get(get_gravatar_profile_url(&address)).send().await {
+    // Most addresses have no Gravatar profile, the 404 is remembered rather than asked again each cycle
+    let gravatar_resp = if offline || !throttle::may_request(&pool, throttle::GRAVATAR, &address, manual_trigger).await {
+        None
+    } else {
+        match client.get(get_gravatar_profile_url(&address)).send().await {
+            Ok(resp) if resp.status() == reqwest::StatusCode::NOT_FOUND => {
+                throttle::record_success(&pool, throttle::GRAVATAR).await;
+                throttle::record_miss(&pool, throttle::GRAVATAR, &address).await;
+                None
+            }
+            Ok(resp) if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS || resp.status().is_server_error() => {
+                throttle::record_failure(&pool, throttle::GRAVATAR).await;
+                None
+            }
+            Ok(resp) => {
+                throttle::record_success(&pool, throttle::GRAVATAR).await;
+                Some(resp)
+            }
+            Err(e) => {
+                log::error!("Gravatar lookup failed for {}: {}", address, e);
+                throttle::record_failure(&pool, throttle::GRAVATAR).await;
+                None
+            }
+        }
+    };
+
+    if let Some(resp) = gravatar_resp {
         if resp.status().is_success() {
             if let Ok(profile) = resp.json::<GravatarProfile>().await {
                 if let Some(entry) = profile.entry.first() {
@@ -556,14 +936,9 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
     }
 
     // 4. AI Enrichment (optional and sparing)
-    let settings: Vec<(String, String)> = sqlx::query_as("SELECT key, value FROM settings WHERE key IN ('aiEnabled', 'aiSenderEnrichmentEnabled')")
-        .fetch_all(&*pool)
-        .await
-        .unwrap_or_default();
-
-    let settings_map: HashMap<String, String> = settings.into_iter().collect();
-    let ai_enabled = settings_map.get("aiEnabled").map(|v| v.as_str()).unwrap_or("false") == "true";
-    let ai_sender_enrichment_enabled = settings_map.get("aiSenderEnrichmentEnabled").map(|v| v.as_str()).unwrap_or("true") == "true";
+    let settings = Settings::load(&pool).await.unwrap_or_default();
+    let ai_enabled = settings.ai_enabled;
+    let ai_sender_enrichment_enabled = settings.ai_sender_enrichment_enabled;
 
     // Check if we already have AI data to avoid redundant calls
     let existing_ai_data: Option<(Option<String>, Option<chrono::DateTime<Utc>>)> = sqlx::query_as(
@@ -576,7 +951,7 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
 
     log::info!("AI enrichment status - global: {}, sender: {}", ai_enabled, ai_sender_enrichment_enabled);
 
-    if ai_enabled && ai_sender_enrichment_enabled {
+    if ai_enabled && ai_sender_enrichment_enabled && !offline {
         let (existing_job, last_ai_run) = existing_ai_data.unwrap_or((None, None));
 
         // Sparsity logic:
@@ -694,7 +1069,8 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
         account_email: None,
         last_synced_at: None,
         ai_last_enriched_at,
-        last_enriched_at: Some(now),
+        // Offline results are partial, leave them stale so we retry once back online
+        last_enriched_at: if offline { None } else { Some(now) },
         created_at: Some(now),
         updated_at: Some(now),
     };
@@ -753,6 +1129,11 @@ async fn enrich_sender_internal<R: tauri::Runtime>(
 pub async fn proactive_enrichment<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
     let pool = app_handle.state::<SqlitePool>();
 
+    let policy = get_enrichment_policy(&pool).await;
+    if !policy.enabled || policy.offline {
+        return Ok(());
+    }
+
     // Find unique senders from emails that are NOT in senders table OR have no avatar OR use the old Clearbit provider
     // AND have at least one email newer than account_creation - 14 days
     let addresses: Vec<String> = sqlx::query_scalar(
@@ -785,3 +1166,87 @@ pub async fn proactive_enrichment<R: tauri::Runtime>(app_handle: &tauri::AppHand
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, insert_test_folder, setup_test_app, setup_test_db};
+
+    async fn insert_email(pool: &SqlitePool, folder_id: i64, sender: &str, to: &str, date: &str) -> i64 {
+        let (id,): (i64,) = sqlx::query_as(
+            "INSERT INTO emails (account_id, folder_id, remote_id, message_id, subject, sender_address, recipient_to, date, flags)
+             SELECT account_id, id, ?2 || ?4, ?2 || ?4, 'Hello', ?2, ?3, ?4, '[]' FROM folders WHERE id = ?1 RETURNING id"
+        )
+        .bind(folder_id)
+        .bind(sender)
+        .bind(to)
+        .bind(date)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_timeline_matches_whole_addresses() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let mut folders = Vec::new();
+        for (path, role) in [("INBOX", "inbox"), ("Sent", "sent")] {
+            let id = insert_test_folder(&pool, account_id, path, Some(role)).await;
+            folders.push(id);
+        }
+        let from_bob = insert_email(&pool, folders[0], "bob@example.com", "me@example.com", "2026-01-03T10:00:00Z").await;
+        let to_bob = insert_email(&pool, folders[1], "me@example.com", "Bob <Bob@example.com>, ann@example.com", "2026-01-02T10:00:00Z").await;
+        insert_email(&pool, folders[0], "rebob@example.com", "me@example.com", "2026-01-01T10:00:00Z").await;
+        insert_email(&pool, folders[1], "me@example.com", "rebob@example.com", "2026-01-01T10:00:00Z").await;
+        sqlx::query("INSERT INTO links (email_id, url, domain) VALUES (?, 'https://example.com/plan', 'example.com')")
+            .bind(from_bob)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (app, _dir) = setup_test_app(pool).await;
+
+        let timeline = get_sender_timeline(app.handle().clone(), "bob@example.com".to_string(), None).await.unwrap();
+        let ids: Vec<i64> = timeline
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                TimelineItem::Email(email) => Some(email.id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ids, vec![from_bob, to_bob]);
+        assert!(timeline.next_cursor.is_none());
+        assert!(timeline.items.iter().any(|item| matches!(item, TimelineItem::Link { email_id, url, .. } if *email_id == from_bob && url == "https://example.com/plan")));
+
+        let after_first = format!("2026-01-03T10:00:00Z|{}", from_bob);
+        let page = get_sender_timeline(app.handle().clone(), "bob@example.com".to_string(), Some(after_first)).await.unwrap();
+        assert_eq!(page.items.len(), 1);
+
+        assert!(get_sender_timeline(app.handle().clone(), "bob@example.com".to_string(), Some("garbage".to_string())).await.is_err());
+        assert!(get_sender_timeline(app.handle().clone(), "bob@example.com".to_string(), Some("2026-01-03|x".to_string())).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_merged_sender_counts_as_one() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        let inbox = insert_test_folder(&pool, account_id, "INBOX", Some("inbox")).await;
+        let now = Utc::now();
+        for (sender, hours) in [("ann@work.example.com", 1), ("Ann@home.example.com", 2), ("ann@home.example.com", 3), ("bob@example.com", 4)] {
+            let date = (now - chrono::Duration::hours(hours)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+            insert_email(&pool, inbox, sender, "me@example.com", &date).await;
+        }
+        let (app, _dir) = setup_test_app(pool).await;
+
+        merge_senders(app.handle().clone(), "ann@work.example.com".to_string(), vec!["ann@home.example.com".to_string()]).await.unwrap();
+
+        let emails = get_emails_by_sender(app.handle().clone(), "Ann@home.example.com".to_string(), 10, None, None).await.unwrap();
+        assert_eq!(emails.len(), 3);
+
+        let analytics = crate::email_backend::emails::analytics::get_mailbox_analytics(app.handle().clone(), "week".to_string(), None).await.unwrap();
+        let top: Vec<(String, i64)> = analytics.top_senders.into_iter().map(|s| (s.address, s.count)).collect();
+        assert_eq!(top, vec![("ann@work.example.com".to_string(), 3), ("bob@example.com".to_string(), 1)]);
+    }
+}
@@ -2,5 +2,6 @@ pub mod commands;
 pub mod types;
 pub mod providers;
 pub mod people;
+pub mod throttle;
 
 pub use types::*;
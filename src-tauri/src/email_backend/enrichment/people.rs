@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use async_trait::async_trait;
-use reqwest::Client;
+use crate::utils::proxy;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct PeopleEnrichmentData {
@@ -26,7 +26,7 @@ pub async fn get_google_avatar_url(
     address: &str,
     tokens: &[(String, String)],
 ) -> Option<String> {
-    let client = Client::new();
+    let client = proxy::http_client().ok()?;
     for (account_email, token) in tokens {
         log::debug!("Attempting get_google_avatar_url for {} using account {}", address, account_email);
         // We try to search for the contact to get their photo
@@ -137,7 +137,7 @@ impl PeopleProvider for GooglePeopleProvider {
     }
 
     async fn enrich(&self, address: &str) -> Result<Option<PeopleEnrichmentData>, String> {
-        let client = Client::new();
+        let client = proxy::http_client()?;
         log::info!("Attempting Google People API enrichment for {} with {} accounts", address, self.accounts.len());
 
         for (account_email, token) in &self.accounts {
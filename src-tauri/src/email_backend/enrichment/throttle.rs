@@ -0,0 +1,130 @@
+use sqlx::SqlitePool;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+pub const GRAVATAR: &str = "gravatar";
+pub const GOOGLE_PEOPLE: &str = "google_people";
+
+/// Gap between two outbound enrichment requests, across every provider and caller.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+/// A lookup that found nothing isn't repeated for this long, profiles rarely appear overnight.
+const MISS_TTL_DAYS: i64 = 30;
+const BASE_COOLDOWN_SECS: i64 = 300;
+const MAX_COOLDOWN_SECS: i64 = 86_400;
+
+/// Waits for the global rate limit. Callers queue on the lock, so requests go out one per interval.
+async fn wait_turn() {
+    static NEXT_REQUEST: OnceLock<Mutex<Instant>> = OnceLock::new();
+    let mut next = NEXT_REQUEST.get_or_init(|| Mutex::new(Instant::now())).lock().await;
+    let now = Instant::now();
+    if *next > now {
+        tokio::time::sleep(*next - now).await;
+    }
+    *next = Instant::now() + MIN_REQUEST_INTERVAL;
+}
+
+/// Whether `key` may be looked up at `provider` now: the provider isn't cooling down and,
+/// unless `retry_misses`, the key wasn't missed lately. Waits for the rate limit when it may.
+pub async fn may_request(pool: &SqlitePool, provider: &str, key: &str, retry_misses: bool) -> bool {
+    let cooling_down: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM enrichment_cooldowns WHERE provider = ? AND until > CURRENT_TIMESTAMP)")
+        .bind(provider)
+        .fetch_one(pool)
+        .await
+        .unwrap_or(false);
+    if cooling_down {
+        return false;
+    }
+
+    if !retry_misses {
+        let missed: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM enrichment_misses WHERE provider = ? AND key = ? AND checked_at > datetime('now', ?))")
+            .bind(provider)
+            .bind(key)
+            .bind(format!("-{} days", MISS_TTL_DAYS))
+            .fetch_one(pool)
+            .await
+            .unwrap_or(false);
+        if missed {
+            return false;
+        }
+    }
+
+    wait_turn().await;
+    true
+}
+
+/// Remembers that `provider` has nothing for `key`.
+pub async fn record_miss(pool: &SqlitePool, provider: &str, key: &str) {
+    let _ = sqlx::query(
+        "INSERT INTO enrichment_misses (provider, key) VALUES (?, ?)
+         ON CONFLICT(provider, key) DO UPDATE SET checked_at = CURRENT_TIMESTAMP"
+    )
+    .bind(provider)
+    .bind(key)
+    .execute(pool)
+    .await
+    .map_err(|e| log::error!("Failed to record enrichment miss: {}", e));
+}
+
+/// Ends the provider's cooldown once it answers again.
+pub async fn record_success(pool: &SqlitePool, provider: &str) {
+    let _ = sqlx::query("DELETE FROM enrichment_cooldowns WHERE provider = ?")
+        .bind(provider)
+        .execute(pool)
+        .await;
+}
+
+fn cooldown_secs(failures: i64) -> i64 {
+    (BASE_COOLDOWN_SECS << failures.clamp(0, 16)).min(MAX_COOLDOWN_SECS)
+}
+
+/// Leaves `provider` alone for a while after an error or a rate limit response, longer
+/// with each failure in a row.
+pub async fn record_failure(pool: &SqlitePool, provider: &str) {
+    let failures: i64 = sqlx::query_scalar("SELECT failures FROM enrichment_cooldowns WHERE provider = ?")
+        .bind(provider)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(0);
+    let secs = cooldown_secs(failures);
+    log::warn!("Enrichment provider {} failed, pausing it for {}s", provider, secs);
+
+    let _ = sqlx::query(
+        "INSERT INTO enrichment_cooldowns (provider, failures, until) VALUES (?, 1, datetime('now', ?))
+         ON CONFLICT(provider) DO UPDATE SET failures = failures + 1, until = excluded.until"
+    )
+    .bind(provider)
+    .bind(format!("+{} seconds", secs))
+    .execute(pool)
+    .await
+    .map_err(|e| log::error!("Failed to record enrichment cooldown: {}", e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_db;
+
+    #[tokio::test]
+    async fn test_misses_and_cooldowns_skip_requests() {
+        let pool = setup_test_db().await;
+        assert!(may_request(&pool, GRAVATAR, "a@example.com", false).await);
+
+        record_miss(&pool, GRAVATAR, "a@example.com").await;
+        assert!(!may_request(&pool, GRAVATAR, "a@example.com", false).await);
+        assert!(may_request(&pool, GRAVATAR, "a@example.com", true).await);
+        assert!(may_request(&pool, GRAVATAR, "b@example.com", false).await);
+
+        record_failure(&pool, GRAVATAR).await;
+        assert!(!may_request(&pool, GRAVATAR, "b@example.com", false).await);
+        assert!(may_request(&pool, GOOGLE_PEOPLE, "b@example.com", false).await);
+        record_success(&pool, GRAVATAR).await;
+        assert!(may_request(&pool, GRAVATAR, "b@example.com", false).await);
+
+        assert_eq!(cooldown_secs(0), 300);
+        assert_eq!(cooldown_secs(2), 1200);
+        assert_eq!(cooldown_secs(40), MAX_COOLDOWN_SECS);
+    }
+}
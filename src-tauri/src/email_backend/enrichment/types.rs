@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use crate::email_backend::emails::commands::{Email, Attachment};
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
 pub struct Sender {
@@ -39,3 +40,54 @@ pub struct Domain {
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum TimelineItem {
+    Email(Email),
+    Attachment {
+        date: String,
+        attachment: Attachment,
+    },
+    Link {
+        email_id: i64,
+        date: String,
+        url: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SenderTimeline {
+    pub items: Vec<TimelineItem>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SharedLink {
+    pub url: String,
+    pub domain: String,
+    pub title: Option<String>,
+    pub email_id: i64,
+    pub subject: Option<String>,
+    pub date: String,
+    /// Shared by us rather than by the contact
+    pub from_me: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SharedFile {
+    pub attachment_id: i64,
+    pub filename: Option<String>,
+    pub mime_type: Option<String>,
+    pub size: i64,
+    pub email_id: i64,
+    pub subject: Option<String>,
+    pub date: String,
+    pub from_me: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SharedItems {
+    pub links: Vec<SharedLink>,
+    pub files: Vec<SharedFile>,
+}
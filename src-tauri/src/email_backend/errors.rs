@@ -0,0 +1,56 @@
+use serde::Serialize;
+use tauri::Emitter;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Sync,
+    Network,
+    Auth,
+    Send,
+    Storage,
+    Notification,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorSeverity {
+    /// Local state is fine but something in the background didn't happen
+    Warning,
+    /// The user's action did not (fully) take effect
+    Error,
+}
+
+/// Payload of the `backend-error` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendError {
+    pub category: ErrorCategory,
+    pub severity: ErrorSeverity,
+    pub retryable: bool,
+    pub message: String,
+}
+
+impl BackendError {
+    pub fn new(category: ErrorCategory, severity: ErrorSeverity, message: impl Into<String>) -> Self {
+        Self { category, severity, retryable: false, message: message.into() }
+    }
+
+    pub fn retryable(mut self) -> Self {
+        self.retryable = true;
+        self
+    }
+}
+
+/// Logs the error, emits it as `backend-error` and hands back the message so it can
+/// still be returned from the command, e.g. `.map_err(|e| report_error(&app_handle, ...))?`.
+pub fn report_error<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, error: BackendError) -> String {
+    log::error!("[{:?}] {}", error.category, error.message);
+    let message = error.message.clone();
+    let _ = app_handle.emit("backend-error", error);
+    message
+}
+
+/// Whether a server error is about the credentials, an expired OAuth token or a wrong password.
+pub fn is_auth_error(err: &str) -> bool {
+    err.contains("auth") || err.contains("Unauthorized") || err.contains("token") || err.contains("credentials")
+}
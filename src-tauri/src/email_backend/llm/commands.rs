@@ -1,5 +1,8 @@
 use tauri::command;
 use serde::{Deserialize, Serialize};
+use crate::db::settings::MASKED_SECRET;
+use crate::utils::proxy;
+use crate::utils::security::get_secret;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AIModel {
@@ -13,7 +16,14 @@ struct OpenAIModelsResponse {
 
 #[command]
 pub async fn get_available_models(base_url: String, api_key: String) -> Result<Vec<AIModel>, String> {
-    let client = reqwest::Client::new();
+    // The settings UI only ever sees the masked key, resolve the real one from the keyring
+    let api_key = if api_key == MASKED_SECRET {
+        get_secret("aiApiKey").await?.unwrap_or_default()
+    } else {
+        api_key
+    };
+
+    let client = proxy::http_client()?;
     let url = if base_url.ends_with("/models") {
         base_url
     } else {
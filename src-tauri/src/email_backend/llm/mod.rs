@@ -1,4 +1,5 @@
 pub mod commands;
 pub mod client;
 pub mod enrichment;
+pub mod needs_reply;
 pub mod summarization;
@@ -0,0 +1,81 @@
+use serde_json::{Value, json};
+use log::debug;
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::db::settings::Settings;
+use crate::utils::{i18n, proxy};
+
+/// Asks the AI whether the sender of an email is waiting for an answer from the user.
+pub async fn needs_reply_with_ai<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    email_id: i64,
+    subject: &str,
+    body_text: &str,
+) -> Result<bool, String> {
+    debug!("Asking the AI whether email {} needs a reply", email_id);
+
+    let pool = app_handle.state::<SqlitePool>();
+    let Settings { ai_api_key: api_key, ai_base_url: base_url, ai_model: model, .. } = Settings::load(&pool).await?;
+
+    if api_key.is_empty() || model.is_empty() {
+        return Err(i18n::t("error.ai_not_configured", &[]));
+    }
+
+    let client = proxy::http_client()?;
+    let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
+
+    // The opening of the email is where requests are made, quoted history follows it
+    let mut end = body_text.len().min(2000);
+    while !body_text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let system_prompt = r#"You decide whether an email expects a personal answer from its recipient.
+Answer "yes" when it asks the recipient a question, asks them to do, confirm or decide something, or waits on their input.
+Answer "no" for announcements, receipts, notifications, thank-you notes and anything sent to a crowd.
+Answer with a single word: yes or no."#;
+
+    let body = json!({
+        "model": model,
+        "messages": [
+            {
+                "role": "system",
+                "content": system_prompt
+            },
+            {
+                "role": "user",
+                "content": format!("Subject: {}\n\n{}", subject, &body_text[..end])
+            }
+        ],
+        "temperature": 0.0,
+        "stream": false
+    });
+
+    let resp = client.post(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let err_text = resp.text().await.unwrap_or_default();
+        return Err(format!("AI API error ({}): {}", status, err_text));
+    }
+
+    let response_json: Value = resp.json().await.map_err(|e| format!("Failed to parse response JSON: {}", e))?;
+
+    let answer = response_json["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or_else(|| format!("Unexpected AI response structure: {:?}", response_json))?
+        .trim()
+        .trim_matches(['"', '.'])
+        .to_lowercase();
+
+    match answer.as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("Unexpected AI answer about email {}: {}", email_id, answer)),
+    }
+}
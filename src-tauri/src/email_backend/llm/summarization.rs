@@ -2,6 +2,8 @@ use serde_json::{Value, json};
 use log::{info, debug, warn};
 use sqlx::SqlitePool;
 use tauri::Manager;
+use crate::db::settings::Settings;
+use crate::utils::{i18n, proxy};
 
 pub async fn summarize_email_with_ai<R: tauri::Runtime>(
     app_handle: &tauri::AppHandle<R>,
@@ -63,30 +65,13 @@ pub async fn summarize_email_with_ai<R: tauri::Runtime>(
         }
     }
 
-    let rows: Vec<(String, String)> = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM settings WHERE key IN ('aiApiKey', 'aiBaseUrl', 'aiModel')")
-        .fetch_all(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let mut api_key = String::new();
-    let mut base_url = String::from("https://api.openai.com/v1");
-    let mut model = String::new();
-
-    for (key, value) in rows {
-        let unquoted = serde_json::from_str::<String>(&value).unwrap_or(value);
-        match key.as_str() {
-            "aiApiKey" => api_key = unquoted,
-            "aiBaseUrl" => base_url = unquoted,
-            "aiModel" => model = unquoted,
-            _ => {} // Ignore other keys
-        }
-    }
+    let Settings { ai_api_key: api_key, ai_base_url: base_url, ai_model: model, .. } = Settings::load(&pool).await?;
 
     if api_key.is_empty() || model.is_empty() {
-        return Err("AI API Key or Model not configured".to_string());
+        return Err(i18n::t("error.ai_not_configured", &[]));
     }
 
-    let client = reqwest::Client::new();
+    let client = proxy::http_client()?;
     let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
 
     // Truncate body_text if too long (e.g., to ~4000 chars) to avoid token limits
@@ -2,4 +2,5 @@ pub mod accounts;
 pub mod sync;
 pub mod emails;
 pub mod enrichment;
-pub mod llm;
\ No newline at end of file
+pub mod llm;
+pub mod errors;
\ No newline at end of file
@@ -0,0 +1,174 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Failed,
+    Delayed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Delayed => "delayed",
+        }
+    }
+}
+
+/// What a DSN (RFC 3464) or a classic bounce tells us about a message we sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryReport {
+    pub original_message_id: Option<String>,
+    pub status: DeliveryStatus,
+    pub recipient: Option<String>,
+    pub diagnostic: Option<String>,
+}
+
+const BOUNCE_SENDERS: [&str; 3] = ["mailer-daemon", "postmaster", "mail-daemon"];
+
+const BOUNCE_SUBJECTS: [&str; 6] = [
+    "undeliverable",
+    "undelivered mail",
+    "delivery status notification",
+    "mail delivery failed",
+    "delivery failure",
+    "returned mail",
+];
+
+/// Cheap check on the envelope alone, used to decide whether a message is worth parsing as a report.
+pub fn looks_like_bounce(sender_address: &str, subject: &str) -> bool {
+    let local = sender_address.split('@').next().unwrap_or("").to_lowercase();
+    let subject = subject.to_lowercase();
+    BOUNCE_SENDERS.contains(&local.as_str()) || BOUNCE_SUBJECTS.iter().any(|s| subject.contains(s))
+}
+
+fn header_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let (key, value) = line.split_once(':')?;
+    key.trim().eq_ignore_ascii_case(name).then_some(value.trim())
+}
+
+/// Parses the raw report. The top-level headers are skipped so the `Message-ID` found is the one
+/// of the returned message, not of the bounce itself.
+pub fn parse_report(sender_address: &str, subject: &str, raw: &[u8]) -> Option<DeliveryReport> {
+    let raw = String::from_utf8_lossy(raw);
+    let (headers, body) = raw.split_once("\r\n\r\n").or_else(|| raw.split_once("\n\n"))?;
+
+    let is_report = headers.lines().any(|l| {
+        header_value(l, "Content-Type").map(|v| v.to_lowercase().starts_with("multipart/report")).unwrap_or(false)
+    });
+    if !is_report && !looks_like_bounce(sender_address, subject) {
+        return None;
+    }
+
+    let mut report = DeliveryReport {
+        original_message_id: None,
+        status: DeliveryStatus::Failed,
+        recipient: None,
+        diagnostic: None,
+    };
+    let mut action = None;
+
+    for line in body.lines() {
+        if let Some(v) = header_value(line, "Message-ID") {
+            report.original_message_id.get_or_insert_with(|| v.trim_matches(|c| c == '<' || c == '>').to_string());
+        } else if let Some(v) = header_value(line, "Action") {
+            action.get_or_insert_with(|| v.to_lowercase());
+        } else if let Some(v) = header_value(line, "Final-Recipient").or_else(|| header_value(line, "Original-Recipient")) {
+            // "rfc822; someone@example.com"
+            report.recipient.get_or_insert_with(|| v.rsplit(';').next().unwrap_or(v).trim().to_string());
+        } else if let Some(v) = header_value(line, "Diagnostic-Code") {
+            report.diagnostic.get_or_insert_with(|| v.to_string());
+        }
+    }
+
+    match action.as_deref() {
+        Some("delayed") => report.status = DeliveryStatus::Delayed,
+        // Successful or relayed DSNs aren't failures
+        Some("delivered") | Some("relayed") | Some("expanded") => return None,
+        _ => {}
+    }
+
+    // Without the original Message-ID there is nothing to link the report to
+    report.original_message_id.as_ref()?;
+    Some(report)
+}
+
+/// Payload of the `delivery-status-changed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryStatusChanged {
+    pub email_ids: Vec<i64>,
+    pub status: DeliveryStatus,
+    pub bounce_email_id: i64,
+}
+
+/// Marks our copies of the original message with the report, returning the ids that were updated.
+pub async fn apply_report(pool: &SqlitePool, account_id: i64, bounce_email_id: i64, report: &DeliveryReport) -> Result<Vec<i64>, String> {
+    let message_id = match &report.original_message_id {
+        Some(id) => id,
+        None => return Ok(Vec::new()),
+    };
+    let error = match (&report.recipient, &report.diagnostic) {
+        (Some(r), Some(d)) => Some(format!("{}: {}", r, d)),
+        (Some(r), None) => Some(r.clone()),
+        (None, d) => d.clone(),
+    };
+
+    // Message-IDs are stored as they came from the envelope, with or without brackets.
+    // A later "failed" report must not be downgraded by an earlier "delayed" one.
+    sqlx::query_scalar(
+        "UPDATE emails SET delivery_status = ?, delivery_error = ?, bounce_email_id = ?
+         WHERE account_id = ? AND message_id IN (?, ?) AND COALESCE(delivery_status, '') != 'failed'
+         RETURNING id"
+    )
+    .bind(report.status.as_str())
+    .bind(error)
+    .bind(bounce_email_id)
+    .bind(account_id)
+    .bind(message_id)
+    .bind(format!("<{}>", message_id))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report_links_original_message() {
+        let raw = "From: Mail Delivery Subsystem <mailer-daemon@example.com>\r\n\
+            Message-ID: <bounce-1@example.com>\r\n\
+            Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\r\n\
+            \r\n\
+            --b\r\n\
+            Content-Type: message/delivery-status\r\n\
+            \r\n\
+            Final-Recipient: rfc822; nobody@example.org\r\n\
+            Action: failed\r\n\
+            Status: 5.1.1\r\n\
+            Diagnostic-Code: smtp; 550 5.1.1 User unknown\r\n\
+            \r\n\
+            --b\r\n\
+            Content-Type: text/rfc822-headers\r\n\
+            \r\n\
+            Message-ID: <original-1@example.com>\r\n\
+            Subject: Hello\r\n\
+            --b--\r\n";
+
+        let report = parse_report("mailer-daemon@example.com", "Undelivered Mail Returned to Sender", raw.as_bytes()).unwrap();
+        assert_eq!(report.original_message_id.as_deref(), Some("original-1@example.com"));
+        assert_eq!(report.status, DeliveryStatus::Failed);
+        assert_eq!(report.recipient.as_deref(), Some("nobody@example.org"));
+        assert_eq!(report.diagnostic.as_deref(), Some("smtp; 550 5.1.1 User unknown"));
+    }
+
+    #[test]
+    fn test_parse_report_ignores_regular_mail() {
+        let raw = "From: friend@example.com\r\nMessage-ID: <a@example.com>\r\n\r\nMessage-ID: in the body\r\n";
+        assert!(parse_report("friend@example.com", "Lunch?", raw.as_bytes()).is_none());
+        assert!(looks_like_bounce("MAILER-DAEMON@mx.example.com", "Anything"));
+    }
+}
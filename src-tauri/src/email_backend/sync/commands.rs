@@ -0,0 +1,75 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::email_backend::sync::SyncEngine;
+use crate::email_backend::sync::engine::IdleState;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FolderSyncHealth {
+    pub folder_id: i64,
+    pub path: String,
+    pub role: Option<String>,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+    pub pending_index: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountSyncHealth {
+    pub account_id: i64,
+    pub email: String,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+    pub idle_state: Option<IdleState>,
+    pub folders: Vec<FolderSyncHealth>,
+}
+
+#[tauri::command]
+pub async fn get_sync_health<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+) -> Result<Vec<AccountSyncHealth>, String> {
+    let pool = app_handle.state::<SqlitePool>();
+    let engine = app_handle.state::<SyncEngine<R>>();
+
+    let accounts: Vec<(i64, String, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT id, email, CAST(last_synced_at AS TEXT), last_sync_error FROM accounts ORDER BY id"
+    )
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut health = Vec::new();
+    for (account_id, email, last_synced_at, last_error) in accounts {
+        // Pending index = envelopes we have but whose bodies the worker hasn't fetched yet
+        let folders = sqlx::query_as::<_, FolderSyncHealth>(
+            "SELECT f.id as folder_id, f.path, f.role, CAST(f.last_synced_at AS TEXT) as last_synced_at, f.last_sync_error as last_error,
+                    (SELECT COUNT(*) FROM emails e WHERE e.folder_id = f.id AND e.body_text IS NULL) as pending_index
+             FROM folders f
+             WHERE f.account_id = ?
+             ORDER BY f.path"
+        )
+        .bind(account_id)
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        health.push(AccountSyncHealth {
+            account_id,
+            email,
+            last_synced_at,
+            last_error,
+            idle_state: engine.get_idle_state(account_id).await,
+            folders,
+        });
+    }
+
+    Ok(health)
+}
+
+/// For the frontend to report the app coming back to the foreground (`visibilitychange`),
+/// mobile webviews don't always get a window focus event.
+#[tauri::command]
+pub async fn sync_on_foreground<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<(), String> {
+    app_handle.state::<SyncEngine<R>>().on_foreground();
+    Ok(())
+}
@@ -1,60 +1,246 @@
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::time::{Duration, Instant};
 use std::sync::Arc;
 use std::num::NonZeroU32;
-use tauri::{Manager, Emitter};
+use tauri::{Manager, Emitter, Listener};
 use crate::email_backend::accounts::manager::{AccountManager, Account};
 use tokio::time::sleep;
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::{oneshot, Mutex, Notify, OwnedRwLockReadGuard, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 use log::{info, error};
 use email::imap::{ImapContext, ImapContextBuilder, ImapClient};
+use email::smtp::{SmtpContextBuilder, SmtpContextSync};
 use email::backend::{Backend, context::BackendContextBuilder};
-use email::folder::list::ListFolders;
-use email::envelope::Envelopes;
+use email::envelope::{Envelope, Envelopes};
+use imap_client::imap_next::imap_types::core::Vec1;
+use imap_client::imap_next::imap_types::fetch::MessageDataItem;
+use imap_client::imap_next::imap_types::search::SearchKey;
+use crate::email_backend::emails::body_structure::{self, MessageLayout};
+use crate::email_backend::emails::commands::{fetch_section, record_attachments};
+use crate::email_backend::emails::events::EmailEvent;
 use imap_client::tasks::tasks::select::SelectDataUnvalidated;
 use sqlx::SqlitePool;
+use crate::db::settings::{Settings, SettingChanged};
+use crate::db::writer::WritePool;
+use serde::Serialize;
+use crate::email_backend::errors::{is_auth_error, report_error, BackendError, ErrorCategory, ErrorSeverity};
+use crate::email_backend::sync::{bounce, monitor, throttle};
+use crate::email_backend::sync::worker::snippet;
+use crate::email_backend::sync::schedule::{SyncMode, FOREGROUND_SYNC_FINISHED, MIN_FOREGROUND_SYNC_SECS};
+use crate::email_backend::emails::{charset, confidential, domain_policies, screener, spam_signals};
+use crate::email_backend::emails::bulk::{emails_by_id, run_bulk, BulkAction};
+use crate::utils::i18n;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleState {
+    Connecting,
+    Syncing,
+    Idling,
+    Reconnecting,
+    Stopped,
+}
 
 pub struct SyncEngine<R: tauri::Runtime = tauri::Wry> {
     app_handle: tauri::AppHandle<R>,
     idle_senders: Arc<Mutex<HashMap<i64, oneshot::Sender<()>>>>,
+    idle_states: Arc<Mutex<HashMap<i64, IdleState>>>,
     contexts: Arc<Mutex<HashMap<i64, ImapContext>>>,
+    smtp_contexts: Arc<Mutex<HashMap<i64, PooledSmtp>>>,
+    last_foreground_sync: Arc<Mutex<Option<Instant>>>,
+    /// Read-held by every sync and background batch, `shutdown` takes it to wait for them
+    work: Arc<RwLock<()>>,
+    shutting_down: Arc<AtomicBool>,
+    notified: Arc<Mutex<NotifiedMessages>>,
+    /// Emails the UI expects to be opened next, their bodies are fetched by the indexer
+    prefetch: Arc<Mutex<VecDeque<i64>>>,
+}
+
+/// Messages notified about lately. IDLE and the periodic sync can both save the same new
+/// message, and Gmail files it in more than one folder, but it should only alert once.
+#[derive(Default)]
+struct NotifiedMessages {
+    keys: HashSet<(i64, String)>,
+    order: VecDeque<(i64, String)>,
+}
+
+/// Old entries are dropped past this, by then the message is in the database and no longer new.
+const NOTIFIED_CAPACITY: usize = 1000;
+
+impl NotifiedMessages {
+    /// `false` when the message was already notified about.
+    fn insert(&mut self, key: (i64, String)) -> bool {
+        if !self.keys.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > NOTIFIED_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// An SMTP session kept signed in between sends.
+struct PooledSmtp {
+    context: SmtpContextSync,
+    last_used: Instant,
+}
+
+/// Idle SMTP sessions are dropped before this, servers may hang up after five minutes (RFC 5321).
+const SMTP_IDLE_TIMEOUT_SECS: u64 = 240;
+
+/// Floor of the `imapTimeoutSecs` setting, a large fetch on a slow link needs a moment.
+/// Tests go lower so a hung server doesn't keep them waiting.
+const MIN_IMAP_TIMEOUT_SECS: u64 = if cfg!(test) { 1 } else { 10 };
+
+/// The columns a sync may rewrite of an email it saved before.
+#[derive(sqlx::FromRow)]
+struct StoredEnvelope {
+    id: i64,
+    flags: String,
+    has_attachments: bool,
+    has_recipient: bool,
+    has_size: bool,
+}
+
+impl StoredEnvelope {
+    /// Whether saving the envelope again would leave the row as it is. Flags are compared as a
+    /// set, marking mail read here may have stored them in another order.
+    fn unchanged(&self, flags: &[String], has_attachments: bool, size: Option<i64>) -> bool {
+        let mut stored: Vec<String> = serde_json::from_str(&self.flags).unwrap_or_default();
+        let mut incoming = flags.to_vec();
+        stored.sort();
+        incoming.sort();
+        stored == incoming && self.has_attachments == has_attachments && self.has_recipient && (self.has_size || size.is_none())
+    }
 }
 
+/// How long quitting waits for running syncs before closing anyway.
+const SHUTDOWN_TIMEOUT_SECS: u64 = 10;
+
+/// Bytes of the text part fetched for the preview of new mail, enough for a 200 character snippet.
+const PREVIEW_FETCH_BYTES: u32 = 2048;
+/// Previews fetched per batch of envelopes, one round trip each. The indexer does the rest.
+const MAX_PREVIEWS: usize = 50;
+
 impl<R: tauri::Runtime> Clone for SyncEngine<R> {
     fn clone(&self) -> Self {
         Self {
             app_handle: self.app_handle.clone(),
             idle_senders: self.idle_senders.clone(),
+            idle_states: self.idle_states.clone(),
             contexts: self.contexts.clone(),
+            smtp_contexts: self.smtp_contexts.clone(),
+            last_foreground_sync: self.last_foreground_sync.clone(),
+            work: self.work.clone(),
+            shutting_down: self.shutting_down.clone(),
+            notified: self.notified.clone(),
+            prefetch: self.prefetch.clone(),
         }
     }
 }
 
-const SYNC_BATCH_SIZE: u32 = 100;
-const MAX_SYNC_MESSAGES_PER_FOLDER: u32 = 500;
+/// Payload of the `backfill-progress` event, kept apart from `emails-updated` so the UI can
+/// show initial sync progress without treating old mail as new.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillProgress {
+    pub account_id: i64,
+    pub folder_id: i64,
+    pub folder: String,
+    pub fetched: u32,
+    pub total: u32,
+    pub done: bool,
+}
 
 use tauri_plugin_notification::NotificationExt;
 
+/// Reply and forward prefixes across mail clients and languages, lowercase.
+const SUBJECT_PREFIXES: &[&str] = &[
+    "re", "fw", "fwd",
+    "aw", "wg",              // German
+    "sv", "vs", "vl",        // Scandinavian, Finnish
+    "tr", "réf", "ref",      // French
+    "r", "i", "rif",         // Italian
+    "rv", "res", "enc",      // Spanish, Portuguese
+    "antw", "doorst",        // Dutch
+    "odp", "pd",             // Polish
+    "ynt", "ile",            // Turkish
+    "回复", "答复", "转发", "回覆", "轉寄",
+];
+
+/// `s` without one leading prefix such as `re:`, `AW :`, `Re[2]:` or `fwd^3:`.
+fn strip_subject_prefix(s: &str) -> Option<&str> {
+    let colon = s.find([':', '：'])?;
+    let word = s[..colon].trim_end();
+    let base = word.split(['[', '(', '^']).next()?;
+    if !SUBJECT_PREFIXES.contains(&base) {
+        return None;
+    }
+
+    // Some clients count replies: re[2], re(2), re^2
+    let counter = &word[base.len()..];
+    let digits = |d: &str| !d.is_empty() && d.chars().all(|c| c.is_ascii_digit());
+    let counted = match counter.chars().next() {
+        None => true,
+        Some('[') => counter.ends_with(']') && digits(&counter[1..counter.len() - 1]),
+        Some('(') => counter.ends_with(')') && digits(&counter[1..counter.len() - 1]),
+        Some('^') => digits(&counter[1..]),
+        Some(_) => false,
+    };
+    if !counted {
+        return None;
+    }
+
+    let rest = &s[colon..];
+    Some(rest[rest.chars().next()?.len_utf8()..].trim_start())
+}
+
+/// Envelopes from raw fetch items, with the layout their BODYSTRUCTURE describes by UID.
+fn envelopes_with_layouts(fetches: HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>) -> (Envelopes, HashMap<String, MessageLayout>) {
+    let mut layouts = HashMap::new();
+    let envelopes = fetches
+        .into_values()
+        .map(|items| {
+            let envelope = Envelope::from_imap_data_items(items.as_ref());
+            let layout = items.as_ref().iter().find_map(|item| match item {
+                MessageDataItem::BodyStructure(body) => Some(body_structure::layout(body)),
+                _ => None,
+            });
+            let size = items.as_ref().iter().find_map(|item| match item {
+                MessageDataItem::Rfc822Size(size) => Some(*size as i64),
+                _ => None,
+            });
+            if let Some(mut layout) = layout {
+                layout.size = size;
+                layouts.insert(envelope.id.clone(), layout);
+            }
+            envelope
+        })
+        .collect();
+    (envelopes, layouts)
+}
+
 fn normalize_subject(subject: &str) -> String {
     let mut s = subject.trim().to_lowercase();
 
     loop {
-        let prev = s.clone();
-
-        // Strip common email prefixes
-        if s.starts_with("re:") {
-            s = s[3..].trim().to_string();
-        } else if s.starts_with("fwd:") {
-            s = s[4..].trim().to_string();
-        } else if s.starts_with("fw:") {
-            s = s[3..].trim().to_string();
-        } else {
-            break;
+        if let Some(rest) = strip_subject_prefix(&s) {
+            s = rest.to_string();
+            continue;
         }
 
-        if s == prev {
-            break;
+        // Forwards are sometimes wrapped whole: "[Fwd: Subject]"
+        if let Some(inner) = s.strip_prefix('[').and_then(|i| i.strip_suffix(']')) {
+            if let Some(rest) = strip_subject_prefix(inner.trim()) {
+                s = rest.trim().to_string();
+                continue;
+            }
         }
+        break;
     }
     s
 }
@@ -64,10 +250,65 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         Self {
             app_handle,
             idle_senders: Arc::new(Mutex::new(HashMap::new())),
+            idle_states: Arc::new(Mutex::new(HashMap::new())),
             contexts: Arc::new(Mutex::new(HashMap::new())),
+            smtp_contexts: Arc::new(Mutex::new(HashMap::new())),
+            last_foreground_sync: Arc::new(Mutex::new(None)),
+            work: Arc::new(RwLock::new(())),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            notified: Arc::new(Mutex::new(NotifiedMessages::default())),
+            prefetch: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 
+    /// Replaces the prefetch queue, the rows on screen now matter more than the ones before.
+    pub async fn queue_prefetch(&self, email_ids: Vec<i64>) {
+        *self.prefetch.lock().await = email_ids.into();
+    }
+
+    /// The next email to prefetch, if any.
+    pub async fn next_prefetch(&self) -> Option<i64> {
+        self.prefetch.lock().await.pop_front()
+    }
+
+    /// Marks a sync or background batch as running until the guard drops, `None` once quitting has begun.
+    pub async fn begin_work(&self) -> Option<OwnedRwLockReadGuard<()>> {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return None;
+        }
+        Some(self.work.clone().read_owned().await)
+    }
+
+    /// Waits for running syncs and batches, stops IDLE, then closes the IMAP connections and the database.
+    pub async fn shutdown(&self) {
+        info!("Shutting down sync...");
+        self.shutting_down.store(true, Ordering::SeqCst);
+
+        // Held until exit so nothing starts in between
+        let work = tokio::time::timeout(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS), self.work.write()).await;
+        if work.is_err() {
+            error!("Sync still running after {}s, closing anyway", SHUTDOWN_TIMEOUT_SECS);
+        }
+
+        // Only waiting for new mail by now, IDLE is safe to drop
+        for (_, stop) in self.idle_senders.lock().await.drain() {
+            let _ = stop.send(());
+        }
+
+        self.close_connections().await;
+        self.app_handle.state::<WritePool>().close().await;
+        self.app_handle.state::<SqlitePool>().close().await;
+        info!("Sync shut down");
+    }
+
+    pub async fn get_idle_state(&self, account_id: i64) -> Option<IdleState> {
+        self.idle_states.lock().await.get(&account_id).copied()
+    }
+
+    async fn set_idle_state(&self, account_id: i64, state: IdleState) {
+        self.idle_states.lock().await.insert(account_id, state);
+    }
+
     pub async fn get_context(&self, account_id: i64) -> Result<ImapContext, String> {
         let mut contexts = self.contexts.lock().await;
         if let Some(ctx) = contexts.get(&account_id) {
@@ -82,11 +323,13 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         let ctx_builder = ImapContextBuilder::new(account_config.clone(), imap_config)
             .with_pool_size(2);
 
-        let context: ImapContext = match BackendContextBuilder::build(ctx_builder).await {
+        let limit = self.imap_timeout().await;
+        let timed_out = |_| i18n::t("error.imap_timeout", &[("seconds", &limit.as_secs().to_string())]);
+        let context: ImapContext = match tokio::time::timeout(limit, BackendContextBuilder::build(ctx_builder)).await.map_err(timed_out)? {
             Ok(ctx) => ctx,
             Err(e) => {
                 let err_str = e.to_string();
-                if err_str.contains("auth") || err_str.contains("Unauthorized") || err_str.contains("token") || err_str.contains("credentials") {
+                if is_auth_error(&err_str) {
                     info!("Refreshing token for account {} due to context build error: {}", account.email(), err_str);
                     manager.refresh_access_token(account.email()).await?;
 
@@ -96,8 +339,9 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                     let ctx_builder = ImapContextBuilder::new(account_config, imap_config)
                         .with_pool_size(2);
 
-                    BackendContextBuilder::build(ctx_builder)
+                    tokio::time::timeout(limit, BackendContextBuilder::build(ctx_builder))
                         .await
+                        .map_err(timed_out)?
                         .map_err(|e| e.to_string())?
                 } else {
                     return Err(err_str);
@@ -109,6 +353,79 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         Ok(context)
     }
 
+    async fn imap_timeout(&self) -> Duration {
+        let pool = self.app_handle.state::<SqlitePool>();
+        let secs = Settings::load(&pool).await.unwrap_or_default().imap_timeout_secs;
+        Duration::from_secs(secs.max(MIN_IMAP_TIMEOUT_SECS))
+    }
+
+    /// Runs an exchange with the IMAP server of the account, giving up after `imapTimeoutSecs`
+    /// so a server that stopped answering can't hang the caller. The connection is left
+    /// mid-command then, it is dropped and the next request connects again. Take the pooled
+    /// client before calling, waiting for one to be free isn't the server's fault.
+    pub async fn with_timeout<F: Future>(&self, account_id: i64, operation: F) -> Result<F::Output, String> {
+        let limit = self.imap_timeout().await;
+        match tokio::time::timeout(limit, operation).await {
+            Ok(output) => Ok(output),
+            Err(_) => {
+                error!("IMAP request for account {} timed out after {:?}, dropping the connection", account_id, limit);
+                self.contexts.lock().await.remove(&account_id);
+                Err(i18n::t("error.imap_timeout", &[("seconds", &limit.as_secs().to_string())]))
+            }
+        }
+    }
+
+    /// A signed in SMTP session for the account, reused between sends while it hasn't sat idle
+    /// for too long so quick sends skip the TLS and auth handshake.
+    pub async fn get_smtp_context(&self, account_id: i64) -> Result<SmtpContextSync, String> {
+        {
+            let mut pooled = self.smtp_contexts.lock().await;
+            pooled.retain(|_, smtp| smtp.last_used.elapsed() < Duration::from_secs(SMTP_IDLE_TIMEOUT_SECS));
+            if let Some(smtp) = pooled.get_mut(&account_id) {
+                smtp.last_used = Instant::now();
+                return Ok(smtp.context.clone());
+            }
+        }
+
+        // Not locked while connecting, refreshing the token below invalidates the pool
+        let manager = AccountManager::new(&self.app_handle).await?;
+        let account = manager.get_account_by_id(account_id).await?;
+        let (account_config, _, smtp_config) = account.get_configs()?;
+
+        let context = match BackendContextBuilder::build(SmtpContextBuilder::new(account_config, smtp_config)).await {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                let err_str = e.to_string();
+                if is_auth_error(&err_str) {
+                    info!("Refreshing token for account {} due to SMTP build error: {}", account.email(), err_str);
+                    manager.refresh_access_token(account.email()).await?;
+
+                    let account = manager.get_account_by_id(account_id).await?;
+                    let (account_config, _, smtp_config) = account.get_configs()?;
+                    BackendContextBuilder::build(SmtpContextBuilder::new(account_config, smtp_config))
+                        .await
+                        .map_err(|e| e.to_string())?
+                } else {
+                    return Err(err_str);
+                }
+            }
+        };
+
+        self.smtp_contexts.lock().await.insert(account_id, PooledSmtp { context: context.clone(), last_used: Instant::now() });
+        Ok(context)
+    }
+
+    /// Closes the pooled SMTP session of the account, the next send signs in again.
+    pub async fn invalidate_smtp_context(&self, account_id: i64) {
+        self.smtp_contexts.lock().await.remove(&account_id);
+    }
+
+    /// Drops the account's cached IMAP and SMTP connections, for when how it connects changed.
+    pub async fn close_account_connections(&self, account_id: i64) {
+        self.contexts.lock().await.remove(&account_id);
+        self.smtp_contexts.lock().await.remove(&account_id);
+    }
+
     pub async fn get_backend(&self, account_id: i64) -> Result<Backend<ImapContext>, String> {
         let context = self.get_context(account_id).await?;
         let manager = AccountManager::new(&self.app_handle).await?;
@@ -147,24 +464,56 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         info!("Starting Sync Engine...");
         let app_handle = self.app_handle.clone();
 
+        if SyncMode::current(&app_handle.state::<SqlitePool>()).await == SyncMode::Mobile {
+            info!("Mobile sync mode, syncing only while in the foreground");
+            self.foreground_sync().await;
+            return;
+        }
+
         // Initial sync of all accounts
         if let Err(e) = Self::sync_all_accounts(&app_handle).await {
             error!("Initial sync failed: {}", e);
         }
 
+        // Restart the periodic timer whenever the interval is changed from settings
+        let interval_changed = Arc::new(Notify::new());
+        let interval_changed_listener = interval_changed.clone();
+        app_handle.listen("settings-changed", move |event| {
+            if let Ok(change) = serde_json::from_str::<SettingChanged>(event.payload()) {
+                if change.key == "syncIntervalMinutes" {
+                    interval_changed_listener.notify_one();
+                }
+            }
+        });
+
         // Start background periodic sync
         let app_handle_periodic = app_handle.clone();
         tauri::async_runtime::spawn(async move {
             loop {
-                sleep(Duration::from_secs(300)).await;
+                let pool = app_handle_periodic.state::<SqlitePool>();
+                let interval_minutes = Settings::load(&pool).await.unwrap_or_default().sync_interval_minutes.max(1);
+
+                tokio::select! {
+                    _ = sleep(Duration::from_secs(interval_minutes as u64 * 60)) => {}
+                    _ = interval_changed.notified() => {
+                        info!("Sync interval changed, rescheduling periodic sync");
+                        continue;
+                    }
+                }
+
                 if let Err(e) = Self::sync_all_accounts(&app_handle_periodic).await {
                     error!("Error during periodic sync: {}", e);
                 }
             }
         });
 
-        // Start IDLE for all accounts
-        if let Ok(manager) = AccountManager::new(&app_handle).await {
+        self.start_idle_for_all_accounts().await;
+
+        monitor::spawn(self.clone());
+    }
+
+    async fn start_idle_for_all_accounts(&self) {
+        if let Ok(manager) = AccountManager::new(&self.app_handle).await {
             if let Ok(registry) = manager.load().await {
                 for account in registry.accounts {
                     let engine = self.clone();
@@ -176,6 +525,23 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         }
     }
 
+    /// Throws away every connection and IDLE session, syncs and starts IDLE again. Sockets don't
+    /// survive a sleep or a network switch, IDLE would otherwise sit on a dead one for minutes.
+    pub async fn reconnect_all(&self, reason: &str) {
+        info!("Reconnecting all accounts after {}", reason);
+
+        for (_, stop) in self.idle_senders.lock().await.drain() {
+            let _ = stop.send(());
+        }
+        self.contexts.lock().await.clear();
+        self.smtp_contexts.lock().await.clear();
+
+        if let Err(e) = Self::sync_all_accounts(&self.app_handle).await {
+            error!("Sync after {} failed: {}", reason, e);
+        }
+        self.start_idle_for_all_accounts().await;
+    }
+
     pub fn trigger_sync_for_account(&self, account: Account) {
         let engine = self.clone();
 
@@ -185,11 +551,50 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 error!("Initial sync failed for {}: {}", account.email(), e);
             }
 
-            // 2. Start IDLE
+            // 2. Start IDLE, mobile builds don't keep a connection open
+            let pool = engine.app_handle.state::<SqlitePool>();
+            if SyncMode::current(&pool).await == SyncMode::Mobile {
+                let _ = engine.app_handle.emit(FOREGROUND_SYNC_FINISHED, ());
+                return;
+            }
             engine.start_idle_for_account(account).await;
         });
     }
 
+    /// Called whenever the app comes to the foreground. Desktop is kept current by IDLE,
+    /// mobile syncs every account now.
+    pub fn on_foreground(&self) {
+        let engine = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let pool = engine.app_handle.state::<SqlitePool>();
+            if SyncMode::current(&pool).await == SyncMode::Mobile {
+                engine.foreground_sync().await;
+            }
+        });
+    }
+
+    async fn foreground_sync(&self) {
+        {
+            let mut last = self.last_foreground_sync.lock().await;
+            if last.is_some_and(|at| at.elapsed() < Duration::from_secs(MIN_FOREGROUND_SYNC_SECS)) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+
+        if let Err(e) = Self::sync_all_accounts(&self.app_handle).await {
+            error!("Foreground sync failed: {}", e);
+        }
+        // The worker indexes what came in and closes the connections afterwards
+        let _ = self.app_handle.emit(FOREGROUND_SYNC_FINISHED, ());
+    }
+
+    /// Drops the cached IMAP and SMTP connections, mobile closes them once its batch of work is done.
+    pub async fn close_connections(&self) {
+        self.contexts.lock().await.clear();
+        self.smtp_contexts.lock().await.clear();
+    }
+
     pub async fn refresh_folder(app_handle: &tauri::AppHandle<R>, account_id: i64, folder_id: i64) -> Result<(), String> {
         let pool = app_handle.state::<SqlitePool>();
         let folder_info: (String, Option<String>) = sqlx::query_as("SELECT path, role FROM folders WHERE id = ?")
@@ -206,7 +611,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
         let mut client = context.client().await;
 
-        let folder_data = match client.examine_mailbox(&folder_path).await {
+        let folder_data = match engine.with_timeout(account_id, client.examine_mailbox(&folder_path)).await? {
             Ok(data) => data,
             Err(e) => {
                 error!("Failed to examine mailbox {}: {}", folder_path, e);
@@ -225,27 +630,30 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
     async fn is_ai_summary_enabled(app_handle: &tauri::AppHandle<R>) -> bool {
         let pool = app_handle.state::<SqlitePool>();
-        let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
-
-        let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
+        let settings = Settings::load(&pool).await.unwrap_or_default();
+        settings.ai_enabled && settings.ai_summarization_enabled
+    }
 
-        ai_enabled.0 == "true" && ai_summarization_enabled.0 == "true"
+    pub(crate) async fn is_notifications_enabled(app_handle: &tauri::AppHandle<R>) -> bool {
+        let pool = app_handle.state::<SqlitePool>();
+        Settings::load(&pool).await.unwrap_or_default().notifications_enabled
     }
 
-    async fn is_notifications_enabled(app_handle: &tauri::AppHandle<R>) -> bool {
+    /// Shows a notification with the sound picked in settings: `default` leaves it to the
+    /// system, `none` is silent, anything else is a system sound name.
+    pub(crate) async fn show_notification(app_handle: &tauri::AppHandle<R>, title: String, body: String) {
         let pool = app_handle.state::<SqlitePool>();
-        let notifications_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'notificationsEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("true".to_string(),));
+        let sound = Settings::load(&pool).await.unwrap_or_default().notification_sound;
 
-        notifications_enabled.0 == "true"
+        let mut builder = app_handle.notification().builder().title(title).body(body);
+        match sound.as_str() {
+            "default" | "" => {}
+            "none" => builder = builder.silent(),
+            name => builder = builder.sound(name),
+        }
+        if let Err(e) = builder.show() {
+            report_error(app_handle, BackendError::new(ErrorCategory::Notification, ErrorSeverity::Warning, i18n::t("error.show_notification", &[("error", &e.to_string())])));
+        }
     }
 
     async fn handle_notification(
@@ -259,11 +667,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         }
 
         if !Self::is_ai_summary_enabled(&app_handle).await {
-            let _ = app_handle.notification()
-                .builder()
-                .title(format!("New Email: {}", subject))
-                .body(format!("From: {}", sender))
-                .show();
+            Self::show_notification(&app_handle, i18n::t("notification.new_email", &[("subject", &subject)]), i18n::t("notification.from", &[("sender", &sender)])).await;
             return;
         }
 
@@ -293,11 +697,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                  .unwrap_or(None);
 
              if let Some(Some(s)) = summary {
-                 let _ = app_handle.notification()
-                     .builder()
-                     .title(format!("New Email: {}", subject))
-                     .body(format!("{}", s))
-                     .show();
+                 Self::show_notification(&app_handle, i18n::t("notification.new_email", &[("subject", &subject)]), s).await;
                  return;
              }
 
@@ -305,11 +705,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         }
 
         // Timeout reached, send default notification
-        let _ = app_handle.notification()
-            .builder()
-            .title(format!("New Email: {}", subject))
-            .body(format!("From: {}", sender))
-            .show();
+        Self::show_notification(&app_handle, i18n::t("notification.new_email", &[("subject", &subject)]), i18n::t("notification.from", &[("sender", &sender)])).await;
     }
 
     async fn save_envelopes(
@@ -317,6 +713,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         account_id: i64,
         folder_id: i64,
         envelopes: Envelopes,
+        layouts: &HashMap<String, MessageLayout>,
         notify: bool,
     ) -> Result<Vec<i64>, String> {
         let pool = app_handle.state::<SqlitePool>();
@@ -325,20 +722,100 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         let mut failure_count = 0;
         let mut last_error = None;
         let total = envelopes.len();
+        let mut written = 0;
+
+        let role = sqlx::query_scalar::<_, Option<String>>("SELECT role FROM folders WHERE id = ?")
+            .bind(folder_id)
+            .fetch_one(&*pool)
+            .await
+            .ok()
+            .flatten();
+        // Only new inbox mail goes through the screener, never the initial backfill
+        let screening_active = notify && role.as_deref() == Some("inbox") && screener::screener_enabled(&pool).await;
+        // New mail the user's past moves say belongs on the other side of the spam filter, and
+        // mail from domains the user archives on arrival, by what sorted it and where to
+        let mut presorted: HashMap<(&'static str, &'static str), Vec<i64>> = HashMap::new();
+
+        for mut env in envelopes {
+            // Encoded-words the envelope parser left in place, in any charset
+            env.subject = charset::decode_header(&env.subject);
+            env.from.name = env.from.name.as_deref().map(charset::decode_header);
+
+            // A sent message both filed by the server and appended by us is the same message twice
+            if role.as_deref() == Some("sent") && !env.message_id.is_empty() {
+                let duplicate = sqlx::query_scalar::<_, i64>("SELECT id FROM emails WHERE folder_id = ? AND message_id = ? AND remote_id != ?")
+                    .bind(folder_id)
+                    .bind(&env.message_id)
+                    .bind(&env.id)
+                    .fetch_optional(&*pool)
+                    .await
+                    .ok()
+                    .flatten();
+                if let Some(email_id) = duplicate {
+                    info!("Skipping uid {} in folder {}, a copy of sent email {}", env.id, folder_id, email_id);
+                    continue;
+                }
+            }
+
+            // Mail the app moved here waits under a placeholder for the UID it got in this folder
+            if !env.message_id.is_empty() {
+                if let Err(e) = sqlx::query(
+                    "UPDATE emails SET remote_id = ? WHERE id = (
+                        SELECT id FROM emails WHERE folder_id = ? AND message_id = ? AND remote_id LIKE 'moved:%' LIMIT 1
+                     ) AND NOT EXISTS (SELECT 1 FROM emails WHERE folder_id = ? AND remote_id = ?)"
+                )
+                .bind(&env.id)
+                .bind(folder_id)
+                .bind(&env.message_id)
+                .bind(folder_id)
+                .bind(&env.id)
+                .execute(&*pool)
+                .await
+                {
+                    error!("Failed to match moved email to uid {} in folder {}: {}", env.id, folder_id, e);
+                }
+            }
 
-        for env in envelopes {
             let flags: Vec<String> = env.flags.clone().into();
+            // The upsert below also touches known mail, only a real insert is new mail
+            let stored = sqlx::query_as::<_, StoredEnvelope>(
+                "SELECT id, COALESCE(flags, '[]') AS flags, COALESCE(has_attachments, 0) AS has_attachments, recipient_to IS NOT NULL AS has_recipient, size IS NOT NULL AS has_size
+                 FROM emails WHERE folder_id = ? AND remote_id = ?"
+            )
+            .bind(folder_id)
+            .bind(&env.id)
+            .fetch_optional(&*pool)
+            .await
+            .ok()
+            .flatten();
+            let existed = stored.is_some();
             let date_str = env.date.with_timezone(&chrono::Utc).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
             let norm_subject = normalize_subject(&env.subject);
             let recipient_to = Some(env.to.addr.clone());
+            // The paperclip is for files sent along, not for images shown in the body
+            let has_attachments = layouts
+                .get(&env.id)
+                .map(|layout| layout.attachments.iter().any(|part| !part.is_inline))
+                .unwrap_or(env.has_attachment);
+            let size = layouts.get(&env.id).and_then(|layout| layout.size);
+
+            // Most of a sync is mail already saved as it is, rewriting it would only churn the
+            // WAL and the search index triggers
+            if let Some(stored) = stored.filter(|stored| stored.unchanged(&flags, has_attachments, size)) {
+                success_count += 1;
+                saved_ids.push(stored.id);
+                continue;
+            }
+            written += 1;
 
             let res: Result<(i64,), sqlx::Error> = sqlx::query_as(
-                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, in_reply_to, references_header, subject, normalized_subject, sender_name, sender_address, recipient_to, date, flags, has_attachments)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "INSERT INTO emails (account_id, folder_id, remote_id, message_id, thread_id, in_reply_to, references_header, subject, normalized_subject, sender_name, sender_address, recipient_to, date, flags, has_attachments, size)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                  ON CONFLICT(folder_id, remote_id) DO UPDATE SET
                     flags=excluded.flags,
                     recipient_to=COALESCE(emails.recipient_to, excluded.recipient_to),
-                    has_attachments=excluded.has_attachments
+                    has_attachments=excluded.has_attachments,
+                    size=COALESCE(excluded.size, emails.size)
                  RETURNING id"
             )
             .bind(account_id)
@@ -355,7 +832,8 @@ impl<R: tauri::Runtime> SyncEngine<R> {
             .bind(recipient_to)
             .bind(&date_str)
             .bind(serde_json::to_string(&flags).unwrap_or_default())
-            .bind(env.has_attachment)
+            .bind(has_attachments)
+            .bind(size)
             .fetch_one(&*pool)
             .await;
 
@@ -363,7 +841,66 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 Ok((email_id,)) => {
                     success_count += 1;
                     saved_ids.push(email_id);
-                    if notify && !flags.contains(&"seen".to_string()) {
+
+                    // Names and sizes show in the list before any body is downloaded
+                    if let Some(parts) = layouts.get(&env.id).map(|l| &l.attachments).filter(|parts| !parts.is_empty()) {
+                        let recorded = match pool.acquire().await {
+                            Ok(mut conn) => record_attachments(&mut conn, email_id, parts).await,
+                            Err(e) => Err(e.to_string()),
+                        };
+                        if let Err(e) = recorded {
+                            error!("Failed to save attachment list of email {}: {}", email_id, e);
+                        }
+                    }
+
+                    let mut held_back = false;
+                    let learned = match role.as_deref() {
+                        Some(current @ ("inbox" | "spam")) if notify && !existed => {
+                            spam_signals::learned_role(&pool, &env.from.addr).await.filter(|target| *target != current)
+                        }
+                        _ => None,
+                    };
+                    if let Some(target) = learned {
+                        presorted.entry(("spam_signals", target)).or_default().push(email_id);
+                        held_back = target == "spam";
+                    }
+                    let mut muted = false;
+                    if notify && !existed && !held_back && role.as_deref() == Some("inbox") {
+                        match domain_policies::policy_for_sender(&pool, &env.from.addr).await {
+                            Ok(Some(policy)) => {
+                                if policy.auto_archive {
+                                    presorted.entry(("domain_policies", "archive")).or_default().push(email_id);
+                                    held_back = true;
+                                }
+                                muted = policy.mute_notifications;
+                            }
+                            Ok(None) => {}
+                            Err(e) => error!("Failed to look up the domain policy for email {}: {}", email_id, e),
+                        }
+                    }
+                    if screening_active && !existed && !held_back {
+                        match screener::screen_new_email(&pool, email_id, &env.from.addr).await {
+                            Ok(status) => held_back = status.is_some(),
+                            Err(e) => error!("Failed to screen email {}: {}", email_id, e),
+                        }
+                    }
+                    // Don't wait for the background indexer to learn that a message bounced
+                    if notify && bounce::looks_like_bounce(&env.from.addr, &env.subject) {
+                        let app_handle_clone = app_handle.clone();
+                        tauri::async_runtime::spawn(async move {
+                            use crate::email_backend::sync::worker::SyncWorker;
+                            if let Err(e) = SyncWorker::index_specific_email(&app_handle_clone, email_id).await {
+                                error!("Failed to process delivery report {}: {}", email_id, e);
+                            }
+                        });
+                    }
+                    let notification_key = match env.message_id.as_str() {
+                        "" => (account_id, format!("{}:{}", folder_id, env.id)),
+                        message_id => (account_id, message_id.to_string()),
+                    };
+                    if notify && !existed && !held_back && !muted && !flags.contains(&"seen".to_string())
+                        && app_handle.state::<SyncEngine<R>>().notified.lock().await.insert(notification_key)
+                    {
                         info!("Scheduling notification for email: {}", env.subject);
                         let app_handle_clone = app_handle.clone();
                         let subject = env.subject.clone();
@@ -382,25 +919,170 @@ impl<R: tauri::Runtime> SyncEngine<R> {
             }
         }
 
-        info!("Saved {}/{} envelopes for folder {}", success_count, total, folder_id);
+        info!("Saved {}/{} envelopes for folder {}, {} of them changed", success_count, total, folder_id, written);
+
+        // Moved once the sync is done with the connection
+        for ((operation, target), ids) in presorted {
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let pool = app_handle.state::<SqlitePool>().inner().clone();
+                let moved = match emails_by_id(&pool, &ids).await {
+                    Ok(groups) => run_bulk(&app_handle, operation, BulkAction::MoveToRole(target), groups).await,
+                    Err(e) => Err(e),
+                };
+                match moved {
+                    Ok(ids) => info!("Sorted {} new email(s) to {} by {}", ids.len(), target, operation),
+                    Err(e) => error!("Failed to sort new mail to {}: {}", target, e),
+                }
+            });
+        }
 
         // Update unread count for the folder based on actual emails in DB
-        let _ = sqlx::query(
+        if written > 0 {
+            let _ = sqlx::query(
+                "UPDATE folders SET unread_count = (
+                    SELECT COUNT(*) FROM emails
+                    WHERE folder_id = ? AND flags NOT LIKE '%\"seen\"%'
+                ) WHERE id = ?"
+            )
+            .bind(folder_id)
+            .bind(folder_id)
+            .execute(&*pool)
+            .await;
+        }
+
+        if failure_count > 0 && success_count == 0 {
+            return Err(format!("Failed to save any emails in batch. Last error: {}", last_error.unwrap_or_default()));
+        }
+
+        Ok(saved_ids)
+    }
+
+    /// Fills in the snippets of just saved mail from the start of its text part, so the list
+    /// has a preview right away instead of once the background indexer gets to it.
+    async fn save_previews(
+        app_handle: &tauri::AppHandle<R>,
+        client: &mut ImapClient,
+        folder_id: i64,
+        layouts: &HashMap<String, MessageLayout>,
+    ) {
+        let pool = app_handle.state::<SqlitePool>();
+        let mut previewed = 0;
+
+        for (remote_id, layout) in layouts {
+            if previewed >= MAX_PREVIEWS {
+                break;
+            }
+            // Invites are left to the indexer, which also saves the event
+            if layout.calendar.is_some() {
+                continue;
+            }
+            let Some(part) = layout.text.as_ref().or(layout.html.as_ref()) else { continue };
+            let Some(uid) = remote_id.parse::<u32>().ok().and_then(NonZeroU32::new) else { continue };
+
+            let pending = sqlx::query_scalar::<_, i64>(&format!(
+                "SELECT e.id FROM emails e WHERE e.folder_id = ? AND e.remote_id = ? AND e.snippet IS NULL AND e.body_text IS NULL AND NOT {}",
+                confidential::uncached_condition("e")
+            ))
+                .bind(folder_id)
+                .bind(remote_id)
+                .fetch_optional(&*pool)
+                .await
+                .ok()
+                .flatten();
+            let Some(email_id) = pending else { continue };
+            previewed += 1;
+
+            let partial = Some((0, NonZeroU32::new(PREVIEW_FETCH_BYTES).unwrap()));
+            let raw = match fetch_section(client, uid, &part.section, partial).await {
+                Ok(raw) => raw,
+                Err(e) => {
+                    error!("Failed to fetch preview of uid {} in folder {}: {}", remote_id, folder_id, e);
+                    continue;
+                }
+            };
+            let Some(preview) = body_structure::decode(part, &raw).body_text(0).map(|text| snippet(&text)) else { continue };
+
+            if let Err(e) = sqlx::query("UPDATE emails SET snippet = ? WHERE id = ? AND snippet IS NULL")
+                .bind(&preview)
+                .bind(email_id)
+                .execute(&*pool)
+                .await
+            {
+                error!("Failed to save preview of email {}: {}", email_id, e);
+            }
+        }
+    }
+
+    /// Brings the seen flag of the folder's mail in line with the server's UNSEEN search, for
+    /// mail read or marked unread in another client, and recounts the folder's unread mail.
+    async fn reconcile_read_state(app_handle: &tauri::AppHandle<R>, client: &mut ImapClient, folder_id: i64) -> Result<(), String> {
+        let unseen: HashSet<String> = client
+            .search_uids([SearchKey::Unseen])
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|uid| uid.to_string())
+            .collect();
+
+        let pool = app_handle.state::<SqlitePool>();
+        let local: Vec<(i64, String, String)> = sqlx::query_as("SELECT id, remote_id, flags FROM emails WHERE folder_id = ?")
+            .bind(folder_id)
+            .fetch_all(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut changed = Vec::new();
+        for (id, remote_id, current_flags) in local {
+            let mut flags: Vec<String> = serde_json::from_str(&current_flags).unwrap_or_default();
+            let seen = flags.iter().any(|f| f == "seen");
+            if seen == !unseen.contains(&remote_id) {
+                continue;
+            }
+            if seen {
+                flags.retain(|f| f != "seen");
+            } else {
+                flags.push("seen".to_string());
+            }
+            changed.push((id, serde_json::to_string(&flags).unwrap_or_default()));
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        info!("Read state of {} emails in folder {} changed on the server", changed.len(), folder_id);
+        let mut tx = app_handle.state::<WritePool>().begin().await?;
+        for (id, flags) in &changed {
+            sqlx::query("UPDATE emails SET flags = ? WHERE id = ?")
+                .bind(flags)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        sqlx::query(
             "UPDATE folders SET unread_count = (
                 SELECT COUNT(*) FROM emails
-                WHERE folder_id = ? AND (flags NOT LIKE '%seen%' AND flags NOT LIKE '%\"seen\"%')
+                WHERE folder_id = ? AND flags NOT LIKE '%\"seen\"%'
             ) WHERE id = ?"
         )
         .bind(folder_id)
         .bind(folder_id)
-        .execute(&*pool)
-        .await;
-
-        if failure_count > 0 && success_count == 0 {
-            return Err(format!("Failed to save any emails in batch. Last error: {}", last_error.unwrap_or_default()));
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        for (id, flags) in changed {
+            let _ = app_handle.emit("emails-updated", EmailEvent::Updated {
+                id,
+                address: None,
+                flags: Some(flags),
+                summary: None,
+                thread_count: None,
+            });
         }
-
-        Ok(saved_ids)
+        Ok(())
     }
 
     pub async fn start_idle_for_account(&self, account: Account) {
@@ -413,6 +1095,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
         let (tx, mut rx) = oneshot::channel();
         self.idle_senders.lock().await.insert(account_id, tx);
+        self.set_idle_state(account_id, IdleState::Connecting).await;
 
         loop {
             let res = tokio::select! {
@@ -425,9 +1108,12 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
             if let Err(e) = res {
                 error!("IDLE loop error for {}: {}. Retrying in 30s...", account.email(), e);
+                self.set_idle_state(account_id, IdleState::Reconnecting).await;
                 sleep(Duration::from_secs(30)).await;
             }
         }
+
+        self.set_idle_state(account_id, IdleState::Stopped).await;
     }
 
     async fn run_idle_loop(&self, account: &Account) -> Result<(), String> {
@@ -440,10 +1126,13 @@ impl<R: tauri::Runtime> SyncEngine<R> {
             info!("IDLE waiting for updates for {}...", account.email());
 
             // Select INBOX and get current state
+            let work = self.begin_work().await.ok_or("Shutting down")?;
             let folder_data = client.select_mailbox("INBOX").await.map_err(|e| e.to_string())?;
 
             // Sync current state
+            self.set_idle_state(account_id, IdleState::Syncing).await;
             Self::sync_folder(&self.app_handle, &mut *client, account, "INBOX", Some("inbox".to_string()), &folder_data).await?;
+            drop(work);
 
             let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
 
@@ -455,6 +1144,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 info!("Refreshing IDLE for {} after timeout", account_email);
             });
 
+            self.set_idle_state(account_id, IdleState::Idling).await;
             client.idle(&mut shutdown_rx).await.map_err(|e| e.to_string())?;
             info!("IDLE notification received or timeout for {}", account.email());
         }
@@ -467,15 +1157,39 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         folder_name: &str,
         role: Option<String>,
         folder_data: &SelectDataUnvalidated
+    ) -> Result<(), String> {
+        let result = Self::sync_folder_state(app_handle, client, account, folder_name, role, folder_data).await;
+
+        // Remember the outcome for get_sync_health
+        if let Some(account_id) = account.id() {
+            let pool = app_handle.state::<SqlitePool>();
+            let query = match &result {
+                Ok(_) => sqlx::query("UPDATE folders SET last_synced_at = CURRENT_TIMESTAMP, last_sync_error = NULL WHERE account_id = ? AND path = ?"),
+                Err(e) => sqlx::query("UPDATE folders SET last_sync_error = ? WHERE account_id = ? AND path = ?").bind(e.clone()),
+            };
+            let _ = query.bind(account_id).bind(folder_name).execute(&*pool).await;
+        }
+
+        result
+    }
+
+    async fn sync_folder_state(
+        app_handle: &tauri::AppHandle<R>,
+        client: &mut ImapClient,
+        account: &Account,
+        folder_name: &str,
+        role: Option<String>,
+        folder_data: &SelectDataUnvalidated
     ) -> Result<(), String> {
         let account_id = account.id().ok_or("Account ID missing")?;
         let pool = app_handle.state::<SqlitePool>();
+        let engine = app_handle.state::<SyncEngine<R>>();
 
-        let sync_months_setting: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'syncMonths'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("3".to_string(),));
-        let sync_months = sync_months_setting.0.parse::<i32>().unwrap_or(3);
+        let settings = Settings::load(&pool).await.unwrap_or_default();
+        let sync_months = settings.sync_months as i32;
+        let configured_batch_size = settings.sync_batch_size.max(1);
+        let mut batch_size = configured_batch_size;
+        let mut throttled = 0;
 
         info!("Syncing folder {} for {}. Role: {:?}. SyncMonths: {}", folder_name, account.email(), role, sync_months);
 
@@ -486,8 +1200,8 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         info!("Folder {} state: UIDValidity={}, UIDNext={}, Exists={}", folder_name, current_uid_validity, current_uid_next, total_count);
 
         // 1. Get stored folder info
-        let stored_folder: Option<(i64, i64, i64, Option<String>)> = sqlx::query_as(
-            "SELECT id, uid_validity, uid_next, role FROM folders WHERE account_id = ? AND path = ?"
+        let stored_folder: Option<(i64, i64, i64, Option<String>, Option<i64>, Option<i64>)> = sqlx::query_as(
+            "SELECT id, uid_validity, uid_next, role, full_sync_next_seq, full_sync_uid_next FROM folders WHERE account_id = ? AND path = ?"
         )
         .bind(account_id)
         .bind(folder_name)
@@ -495,8 +1209,8 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         .await
         .map_err(|e| e.to_string())?;
 
-        let (folder_id, stored_uid_validity, stored_uid_next) = match stored_folder {
-            Some((id, uv, un, stored_role)) => {
+        let (folder_id, stored_uid_validity, stored_uid_next, checkpoint) = match stored_folder {
+            Some((id, uv, un, stored_role, next_seq, start_uid_next)) => {
                 info!("Found stored folder {} (id={}). Stored UIDValidity={}, UIDNext={}", folder_name, id, uv, un);
                 // If role changed or was empty, update it
                 if let Some(ref new_role) = role {
@@ -509,7 +1223,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                             .map_err(|e| e.to_string())?;
                     }
                 }
-                (id, uv, un)
+                (id, uv, un, next_seq.zip(start_uid_next))
             },
             None => {
                 info!("Folder {} not in DB, creating entry", folder_name);
@@ -530,12 +1244,13 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 .await
                 .map_err(|e| e.to_string())?;
                 info!("Created folder entry {} with id {}", folder_name, row.0);
-                (row.0, 0, 0) // Treat as full sync
+                (row.0, 0, 0, None) // Treat as full sync
             }
         };
 
         // Handle UID validity change: clear local cache as UIDs are no longer valid
-        if stored_uid_validity != 0 && stored_uid_validity != current_uid_validity {
+        let validity_changed = stored_uid_validity != 0 && stored_uid_validity != current_uid_validity;
+        if validity_changed {
             info!("UID validity changed for folder {} of {}, clearing local cache", folder_name, account.email());
             sqlx::query("DELETE FROM emails WHERE folder_id = ?")
                 .bind(folder_id)
@@ -544,23 +1259,65 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 .map_err(|e| e.to_string())?;
         }
 
-        if stored_uid_validity != current_uid_validity || stored_uid_next == 0 {
-            info!("Performing full sync for folder {} of {} (total={})", folder_name, account.email(), total_count);
-            let mut end = total_count as u32;
+        // An interrupted full sync is resumed from its checkpoint instead of starting over
+        let checkpoint = if validity_changed { None } else { checkpoint };
+        let needs_full_sync = stored_uid_validity != current_uid_validity || stored_uid_next == 0 || checkpoint.is_some();
+        let mut incremental_from = stored_uid_next;
+
+        if needs_full_sync {
+            // Sequence numbers below the checkpoint only shift down on expunge, so resuming
+            // may refetch a few envelopes (upserted) but never skips any
+            let (mut end, full_sync_uid_next) = match checkpoint {
+                Some((next_seq, start_uid_next)) => {
+                    info!("Resuming full sync for folder {} of {} from sequence {}", folder_name, account.email(), next_seq);
+                    ((next_seq.max(0) as u32).min(total_count as u32), start_uid_next)
+                }
+                None => {
+                    info!("Performing full sync for folder {} of {} (total={})", folder_name, account.email(), total_count);
+                    (total_count as u32, current_uid_next)
+                }
+            };
             let mut synced_count = 0;
 
-            while end > 0 {
-                let start = if end > SYNC_BATCH_SIZE { end - SYNC_BATCH_SIZE + 1 } else { 1 };
+            sqlx::query("UPDATE folders SET full_sync_next_seq = ?, full_sync_uid_next = ? WHERE id = ?")
+                .bind(end as i64)
+                .bind(full_sync_uid_next)
+                .bind(folder_id)
+                .execute(&*pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // Depth counts from the newest message, so it also holds across resumes
+            let lowest_seq = match settings.sync_backfill_depth {
+                0 => 0,
+                depth => (total_count as u32).saturating_sub(depth),
+            };
+
+            while end > lowest_seq {
+                let start = (if end > batch_size { end - batch_size + 1 } else { 1 }).max(lowest_seq + 1);
                 info!("Fetching envelopes sequence {}:{} for folder {}", start, end, folder_name);
 
                 let start_nz = NonZeroU32::new(start).unwrap_or(NonZeroU32::new(1).unwrap());
                 let end_nz = NonZeroU32::new(end).unwrap_or(NonZeroU32::new(1).unwrap());
                 let seq = (start_nz..=end_nz).into();
 
-                let envelopes = client.fetch_envelopes_by_sequence(seq).await.map_err(|e| {
-                    error!("Failed to fetch envelopes batch {}:{} for {}: {}", start, end, folder_name, e);
-                    e.to_string()
-                })?;
+                let (envelopes, layouts) = match engine.with_timeout(account_id, client.fetch_envelope_items_by_sequence(seq)).await? {
+                    Ok(fetches) => envelopes_with_layouts(fetches),
+                    Err(e) if throttle::is_throttled(&format!("{:?}", e)) && throttled < throttle::MAX_RETRIES => {
+                        throttled += 1;
+                        batch_size = throttle::reduce(batch_size);
+                        let delay = throttle::backoff(throttled);
+                        info!("Throttled fetching {}:{} for {}, retrying in {:?} with batches of {}: {}", start, end, folder_name, delay, batch_size, e);
+                        sleep(delay).await;
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to fetch envelopes batch {}:{} for {}: {}", start, end, folder_name, e);
+                        return Err(e.to_string());
+                    }
+                };
+                throttled = 0;
+                batch_size = throttle::recover(batch_size, configured_batch_size);
 
                 if envelopes.is_empty() {
                     info!("No envelopes returned for sequence {}:{} in folder {}", start, end, folder_name);
@@ -570,47 +1327,99 @@ impl<R: tauri::Runtime> SyncEngine<R> {
                 let batch_len = envelopes.len() as u32;
                 info!("Fetched {} envelopes for sequence {}:{} in folder {}", batch_len, start, end, folder_name);
 
-                let is_initial = stored_uid_next == 0;
-                let _saved_ids = match Self::save_envelopes(app_handle, account_id, folder_id, envelopes, !is_initial).await {
+                let is_initial = stored_uid_next == 0 || checkpoint.is_some();
+                let _saved_ids = match Self::save_envelopes(app_handle, account_id, folder_id, envelopes, &layouts, !is_initial).await {
                     Ok(ids) => ids,
                     Err(e) => {
                         error!("Critical failure saving envelopes for {}: {}. Aborting folder sync.", folder_name, e);
                         return Err(e);
                     }
                 };
+                // The rest of a backfill waits for the indexer, only the newest batch is on screen
+                if !is_initial || (checkpoint.is_none() && synced_count == 0) {
+                    Self::save_previews(app_handle, client, folder_id, &layouts).await;
+                }
 
                 synced_count += batch_len;
                 // Signal that new emails are available without spamming granular events
                 let _ = app_handle.emit("emails-updated", "bulk-add");
 
                 end = if start > 1 { start - 1 } else { 0 };
+
+                let _ = app_handle.emit("backfill-progress", BackfillProgress {
+                    account_id,
+                    folder_id,
+                    folder: folder_name.to_string(),
+                    fetched: (total_count as u32).saturating_sub(end),
+                    total: (total_count as u32).saturating_sub(lowest_seq),
+                    done: end <= lowest_seq,
+                });
+
+                sqlx::query("UPDATE folders SET full_sync_next_seq = ? WHERE id = ?")
+                    .bind(end as i64)
+                    .bind(folder_id)
+                    .execute(&*pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+
+                if settings.sync_batch_delay_ms > 0 && end > lowest_seq {
+                    sleep(Duration::from_millis(settings.sync_batch_delay_ms)).await;
+                }
             }
-        } else if (stored_uid_next as u32) < (current_uid_next as u32) {
-            info!("Performing incremental sync for folder {} of {} (UID {}:*)", folder_name, account.email(), stored_uid_next);
 
-            let start_uid = NonZeroU32::new(stored_uid_next as u32).unwrap_or(NonZeroU32::new(1).unwrap());
-            let uids = (start_uid..).into();
-            let mut envelopes = client.fetch_envelopes(uids).await.map_err(|e| {
-                error!("Failed to fetch envelopes incremental UID {}:* for {}: {}", stored_uid_next, folder_name, e);
-                e.to_string()
-            })?;
+            sqlx::query("UPDATE folders SET full_sync_next_seq = NULL, full_sync_uid_next = NULL WHERE id = ?")
+                .bind(folder_id)
+                .execute(&*pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            // Mail that arrived while a resumed full sync was paused sits above its original range
+            incremental_from = full_sync_uid_next;
+        }
+
+        if incremental_from != 0 && (incremental_from as u32) < (current_uid_next as u32) {
+            info!("Performing incremental sync for folder {} of {} (UID {}:*)", folder_name, account.email(), incremental_from);
+
+            let start_uid = NonZeroU32::new(incremental_from as u32).unwrap_or(NonZeroU32::new(1).unwrap());
+            let (envelopes, layouts) = loop {
+                let uids = (start_uid..).into();
+                match engine.with_timeout(account_id, client.fetch_envelope_items(uids)).await? {
+                    Ok(fetches) => break envelopes_with_layouts(fetches),
+                    Err(e) if throttle::is_throttled(&format!("{:?}", e)) && throttled < throttle::MAX_RETRIES => {
+                        throttled += 1;
+                        let delay = throttle::backoff(throttled);
+                        info!("Throttled fetching UID {}:* for {}, retrying in {:?}: {}", incremental_from, folder_name, delay, e);
+                        sleep(delay).await;
+                    }
+                    Err(e) => {
+                        error!("Failed to fetch envelopes incremental UID {}:* for {}: {}", incremental_from, folder_name, e);
+                        return Err(e.to_string());
+                    }
+                }
+            };
 
             if !envelopes.is_empty() {
                 info!("Fetched {} new envelopes incrementally for folder {}", envelopes.len(), folder_name);
-                let _saved_ids = match Self::save_envelopes(app_handle, account_id, folder_id, envelopes, true).await {
+                let _saved_ids = match Self::save_envelopes(app_handle, account_id, folder_id, envelopes, &layouts, true).await {
                     Ok(ids) => ids,
                     Err(e) => {
                         error!("Critical failure saving incremental envelopes for {}: {}. Aborting folder sync.", folder_name, e);
                         return Err(e);
                     }
                 };
+                Self::save_previews(app_handle, client, folder_id, &layouts).await;
 
                 let _ = app_handle.emit("emails-updated", "bulk-add");
             }
-        } else {
+        } else if !needs_full_sync {
             info!("Folder {} of {} is up to date", folder_name, account.email());
         }
 
+        // Incremental syncs only fetch new mail, mail read elsewhere is caught up here
+        if let Err(e) = Self::reconcile_read_state(app_handle, client, folder_id).await {
+            error!("Failed to reconcile read state of folder {} of {}: {}", folder_name, account.email(), e);
+        }
+
         // Update folder info with latest state from server
         info!("Updating folder {} entry with new UIDNext={}", folder_name, current_uid_next);
         sqlx::query(
@@ -632,8 +1441,15 @@ impl<R: tauri::Runtime> SyncEngine<R> {
         let registry = manager.load().await?;
 
         for account in registry.accounts {
-            if let Err(e) = Self::sync_account(app_handle, &account).await {
-                error!("Failed to sync account {}: {}", account.email(), e);
+            match Self::sync_account(app_handle, &account).await {
+                // Signing in failed even with a refreshed token, retrying won't help until the user acts
+                Err(e) if is_auth_error(&e) => {
+                    report_error(app_handle, BackendError::new(ErrorCategory::Auth, ErrorSeverity::Error, i18n::t("error.auth_failed", &[("account", account.email()), ("error", &e)])));
+                }
+                Err(e) => {
+                    report_error(app_handle, BackendError::new(ErrorCategory::Sync, ErrorSeverity::Warning, i18n::t("error.sync_account", &[("account", account.email()), ("error", &e)])).retryable());
+                }
+                Ok(()) => {}
             }
         }
 
@@ -641,11 +1457,23 @@ impl<R: tauri::Runtime> SyncEngine<R> {
     }
 
     pub async fn sync_account(app_handle: &tauri::AppHandle<R>, account: &Account) -> Result<(), String> {
+        let _work = app_handle.state::<SyncEngine<R>>().begin_work().await.ok_or("Shutting down")?;
+
         // Ensure we have the latest account info with ID from DB
         let manager = AccountManager::new(app_handle).await?;
-        let account = manager.get_account_by_id(account.id().ok_or("Account ID missing before sync")?).await?;
+        let account_id = account.id().ok_or("Account ID missing before sync")?;
+        let account = manager.get_account_by_id(account_id).await?;
 
-        Self::sync_imap_account(app_handle, &account).await
+        let result = Self::sync_imap_account(app_handle, &account).await;
+
+        let pool = app_handle.state::<SqlitePool>();
+        let query = match &result {
+            Ok(_) => sqlx::query("UPDATE accounts SET last_synced_at = CURRENT_TIMESTAMP, last_sync_error = NULL WHERE id = ?"),
+            Err(e) => sqlx::query("UPDATE accounts SET last_sync_error = ? WHERE id = ?").bind(e.clone()),
+        };
+        let _ = query.bind(account_id).execute(&*pool).await;
+
+        result
     }
 
     async fn sync_imap_account(app_handle: &tauri::AppHandle<R>, account: &Account) -> Result<(), String> {
@@ -654,10 +1482,16 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
         let engine = app_handle.state::<SyncEngine<R>>();
         let backend = engine.get_backend(account_id).await?;
-        let folders = backend.list_folders().await.map_err(|e| e.to_string())?;
-
         let context = (*backend.context).clone();
 
+        // The client is taken before the timeout starts, IDLE and an open email may hold the others
+        let mut client = context.client().await;
+        let folders = engine
+            .with_timeout(account_id, client.list_all_mailboxes(&context.account_config))
+            .await?
+            .map_err(|e| e.to_string())?;
+        drop(client);
+
         for folder in folders {
             let name_lower = folder.name.to_lowercase();
             let role = if folder.is_inbox() {
@@ -679,7 +1513,7 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 
             let mut client = context.client().await;
             info!("Syncing revamped folder: {} as {:?} for {}", folder.name, role, account.email());
-            let folder_data = client.select_mailbox(&folder.name).await.map_err(|e| {
+            let folder_data = engine.with_timeout(account_id, client.select_mailbox(&folder.name)).await?.map_err(|e| {
                 error!("Failed to select mailbox {}: {}", folder.name, e);
                 e.to_string()
             })?;
@@ -693,9 +1527,11 @@ impl<R: tauri::Runtime> SyncEngine<R> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::test_utils::setup_test_db;
+    use crate::email_backend::emails::body_structure::MessagePart;
+    use crate::utils::test_utils::{add_mock_account, insert_test_account, insert_test_folder, mock_message, setup_test_app, setup_test_db, MockMailServer};
     use tauri::test::mock_builder;
     use email::envelope::{Envelope, Envelopes, Address};
+    use email::flag::Flag;
     use chrono::Utc;
     use tauri::Manager;
 
@@ -735,10 +1571,30 @@ mod tests {
         let app = mock_builder().build(tauri::generate_context!()).unwrap();
         app.manage(pool.clone());
 
-        SyncEngine::save_envelopes(&app.handle(), account_id, folder_id, envelopes, false)
+        let layouts = HashMap::from([("1".to_string(), MessageLayout {
+            attachments: vec![MessagePart {
+                section: "2".to_string(),
+                mime_type: "application/pdf".to_string(),
+                charset: None,
+                encoding: "base64".to_string(),
+                filename: Some("invoice.pdf".to_string()),
+                size: 4096,
+                content_id: None,
+                is_inline: false,
+            }],
+            ..Default::default()
+        })]);
+
+        SyncEngine::save_envelopes(&app.handle(), account_id, folder_id, envelopes, &layouts, false)
             .await
             .expect("Failed to save envelopes");
 
+        let attachment: (String, String) = sqlx::query_as("SELECT filename, section FROM attachments")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(attachment, ("invoice.pdf".to_string(), "2".to_string()));
+
         let has_attachments: bool = sqlx::query_scalar("SELECT has_attachments FROM emails WHERE remote_id = '1'")
             .fetch_one(&pool)
             .await
@@ -746,4 +1602,159 @@ mod tests {
 
         assert!(has_attachments, "has_attachments should be true");
     }
+
+    #[tokio::test]
+    async fn test_save_envelopes_skips_second_copy_of_sent_message() {
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let pool = app.state::<SqlitePool>().inner().clone();
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "Sent", Some("sent")).await;
+
+        let envelopes: Envelopes = ["1", "2"].into_iter().map(|uid| {
+            let mut envelope = Envelope::default();
+            envelope.id = uid.to_string();
+            envelope.message_id = "<reply@example.com>".to_string();
+            envelope.subject = "Re: Plans".to_string();
+            envelope.from = Address::new(None, "me@example.com".to_string());
+            envelope.date = Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap());
+            envelope
+        }).collect();
+
+        SyncEngine::save_envelopes(app.handle(), account_id, folder_id, envelopes, &HashMap::new(), true)
+            .await
+            .expect("Failed to save envelopes");
+
+        let copies: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE folder_id = ?")
+            .bind(folder_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(copies, 1);
+    }
+
+    #[tokio::test]
+    async fn test_save_envelopes_leaves_unchanged_rows_alone() {
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let pool = app.state::<SqlitePool>().inner().clone();
+        let account_id = insert_test_account(&pool).await;
+        let folder_id = insert_test_folder(&pool, account_id, "Archive", Some("archive")).await;
+        sqlx::query("CREATE TABLE email_updates (id INTEGER)").execute(&pool).await.unwrap();
+        sqlx::query("CREATE TRIGGER count_email_updates AFTER UPDATE ON emails BEGIN INSERT INTO email_updates VALUES (new.id); END")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let envelope = |flags: &[Flag]| -> Envelopes {
+            let mut envelope = Envelope::default();
+            envelope.id = "1".to_string();
+            envelope.message_id = "<plans@example.com>".to_string();
+            envelope.subject = "Plans".to_string();
+            envelope.from = Address::new(None, "alice@example.com".to_string());
+            envelope.date = Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap());
+            envelope.flags = flags.iter().cloned().collect();
+            std::iter::once(envelope).collect()
+        };
+
+        let first = SyncEngine::save_envelopes(app.handle(), account_id, folder_id, envelope(&[Flag::Seen]), &HashMap::new(), false).await.unwrap();
+        // Insert triggers may touch the new row too
+        let after_insert: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM email_updates").fetch_one(&pool).await.unwrap();
+
+        let again = SyncEngine::save_envelopes(app.handle(), account_id, folder_id, envelope(&[Flag::Seen]), &HashMap::new(), false).await.unwrap();
+        assert_eq!(first, again);
+        let updates: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM email_updates").fetch_one(&pool).await.unwrap();
+        assert_eq!(updates, after_insert);
+
+        SyncEngine::save_envelopes(app.handle(), account_id, folder_id, envelope(&[Flag::Seen, Flag::Flagged]), &HashMap::new(), false).await.unwrap();
+        let updates: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM email_updates").fetch_one(&pool).await.unwrap();
+        assert!(updates > after_insert);
+    }
+
+    #[test]
+    fn test_notified_messages_alert_once() {
+        let mut notified = NotifiedMessages::default();
+        assert!(notified.insert((1, "<a@example.com>".to_string())));
+        assert!(!notified.insert((1, "<a@example.com>".to_string())));
+        assert!(notified.insert((2, "<a@example.com>".to_string())));
+
+        for i in 0..NOTIFIED_CAPACITY {
+            notified.insert((1, format!("<{}@example.com>", i)));
+        }
+        assert!(notified.insert((1, "<a@example.com>".to_string())));
+        assert_eq!(notified.order.len(), NOTIFIED_CAPACITY);
+    }
+
+    #[test]
+    fn test_normalize_subject_strips_international_prefixes() {
+        assert_eq!(normalize_subject("Re: Fwd: Budget"), "budget");
+        assert_eq!(normalize_subject("AW: WG: Angebot"), "angebot");
+        assert_eq!(normalize_subject("SV: Möte"), "möte");
+        assert_eq!(normalize_subject("TR : RE : Réunion"), "réunion");
+        assert_eq!(normalize_subject("回复：会议"), "会议");
+    }
+
+    #[test]
+    fn test_normalize_subject_handles_counters_and_brackets() {
+        assert_eq!(normalize_subject("Re[2]: Re^3: re(4): Plan"), "plan");
+        assert_eq!(normalize_subject("[Fwd: Re: Plan]"), "plan");
+        assert_eq!(normalize_subject("Re: [dev] Plan"), "[dev] plan");
+        assert_eq!(normalize_subject("Note: the plan"), "note: the plan");
+        assert_eq!(normalize_subject("Re[x]: Plan"), "re[x]: plan");
+    }
+
+    #[tokio::test]
+    async fn test_sync_account_from_mock_server() {
+        let server = MockMailServer::start().await;
+        server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Quarterly numbers", "<q1@example.com>", "See attached"), &["\\Seen"]);
+        server.add_message("INBOX", &mock_message("Bob <bob@example.com>", "Lunch?", "<lunch@example.com>", "Noon works"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let pool = app.state::<SqlitePool>();
+        let emails: Vec<(String, String, String, Option<String>)> = sqlx::query_as(
+            "SELECT e.subject, e.sender_address, e.flags, e.snippet FROM emails e JOIN folders f ON e.folder_id = f.id WHERE f.role = 'inbox' ORDER BY e.remote_id"
+        )
+        .fetch_all(&*pool)
+        .await
+        .unwrap();
+
+        assert_eq!(emails.len(), 2);
+        assert_eq!(emails[0].0, "Quarterly numbers");
+        assert_eq!(emails[0].1, "alice@example.com");
+        assert!(emails[0].2.contains("seen"));
+        assert_eq!(emails[1].0, "Lunch?");
+        assert!(!emails[1].2.contains("seen"));
+        // Previews are there without waiting for the indexer
+        assert_eq!(emails[0].3.as_deref().map(str::trim), Some("See attached"));
+        assert_eq!(emails[1].3.as_deref().map(str::trim), Some("Noon works"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_picks_up_mail_read_elsewhere() {
+        let server = MockMailServer::start().await;
+        let read_elsewhere = server.add_message("INBOX", &mock_message("Alice <alice@example.com>", "Read on the phone", "<phone@example.com>", "Hi"), &[]);
+        server.add_message("INBOX", &mock_message("Bob <bob@example.com>", "Still unread", "<unread@example.com>", "Hi"), &[]);
+
+        let (app, _dir) = setup_test_app(setup_test_db().await).await;
+        let account = add_mock_account(&app, &server).await;
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        server.set_flags("INBOX", read_elsewhere, &["\\Seen"]);
+        SyncEngine::sync_account(app.handle(), &account).await.expect("Sync failed");
+
+        let pool = app.state::<SqlitePool>();
+        let flags: String = sqlx::query_scalar("SELECT flags FROM emails WHERE message_id = '<phone@example.com>'")
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+        assert!(flags.contains("seen"));
+
+        let unread: i64 = sqlx::query_scalar("SELECT unread_count FROM folders WHERE role = 'inbox'")
+            .fetch_one(&*pool)
+            .await
+            .unwrap();
+        assert_eq!(unread, 1);
+    }
 }
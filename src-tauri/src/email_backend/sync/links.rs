@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+/// Links kept per email, newsletters can easily carry hundreds.
+const MAX_LINKS_PER_EMAIL: usize = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedLink {
+    pub url: String,
+    pub domain: String,
+    pub title: Option<String>,
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    decode_entities(text.split_whitespace().collect::<Vec<_>>().join(" ").as_str())
+}
+
+/// Only web links a person would want to find again, not mailto:, tracking pixels or unsubscribe links.
+fn to_link(raw: &str, title: Option<String>) -> Option<ExtractedLink> {
+    let url = url::Url::parse(raw.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") || raw.len() > 2048 {
+        return None;
+    }
+    if raw.to_lowercase().contains("unsubscribe") {
+        return None;
+    }
+    let domain = url.host_str()?.trim_start_matches("www.").to_string();
+    Some(ExtractedLink { url: url.to_string(), domain, title: title.filter(|t| !t.is_empty() && t != raw) })
+}
+
+fn links_in_html(html: &str) -> Vec<ExtractedLink> {
+    // ASCII lowercasing keeps byte offsets identical between `lower` and `html`
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = lower[pos..].find("href=") {
+        let start = pos + found + 5;
+        let quote = html[start..].chars().next();
+        let (value_start, end_char) = match quote {
+            Some(q @ ('"' | '\'')) => (start + 1, q),
+            _ => (start, ' '),
+        };
+        let value_end = html[value_start..]
+            .find(|c: char| c == end_char || c == '>' || (end_char == ' ' && c.is_whitespace()))
+            .map(|i| value_start + i)
+            .unwrap_or(html.len());
+        let href = decode_entities(&html[value_start..value_end]);
+
+        // Anchor text runs from the end of the opening tag to the closing </a>
+        let title = lower[value_end..].find('>').and_then(|tag_end| {
+            let text_start = value_end + tag_end + 1;
+            lower[text_start..].find("</a").map(|close| strip_tags(&html[text_start..text_start + close]))
+        });
+
+        links.extend(to_link(&href, title));
+        pos = value_end;
+    }
+    links
+}
+
+fn links_in_text(text: &str) -> Vec<ExtractedLink> {
+    text.split(|c: char| c.is_whitespace() || "<>\"'()[]".contains(c))
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .filter_map(|word| to_link(word.trim_end_matches(['.', ',', ';', ':', '!', '?']), None))
+        .collect()
+}
+
+/// Links of an email, from the HTML part when there is one so anchor texts can serve as titles.
+pub fn extract_links(body_text: Option<&str>, body_html: Option<&str>) -> Vec<ExtractedLink> {
+    let links = match (body_html, body_text) {
+        (Some(html), _) => links_in_html(html),
+        (None, Some(text)) => links_in_text(text),
+        (None, None) => Vec::new(),
+    };
+
+    let mut seen = HashSet::new();
+    links
+        .into_iter()
+        .filter(|link| seen.insert(link.url.clone()))
+        .take(MAX_LINKS_PER_EMAIL)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links_from_html() {
+        let html = r#"<p>See <a href="https://www.example.com/doc?a=1&amp;b=2"><b>the doc</b></a>,
+            <a href='mailto:me@example.com'>mail</a> and <a href="https://lists.example.com/unsubscribe?u=1">stop</a>.
+            Again: <A HREF="https://www.example.com/doc?a=1&amp;b=2">same</A></p>"#;
+
+        let links = extract_links(None, Some(html));
+        assert_eq!(links, vec![ExtractedLink {
+            url: "https://www.example.com/doc?a=1&b=2".to_string(),
+            domain: "example.com".to_string(),
+            title: Some("the doc".to_string()),
+        }]);
+    }
+
+    #[test]
+    fn test_extract_links_from_text() {
+        let links = extract_links(Some("Slides: https://example.org/slides.pdf. Repo (https://git.example.org/x)!"), None);
+        let urls: Vec<&str> = links.iter().map(|l| l.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.org/slides.pdf", "https://git.example.org/x"]);
+    }
+}
@@ -1,5 +1,12 @@
 pub mod engine;
 pub mod worker;
+pub mod commands;
+pub mod bounce;
+pub mod links;
+pub mod monitor;
+pub mod schedule;
+pub mod scheduler;
+pub mod throttle;
 
 pub use engine::SyncEngine;
 pub use worker::SyncWorker;
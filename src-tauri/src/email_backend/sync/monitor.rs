@@ -0,0 +1,55 @@
+use std::net::{IpAddr, UdpSocket};
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+use crate::email_backend::sync::SyncEngine;
+
+const CHECK_EVERY: Duration = Duration::from_secs(15);
+
+/// Wall clock time beyond the check interval that means the machine was asleep.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Local address the OS would route internet traffic from, `None` when offline. IPv6 is
+/// tried when there is no IPv4 route, an IPv6-only network is online too.
+///
+/// Connecting a UDP socket only picks a route, nothing is sent. A different address means
+/// the machine changed networks (Wi-Fi to Ethernet, VPN up or down, a new hotspot).
+fn route_address() -> Option<IpAddr> {
+    probe("0.0.0.0:0", "1.1.1.1:53").or_else(|| probe("[::]:0", "[2606:4700:4700::1111]:53"))
+}
+
+fn probe(bind: &str, target: &str) -> Option<IpAddr> {
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(target).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+/// Watches for system wake and network changes and reconnects every account when either happens.
+///
+/// There is no portable sleep notification, but timers don't run while the machine sleeps: a
+/// check that wakes up long after it should have means the system was suspended in between.
+pub fn spawn<R: tauri::Runtime>(engine: SyncEngine<R>) {
+    tauri::async_runtime::spawn(async move {
+        let mut address = route_address();
+
+        loop {
+            let before = SystemTime::now();
+            sleep(CHECK_EVERY).await;
+            let slept = SystemTime::now()
+                .duration_since(before)
+                .map(|elapsed| elapsed > CHECK_EVERY + SLEEP_THRESHOLD)
+                .unwrap_or(false);
+
+            let current = route_address();
+            let network_changed = current != address;
+            address = current;
+
+            // Connections from before a suspend are dead even when the network isn't back yet,
+            // or the probe missed it. Otherwise, offline, the IDLE loops retry on their own.
+            if slept {
+                engine.reconnect_all("system wake").await;
+            } else if network_changed && current.is_some() {
+                engine.reconnect_all("network change").await;
+            }
+        }
+    });
+}
@@ -0,0 +1,37 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use crate::db::settings::Settings;
+
+/// Emitted once a mobile foreground sync is done, the worker runs its jobs as one batch then.
+pub const FOREGROUND_SYNC_FINISHED: &str = "foreground-sync-finished";
+
+/// Foreground syncs closer together than this are skipped, app switching fires focus events often.
+pub const MIN_FOREGROUND_SYNC_SECS: u64 = 60;
+
+/// How the engine keeps mailboxes fresh.
+///
+/// Desktop keeps a process alive with an IDLE connection per account and a periodic timer.
+/// Mobile only syncs while the app is in the foreground, closes its connections once done and
+/// leaves background jobs to a single batch after each sync, so a suspended app costs nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    Desktop,
+    Mobile,
+}
+
+impl SyncMode {
+    /// `syncMode` is `auto`, `desktop` or `mobile`; `auto` follows the platform of the build.
+    pub fn from_settings(settings: &Settings) -> Self {
+        match settings.sync_mode.as_str() {
+            "desktop" => SyncMode::Desktop,
+            "mobile" => SyncMode::Mobile,
+            _ if cfg!(mobile) => SyncMode::Mobile,
+            _ => SyncMode::Desktop,
+        }
+    }
+
+    pub async fn current(pool: &SqlitePool) -> Self {
+        Self::from_settings(&Settings::load(pool).await.unwrap_or_default())
+    }
+}
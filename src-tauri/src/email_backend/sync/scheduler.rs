@@ -0,0 +1,150 @@
+use rand::Rng;
+use serde::Serialize;
+use std::future::Future;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tokio::sync::Notify;
+use crate::email_backend::sync::SyncEngine;
+
+/// Intervals are spread by up to this fraction either way, so jobs started together drift apart.
+const JITTER: f64 = 0.1;
+
+/// What a run returns: `Some` to come back after that delay instead of the usual interval,
+/// for jobs that know there is a backlog.
+pub type JobResult = Result<Option<Duration>, String>;
+
+/// A job's state, returned by `get_background_jobs`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackgroundJob {
+    pub name: &'static str,
+    pub interval_secs: u64,
+    pub running: bool,
+    pub runs: u64,
+    pub last_started_at: Option<String>,
+    pub last_duration_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub next_run_at: Option<String>,
+}
+
+/// Runs the worker's background jobs, each in its own loop. A run starts only after the previous
+/// one finished, so a job never overlaps itself however long it takes.
+#[derive(Default, Clone)]
+pub struct JobScheduler {
+    jobs: Arc<Mutex<Vec<BackgroundJob>>>,
+    wakers: Arc<Mutex<HashMap<&'static str, Arc<Notify>>>>,
+}
+
+fn jittered(interval: Duration) -> Duration {
+    let factor = rand::thread_rng().gen_range(1.0 - JITTER..=1.0 + JITTER);
+    interval.mul_f64(factor)
+}
+
+impl JobScheduler {
+    fn update(&self, name: &str, change: impl FnOnce(&mut BackgroundJob)) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if let Some(job) = jobs.iter_mut().find(|j| j.name == name) {
+                change(job);
+            }
+        }
+    }
+
+    /// Starts `job` after `initial_delay`, then every `interval`. Runs hold a sync work guard,
+    /// so quitting waits for them, and the loop ends once shutdown has begun.
+    pub fn schedule<R, F, Fut>(&self, app_handle: &tauri::AppHandle<R>, name: &'static str, initial_delay: Duration, interval: Duration, job: F)
+    where
+        R: tauri::Runtime,
+        F: Fn(tauri::AppHandle<R>) -> Fut + Send + 'static,
+        Fut: Future<Output = JobResult> + Send,
+    {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            if jobs.iter().any(|j| j.name == name) {
+                log::warn!("Background job {} is already scheduled", name);
+                return;
+            }
+            jobs.push(BackgroundJob {
+                name,
+                interval_secs: interval.as_secs(),
+                running: false,
+                runs: 0,
+                last_started_at: None,
+                last_duration_ms: None,
+                last_error: None,
+                next_run_at: None,
+            });
+        }
+
+        let waker = Arc::new(Notify::new());
+        if let Ok(mut wakers) = self.wakers.lock() {
+            wakers.insert(name, waker.clone());
+        }
+
+        let scheduler = self.clone();
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut delay = initial_delay;
+            loop {
+                let next_run = chrono::Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+                scheduler.update(name, |j| j.next_run_at = Some(next_run.to_rfc3339()));
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = waker.notified() => {}
+                }
+
+                let Some(_work) = app_handle.state::<SyncEngine<R>>().begin_work().await else { break };
+                scheduler.update(name, |j| {
+                    j.running = true;
+                    j.next_run_at = None;
+                    j.last_started_at = Some(chrono::Utc::now().to_rfc3339());
+                });
+
+                let started = Instant::now();
+                let result = job(app_handle.clone()).await;
+                if let Err(e) = &result {
+                    log::error!("Background job {} failed: {}", name, e);
+                }
+
+                scheduler.update(name, |j| {
+                    j.running = false;
+                    j.runs += 1;
+                    j.last_duration_ms = Some(started.elapsed().as_millis() as u64);
+                    j.last_error = result.as_ref().err().cloned();
+                });
+                delay = jittered(result.ok().flatten().unwrap_or(interval));
+            }
+            scheduler.update(name, |j| j.next_run_at = None);
+        });
+    }
+
+    /// Runs `name` now instead of at its next interval, or right after the current run.
+    pub fn wake(&self, name: &str) {
+        if let Some(waker) = self.wakers.lock().ok().and_then(|w| w.get(name).cloned()) {
+            waker.notify_one();
+        }
+    }
+
+    pub fn jobs(&self) -> Vec<BackgroundJob> {
+        let mut jobs = self.jobs.lock().map(|jobs| jobs.clone()).unwrap_or_default();
+        jobs.sort_by_key(|j| j.name);
+        jobs
+    }
+}
+
+#[tauri::command]
+pub async fn get_background_jobs<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Vec<BackgroundJob>, String> {
+    Ok(app_handle.try_state::<JobScheduler>().map(|s| s.jobs()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        for _ in 0..100 {
+            let delay = jittered(Duration::from_secs(100));
+            assert!(delay >= Duration::from_secs(90) && delay <= Duration::from_secs(110));
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+use rand::Rng;
+
+/// Throttled fetches in a row before the folder sync gives up until the next sync, which
+/// resumes from the full sync checkpoint.
+pub const MAX_RETRIES: u32 = 6;
+
+const BASE_DELAY: Duration = Duration::from_secs(2);
+const MAX_DELAY: Duration = Duration::from_secs(120);
+const MIN_BATCH_SIZE: u32 = 10;
+
+/// Whether an IMAP error is the server asking to slow down rather than a real failure.
+///
+/// Gmail answers `NO [THROTTLED]` under load, and `[UNAVAILABLE]`/"Temporary System Problem"
+/// for short outages that clear the same way. Pass the `Debug` output, `Display` only shows
+/// the outermost error and not the server's response.
+pub fn is_throttled(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["throttled", "[unavailable]", "temporary system problem", "try again later", "too many simultaneous"]
+        .iter()
+        .any(|marker| error.contains(marker))
+}
+
+/// Exponential backoff for the `attempt`th retry (from 1), jittered down to half of it so
+/// accounts throttled together don't come back together.
+pub fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(MAX_DELAY);
+    delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+/// Halves the batch after a throttle, fewer envelopes per FETCH keeps each request cheap.
+pub fn reduce(batch_size: u32) -> u32 {
+    (batch_size / 2).max(MIN_BATCH_SIZE.min(batch_size))
+}
+
+/// Grows the batch back towards the configured size once fetches succeed again.
+pub fn recover(batch_size: u32, configured: u32) -> u32 {
+    (batch_size + batch_size / 4 + 1).min(configured)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_detection_and_batch_sizing() {
+        assert!(is_throttled("Error: NO [THROTTLED] Account exceeded command or bandwidth limits"));
+        assert!(is_throttled("NO [UNAVAILABLE] Temporary System Problem. Try again later"));
+        assert!(!is_throttled("NO [AUTHENTICATIONFAILED] Invalid credentials"));
+
+        assert!(backoff(1) <= BASE_DELAY && backoff(1) >= BASE_DELAY / 2);
+        assert!(backoff(30) <= MAX_DELAY);
+
+        assert_eq!(reduce(500), 250);
+        assert_eq!(reduce(12), 10);
+        assert_eq!(reduce(4), 4);
+        assert_eq!(recover(10, 500), 13);
+        assert_eq!(recover(480, 500), 500);
+    }
+}
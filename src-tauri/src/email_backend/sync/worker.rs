@@ -1,14 +1,47 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use tauri::{Manager, Emitter};
+use tauri::{Manager, Emitter, Listener};
 use crate::email_backend::emails::events::EmailEvent;
+use crate::email_backend::emails::{needs_reply, retention, stacks, tasks};
 use log::{info, error};
+use mail_parser::MimeHeaders;
 use sqlx::SqlitePool;
+use crate::db::settings::{Settings, SettingChanged};
+use crate::db::writer::WritePool;
 use tokio::time::sleep;
 
-use crate::email_backend::sync::SyncEngine;
+use crate::email_backend::sync::{bounce, links, SyncEngine};
+use crate::email_backend::sync::scheduler::JobScheduler;
+use crate::email_backend::sync::schedule::{SyncMode, FOREGROUND_SYNC_FINISHED};
+use crate::email_backend::emails::{body_limits, body_structure, calendar, confidential};
+use crate::email_backend::emails::commands as email_commands;
 use email::envelope::Id;
+use email::imap::ImapClient;
+use imap_client::imap_next::imap_types::sequence::Sequence;
 use email::message::get::GetMessages;
+use std::num::NonZeroU32;
+
+const MOBILE_INDEX_ROUNDS: usize = 10;
+
+/// Bytes of the text part fetched for a snippet, plenty for 200 characters even after
+/// transfer decoding and stripping HTML.
+const SNIPPET_FETCH_BYTES: u32 = 10240;
+
+pub(crate) fn snippet(text: &str) -> String {
+    let s = text.chars().take(200).collect::<String>();
+    s.replace('\n', " ").replace('\r', "")
+}
+
+/// What `fetch_snippet` read from the server for an email that wasn't opened yet.
+struct SnippetParts {
+    layout: body_structure::MessageLayout,
+    body_text: Option<String>,
+    /// Only for an HTML-only message, whose links are in the tags the text leaves out
+    body_html: Option<String>,
+    invite: Option<Vec<u8>>,
+}
 
 pub struct SyncWorker<R: tauri::Runtime> {
     app_handle: tauri::AppHandle<R>,
@@ -24,59 +57,159 @@ impl<R: tauri::Runtime> SyncWorker<R> {
     pub async fn start(&self) {
         info!("Starting Sync Worker...");
 
-        let app_handle = self.app_handle.clone();
-        tokio::spawn(async move {
-            loop {
-                // Indexing
-                if let Err(e) = Self::index_pending_emails(&app_handle).await {
-                    error!("Error during background indexing: {}", e);
-                }
-                sleep(Duration::from_secs(10)).await;
-
-                // Thread Resolution
-                let app_handle_threading = app_handle.clone();
-                tokio::spawn(async move {
-                    let pool = app_handle_threading.state::<SqlitePool>();
-                    let backlog_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE thread_id = message_id AND normalized_subject IS NOT NULL AND normalized_subject != ''")
-                        .fetch_one(&*pool)
-                        .await
-                        .unwrap_or(0);
+        self.listen_for_settings_changes();
 
-                    let sleep_time = if backlog_count > 1000 { 5 } else { 30 };
-                    let batch_size = if backlog_count > 1000 { 2000 } else { 100 };
+        if SyncMode::current(&self.pool).await == SyncMode::Mobile {
+            self.run_after_foreground_syncs();
+            return;
+        }
 
-                    if let Err(e) = Self::resolve_threads(&app_handle_threading, batch_size).await {
-                        error!("Error during background threading: {}", e);
-                    }
-                    sleep(Duration::from_secs(sleep_time)).await;
-                });
+        let scheduler = self.app_handle.state::<JobScheduler>();
+        let hour = Duration::from_secs(3600);
 
-                // Proactive Enrichment
-                let app_handle_enrichment = app_handle.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = crate::email_backend::enrichment::commands::proactive_enrichment(&app_handle_enrichment).await {
-                        error!("Error during background enrichment: {}", e);
-                    }
-                    sleep(Duration::from_secs(120)).await;
-                });
+        // Retention rules and reply reminders, hourly is plenty for rules counted in days
+        scheduler.schedule(&self.app_handle, "retention", Duration::from_secs(300), hour, |app_handle| async move {
+            retention::apply_retention_rules(&app_handle).await?;
+            Ok(None)
+        });
+        scheduler.schedule(&self.app_handle, "trash_retention", Duration::from_secs(300), hour, |app_handle| async move {
+            retention::empty_expired_trash(&app_handle).await?;
+            Ok(None)
+        });
+        scheduler.schedule(&self.app_handle, "reply_later_nudges", Duration::from_secs(300), hour, |app_handle| async move {
+            stacks::nudge_stale_reply_later(&app_handle).await?;
+            Ok(None)
+        });
+        scheduler.schedule(&self.app_handle, "needs_reply_nudges", Duration::from_secs(300), hour, |app_handle| async move {
+            needs_reply::nudge_unanswered(&app_handle).await?;
+            Ok(None)
+        });
 
-                // Proactive Summarization
-                let app_handle_summarization = app_handle.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = Self::proactive_summarization(&app_handle_summarization).await {
-                        error!("Error during background summarization: {}", e);
-                    }
-                    sleep(Duration::from_secs(120)).await;
-                });
+        // Task reminders need to fire close to their due time
+        scheduler.schedule(&self.app_handle, "task_reminders", Duration::ZERO, Duration::from_secs(60), |app_handle| async move {
+            tasks::notify_due_tasks(&app_handle).await?;
+            Ok(None)
+        });
 
-                // Contact Sync
-                let app_handle_contacts = app_handle.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = crate::email_backend::enrichment::commands::sync_contacts_internal(&app_handle_contacts).await {
-                        error!("Error during background contact sync: {}", e);
-                    }
-                    sleep(Duration::from_secs(1800)).await; // Sync every 30 minutes
-                });
+        scheduler.schedule(&self.app_handle, "indexing", Duration::ZERO, Duration::from_secs(10), |app_handle| async move {
+            Self::index_pending_emails(&app_handle).await?;
+            Ok(None)
+        });
+
+        // Comes back sooner while there is a backlog, with bigger batches
+        scheduler.schedule(&self.app_handle, "threading", Duration::from_secs(10), Duration::from_secs(30), |app_handle| async move {
+            let backlog_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM emails WHERE thread_id = message_id AND normalized_subject IS NOT NULL AND normalized_subject != ''")
+                .fetch_one(&*app_handle.state::<SqlitePool>())
+                .await
+                .unwrap_or(0);
+            let batch_size = if backlog_count > 1000 { 2000 } else { 100 };
+
+            Self::resolve_threads(&app_handle, batch_size).await?;
+            Ok((backlog_count > 1000).then(|| Duration::from_secs(5)))
+        });
+
+        scheduler.schedule(&self.app_handle, "enrichment", Duration::from_secs(10), Duration::from_secs(120), |app_handle| async move {
+            crate::email_backend::enrichment::commands::proactive_enrichment(&app_handle).await?;
+            Ok(None)
+        });
+        scheduler.schedule(&self.app_handle, "summarization", Duration::from_secs(10), Duration::from_secs(120), |app_handle| async move {
+            Self::proactive_summarization(&app_handle).await?;
+            Ok(None)
+        });
+        scheduler.schedule(&self.app_handle, "contact_sync", Duration::from_secs(10), Duration::from_secs(1800), |app_handle| async move {
+            crate::email_backend::enrichment::commands::sync_contacts_internal(&app_handle).await?;
+            Ok(None)
+        });
+    }
+
+    /// Mobile has no long running loops, every job runs once after each foreground sync.
+    fn run_after_foreground_syncs(&self) {
+        let app_handle = self.app_handle.clone();
+        let running = Arc::new(AtomicBool::new(false));
+        self.app_handle.listen(FOREGROUND_SYNC_FINISHED, move |_| {
+            if running.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let app_handle = app_handle.clone();
+            let running = running.clone();
+            tauri::async_runtime::spawn(async move {
+                Self::run_batch(&app_handle).await;
+                app_handle.state::<SyncEngine<R>>().close_connections().await;
+                running.store(false, Ordering::SeqCst);
+            });
+        });
+    }
+
+    async fn run_batch(app_handle: &tauri::AppHandle<R>) {
+        let Some(_work) = app_handle.state::<SyncEngine<R>>().begin_work().await else { return };
+        info!("Running background batch after foreground sync");
+
+        // Indexing takes 20 emails a round, stop early rather than keep the radio busy
+        for _ in 0..MOBILE_INDEX_ROUNDS {
+            if let Err(e) = Self::index_pending_emails(app_handle).await {
+                error!("Error during batch indexing: {}", e);
+                break;
+            }
+        }
+        if let Err(e) = Self::resolve_threads(app_handle, 2000).await {
+            error!("Error during batch threading: {}", e);
+        }
+        if let Err(e) = crate::email_backend::enrichment::commands::proactive_enrichment(app_handle).await {
+            error!("Error during batch enrichment: {}", e);
+        }
+        if let Err(e) = Self::proactive_summarization(app_handle).await {
+            error!("Error during batch summarization: {}", e);
+        }
+        if let Err(e) = retention::apply_retention_rules(app_handle).await {
+            error!("Error applying retention rules: {}", e);
+        }
+        if let Err(e) = retention::empty_expired_trash(app_handle).await {
+            error!("Error emptying expired trash: {}", e);
+        }
+        if let Err(e) = stacks::nudge_stale_reply_later(app_handle).await {
+            error!("Error sending reply later reminders: {}", e);
+        }
+        if let Err(e) = needs_reply::nudge_unanswered(app_handle).await {
+            error!("Error sending reminders about unanswered mail: {}", e);
+        }
+        if let Err(e) = tasks::notify_due_tasks(app_handle).await {
+            error!("Error sending task reminders: {}", e);
+        }
+    }
+
+    /// Kicks the relevant background job right away instead of waiting for its next tick
+    fn listen_for_settings_changes(&self) {
+        let app_handle = self.app_handle.clone();
+        self.app_handle.listen("settings-changed", move |event| {
+            let change = match serde_json::from_str::<SettingChanged>(event.payload()) {
+                Ok(c) => c,
+                Err(_) => return,
+            };
+
+            let app_handle = app_handle.clone();
+            match change.key.as_str() {
+                "aiEnabled" | "aiSummarizationEnabled" | "aiApiKey" | "aiBaseUrl" | "aiModel" => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = Self::proactive_summarization(&app_handle).await {
+                            error!("Error during summarization after settings change: {}", e);
+                        }
+                    });
+                }
+                "aiSenderEnrichmentEnabled" | "enrichmentEnabled" | "offlineMode" => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::email_backend::enrichment::commands::proactive_enrichment(&app_handle).await {
+                            error!("Error during enrichment after settings change: {}", e);
+                        }
+                    });
+                }
+                "syncMonths" => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = Self::index_pending_emails(&app_handle).await {
+                            error!("Error during indexing after settings change: {}", e);
+                        }
+                    });
+                }
+                _ => {}
             }
         });
     }
@@ -85,17 +218,8 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         let pool = app_handle.state::<SqlitePool>();
 
         // Check if enabled
-        let ai_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
-        
-        let ai_summarization_enabled: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'aiSummarizationEnabled'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("false".to_string(),));
-
-        if ai_enabled.0 != "true" || ai_summarization_enabled.0 != "true" {
+        let settings = Settings::load(&pool).await.unwrap_or_default();
+        if !settings.ai_enabled || !settings.ai_summarization_enabled {
             return Ok(());
         }
 
@@ -187,17 +311,20 @@ impl<R: tauri::Runtime> SyncWorker<R> {
 
     async fn index_pending_emails(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
         let pool = app_handle.state::<SqlitePool>();
+        Self::prefetch_queued(app_handle, &pool).await;
 
-        let sync_months_setting: (String,) = sqlx::query_as("SELECT value FROM settings WHERE key = 'syncMonths'")
-            .fetch_one(&*pool)
-            .await
-            .unwrap_or(("3".to_string(),));
-        let sync_months = sync_months_setting.0.parse::<i32>().unwrap_or(3);
+        let sync_months = Settings::load(&pool).await.unwrap_or_default().sync_months as i32;
 
-        let mut query = "SELECT e.id, e.account_id, e.remote_id, f.path
+        // Indexing only fills in the snippet, full bodies are fetched when an email is opened.
+        // Confidential mail gets no snippet, it would be a piece of the body on disk.
+        let mut query = format!(
+            "SELECT e.id, e.account_id, e.remote_id, f.path, e.sender_address, e.subject
              FROM emails e
              JOIN folders f ON e.folder_id = f.id
-             WHERE e.body_text IS NULL AND f.role != 'trash' AND f.role != 'spam'".to_string();
+             WHERE e.snippet IS NULL AND e.body_text IS NULL AND f.role != 'trash' AND f.role != 'spam'
+               AND e.remote_id NOT LIKE 'moved:%' AND NOT {}",
+            confidential::uncached_condition("e")
+        );
 
         if sync_months > 0 {
             query.push_str(&format!(" AND datetime(e.date) > datetime('now', '-{} months')", sync_months));
@@ -205,7 +332,7 @@ impl<R: tauri::Runtime> SyncWorker<R> {
 
         query.push_str(" ORDER BY e.date DESC LIMIT 20");
 
-        let pending_emails: Vec<(i64, i64, String, String)> = sqlx::query_as(&query)
+        let pending_emails: Vec<(i64, i64, String, String, String, Option<String>)> = sqlx::query_as(&query)
             .fetch_all(&*pool)
             .await
             .map_err(|e| e.to_string())?;
@@ -216,32 +343,58 @@ impl<R: tauri::Runtime> SyncWorker<R> {
 
         info!("Background indexing {} emails...", pending_emails.len());
 
-        let mut by_account: HashMap<i64, Vec<(i64, String, String)>> = HashMap::new();
-        for (id, account_id, remote_id, folder_path) in pending_emails {
-            by_account.entry(account_id).or_default().push((id, remote_id, folder_path));
+        let mut by_account: HashMap<i64, Vec<(i64, String, String, bool)>> = HashMap::new();
+        for (id, account_id, remote_id, folder_path, sender_address, subject) in pending_emails {
+            // Delivery reports are parsed from the whole message, they are small anyway
+            let full = bounce::looks_like_bounce(&sender_address, subject.as_deref().unwrap_or(""));
+            by_account.entry(account_id).or_default().push((id, remote_id, folder_path, full));
         }
 
         for (account_id, emails) in by_account {
             let engine = app_handle.state::<SyncEngine<R>>();
-            let backend = match engine.get_backend(account_id).await {
-                Ok(b) => b,
+            let context = match engine.get_context(account_id).await {
+                Ok(c) => c,
                 Err(e) => {
-                    error!("Failed to build backend for account {}: {}", account_id, e);
+                    error!("Failed to connect account {}: {}", account_id, e);
                     continue;
                 }
             };
 
-            for (email_id, remote_id, folder_path) in emails {
-                let uids = Id::single(remote_id.clone());
-                
-                match backend.get_messages(&folder_path, &uids).await {
-                    Ok(messages) => {
-                        for message in messages.to_vec() {
-                            Self::save_message_parts(app_handle, email_id, message).await?;
+            for (email_id, remote_id, folder_path, full) in emails {
+                let Some(uid) = remote_id.parse::<u32>().ok().and_then(NonZeroU32::new) else {
+                    error!("Invalid UID {} of email {}", remote_id, email_id);
+                    continue;
+                };
+
+                // Taken before the timeout starts, IDLE or an open email may be holding the other connection
+                let mut client = context.client().await;
+                if full {
+                    let fetched = engine.with_timeout(account_id, async {
+                        client.select_mailbox(&folder_path).await?;
+                        client.fetch_messages(Sequence::from(uid).into()).await
+                    }).await;
+                    drop(client);
+                    match fetched.and_then(|messages| messages.map_err(|e| e.to_string())) {
+                        Ok(messages) => {
+                            for message in messages.to_vec() {
+                                Self::save_message_parts(app_handle, email_id, message).await?;
+                            }
+                        }
+                        Err(e) => {
+                            error!("Failed to fetch message uid {} for indexing: {}", remote_id, e);
                         }
                     }
-                    Err(e) => {
-                        error!("Failed to fetch message uid {} for indexing: {}", remote_id, e);
+                } else {
+                    let fetched = engine
+                        .with_timeout(account_id, Self::fetch_snippet(&mut client, uid, &folder_path))
+                        .await
+                        .and_then(|fetched| fetched);
+                    drop(client);
+                    if let Err(e) = match fetched {
+                        Ok(parts) => Self::save_snippet(app_handle, email_id, parts).await,
+                        Err(e) => Err(e),
+                    } {
+                        error!("Failed to fetch snippet of uid {} for indexing: {}", remote_id, e);
                     }
                 }
                 sleep(Duration::from_millis(100)).await;
@@ -251,10 +404,94 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         Ok(())
     }
 
+    /// Fetches the bodies queued by `prefetch_email_content`, unless the email was opened meanwhile.
+    async fn prefetch_queued(app_handle: &tauri::AppHandle<R>, pool: &SqlitePool) {
+        let engine = app_handle.state::<SyncEngine<R>>();
+        while let Some(email_id) = engine.next_prefetch().await {
+            let cached: bool = sqlx::query_scalar("SELECT body_text IS NOT NULL OR body_html IS NOT NULL FROM emails WHERE id = ?")
+                .bind(email_id)
+                .fetch_optional(pool)
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or(true);
+            if cached {
+                continue;
+            }
+            if let Err(e) = email_commands::cache_email_content(app_handle, email_id).await {
+                error!("Failed to prefetch email {}: {}", email_id, e);
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Fetches the structure and the first `SNIPPET_FETCH_BYTES` of the text part, rather
+    /// than the whole message, to fill in the snippet and the attachment list.
+    async fn fetch_snippet(client: &mut ImapClient, uid: NonZeroU32, folder_path: &str) -> Result<SnippetParts, String> {
+        client.examine_mailbox(folder_path).await.map_err(|e| e.to_string())?;
+        let layout = email_commands::fetch_layout(client, uid).await?;
+
+        // Plain text when there is one, mail_parser strips the tags of an HTML-only message
+        let (body_text, body_html) = match layout.text.as_ref().or(layout.html.as_ref()) {
+            Some(part) => {
+                let partial = Some((0, NonZeroU32::new(SNIPPET_FETCH_BYTES).unwrap()));
+                let raw = email_commands::fetch_section(client, uid, &part.section, partial).await?;
+                let raw = body_structure::complete_lines(&raw, SNIPPET_FETCH_BYTES as usize);
+                let decoded = body_structure::decode(part, raw);
+                let body_html = match part.mime_type.as_str() {
+                    "text/html" => decoded.body_html(0).map(|html| html.to_string()),
+                    _ => None,
+                };
+                (decoded.body_text(0).map(|text| text.to_string()), body_html)
+            }
+            None => (None, None),
+        };
+
+        // Invites are small, the whole calendar part is worth fetching for the agenda
+        let invite = match &layout.calendar {
+            Some(part) => Some(body_structure::decode_bytes(&part.encoding, &email_commands::fetch_section(client, uid, &part.section, None).await?)),
+            None => None,
+        };
+        Ok(SnippetParts { layout, body_text, body_html, invite })
+    }
+
+    async fn save_snippet(app_handle: &tauri::AppHandle<R>, email_id: i64, parts: SnippetParts) -> Result<(), String> {
+        let pool = app_handle.state::<SqlitePool>();
+        let SnippetParts { layout, body_text, body_html, invite } = parts;
+        let snippet = body_text.as_deref().map(snippet).unwrap_or_default();
+
+        let mut tx = app_handle.state::<WritePool>().begin().await?;
+        sqlx::query("UPDATE emails SET snippet = ?, has_attachments = ? WHERE id = ?")
+            .bind(&snippet)
+            .bind(layout.attachments.iter().any(|part| !part.is_inline))
+            .bind(email_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        email_commands::record_attachments(&mut *tx, email_id, &layout.attachments).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        // Confidential mail keeps neither links nor events, both are pieces of the body
+        if confidential::is_uncached(&pool, email_id).await? {
+            return Ok(());
+        }
+
+        // Only links in the fetched start of the body are found, the rest replace them once
+        // the email is opened and fetched in full
+        Self::save_links(&pool, email_id, links::extract_links(body_text.as_deref(), body_html.as_deref())).await;
+
+        if let Some(invite) = invite {
+            if let Err(e) = calendar::save_invite(&pool, email_id, &String::from_utf8_lossy(&invite), body_text.as_deref()).await {
+                error!("Failed to save the invite in email {}: {}", email_id, e);
+            }
+        }
+        Ok(())
+    }
+
     pub async fn index_specific_email(app_handle: &tauri::AppHandle<R>, email_id: i64) -> Result<(), String> {
         let pool = app_handle.state::<SqlitePool>();
-        let email_info: Option<(i64, String, String)> = sqlx::query_as(
-            "SELECT e.account_id, e.remote_id, f.path 
+        let email_info: Option<(i64, String, i64, String)> = sqlx::query_as(
+            "SELECT e.account_id, e.remote_id, e.folder_id, f.path 
              FROM emails e 
              JOIN folders f ON e.folder_id = f.id 
              WHERE e.id = ?"
@@ -264,9 +501,10 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         .await
         .map_err(|e| e.to_string())?;
 
-        if let Some((account_id, remote_id, folder_path)) = email_info {
+        if let Some((account_id, remote_id, folder_id, folder_path)) = email_info {
             let engine = app_handle.state::<SyncEngine<R>>();
             let backend = engine.get_backend(account_id).await?;
+            let remote_id = email_commands::server_remote_id(app_handle, email_id, account_id, folder_id, remote_id).await?;
             let uids = Id::single(remote_id.clone());
             
             match backend.get_messages(&folder_path, &uids).await {
@@ -281,43 +519,127 @@ impl<R: tauri::Runtime> SyncWorker<R> {
         Ok(())
     }
 
+    /// Links a bounce or DSN to the sent message it is about. Only a `multipart/report` or mail
+    /// that looks like a bounce by its sender or subject gets its raw text scanned.
+    async fn record_delivery_report(app_handle: &tauri::AppHandle<R>, email_id: i64, parsed: &mail_parser::Message<'_>) {
+        let is_report = parsed
+            .content_type()
+            .is_some_and(|ct| ct.ctype().eq_ignore_ascii_case("multipart") && ct.subtype().is_some_and(|s| s.eq_ignore_ascii_case("report")));
+        let pool = app_handle.state::<SqlitePool>();
+        let info: Option<(i64, String, Option<String>)> = sqlx::query_as("SELECT account_id, sender_address, subject FROM emails WHERE id = ?")
+            .bind(email_id)
+            .fetch_optional(&*pool)
+            .await
+            .unwrap_or(None);
+        let Some((account_id, sender_address, subject)) = info else { return };
+        let subject = subject.unwrap_or_default();
+        if !is_report && !bounce::looks_like_bounce(&sender_address, &subject) {
+            return;
+        }
+
+        let Some(report) = bounce::parse_report(&sender_address, &subject, parsed.raw_message()) else { return };
+
+        match bounce::apply_report(&pool, account_id, email_id, &report).await {
+            Ok(ids) if !ids.is_empty() => {
+                info!("Delivery report {} marks {} sent email(s) as {}", email_id, ids.len(), report.status.as_str());
+                let _ = app_handle.emit("delivery-status-changed", bounce::DeliveryStatusChanged {
+                    email_ids: ids,
+                    status: report.status,
+                    bounce_email_id: email_id,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to record delivery report {}: {}", email_id, e),
+        }
+    }
+
+    pub(crate) async fn save_links(pool: &SqlitePool, email_id: i64, extracted: Vec<links::ExtractedLink>) {
+        // Re-indexing replaces what an earlier pass found
+        let _ = sqlx::query("DELETE FROM links WHERE email_id = ?")
+            .bind(email_id)
+            .execute(pool)
+            .await;
+
+        for link in extracted {
+            let _ = sqlx::query("INSERT OR IGNORE INTO links (email_id, url, domain, title) VALUES (?, ?, ?, ?)")
+                .bind(email_id)
+                .bind(&link.url)
+                .bind(&link.domain)
+                .bind(&link.title)
+                .execute(pool)
+                .await
+                .map_err(|e| error!("Failed to save link for email {}: {}", email_id, e));
+        }
+    }
+
     async fn save_message_parts(app_handle: &tauri::AppHandle<R>, email_id: i64, message: &email::message::Message<'_>) -> Result<(), String> {
         let pool = app_handle.state::<SqlitePool>();
-        
-        // Save attachments if any
-        if let Ok(attachments) = message.attachments() {
-            for att in attachments {
+
+        if let Ok(parsed) = message.parsed() {
+            let parsed: &mail_parser::Message = parsed;
+
+            for att in parsed.attachments() {
+                let mime_type = att.content_type().map(|ct| match ct.subtype() {
+                    Some(subtype) => format!("{}/{}", ct.ctype(), subtype).to_lowercase(),
+                    None => ct.ctype().to_lowercase(),
+                });
+                let is_inline = body_structure::is_inline(
+                    mime_type.as_deref().unwrap_or_default(),
+                    att.content_disposition().map(|d| d.ctype()),
+                    att.content_id().is_some(),
+                );
                 let _ = sqlx::query(
-                    "INSERT INTO attachments (email_id, filename, mime_type, size)
-                     VALUES (?, ?, ?, ?)"
+                    "INSERT INTO attachments (email_id, filename, mime_type, size, content_id, is_inline)
+                     VALUES (?, ?, ?, ?, ?, ?)"
                 )
                 .bind(email_id)
-                .bind(&att.filename)
-                .bind(&att.mime)
-                .bind(att.body.len() as i64)
+                .bind(att.attachment_name())
+                .bind(&mime_type)
+                .bind(att.contents().len() as i64)
+                .bind(att.content_id().map(|id| id.trim_matches(['<', '>'])))
+                .bind(is_inline)
                 .execute(&*pool)
                 .await
                 .map_err(|e| error!("Failed to save attachment for email {}: {}", email_id, e));
             }
-        }
+            Self::record_delivery_report(app_handle, email_id, parsed).await;
 
-        if let Ok(parsed) = message.parsed() {
-            let parsed: &mail_parser::Message = parsed;
             let body_text: Option<String> = parsed.body_text(0).map(|b| b.to_string());
             let body_html: Option<String> = parsed.body_html(0).map(|b| b.to_string());
-            let snippet = body_text.as_ref().map(|t: &String| {
-                let s = t.chars().take(200).collect::<String>();
-                s.replace('\n', " ").replace('\r', "")
-            });
+            let snippet = body_text.as_deref().map(snippet);
+
+            let list_id = parsed.header_raw("List-Id").and_then(retention::parse_list_id);
+            let extracted_links = links::extract_links(body_text.as_deref(), body_html.as_deref());
+            if confidential::is_uncached(&pool, email_id).await? {
+                sqlx::query("UPDATE emails SET list_id = ? WHERE id = ?")
+                    .bind(list_id)
+                    .bind(email_id)
+                    .execute(&*pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            } else {
+                let (html_limit, text_limit) = body_limits::limits(&pool).await;
+                let cached = body_limits::cap(body_text.as_deref(), body_html.as_deref(), html_limit, text_limit);
+
+                let _ = sqlx::query("UPDATE emails SET body_text = ?, body_html = ?, body_truncated = ?, snippet = ?, list_id = ? WHERE id = ?")
+                    .bind(cached.body_text)
+                    .bind(cached.body_html)
+                    .bind(cached.truncated)
+                    .bind(snippet)
+                    .bind(list_id)
+                    .bind(email_id)
+                    .execute(&*pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
 
-            let _ = sqlx::query("UPDATE emails SET body_text = ?, body_html = ?, snippet = ? WHERE id = ?")
-                .bind(body_text)
-                .bind(body_html)
-                .bind(snippet)
-                .bind(email_id)
-                .execute(&*pool)
-                .await
-                .map_err(|e| e.to_string())?;
+                Self::save_links(&pool, email_id, extracted_links).await;
+
+                if let Some(invite) = calendar::invite_in(parsed) {
+                    if let Err(e) = calendar::save_invite(&pool, email_id, &invite, body_text.as_deref()).await {
+                        error!("Failed to save the invite in email {}: {}", email_id, e);
+                    }
+                }
+            }
         }
         Ok(())
     }
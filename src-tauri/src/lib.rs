@@ -1,9 +1,40 @@
-use crate::email_backend::accounts::commands::{login_with_google, login_with_microsoft, add_imap_smtp_account, get_accounts, remove_account, verify_imap_smtp_credentials};
-use crate::email_backend::emails::commands::{get_emails, get_folders, refresh_folder, get_unified_counts, get_email_content, regenerate_summary, get_attachments, get_attachment_data, save_attachment_to_path, open_attachment, mark_as_read, move_to_trash, archive_emails, move_to_inbox, get_email_by_id, get_thread_emails, send_email, save_draft, get_drafts, delete_draft, get_draft_by_id, search_emails};
-use crate::email_backend::enrichment::commands::{get_sender_info, get_domain_info, get_emails_by_sender, regenerate_sender_info, update_sender_info, search_contacts, sync_contacts};
+use crate::email_backend::accounts::commands::{login_with_google, login_with_microsoft, login_with_oauth_provider, discover_account_config, login_with_imap_smtp, add_google_app_password_account, test_account_connection, get_accounts, set_account_appearance, reorder_accounts, get_default_account, get_own_addresses, add_account_alias, remove_account_alias, remove_account, verify_imap_smtp_credentials, get_server_certificate_fingerprint, set_account_trust};
+use crate::email_backend::emails::commands::{get_emails, get_emails_by_account, get_attachment_facets, get_folders, refresh_folder, get_unified_counts, get_email_content, prefetch_email_content, regenerate_summary, get_attachments, get_attachment_data, download_attachment, cancel_attachment_download, save_attachment_to_path, open_attachment, mark_as_read, move_to_trash, archive_emails, move_to_inbox, report_spam, get_email_by_id, get_thread_emails, send_email, cancel_send, save_draft, get_drafts, delete_draft, get_draft_by_id, search_emails};
+use crate::email_backend::emails::undo::undo_action;
+use crate::email_backend::emails::bulk::{archive_all_read, mark_folder_read, delete_all_in_view};
+use crate::email_backend::emails::newsletters::{get_newsletter_rollups, expand_newsletter_rollup, get_newsletter_senders, set_newsletter_rollup};
+use crate::email_backend::emails::screener::{get_screened_senders, approve_sender, screen_out_sender};
+use crate::email_backend::emails::remote_content::set_sender_content_rules;
+use crate::email_backend::emails::print::get_printable_email;
+use crate::email_backend::accounts::vacation::{get_vacation_responder, set_vacation_responder};
+use crate::email_backend::accounts::sieve::{list_sieve_scripts, get_sieve_script, save_sieve_script, delete_sieve_script, activate_sieve_script, preview_sieve_rules, push_rules_to_sieve};
+use crate::email_backend::emails::stacks::{set_reply_later, set_aside, clear_stack};
+use crate::email_backend::emails::analytics::get_mailbox_analytics;
+use crate::email_backend::emails::domain_policies::{get_noisy_domains, get_domain_policies, set_domain_policy, delete_domain_policy};
+use crate::email_backend::emails::confidential::{set_email_no_cache, get_no_cache_senders, add_no_cache_sender, remove_no_cache_sender};
+use crate::email_backend::emails::needs_reply::{get_needs_reply, dismiss_needs_reply};
+use crate::email_backend::emails::export::export_thread;
+use crate::email_backend::emails::reading::{set_reading_position, set_messages_expanded, get_thread_reading_state};
+use crate::email_backend::emails::duplicates::{find_duplicates, remove_duplicates};
+use crate::email_backend::emails::cleanup::{get_cleanup_suggestions, apply_cleanup_action};
+use crate::email_backend::emails::retention::{get_retention_rules, save_retention_rule, delete_retention_rule, get_retention_log, get_trash_retention, set_account_trash_retention};
+use crate::email_backend::emails::keywords::{add_keyword, remove_keyword, get_keyword_tags};
+use crate::email_backend::emails::notes::set_email_note;
+use crate::email_backend::emails::calendar::get_upcoming_events;
+use crate::email_backend::emails::tasks::{create_task_from_email, get_tasks, complete_task, delete_task};
+use crate::email_backend::emails::tags::{get_tags, create_tag, update_tag, delete_tag, tag_emails, untag_emails};
+use crate::email_backend::enrichment::commands::{get_sender_info, get_domain_info, get_emails_by_sender, get_sender_shared_items, get_sender_timeline, merge_senders, regenerate_sender_info, update_sender_info, refresh_sender_enrichment, clear_enrichment_data, search_contacts, sync_contacts};
 use crate::email_backend::llm::commands::get_available_models;
 use crate::db::settings::{get_settings, update_setting};
+use crate::utils::logging::{get_recent_logs, set_log_level};
+use crate::utils::diagnostics::export_diagnostics;
+use crate::utils::cli::take_pending_compose;
+use crate::utils::updater::{check_for_updates, install_update};
+use crate::utils::profile::{export_profile, import_profile};
+use crate::utils::security::{get_portable_status, unlock_portable};
 use crate::email_backend::sync::{SyncEngine, SyncWorker};
+use crate::email_backend::sync::commands::{get_sync_health, sync_on_foreground};
+use crate::email_backend::sync::scheduler::{get_background_jobs, JobScheduler};
 use crate::db::setup::setup_database;
 use tauri::Manager;
 use tauri::menu::{Menu, MenuItem};
@@ -13,27 +44,32 @@ mod email_backend;
 mod utils;
 mod db;
 
+/// What `benches/` drives directly, not part of the app.
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::email_backend::emails::commands::{get_emails, search_emails};
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
-        .plugin(
-            tauri_plugin_log::Builder::new()
-                .target(tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::Stdout))
-                .level(log::LevelFilter::Warn)
-                .level_for("dueam_lib", log::LevelFilter::Info)
-                .level_for("langchain_rust", log::LevelFilter::Warn)
-                .level_for("reqwest", log::LevelFilter::Warn)
-                .level_for("sqlx", log::LevelFilter::Warn)
-                .level_for("tower", log::LevelFilter::Warn)
-                .level_for("hyper", log::LevelFilter::Warn)
-                .build()
-        )
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
-            if let Some(window) = app.get_webview_window("main") {
-                let _ = window.show();
-                let _ = window.set_focus();
-            }
-        }))
+    crate::utils::instance::init().expect("Failed to read command line");
+
+    let mut builder = tauri::Builder::default()
+        .plugin(crate::utils::logging::plugin());
+    // The single instance lock is per app, holding it in a named profile would keep the default one from starting
+    if crate::utils::instance::profile().is_none() {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            crate::utils::cli::handle(app, crate::utils::cli::parse(args.into_iter().skip(1)), false);
+        }));
+    }
+
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(crate::utils::updater::plugin());
+    }
+
+    builder
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_dialog::init())
@@ -43,23 +79,49 @@ pub fn run() {
                 window.hide().unwrap();
                 api.prevent_close();
             }
+            tauri::WindowEvent::Focused(true) => {
+                if let Some(engine) = window.try_state::<SyncEngine>() {
+                    engine.on_foreground();
+                }
+            }
+            tauri::WindowEvent::ThemeChanged(theme) => {
+                crate::utils::tray::set_theme(window.app_handle(), *theme);
+            }
             _ => {}
         })
         .setup(|app| {
             let handle = app.handle().clone();
+            crate::utils::logging::apply_max_level();
+
+            // Block on database setup to ensure it's ready before any commands run
+            let (pool, writer) = tauri::async_runtime::block_on(async {
+                let (pool, writer) = setup_database(&handle).await?;
+                crate::utils::i18n::init(&handle, &pool).await;
+                crate::utils::proxy::init(&handle, &pool).await;
+                Ok::<_, String>((pool, writer))
+            }).expect("Failed to setup database");
+
+            if let (Some(profile), Some(window)) = (crate::utils::instance::profile(), app.get_webview_window("main")) {
+                let _ = window.set_title(&format!("dueam ({})", profile));
+            }
 
             // Tray Icon Setup
-            let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let show_i = MenuItem::with_id(app, "show", "Show Dueam", true, None::<&str>)?;
+            let quit_i = MenuItem::with_id(app, "quit", crate::utils::i18n::t("tray.quit", &[]), true, None::<&str>)?;
+            let show_i = MenuItem::with_id(app, "show", crate::utils::i18n::t("tray.show", &[]), true, None::<&str>)?;
             let menu = Menu::with_items(app, &[&show_i, &quit_i])?;
 
-            let _tray = TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
+            let _tray = TrayIconBuilder::with_id(crate::utils::tray::TRAY_ID)
+                .icon(crate::utils::tray::current_icon())
+                .icon_as_template(cfg!(target_os = "macos"))
                 .menu(&menu)
                 .show_menu_on_left_click(false)
                 .on_menu_event(|app, event| match event.id.as_ref() {
                     "quit" => {
-                        app.exit(0);
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            app.state::<SyncEngine>().shutdown().await;
+                            app.exit(0);
+                        });
                     }
                     "show" => {
                         if let Some(window) = app.get_webview_window("main") {
@@ -85,15 +147,17 @@ pub fn run() {
                 })
                 .build(app)?;
 
-            // Block on database setup to ensure it's ready before any commands run
-            let pool = tauri::async_runtime::block_on(async {
-                setup_database(&handle).await
-            }).expect("Failed to setup database");
-
             app.manage(pool);
+            app.manage(writer);
+
+            let tray_handle = handle.clone();
+            tauri::async_runtime::spawn(async move {
+                crate::utils::tray::init(&tray_handle).await;
+            });
 
             let sync_engine = SyncEngine::new(handle.clone());
             app.manage(sync_engine.clone());
+            app.manage(JobScheduler::default());
 
             tauri::async_runtime::spawn(async move {
                 sync_engine.start().await;
@@ -104,44 +168,151 @@ pub fn run() {
                 sync_worker.start().await;
             });
 
+            crate::utils::cli::handle(&handle, crate::utils::cli::parse(std::env::args().skip(1)), true);
+
+            #[cfg(desktop)]
+            crate::utils::updater::check_on_startup(handle.clone());
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             login_with_google,
             login_with_microsoft,
-            add_imap_smtp_account,
+            login_with_oauth_provider,
+            discover_account_config,
+            login_with_imap_smtp,
+            add_google_app_password_account,
+            test_account_connection,
+            get_server_certificate_fingerprint,
+            set_account_trust,
             verify_imap_smtp_credentials,
             get_accounts,
+            set_account_appearance,
+            reorder_accounts,
+            get_default_account,
+            get_own_addresses,
+            add_account_alias,
+            remove_account_alias,
+            get_vacation_responder,
+            set_vacation_responder,
+            list_sieve_scripts,
+            get_sieve_script,
+            save_sieve_script,
+            delete_sieve_script,
+            activate_sieve_script,
+            preview_sieve_rules,
+            push_rules_to_sieve,
             remove_account,
             get_emails,
+            get_emails_by_account,
+            get_attachment_facets,
             get_folders,
             refresh_folder,
             get_unified_counts,
             get_email_content,
+            get_printable_email,
+            export_thread,
+            prefetch_email_content,
             regenerate_summary,
             get_attachments,
             get_attachment_data,
+            download_attachment,
+            cancel_attachment_download,
             save_attachment_to_path,
             open_attachment,
             mark_as_read,
             move_to_trash,
             archive_emails,
             move_to_inbox,
+            report_spam,
+            undo_action,
+            archive_all_read,
+            mark_folder_read,
+            delete_all_in_view,
+            get_retention_rules,
+            save_retention_rule,
+            delete_retention_rule,
+            get_retention_log,
+            get_trash_retention,
+            set_account_trash_retention,
+            add_keyword,
+            remove_keyword,
+            get_keyword_tags,
+            get_tags,
+            create_tag,
+            update_tag,
+            delete_tag,
+            tag_emails,
+            untag_emails,
+            set_email_note,
+            set_reading_position,
+            set_messages_expanded,
+            get_thread_reading_state,
+            create_task_from_email,
+            get_tasks,
+            complete_task,
+            delete_task,
+            get_upcoming_events,
+            get_newsletter_rollups,
+            expand_newsletter_rollup,
+            get_newsletter_senders,
+            set_newsletter_rollup,
+            get_screened_senders,
+            approve_sender,
+            screen_out_sender,
+            set_sender_content_rules,
+            set_reply_later,
+            set_aside,
+            clear_stack,
+            get_mailbox_analytics,
+            get_noisy_domains,
+            get_domain_policies,
+            set_domain_policy,
+            delete_domain_policy,
+            set_email_no_cache,
+            get_no_cache_senders,
+            add_no_cache_sender,
+            remove_no_cache_sender,
+            get_needs_reply,
+            dismiss_needs_reply,
+            find_duplicates,
+            remove_duplicates,
+            get_cleanup_suggestions,
+            apply_cleanup_action,
             get_email_by_id,
             get_thread_emails,
             send_email,
+            cancel_send,
             save_draft,
             get_drafts,
             delete_draft,
             get_draft_by_id,
             search_emails,
+            get_sync_health,
+            get_background_jobs,
+            sync_on_foreground,
             get_settings,
             update_setting,
+            get_recent_logs,
+            set_log_level,
+            export_diagnostics,
+            take_pending_compose,
+            check_for_updates,
+            install_update,
+            export_profile,
+            import_profile,
+            get_portable_status,
+            unlock_portable,
             get_sender_info,
             regenerate_sender_info,
             update_sender_info,
+            refresh_sender_enrichment,
+            clear_enrichment_data,
             get_domain_info,
             get_emails_by_sender,
+            merge_senders,
+            get_sender_timeline,
+            get_sender_shared_items,
             get_available_models,
             search_contacts,
             sync_contacts
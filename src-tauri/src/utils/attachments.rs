@@ -1,11 +1,11 @@
 use std::path::PathBuf;
-use tauri::{AppHandle, Manager, Runtime};
+use tauri::{AppHandle, Runtime};
 use std::fs;
 use sha2::{Sha256, Digest};
 use log::error;
 
 pub fn get_attachments_dir<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf, String> {
-    let mut path = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut path = crate::utils::instance::data_dir(app_handle)?;
     path.push("attachments");
     
     if !path.exists() {
@@ -45,3 +45,25 @@ pub fn read_attachment_data<R: Runtime>(app_handle: &AppHandle<R>, hash: &str) -
     let path = get_attachment_path(app_handle, hash)?;
     fs::read(path).map_err(|e| e.to_string())
 }
+
+/// Raw bytes of a download that hasn't finished yet, kept so it can be resumed.
+pub fn get_partial_download_path<R: Runtime>(app_handle: &AppHandle<R>, attachment_id: i64) -> Result<PathBuf, String> {
+    let mut path = get_attachments_dir(app_handle)?;
+    path.push("partial");
+
+    if !path.exists() {
+        fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    }
+
+    Ok(path.join(format!("{}.part", attachment_id)))
+}
+
+pub fn remove_attachment_file<R: Runtime>(app_handle: &AppHandle<R>, hash: &str) -> Result<u64, String> {
+    let path = get_attachments_dir(app_handle)?.join(hash);
+    let size = match fs::metadata(&path) {
+        Ok(meta) => meta.len(),
+        Err(_) => return Ok(0),
+    };
+    fs::remove_file(path).map_err(|e| e.to_string())?;
+    Ok(size)
+}
@@ -0,0 +1,67 @@
+use sqlx::SqlitePool;
+use tauri::Manager;
+use crate::db::settings::Settings;
+
+/// Unread mail the badge counts: the inbox alone with `badgePrimaryOnly`, otherwise every
+/// folder that isn't spam, trash, sent or drafts.
+pub(crate) async fn unread_count(pool: &SqlitePool, primary_only: bool) -> Result<i64, String> {
+    let condition = if primary_only {
+        "role = 'inbox'"
+    } else {
+        "COALESCE(role, '') NOT IN ('spam', 'trash', 'sent', 'drafts')"
+    };
+    sqlx::query_scalar(&format!("SELECT COALESCE(SUM(unread_count), 0) FROM folders WHERE {}", condition))
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sets the dock/taskbar badge from the current unread count, or clears it when badges are off.
+/// Windows has no badge count, the call fails there and is only logged.
+pub async fn refresh<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) {
+    let Some(pool) = app_handle.try_state::<SqlitePool>() else { return };
+    let Some(window) = app_handle.get_webview_window("main") else { return };
+    let settings = Settings::load(&pool).await.unwrap_or_default();
+
+    let count = if settings.badge_enabled {
+        match unread_count(&pool, settings.badge_primary_only).await {
+            Ok(count) => Some(count).filter(|c| *c > 0),
+            Err(e) => {
+                log::error!("Failed to count unread mail for the badge: {}", e);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Err(e) = window.set_badge_count(count) {
+        log::debug!("Badge count not set: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{insert_test_account, setup_test_db};
+
+    #[tokio::test]
+    async fn test_unread_count_by_scope() {
+        let pool = setup_test_db().await;
+        let account_id = insert_test_account(&pool).await;
+        for (path, role, unread) in [("INBOX", Some("inbox"), 3), ("Work", None, 2), ("Spam", Some("spam"), 7), ("Trash", Some("trash"), 1)] {
+            sqlx::query("INSERT INTO folders (account_id, name, path, role, unread_count) VALUES (?, ?, ?, ?, ?)")
+                .bind(account_id)
+                .bind(path)
+                .bind(path)
+                .bind(role)
+                .bind(unread)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(unread_count(&pool, true).await.unwrap(), 3);
+        assert_eq!(unread_count(&pool, false).await.unwrap(), 5);
+    }
+}
@@ -0,0 +1,181 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use crate::email_backend::sync::SyncEngine;
+use crate::email_backend::accounts::manager::AccountManager;
+
+/// Automation flags, read at startup and forwarded by later launches to the running instance.
+#[derive(Debug, Default, PartialEq)]
+pub struct CliArgs {
+    pub compose: bool,
+    pub mailto: Option<String>,
+    pub check_mail: bool,
+    pub hidden: bool,
+}
+
+/// Payload of the `compose-requested` event, fields as the composer takes them.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ComposeRequest {
+    pub to: String,
+    pub cc: String,
+    pub bcc: String,
+    pub subject: String,
+    pub body: String,
+    /// The default sending account, filled in when the request is handed over
+    pub account_id: Option<i64>,
+}
+
+/// A compose asked for before the frontend could listen, picked up with `take_pending_compose`.
+static PENDING_COMPOSE: Mutex<Option<ComposeRequest>> = Mutex::new(None);
+
+/// Expects the arguments without the executable. A bare `mailto:` URL counts as `--mailto`,
+/// that's how the OS hands over links once the app is the default mail handler.
+pub fn parse(args: impl IntoIterator<Item = String>) -> CliArgs {
+    let mut parsed = CliArgs::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--compose" => parsed.compose = true,
+            "--mailto" => parsed.mailto = args.next(),
+            "--check-mail" => parsed.check_mail = true,
+            "--hidden" => parsed.hidden = true,
+            _ if arg.to_lowercase().starts_with("mailto:") => parsed.mailto = Some(arg),
+            _ => {}
+        }
+    }
+    parsed
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Splits a mailto URL (RFC 6068) into composer fields, `to` headers in the query add to the path's recipients.
+fn parse_mailto(url: &str) -> ComposeRequest {
+    let rest = match url.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &url[7..],
+        _ => url,
+    };
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let mut to: Vec<String> = vec![percent_decode(path)];
+    let mut request = ComposeRequest::default();
+    for pair in query.split('&') {
+        let Some((name, value)) = pair.split_once('=') else { continue };
+        let value = percent_decode(value);
+        match name.to_lowercase().as_str() {
+            "to" => to.push(value),
+            "cc" => request.cc = value,
+            "bcc" => request.bcc = value,
+            "subject" => request.subject = value,
+            "body" => request.body = value,
+            _ => {}
+        }
+    }
+    request.to = to.into_iter().filter(|t| !t.is_empty()).collect::<Vec<_>>().join(", ");
+    request
+}
+
+/// Acts on the flags. `startup` is set for the app's own launch, where the initial sync already checks mail.
+pub fn handle<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, args: CliArgs, startup: bool) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if args.hidden {
+            let _ = window.hide();
+        } else {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+
+    let compose = match args.mailto {
+        Some(url) => Some(parse_mailto(&url)),
+        None => args.compose.then(ComposeRequest::default),
+    };
+    if let Some(request) = compose {
+        if startup {
+            if let Ok(mut pending) = PENDING_COMPOSE.lock() {
+                *pending = Some(request);
+            }
+        } else {
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let request = with_default_account(&app_handle, request).await;
+                let _ = app_handle.emit("compose-requested", request);
+            });
+        }
+    }
+
+    if args.check_mail && !startup {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = SyncEngine::<R>::sync_all_accounts(&app_handle).await {
+                log::error!("Mail check from the command line failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn with_default_account<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, mut request: ComposeRequest) -> ComposeRequest {
+    match AccountManager::new(app_handle).await {
+        Ok(manager) => request.account_id = manager.default_account_id().await.unwrap_or_else(|e| {
+            log::warn!("No default account for compose: {}", e);
+            None
+        }),
+        Err(e) => log::warn!("No default account for compose: {}", e),
+    }
+    request
+}
+
+#[tauri::command]
+pub async fn take_pending_compose<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Option<ComposeRequest>, String> {
+    let pending = PENDING_COMPOSE.lock().map_err(|e| e.to_string())?.take();
+    match pending {
+        Some(request) => Ok(Some(with_default_account(&app_handle, request).await)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_flags_and_bare_mailto() {
+        let parsed = parse(args(&["--profile", "work", "--hidden", "--check-mail"]));
+        assert!(parsed.hidden && parsed.check_mail && !parsed.compose);
+
+        assert_eq!(parse(args(&["--mailto", "mailto:a@example.com"])).mailto.as_deref(), Some("mailto:a@example.com"));
+        assert_eq!(parse(args(&["MAILTO:a@example.com"])).mailto.as_deref(), Some("MAILTO:a@example.com"));
+    }
+
+    #[test]
+    fn test_parse_mailto_fields() {
+        let request = parse_mailto("mailto:a%2Bb@example.com?to=c@example.com&subject=Hello%20there&body=Line%201%0ALine%202&cc=d@example.com");
+        assert_eq!(request.to, "a+b@example.com, c@example.com");
+        assert_eq!(request.cc, "d@example.com");
+        assert_eq!(request.subject, "Hello there");
+        assert_eq!(request.body, "Line 1\nLine 2");
+
+        assert_eq!(parse_mailto("mailto:?subject=x").to, "");
+        assert_eq!(parse_mailto("a@example.com").to, "a@example.com");
+    }
+}
@@ -0,0 +1,160 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::io::Write;
+use tauri::Manager;
+use zip::write::SimpleFileOptions;
+use crate::utils::logging::log_file_path;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct AccountState {
+    id: i64,
+    email: String,
+    account_type: String,
+    created_at: Option<String>,
+    folder_count: i64,
+    email_count: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct FolderCheckpoint {
+    account_id: i64,
+    path: String,
+    role: Option<String>,
+    uid_validity: Option<i64>,
+    uid_next: Option<i64>,
+    total_count: i64,
+    unread_count: i64,
+    stored_count: i64,
+}
+
+/// Masks the local part of anything that looks like an email address and drops bearer tokens.
+fn redact(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let is_local = |c: char| c.is_alphanumeric() || "._%+-".contains(c);
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] == '@' {
+            // Walk back over the local part we already copied and replace it
+            let mut start = i;
+            while start > 0 && is_local(chars[start - 1]) {
+                start -= 1;
+            }
+            if start < i && i + 1 < chars.len() && chars[i + 1].is_alphanumeric() {
+                let local_len: usize = chars[start..i].iter().map(|c| c.len_utf8()).sum();
+                out.truncate(out.len() - local_len);
+                out.push_str("***");
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+
+    let mut search_from = 0;
+    while let Some(pos) = out[search_from..].find("Bearer ") {
+        let token_start = search_from + pos + "Bearer ".len();
+        let token_end = out[token_start..].find(|c: char| c.is_whitespace() || c == '"').map(|e| token_start + e).unwrap_or(out.len());
+        out.replace_range(token_start..token_end, "[redacted]");
+        search_from = token_start + "[redacted]".len();
+    }
+    out
+}
+
+#[tauri::command]
+pub async fn export_diagnostics<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    path: String,
+) -> Result<(), String> {
+    let pool = app_handle.state::<SqlitePool>();
+
+    let schema_version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Tokens live in the encrypted store, the accounts table never holds them
+    let accounts = sqlx::query_as::<_, AccountState>(
+        "SELECT a.id, a.email, a.account_type, CAST(a.created_at AS TEXT) as created_at,
+                (SELECT COUNT(*) FROM folders f WHERE f.account_id = a.id) as folder_count,
+                (SELECT COUNT(*) FROM emails e WHERE e.account_id = a.id) as email_count
+         FROM accounts a"
+    )
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let accounts: Vec<AccountState> = accounts
+        .into_iter()
+        .map(|a| AccountState { email: redact(&a.email), ..a })
+        .collect();
+
+    let folders = sqlx::query_as::<_, FolderCheckpoint>(
+        "SELECT f.account_id, f.path, f.role, f.uid_validity, f.uid_next,
+                COALESCE(f.total_count, 0) as total_count, COALESCE(f.unread_count, 0) as unread_count,
+                (SELECT COUNT(*) FROM emails e WHERE e.folder_id = f.id) as stored_count
+         FROM folders f
+         ORDER BY f.account_id, f.path"
+    )
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let log_content = tokio::fs::read_to_string(log_file_path(&app_handle)?).await.unwrap_or_default();
+    let redacted_logs: Vec<String> = log_content.lines().map(redact).collect();
+    let recent_errors: Vec<&String> = redacted_logs
+        .iter()
+        .filter(|l| l.contains(" ERROR [") || l.contains(" WARN ["))
+        .rev()
+        .take(200)
+        .collect();
+
+    let summary = serde_json::json!({
+        "app_version": app_handle.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "schema_version": schema_version,
+        "generated_at": chrono::Utc::now().to_rfc3339(),
+    });
+
+    let files: Vec<(&str, String)> = vec![
+        ("summary.json", serde_json::to_string_pretty(&summary).map_err(|e| e.to_string())?),
+        ("accounts.json", serde_json::to_string_pretty(&accounts).map_err(|e| e.to_string())?),
+        ("folders.json", serde_json::to_string_pretty(&folders).map_err(|e| e.to_string())?),
+        ("recent_errors.log", recent_errors.into_iter().rev().cloned().collect::<Vec<_>>().join("\n")),
+        ("dueam.log", redacted_logs.join("\n")),
+    ];
+
+    tokio::task::spawn_blocking(move || -> Result<(), String> {
+        let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        for (name, content) in files {
+            zip.start_file(name, options).map_err(|e| e.to_string())?;
+            zip.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+        }
+        zip.finish().map_err(|e| e.to_string())?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    log::info!("Diagnostics bundle exported");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_masks_addresses_and_tokens() {
+        assert_eq!(redact("Syncing folder INBOX for john.doe@example.com"), "Syncing folder INBOX for ***@example.com");
+        assert_eq!(redact("Authorization: Bearer ya29.abc-def rest"), "Authorization: Bearer [redacted] rest");
+        assert_eq!(
+            redact("retry Bearer first.token then Bearer second.token"),
+            "retry Bearer [redacted] then Bearer [redacted]"
+        );
+        assert_eq!(redact("no personal data here"), "no personal data here");
+    }
+}
@@ -0,0 +1,367 @@
+use std::sync::RwLock;
+use tauri::Listener;
+use sqlx::SqlitePool;
+use crate::db::settings::{Settings, SettingChanged};
+
+/// Languages the backend has strings for. The frontend translates its own UI, this only
+/// covers what the backend shows or returns: notifications, the tray menu and error messages.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+static LANGUAGE: RwLock<Language> = RwLock::new(Language::En);
+
+impl Language {
+    /// Accepts BCP 47 tags and POSIX locales alike: `de`, `de-AT`, `fr_FR.UTF-8`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_', '.', '@']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Language::En),
+            "de" => Some(Language::De),
+            "fr" => Some(Language::Fr),
+            "es" => Some(Language::Es),
+            _ => None,
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::En => EN,
+            Language::De => DE,
+            Language::Fr => FR,
+            Language::Es => ES,
+        }
+    }
+}
+
+/// The OS locale as exposed to the process, `C`/`POSIX` meaning none was chosen.
+fn system_language() -> Option<Language> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| Language::from_tag(&value))
+}
+
+/// `language` is a tag like `de` or `system` to follow the OS.
+pub fn set_language(setting: &str) {
+    let language = match setting {
+        "system" => system_language(),
+        tag => Language::from_tag(tag),
+    }
+    .unwrap_or(Language::En);
+
+    if let Ok(mut current) = LANGUAGE.write() {
+        *current = language;
+    }
+}
+
+pub fn current_language() -> Language {
+    LANGUAGE.read().map(|l| *l).unwrap_or(Language::En)
+}
+
+/// Loads the language from settings and follows later changes to it. The tray menu is built
+/// once at startup, so it only picks up a change after a restart.
+pub async fn init<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, pool: &SqlitePool) {
+    set_language(&Settings::load(pool).await.unwrap_or_default().language);
+
+    app_handle.listen("settings-changed", |event| {
+        if let Ok(change) = serde_json::from_str::<SettingChanged>(event.payload()) {
+            if change.key == "language" {
+                set_language(&serde_json::from_str::<String>(&change.value).unwrap_or(change.value));
+            }
+        }
+    });
+}
+
+/// Translates `key` into the current language, filling `{name}` placeholders from `args`.
+/// Falls back to English, then to the key itself.
+pub fn t(key: &str, args: &[(&str, &str)]) -> String {
+    let lookup = |language: Language| language.catalog().iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    let mut text = lookup(current_language()).or_else(|| lookup(Language::En)).unwrap_or(key).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+/// Display name of a folder role, the role itself for ones without a translation.
+pub fn folder(role: &str) -> String {
+    let key = format!("folder.{}", role);
+    match t(&key, &[]) {
+        text if text == key => role.to_string(),
+        text => text,
+    }
+}
+
+const EN: &[(&str, &str)] = &[
+    ("tray.show", "Show Dueam"),
+    ("tray.quit", "Quit"),
+    ("folder.inbox", "Inbox"),
+    ("folder.sent", "Sent"),
+    ("folder.archive", "Archive"),
+    ("folder.trash", "Trash"),
+    ("folder.spam", "Spam"),
+    ("email.no_subject", "(No subject)"),
+    ("print.from", "From"),
+    ("print.to", "To"),
+    ("print.date", "Date"),
+    ("print.attachments", "Attachments"),
+    ("notification.new_email", "New Email: {subject}"),
+    ("notification.from", "From: {sender}"),
+    ("notification.reply_later", "Reply later: {subject}"),
+    ("notification.reply_later_body", "Waiting for your reply for {days}+ days"),
+    ("notification.reply_later_many", "{count} emails waiting for a reply"),
+    ("notification.reply_later_many_body", "Set aside to reply later more than {days} days ago"),
+    ("notification.needs_reply", "Still unanswered: {subject}"),
+    ("notification.needs_reply_body", "{sender} wrote {days}+ days ago"),
+    ("notification.needs_reply_many", "{count} emails still need your reply"),
+    ("notification.needs_reply_many_body", "Received more than {days} days ago and not answered yet"),
+    ("notification.task_due", "Reminder: {title}"),
+    ("notification.tasks_due_many", "{count} tasks are due"),
+    ("error.show_notification", "Failed to show notification: {error}"),
+    ("error.sync_account", "Failed to sync account {account}: {error}"),
+    ("error.auth_failed", "Signing in to {account} failed, check the password or sign in again: {error}"),
+    ("error.mark_read_server", "Failed to mark email as read on server: {error}"),
+    ("error.mark_read_offline", "Marked as read locally only, server unavailable: {error}"),
+    ("error.tag_server", "Failed to update the tag on the server: {error}"),
+    ("error.tag_offline", "Tag not updated, the server is unavailable: {error}"),
+    ("error.uidplus_missing", "The server can't delete single messages for good (no UIDPLUS support), move them to the trash instead"),
+    ("error.move_server", "Failed to move email to {folder} on server: {error}"),
+    ("error.move_offline", "Moved to {folder} locally only, server unavailable: {error}"),
+    ("error.open_attachment", "Failed to open attachment: {error}"),
+    ("error.flag_original_server", "Failed to flag original email on server: {error}"),
+    ("error.flag_original_offline", "Flagged original email locally only, server unavailable: {error}"),
+    ("error.update_flags", "Failed to update flags of email {id}: {error}"),
+    ("error.save_sent", "Email was sent but could not be saved to Sent: {error}"),
+    ("error.refresh_sent", "Failed to refresh Sent folder: {error}"),
+    ("error.save_contacts", "Failed to save recipients as contacts: {error}"),
+    ("error.undo_local", "Undid {action} locally only: {error}"),
+    ("error.undo_no_message_id", "Undid {action} locally only: email has no Message-ID"),
+    ("error.download_in_progress", "Attachment is already being downloaded"),
+    ("error.download_cancelled", "Download cancelled"),
+    ("error.send_cancelled", "Sending cancelled"),
+    ("error.imap_timeout", "The mail server did not answer within {seconds} seconds"),
+    ("error.no_recipients", "At least one recipient is required"),
+    ("error.invalid_address", "Invalid email address: {address}"),
+    ("error.undo_too_late", "It is too late to undo this action"),
+    ("error.nothing_to_undo", "Nothing to undo"),
+    ("error.retention_pattern", "Enter an email address, an @domain or a mailing list id"),
+    ("error.retention_days", "Rules must wait at least one day"),
+    ("error.retention_trash_on_delivery", "Only archive rules can be applied by the server on delivery"),
+    ("error.enrichment_disabled", "Sender enrichment is disabled in settings"),
+    ("error.ai_not_configured", "AI API Key or Model not configured"),
+    ("error.untrusted_certificate", "The {protocol} server's certificate is not trusted, it may be self-signed or issued by a private CA. Trust the CA or the certificate's fingerprint in the account settings: {error}"),
+    ("error.oauth_not_configured", "Signing in with {provider} is not available in this build"),
+];
+
+const DE: &[(&str, &str)] = &[
+    ("tray.show", "Dueam anzeigen"),
+    ("tray.quit", "Beenden"),
+    ("folder.inbox", "Posteingang"),
+    ("folder.sent", "Gesendet"),
+    ("folder.archive", "Archiv"),
+    ("folder.trash", "Papierkorb"),
+    ("folder.spam", "Spam"),
+    ("email.no_subject", "(Kein Betreff)"),
+    ("print.from", "Von"),
+    ("print.to", "An"),
+    ("print.date", "Datum"),
+    ("print.attachments", "Anhänge"),
+    ("notification.new_email", "Neue E-Mail: {subject}"),
+    ("notification.from", "Von: {sender}"),
+    ("notification.reply_later", "Später antworten: {subject}"),
+    ("notification.reply_later_body", "Wartet seit mindestens {days} Tagen auf deine Antwort"),
+    ("notification.reply_later_many", "{count} E-Mails warten auf eine Antwort"),
+    ("notification.reply_later_many_body", "Vor mehr als {days} Tagen zum späteren Antworten zurückgelegt"),
+    ("notification.needs_reply", "Noch unbeantwortet: {subject}"),
+    ("notification.needs_reply_body", "{sender} hat vor mindestens {days} Tagen geschrieben"),
+    ("notification.needs_reply_many", "{count} E-Mails brauchen noch deine Antwort"),
+    ("notification.needs_reply_many_body", "Vor mehr als {days} Tagen erhalten und noch nicht beantwortet"),
+    ("notification.task_due", "Erinnerung: {title}"),
+    ("notification.tasks_due_many", "{count} Aufgaben sind fällig"),
+    ("error.show_notification", "Benachrichtigung konnte nicht angezeigt werden: {error}"),
+    ("error.sync_account", "Konto {account} konnte nicht synchronisiert werden: {error}"),
+    ("error.auth_failed", "Anmeldung bei {account} fehlgeschlagen, bitte Passwort prüfen oder erneut anmelden: {error}"),
+    ("error.mark_read_server", "E-Mail konnte auf dem Server nicht als gelesen markiert werden: {error}"),
+    ("error.mark_read_offline", "Nur lokal als gelesen markiert, Server nicht erreichbar: {error}"),
+    ("error.tag_server", "Schlagwort konnte auf dem Server nicht aktualisiert werden: {error}"),
+    ("error.tag_offline", "Schlagwort nicht aktualisiert, Server nicht erreichbar: {error}"),
+    ("error.uidplus_missing", "Der Server kann einzelne Nachrichten nicht endgültig löschen (keine UIDPLUS-Unterstützung), verschieben Sie sie stattdessen in den Papierkorb"),
+    ("error.move_server", "E-Mail konnte auf dem Server nicht nach {folder} verschoben werden: {error}"),
+    ("error.move_offline", "Nur lokal nach {folder} verschoben, Server nicht erreichbar: {error}"),
+    ("error.open_attachment", "Anhang konnte nicht geöffnet werden: {error}"),
+    ("error.flag_original_server", "Ursprüngliche E-Mail konnte auf dem Server nicht markiert werden: {error}"),
+    ("error.flag_original_offline", "Ursprüngliche E-Mail nur lokal markiert, Server nicht erreichbar: {error}"),
+    ("error.update_flags", "Markierungen der E-Mail {id} konnten nicht aktualisiert werden: {error}"),
+    ("error.save_sent", "E-Mail wurde gesendet, konnte aber nicht in „Gesendet“ gespeichert werden: {error}"),
+    ("error.refresh_sent", "Ordner „Gesendet“ konnte nicht aktualisiert werden: {error}"),
+    ("error.save_contacts", "Empfänger konnten nicht als Kontakte gespeichert werden: {error}"),
+    ("error.undo_local", "{action} nur lokal rückgängig gemacht: {error}"),
+    ("error.undo_no_message_id", "{action} nur lokal rückgängig gemacht: E-Mail hat keine Message-ID"),
+    ("error.download_in_progress", "Der Anhang wird bereits heruntergeladen"),
+    ("error.download_cancelled", "Download abgebrochen"),
+    ("error.send_cancelled", "Senden abgebrochen"),
+    ("error.imap_timeout", "Der Mailserver hat nicht innerhalb von {seconds} Sekunden geantwortet"),
+    ("error.no_recipients", "Mindestens ein Empfänger ist erforderlich"),
+    ("error.invalid_address", "Ungültige E-Mail-Adresse: {address}"),
+    ("error.undo_too_late", "Diese Aktion kann nicht mehr rückgängig gemacht werden"),
+    ("error.nothing_to_undo", "Nichts rückgängig zu machen"),
+    ("error.retention_pattern", "Gib eine E-Mail-Adresse, eine @Domain oder eine Mailinglisten-ID ein"),
+    ("error.retention_days", "Regeln müssen mindestens einen Tag warten"),
+    ("error.retention_trash_on_delivery", "Nur Archivierungsregeln können vom Server bei der Zustellung angewendet werden"),
+    ("error.enrichment_disabled", "Die Absenderanreicherung ist in den Einstellungen deaktiviert"),
+    ("error.ai_not_configured", "KI-API-Schlüssel oder Modell nicht konfiguriert"),
+    ("error.untrusted_certificate", "Das Zertifikat des {protocol}-Servers ist nicht vertrauenswürdig, es ist möglicherweise selbstsigniert oder von einer privaten CA ausgestellt. Vertrauen Sie der CA oder dem Fingerabdruck des Zertifikats in den Kontoeinstellungen: {error}"),
+    ("error.oauth_not_configured", "Die Anmeldung mit {provider} ist in diesem Build nicht verfügbar"),
+];
+
+const FR: &[(&str, &str)] = &[
+    ("tray.show", "Afficher Dueam"),
+    ("tray.quit", "Quitter"),
+    ("folder.inbox", "Boîte de réception"),
+    ("folder.sent", "Envoyés"),
+    ("folder.archive", "Archives"),
+    ("folder.trash", "Corbeille"),
+    ("folder.spam", "Spam"),
+    ("email.no_subject", "(Sans objet)"),
+    ("print.from", "De"),
+    ("print.to", "À"),
+    ("print.date", "Date"),
+    ("print.attachments", "Pièces jointes"),
+    ("notification.new_email", "Nouvel e-mail : {subject}"),
+    ("notification.from", "De : {sender}"),
+    ("notification.reply_later", "Répondre plus tard : {subject}"),
+    ("notification.reply_later_body", "En attente de votre réponse depuis {days} jours ou plus"),
+    ("notification.reply_later_many", "{count} e-mails attendent une réponse"),
+    ("notification.reply_later_many_body", "Mis de côté pour répondre plus tard il y a plus de {days} jours"),
+    ("notification.needs_reply", "Toujours sans réponse : {subject}"),
+    ("notification.needs_reply_body", "{sender} a écrit il y a {days} jours ou plus"),
+    ("notification.needs_reply_many", "{count} e-mails attendent encore votre réponse"),
+    ("notification.needs_reply_many_body", "Reçus il y a plus de {days} jours et toujours sans réponse"),
+    ("notification.task_due", "Rappel : {title}"),
+    ("notification.tasks_due_many", "{count} tâches arrivent à échéance"),
+    ("error.show_notification", "Impossible d'afficher la notification : {error}"),
+    ("error.sync_account", "Impossible de synchroniser le compte {account} : {error}"),
+    ("error.auth_failed", "Échec de la connexion à {account}, vérifiez le mot de passe ou reconnectez-vous : {error}"),
+    ("error.mark_read_server", "Impossible de marquer l'e-mail comme lu sur le serveur : {error}"),
+    ("error.mark_read_offline", "Marqué comme lu localement uniquement, serveur indisponible : {error}"),
+    ("error.tag_server", "Impossible de mettre à jour l'étiquette sur le serveur : {error}"),
+    ("error.tag_offline", "Étiquette non mise à jour, serveur indisponible : {error}"),
+    ("error.uidplus_missing", "Le serveur ne peut pas supprimer définitivement des messages isolés (UIDPLUS non pris en charge), placez-les plutôt dans la corbeille"),
+    ("error.move_server", "Impossible de déplacer l'e-mail vers {folder} sur le serveur : {error}"),
+    ("error.move_offline", "Déplacé vers {folder} localement uniquement, serveur indisponible : {error}"),
+    ("error.open_attachment", "Impossible d'ouvrir la pièce jointe : {error}"),
+    ("error.flag_original_server", "Impossible de marquer l'e-mail d'origine sur le serveur : {error}"),
+    ("error.flag_original_offline", "E-mail d'origine marqué localement uniquement, serveur indisponible : {error}"),
+    ("error.update_flags", "Impossible de mettre à jour les indicateurs de l'e-mail {id} : {error}"),
+    ("error.save_sent", "L'e-mail a été envoyé mais n'a pas pu être enregistré dans Envoyés : {error}"),
+    ("error.refresh_sent", "Impossible d'actualiser le dossier Envoyés : {error}"),
+    ("error.save_contacts", "Impossible d'enregistrer les destinataires comme contacts : {error}"),
+    ("error.undo_local", "{action} annulé localement uniquement : {error}"),
+    ("error.undo_no_message_id", "{action} annulé localement uniquement : l'e-mail n'a pas de Message-ID"),
+    ("error.download_in_progress", "La pièce jointe est déjà en cours de téléchargement"),
+    ("error.download_cancelled", "Téléchargement annulé"),
+    ("error.send_cancelled", "Envoi annulé"),
+    ("error.imap_timeout", "Le serveur de messagerie n'a pas répondu en {seconds} secondes"),
+    ("error.no_recipients", "Au moins un destinataire est requis"),
+    ("error.invalid_address", "Adresse e-mail invalide : {address}"),
+    ("error.undo_too_late", "Il est trop tard pour annuler cette action"),
+    ("error.nothing_to_undo", "Rien à annuler"),
+    ("error.retention_pattern", "Saisissez une adresse e-mail, un @domaine ou l'identifiant d'une liste de diffusion"),
+    ("error.retention_days", "Les règles doivent attendre au moins un jour"),
+    ("error.retention_trash_on_delivery", "Seules les règles d'archivage peuvent être appliquées par le serveur à la réception"),
+    ("error.enrichment_disabled", "L'enrichissement des expéditeurs est désactivé dans les paramètres"),
+    ("error.ai_not_configured", "Clé d'API ou modèle d'IA non configuré"),
+    ("error.untrusted_certificate", "Le certificat du serveur {protocol} n'est pas approuvé, il est peut-être auto-signé ou émis par une autorité privée. Approuvez l'autorité ou l'empreinte du certificat dans les paramètres du compte : {error}"),
+    ("error.oauth_not_configured", "La connexion avec {provider} n'est pas disponible dans cette version"),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("tray.show", "Mostrar Dueam"),
+    ("tray.quit", "Salir"),
+    ("folder.inbox", "Bandeja de entrada"),
+    ("folder.sent", "Enviados"),
+    ("folder.archive", "Archivo"),
+    ("folder.trash", "Papelera"),
+    ("folder.spam", "Spam"),
+    ("email.no_subject", "(Sin asunto)"),
+    ("print.from", "De"),
+    ("print.to", "Para"),
+    ("print.date", "Fecha"),
+    ("print.attachments", "Adjuntos"),
+    ("notification.new_email", "Nuevo correo: {subject}"),
+    ("notification.from", "De: {sender}"),
+    ("notification.reply_later", "Responder más tarde: {subject}"),
+    ("notification.reply_later_body", "Esperando tu respuesta desde hace {days} días o más"),
+    ("notification.reply_later_many", "{count} correos esperan respuesta"),
+    ("notification.reply_later_many_body", "Apartados para responder más tarde hace más de {days} días"),
+    ("notification.needs_reply", "Aún sin respuesta: {subject}"),
+    ("notification.needs_reply_body", "{sender} escribió hace {days} días o más"),
+    ("notification.needs_reply_many", "{count} correos siguen esperando tu respuesta"),
+    ("notification.needs_reply_many_body", "Recibidos hace más de {days} días y aún sin responder"),
+    ("notification.task_due", "Recordatorio: {title}"),
+    ("notification.tasks_due_many", "{count} tareas vencen ahora"),
+    ("error.show_notification", "No se pudo mostrar la notificación: {error}"),
+    ("error.sync_account", "No se pudo sincronizar la cuenta {account}: {error}"),
+    ("error.auth_failed", "No se pudo iniciar sesión en {account}, comprueba la contraseña o vuelve a iniciar sesión: {error}"),
+    ("error.mark_read_server", "No se pudo marcar el correo como leído en el servidor: {error}"),
+    ("error.mark_read_offline", "Marcado como leído solo localmente, servidor no disponible: {error}"),
+    ("error.tag_server", "No se pudo actualizar la etiqueta en el servidor: {error}"),
+    ("error.tag_offline", "Etiqueta no actualizada, servidor no disponible: {error}"),
+    ("error.uidplus_missing", "El servidor no puede eliminar mensajes sueltos de forma definitiva (no admite UIDPLUS), muévalos a la papelera"),
+    ("error.move_server", "No se pudo mover el correo a {folder} en el servidor: {error}"),
+    ("error.move_offline", "Movido a {folder} solo localmente, servidor no disponible: {error}"),
+    ("error.open_attachment", "No se pudo abrir el adjunto: {error}"),
+    ("error.flag_original_server", "No se pudo marcar el correo original en el servidor: {error}"),
+    ("error.flag_original_offline", "Correo original marcado solo localmente, servidor no disponible: {error}"),
+    ("error.update_flags", "No se pudieron actualizar las marcas del correo {id}: {error}"),
+    ("error.save_sent", "El correo se envió pero no se pudo guardar en Enviados: {error}"),
+    ("error.refresh_sent", "No se pudo actualizar la carpeta Enviados: {error}"),
+    ("error.save_contacts", "No se pudieron guardar los destinatarios como contactos: {error}"),
+    ("error.undo_local", "{action} deshecho solo localmente: {error}"),
+    ("error.undo_no_message_id", "{action} deshecho solo localmente: el correo no tiene Message-ID"),
+    ("error.download_in_progress", "El adjunto ya se está descargando"),
+    ("error.download_cancelled", "Descarga cancelada"),
+    ("error.send_cancelled", "Envío cancelado"),
+    ("error.imap_timeout", "El servidor de correo no respondió en {seconds} segundos"),
+    ("error.no_recipients", "Se necesita al menos un destinatario"),
+    ("error.invalid_address", "Dirección de correo no válida: {address}"),
+    ("error.undo_too_late", "Es demasiado tarde para deshacer esta acción"),
+    ("error.nothing_to_undo", "No hay nada que deshacer"),
+    ("error.retention_pattern", "Introduce una dirección de correo, un @dominio o el identificador de una lista de correo"),
+    ("error.retention_days", "Las reglas deben esperar al menos un día"),
+    ("error.retention_trash_on_delivery", "Solo las reglas de archivo pueden aplicarse en el servidor al recibir el correo"),
+    ("error.enrichment_disabled", "El enriquecimiento de remitentes está desactivado en los ajustes"),
+    ("error.ai_not_configured", "Clave de API o modelo de IA sin configurar"),
+    ("error.untrusted_certificate", "El certificado del servidor {protocol} no es de confianza, puede ser autofirmado o emitido por una CA privada. Confíe en la CA o en la huella del certificado en la configuración de la cuenta: {error}"),
+    ("error.oauth_not_configured", "El inicio de sesión con {provider} no está disponible en esta versión"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_cover_every_english_key() {
+        for language in [Language::De, Language::Fr, Language::Es] {
+            for (key, _) in EN {
+                assert!(language.catalog().iter().any(|(k, _)| k == key), "{:?} is missing {}", language, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_language_from_tag_and_placeholders() {
+        assert_eq!(Language::from_tag("de_AT.UTF-8"), Some(Language::De));
+        assert_eq!(Language::from_tag("fr-CA"), Some(Language::Fr));
+        assert_eq!(Language::from_tag("C"), None);
+        assert_eq!(t("notification.new_email", &[("subject", "Hi")]), "New Email: Hi");
+        assert_eq!(t("missing.key", &[]), "missing.key");
+        assert_eq!(folder("custom"), "custom");
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::Manager;
+
+/// The profile picked with `--profile <name>`, `None` for the default one.
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Directory next to the executable holding all data in portable mode, `None` otherwise.
+static PORTABLE_ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// A file with this name next to the executable turns portable mode on, like `--portable`.
+const PORTABLE_MARKER: &str = "portable";
+
+fn parse_profile(args: impl IntoIterator<Item = String>) -> Result<Option<String>, String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let name = if arg == "--profile" {
+            args.next().ok_or("--profile needs a name")?
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            name.to_string()
+        } else {
+            continue;
+        };
+
+        // The name ends up in a directory and a keyring service name
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid profile name: {}", name));
+        }
+        return Ok(Some(name));
+    }
+    Ok(None)
+}
+
+/// Reads `--profile` and `--portable` from the command line, must run before anything touches the data directory or keyring.
+pub fn init() -> Result<(), String> {
+    let profile = parse_profile(std::env::args().skip(1))?;
+    let _ = PROFILE.set(profile);
+
+    let exe_dir = std::env::current_exe().map_err(|e| e.to_string())?
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .ok_or("Executable has no parent directory")?;
+    let portable = std::env::args().any(|arg| arg == "--portable") || exe_dir.join(PORTABLE_MARKER).exists();
+    let _ = PORTABLE_ROOT.set(portable.then(|| exe_dir.join("data")));
+    Ok(())
+}
+
+pub fn profile() -> Option<&'static str> {
+    PROFILE.get().and_then(|p| p.as_deref())
+}
+
+pub fn is_portable() -> bool {
+    PORTABLE_ROOT.get().is_some_and(|root| root.is_some())
+}
+
+fn for_profile(dir: PathBuf) -> PathBuf {
+    match profile() {
+        Some(name) => dir.join("profiles").join(name),
+        None => dir,
+    }
+}
+
+/// The data directory in portable mode, known without an app handle since it only depends on the executable.
+pub fn portable_data_dir() -> Option<PathBuf> {
+    PORTABLE_ROOT.get().cloned().flatten().map(for_profile)
+}
+
+/// Where the database, account registry and attachments live, `profiles/<name>` under the app data dir for a named profile.
+pub fn data_dir<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    #[cfg(test)]
+    if let Some(dir) = app_handle.try_state::<crate::utils::test_utils::TestDataDir>() {
+        return Ok(dir.0.clone());
+    }
+
+    if let Some(dir) = portable_data_dir() {
+        return Ok(dir);
+    }
+    Ok(for_profile(app_handle.path().app_data_dir().map_err(|e| e.to_string())?))
+}
+
+/// Log directory in portable mode, `None` to use the platform's.
+pub fn portable_log_dir() -> Option<PathBuf> {
+    PORTABLE_ROOT.get().cloned().flatten().map(|root| root.join("logs"))
+}
+
+/// Keyring service holding the master key and secrets, so profiles never share credentials.
+pub fn keyring_service() -> String {
+    match profile() {
+        Some(name) => format!("dueam-{}", name),
+        None => "dueam".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_profile_argument() {
+        assert_eq!(parse_profile(args(&[])).unwrap(), None);
+        assert_eq!(parse_profile(args(&["--profile", "work"])).unwrap(), Some("work".to_string()));
+        assert_eq!(parse_profile(args(&["--verbose", "--profile=side_project-2"])).unwrap(), Some("side_project-2".to_string()));
+        assert!(parse_profile(args(&["--profile"])).is_err());
+        assert!(parse_profile(args(&["--profile", "../personal"])).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+use tauri::Manager;
+use tauri::plugin::TauriPlugin;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+const LOG_FILE_NAME: &str = "dueam";
+const MAX_LOG_FILE_SIZE: u128 = 5 * 1024 * 1024;
+const DEFAULT_MODULE: &str = "*";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Runtime-adjustable per-module levels, `*` is the fallback for everything else.
+struct Levels {
+    by_module: HashMap<String, LevelFilter>,
+    /// `module::` prefixes with their level, most specific first, rebuilt on every change
+    /// since every log record is matched against them
+    prefixes: Vec<(String, LevelFilter)>,
+}
+
+impl Levels {
+    fn new(by_module: HashMap<String, LevelFilter>) -> Self {
+        let mut levels = Levels { by_module, prefixes: Vec::new() };
+        levels.rebuild();
+        levels
+    }
+
+    fn set(&mut self, module: String, level: LevelFilter) {
+        self.by_module.insert(module, level);
+        self.rebuild();
+    }
+
+    fn rebuild(&mut self) {
+        self.prefixes = self
+            .by_module
+            .iter()
+            .filter(|(module, _)| module.as_str() != DEFAULT_MODULE)
+            .map(|(module, level)| (format!("{}::", module), *level))
+            .collect();
+        self.prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    }
+
+    /// Most specific module prefix wins, e.g. `dueam_lib::email_backend::sync` over `dueam_lib`.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.prefixes
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()) || target == &prefix[..prefix.len() - 2])
+            .map(|(_, level)| *level)
+            .or_else(|| self.by_module.get(DEFAULT_MODULE).copied())
+            .unwrap_or(LevelFilter::Warn)
+    }
+
+    /// The most verbose level configured anywhere, records beyond it are dropped by `log` itself.
+    fn max(&self) -> LevelFilter {
+        self.by_module.values().copied().max().unwrap_or(LevelFilter::Warn)
+    }
+}
+
+fn levels() -> &'static RwLock<Levels> {
+    static LEVELS: OnceLock<RwLock<Levels>> = OnceLock::new();
+    LEVELS.get_or_init(|| {
+        RwLock::new(Levels::new(HashMap::from([
+            (DEFAULT_MODULE.to_string(), LevelFilter::Warn),
+            ("dueam_lib".to_string(), LevelFilter::Info),
+            ("langchain_rust".to_string(), LevelFilter::Warn),
+            ("reqwest".to_string(), LevelFilter::Warn),
+            ("sqlx".to_string(), LevelFilter::Warn),
+            ("tower".to_string(), LevelFilter::Warn),
+            ("hyper".to_string(), LevelFilter::Warn),
+        ])))
+    })
+}
+
+/// Lowers `log`'s global level to the most verbose configured one. The plugin sets it to
+/// `Trace` when it starts, so this runs once it has and again whenever a level changes.
+pub fn apply_max_level() {
+    if let Ok(levels) = levels().read() {
+        log::set_max_level(levels.max());
+    }
+}
+
+/// Each profile logs to its own file, `dueam-<profile>.log`.
+fn log_file_name() -> String {
+    match crate::utils::instance::profile() {
+        Some(profile) => format!("{}-{}", LOG_FILE_NAME, profile),
+        None => LOG_FILE_NAME.to_string(),
+    }
+}
+
+fn is_enabled(metadata: &log::Metadata) -> bool {
+    match levels().read() {
+        Ok(levels) => metadata.level() <= levels.level_for(metadata.target()),
+        Err(_) => true,
+    }
+}
+
+pub fn plugin<R: tauri::Runtime>() -> TauriPlugin<R> {
+    tauri_plugin_log::Builder::new()
+        .target(Target::new(TargetKind::Stdout))
+        .target(Target::new(match crate::utils::instance::portable_log_dir() {
+            Some(path) => TargetKind::Folder { path, file_name: Some(log_file_name()) },
+            None => TargetKind::LogDir { file_name: Some(log_file_name()) },
+        }))
+        .max_file_size(MAX_LOG_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepOne)
+        // Everything passes the static level, `apply_max_level` and `is_enabled` do the real
+        // filtering so it can change at runtime
+        .level(LevelFilter::Trace)
+        .filter(is_enabled)
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} {} [{}] {}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.target(),
+                message
+            ))
+        })
+        .build()
+}
+
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let mut parts = line.splitn(4, ' ');
+    let date = parts.next()?;
+    let time = parts.next()?;
+    let level = parts.next()?;
+    log::Level::from_str(level).ok()?;
+
+    let rest = parts.next()?;
+    let (target, message) = rest.strip_prefix('[')?.split_once("] ")?;
+
+    Some(LogEntry {
+        timestamp: format!("{} {}", date, time),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+    })
+}
+
+pub fn log_file_path<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<std::path::PathBuf, String> {
+    let dir = match crate::utils::instance::portable_log_dir() {
+        Some(dir) => dir,
+        None => app_handle.path().app_log_dir().map_err(|e| e.to_string())?,
+    };
+    Ok(dir.join(format!("{}.log", log_file_name())))
+}
+
+#[tauri::command]
+pub async fn get_recent_logs<R: tauri::Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    level: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, String> {
+    let min_level = match level {
+        Some(l) => LevelFilter::from_str(&l).map_err(|_| format!("Unknown log level: {}", l))?,
+        None => LevelFilter::Trace,
+    };
+    let limit = limit.unwrap_or(200);
+
+    let path = log_file_path(&app_handle)?;
+    let content = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for line in content.lines() {
+        match parse_line(line) {
+            Some(entry) => entries.push(entry),
+            // Continuation of a multi-line message
+            None => {
+                if let Some(last) = entries.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+
+    let mut filtered: Vec<LogEntry> = entries
+        .into_iter()
+        .filter(|e| log::Level::from_str(&e.level).map(|l| l <= min_level).unwrap_or(true))
+        .collect();
+
+    let start = filtered.len().saturating_sub(limit);
+    Ok(filtered.split_off(start))
+}
+
+#[tauri::command]
+pub async fn set_log_level(module: Option<String>, level: String) -> Result<(), String> {
+    let filter = LevelFilter::from_str(&level).map_err(|_| format!("Unknown log level: {}", level))?;
+    let module = module.unwrap_or_else(|| DEFAULT_MODULE.to_string());
+
+    levels().write().map_err(|e| e.to_string())?.set(module.clone(), filter);
+    apply_max_level();
+    log::info!("Log level for {} set to {}", module, filter);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let entry = parse_line("2024-05-01 10:15:00 INFO [dueam_lib::email_backend::sync::engine] Syncing folder INBOX").unwrap();
+        assert_eq!(entry.timestamp, "2024-05-01 10:15:00");
+        assert_eq!(entry.level, "INFO");
+        assert_eq!(entry.target, "dueam_lib::email_backend::sync::engine");
+        assert_eq!(entry.message, "Syncing folder INBOX");
+
+        assert!(parse_line("    at some continuation line").is_none());
+    }
+
+    #[test]
+    fn test_most_specific_module_level_wins() {
+        let mut levels = Levels::new(HashMap::from([
+            (DEFAULT_MODULE.to_string(), LevelFilter::Warn),
+            ("dueam_lib".to_string(), LevelFilter::Info),
+        ]));
+        levels.set("dueam_lib::email_backend::sync".to_string(), LevelFilter::Debug);
+
+        assert_eq!(levels.level_for("dueam_lib::email_backend::sync::engine"), LevelFilter::Debug);
+        assert_eq!(levels.level_for("dueam_lib::email_backend::sync"), LevelFilter::Debug);
+        assert_eq!(levels.level_for("dueam_lib::email_backend::synced"), LevelFilter::Info);
+        assert_eq!(levels.level_for("dueam_libx"), LevelFilter::Warn);
+        assert_eq!(levels.max(), LevelFilter::Debug);
+    }
+}
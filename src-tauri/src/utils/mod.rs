@@ -1,4 +1,14 @@
 pub mod security;
 pub mod attachments;
+pub mod cli;
+pub mod logging;
+pub mod diagnostics;
+pub mod i18n;
+pub mod instance;
+pub mod proxy;
+pub mod profile;
+pub mod updater;
+pub mod tray;
+pub mod badge;
 #[cfg(test)]
 pub mod test_utils;
@@ -0,0 +1,269 @@
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Nonce
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{Emitter, Manager};
+use crate::db::settings::{SettingChanged, Settings, SECRET_REFERENCE, SECRET_SETTING_KEYS};
+use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::security::{derive_key, get_secret, set_secret};
+
+const MAGIC: &[u8] = b"DUEAMPRF";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct RetentionRule {
+    match_type: String,
+    pattern: String,
+    action: String,
+    after_days: i64,
+    enabled: bool,
+    #[serde(default)]
+    file_on_delivery: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct TagDefinition {
+    name: String,
+    color: String,
+    sync_to_server: bool,
+}
+
+/// Everything needed to set the app up on another machine, tokens and keyring secrets included.
+/// Mail itself is not part of it, the accounts sync again after the import.
+#[derive(Debug, Serialize, Deserialize)]
+struct Profile {
+    exported_at: String,
+    accounts: Vec<Account>,
+    /// Raw `settings` rows, with keyring references replaced by the secret
+    settings: Vec<(String, String)>,
+    retention_rules: Vec<RetentionRule>,
+    /// (address, rollup)
+    newsletter_senders: Vec<(String, bool)>,
+    /// (address, status), decided senders only
+    screened_senders: Vec<(String, String)>,
+    tags: Vec<TagDefinition>,
+}
+
+/// Bundle layout: [magic][version][salt (16 bytes)][nonce (12 bytes)][ciphertext]
+fn seal(data: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?.into());
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce_bytes), data).map_err(|e| e.to_string())?;
+
+    let mut bundle = MAGIC.to_vec();
+    bundle.push(FORMAT_VERSION);
+    bundle.extend_from_slice(&salt);
+    bundle.extend_from_slice(&nonce_bytes);
+    bundle.extend_from_slice(&ciphertext);
+    Ok(bundle)
+}
+
+fn open(bundle: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let header_len = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+    if bundle.len() < header_len || !bundle.starts_with(MAGIC) {
+        return Err("Not a profile bundle".to_string());
+    }
+    if bundle[MAGIC.len()] != FORMAT_VERSION {
+        return Err(format!("Unsupported profile bundle version {}", bundle[MAGIC.len()]));
+    }
+
+    let (salt, rest) = bundle[MAGIC.len() + 1..].split_at(SALT_LEN);
+    let (nonce_bytes, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?.into());
+    cipher.decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| "Wrong passphrase or damaged bundle".to_string())
+}
+
+/// Writes an encrypted bundle of accounts, settings and rules to `path`.
+#[tauri::command]
+pub async fn export_profile<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, path: String, passphrase: String) -> Result<(), String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".to_string());
+    }
+    let pool = app_handle.state::<SqlitePool>();
+
+    let accounts = AccountManager::new(&app_handle).await?.load().await?.accounts;
+
+    let mut settings: Vec<(String, String)> = sqlx::query_as("SELECT key, value FROM settings")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    for (key, value) in &mut settings {
+        if SECRET_SETTING_KEYS.contains(&key.as_str()) && *value == SECRET_REFERENCE {
+            let secret = get_secret(key).await?.unwrap_or_default();
+            *value = serde_json::to_string(&secret).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let retention_rules = sqlx::query_as::<_, RetentionRule>("SELECT match_type, pattern, action, after_days, enabled, file_on_delivery FROM retention_rules")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let newsletter_senders = sqlx::query_as("SELECT address, rollup FROM newsletter_senders")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let screened_senders = sqlx::query_as("SELECT address, status FROM screened_senders WHERE status != 'pending'")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let tags = sqlx::query_as::<_, TagDefinition>("SELECT name, color, sync_to_server FROM tags")
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let profile = Profile {
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        accounts,
+        settings,
+        retention_rules,
+        newsletter_senders,
+        screened_senders,
+        tags,
+    };
+    let data = serde_json::to_vec(&profile).map_err(|e| e.to_string())?;
+
+    tokio::task::spawn_blocking(move || -> Result<(), String> {
+        let bundle = seal(&data, &passphrase)?;
+        std::fs::write(&path, bundle).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    log::info!("Profile exported with {} accounts", profile.accounts.len());
+    Ok(())
+}
+
+/// Restores a bundle written by `export_profile`. Accounts already set up are replaced
+/// with the bundle's copy, settings and rules are merged over the existing ones.
+#[tauri::command]
+pub async fn import_profile<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, path: String, passphrase: String) -> Result<(), String> {
+    let data = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, String> {
+        let bundle = std::fs::read(&path).map_err(|e| e.to_string())?;
+        open(&bundle, &passphrase)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let profile: Profile = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
+    let pool = app_handle.state::<SqlitePool>();
+
+    let mut validated = Settings::default();
+    for (key, value) in profile.settings {
+        if let Err(e) = validated.apply(&key, &value) {
+            log::warn!("Skipping imported setting: {}", e);
+            continue;
+        }
+
+        let mut stored_value = value;
+        if SECRET_SETTING_KEYS.contains(&key.as_str()) {
+            let secret = serde_json::from_str::<String>(&stored_value).unwrap_or_else(|_| stored_value.clone());
+            if secret.is_empty() {
+                continue;
+            }
+            set_secret(&key, &secret).await?;
+            stored_value = SECRET_REFERENCE.to_string();
+        }
+
+        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+            .bind(&key)
+            .bind(&stored_value)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let _ = app_handle.emit("settings-changed", SettingChanged { key, value: stored_value });
+    }
+    Settings::invalidate_cache();
+
+    for rule in profile.retention_rules {
+        sqlx::query(
+            "INSERT INTO retention_rules (match_type, pattern, action, after_days, enabled, file_on_delivery) VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(match_type, pattern) DO UPDATE SET action = excluded.action, after_days = excluded.after_days, enabled = excluded.enabled,
+                file_on_delivery = excluded.file_on_delivery"
+        )
+        .bind(&rule.match_type)
+        .bind(&rule.pattern)
+        .bind(&rule.action)
+        .bind(rule.after_days)
+        .bind(rule.enabled)
+        // A trash rule can't be filed on delivery, one from an older export never is
+        .bind(rule.file_on_delivery && rule.action == "archive")
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    for (address, rollup) in profile.newsletter_senders {
+        sqlx::query("INSERT OR REPLACE INTO newsletter_senders (address, rollup) VALUES (?, ?)")
+            .bind(&address)
+            .bind(rollup)
+            .execute(&*pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    for (address, status) in profile.screened_senders {
+        sqlx::query(
+            "INSERT INTO screened_senders (address, status, decided_at) VALUES (?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(address) DO UPDATE SET status = excluded.status, decided_at = excluded.decided_at"
+        )
+        .bind(&address)
+        .bind(&status)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    for tag in profile.tags {
+        sqlx::query(
+            "INSERT INTO tags (name, color, sync_to_server) VALUES (?, ?, ?)
+             ON CONFLICT(name) DO UPDATE SET color = excluded.color, sync_to_server = excluded.sync_to_server"
+        )
+        .bind(&tag.name)
+        .bind(&tag.color)
+        .bind(tag.sync_to_server)
+        .execute(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    let manager = AccountManager::new(&app_handle).await?;
+    for account in &profile.accounts {
+        manager.add_account(account.clone()).await?;
+    }
+
+    if let Some(sync_engine) = app_handle.try_state::<SyncEngine<R>>() {
+        let registry = manager.load().await?;
+        for account in registry.accounts.into_iter().filter(|a| profile.accounts.iter().any(|p| p.email() == a.email())) {
+            sync_engine.trigger_sync_for_account(account);
+        }
+    }
+
+    let _ = app_handle.emit("emails-updated", ());
+    log::info!("Profile imported with {} accounts", profile.accounts.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip_needs_the_passphrase() {
+        let bundle = seal(b"{\"accounts\":[]}", "correct horse").unwrap();
+        assert!(bundle.starts_with(MAGIC));
+
+        assert_eq!(open(&bundle, "correct horse").unwrap(), b"{\"accounts\":[]}");
+        assert!(open(&bundle, "wrong horse").is_err());
+        assert!(open(b"DUEAMPRF", "correct horse").is_err());
+    }
+}
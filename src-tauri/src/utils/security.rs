@@ -1,20 +1,57 @@
+use argon2::Argon2;
 use keyring::Entry;
 use rand::RngCore;
 use chacha20poly1305::{
     aead::{Aead, KeyInit},
     ChaCha20Poly1305, Nonce
 };
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::{Emitter, Manager};
+use crate::email_backend::accounts::manager::AccountManager;
+use crate::email_backend::sync::SyncEngine;
+use crate::utils::instance::{self, keyring_service};
+
+/// Master key derived from the passphrase in portable mode, set once `unlock_portable` succeeds.
+static PORTABLE_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+const PORTABLE_SALT_FILE: &str = "portable.salt";
+/// Encrypted with the derived key so a wrong passphrase is caught before it touches any data
+const PORTABLE_CHECK_FILE: &str = "portable.check";
+/// Stands in for the keyring in portable mode
+const PORTABLE_SECRETS_FILE: &str = "secrets.json.enc";
+
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
 
 pub struct EncryptedStore {
     key: [u8; 32],
 }
 
 impl EncryptedStore {
+    /// Tests go through the real commands, keep them off the OS keyring.
+    #[cfg(test)]
     pub async fn new() -> Result<Self, String> {
+        Ok(Self::new_test([7u8; 32]))
+    }
+
+    #[cfg(not(test))]
+    pub async fn new() -> Result<Self, String> {
+        if instance::is_portable() {
+            let key = PORTABLE_KEY.get().ok_or("The portable data is locked")?;
+            return Ok(Self { key: *key });
+        }
+
         let key_hex = tokio::task::spawn_blocking(|| {
-            let entry = Entry::new("dueam", "master-key").map_err(|e| e.to_string())?;
+            let entry = Entry::new(&keyring_service(), "master-key").map_err(|e| e.to_string())?;
             
             match entry.get_password() {
                 Ok(k) => Ok(k),
@@ -77,6 +114,140 @@ impl EncryptedStore {
     }
 }
 
+/// Secrets file contents in portable mode, empty while locked.
+fn load_portable_secrets(dir: &std::path::Path) -> Result<HashMap<String, String>, String> {
+    let Some(key) = PORTABLE_KEY.get() else { return Ok(HashMap::new()) };
+    let path = dir.join(PORTABLE_SECRETS_FILE);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let data = EncryptedStore { key: *key }.load(path)?;
+    serde_json::from_slice(&data).map_err(|e| e.to_string())
+}
+
+fn save_portable_secrets(dir: &std::path::Path, secrets: &HashMap<String, String>) -> Result<(), String> {
+    let key = PORTABLE_KEY.get().ok_or("The portable data is locked")?;
+    let data = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+    EncryptedStore { key: *key }.save(dir.join(PORTABLE_SECRETS_FILE), &data)
+}
+
+/// Individual secrets (e.g. API keys) kept in the OS keyring under the same service as the master key,
+/// or in an encrypted file next to the data in portable mode.
+pub async fn set_secret(name: &str, value: &str) -> Result<(), String> {
+    if let Some(dir) = instance::portable_data_dir() {
+        let mut secrets = load_portable_secrets(&dir)?;
+        secrets.insert(name.to_string(), value.to_string());
+        return save_portable_secrets(&dir, &secrets);
+    }
+    let name = name.to_string();
+    let value = value.to_string();
+    tokio::task::spawn_blocking(move || {
+        let entry = Entry::new(&keyring_service(), &name).map_err(|e| e.to_string())?;
+        entry.set_password(&value).map_err(|e| e.to_string())
+    }).await.map_err(|e| e.to_string())?
+}
+
+pub async fn get_secret(name: &str) -> Result<Option<String>, String> {
+    if let Some(dir) = instance::portable_data_dir() {
+        return Ok(load_portable_secrets(&dir)?.remove(name));
+    }
+    let name = name.to_string();
+    tokio::task::spawn_blocking(move || {
+        let entry = Entry::new(&keyring_service(), &name).map_err(|e| e.to_string())?;
+        match entry.get_password() {
+            Ok(v) => Ok(Some(v)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }).await.map_err(|e| e.to_string())?
+}
+
+pub async fn delete_secret(name: &str) -> Result<(), String> {
+    if let Some(dir) = instance::portable_data_dir() {
+        let mut secrets = load_portable_secrets(&dir)?;
+        if secrets.remove(name).is_some() {
+            save_portable_secrets(&dir, &secrets)?;
+        }
+        return Ok(());
+    }
+    let name = name.to_string();
+    tokio::task::spawn_blocking(move || {
+        let entry = Entry::new(&keyring_service(), &name).map_err(|e| e.to_string())?;
+        match entry.delete_credential() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }).await.map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Serialize)]
+pub struct PortableStatus {
+    pub portable: bool,
+    /// A passphrase has been chosen, unlocking checks against it instead of setting it
+    pub initialized: bool,
+    pub unlocked: bool,
+}
+
+fn unlock(dir: &std::path::Path, passphrase: &str) -> Result<[u8; 32], String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let salt_path = dir.join(PORTABLE_SALT_FILE);
+    let check_path = dir.join(PORTABLE_CHECK_FILE);
+
+    if !check_path.exists() {
+        if passphrase.is_empty() {
+            return Err("Passphrase cannot be empty".to_string());
+        }
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        fs::write(&salt_path, salt).map_err(|e| e.to_string())?;
+
+        let key = derive_key(passphrase, &salt)?;
+        EncryptedStore { key }.save(check_path, b"dueam")?;
+        return Ok(key);
+    }
+
+    let salt = fs::read(&salt_path).map_err(|e| e.to_string())?;
+    let key = derive_key(passphrase, &salt)?;
+    EncryptedStore { key }.load(check_path).map_err(|_| "Wrong passphrase".to_string())?;
+    Ok(key)
+}
+
+#[tauri::command]
+pub async fn get_portable_status() -> Result<PortableStatus, String> {
+    Ok(PortableStatus {
+        portable: instance::is_portable(),
+        initialized: instance::portable_data_dir().is_some_and(|dir| dir.join(PORTABLE_CHECK_FILE).exists()),
+        unlocked: PORTABLE_KEY.get().is_some(),
+    })
+}
+
+/// Derives the master key from `passphrase`, choosing it on first use, then starts syncing the accounts
+/// that could not be read while locked.
+#[tauri::command]
+pub async fn unlock_portable<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>, passphrase: String) -> Result<(), String> {
+    let dir = instance::portable_data_dir().ok_or("Not running in portable mode")?;
+    if PORTABLE_KEY.get().is_some() {
+        return Ok(());
+    }
+
+    let key = tokio::task::spawn_blocking(move || unlock(&dir, &passphrase))
+        .await
+        .map_err(|e| e.to_string())??;
+    let _ = PORTABLE_KEY.set(key);
+    log::info!("Portable data unlocked");
+    // Settings loaded while locked are missing their secrets
+    crate::db::settings::Settings::invalidate_cache();
+
+    if let Some(sync_engine) = app_handle.try_state::<SyncEngine<R>>() {
+        let registry = AccountManager::new(&app_handle).await?.load().await?;
+        for account in registry.accounts {
+            sync_engine.trigger_sync_for_account(account);
+        }
+    }
+    let _ = app_handle.emit("emails-updated", ());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,6 +267,16 @@ mod tests {
         assert_eq!(original_data, decrypted_data.as_slice());
     }
 
+    #[test]
+    fn test_portable_unlock_checks_the_passphrase() {
+        let dir = tempdir().unwrap();
+        assert!(unlock(dir.path(), "").is_err());
+
+        let key = unlock(dir.path(), "usb stick").expect("First unlock sets the passphrase");
+        assert_eq!(unlock(dir.path(), "usb stick").unwrap(), key);
+        assert_eq!(unlock(dir.path(), "usb sticks").unwrap_err(), "Wrong passphrase");
+    }
+
     #[test]
     fn test_load_non_existent_file() {
         let key = [1u8; 32];
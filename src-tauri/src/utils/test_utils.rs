@@ -1,10 +1,20 @@
 use sqlx::sqlite::{SqlitePool, SqliteConnectOptions};
 use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::test::{mock_builder, MockRuntime};
+use tauri::Manager;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use crate::db::writer::WritePool;
+use crate::email_backend::accounts::imap_smtp::ImapSmtpAccount;
+use crate::email_backend::accounts::manager::{Account, AccountManager};
+use crate::email_backend::sync::SyncEngine;
 
 pub async fn setup_test_db() -> SqlitePool {
     let mut temp_db = env::temp_dir();
     temp_db.push(format!("test_db_{}.sqlite", rand::random::<u32>()));
-    
+
     let options = SqliteConnectOptions::new()
         .filename(&temp_db)
         .create_if_missing(true);
@@ -12,12 +22,862 @@ pub async fn setup_test_db() -> SqlitePool {
     let pool = SqlitePool::connect_with(options).await.expect("Failed to connect to test db");
 
     let migrations = sqlx::migrate!("./migrations");
-    
+
     migrations
         .run(&pool)
         .await
         .expect("Failed to run migrations");
-    
+
     pool
 }
 
+/// Inserts the me@example.com account for tests that fill the database by hand.
+pub async fn insert_test_account(pool: &SqlitePool) -> i64 {
+    let (account_id,): (i64,) = sqlx::query_as("INSERT INTO accounts (email, account_type) VALUES ('me@example.com', 'imap_smtp') RETURNING id")
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert test account");
+    account_id
+}
+
+/// Inserts a folder of the account, named after its path.
+pub async fn insert_test_folder(pool: &SqlitePool, account_id: i64, path: &str, role: Option<&str>) -> i64 {
+    let (folder_id,): (i64,) = sqlx::query_as("INSERT INTO folders (account_id, name, path, role) VALUES (?, ?, ?, ?) RETURNING id")
+        .bind(account_id)
+        .bind(path)
+        .bind(path)
+        .bind(role)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to insert test folder");
+    folder_id
+}
+
+/// Managed by `setup_test_app` so the account registry and attachments land in a temp dir.
+pub struct TestDataDir(pub PathBuf);
+
+/// A mock app with the database, a temp data dir and a sync engine, enough to run commands
+/// against `MockMailServer`. Keep the returned dir alive for the length of the test.
+pub async fn setup_test_app(pool: SqlitePool) -> (tauri::App<MockRuntime>, tempfile::TempDir) {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let app = mock_builder().build(tauri::generate_context!()).expect("Failed to build mock app");
+    app.manage(WritePool(pool.clone()));
+    app.manage(pool);
+    app.manage(TestDataDir(dir.path().to_path_buf()));
+    app.manage(SyncEngine::new(app.handle().clone()));
+    (app, dir)
+}
+
+/// Registers the mock server's account and returns it with its id.
+pub async fn add_mock_account(app: &tauri::App<MockRuntime>, server: &MockMailServer) -> Account {
+    let manager = AccountManager::new(app.handle()).await.expect("Failed to open account store");
+    let account = Account::ImapSmtp(server.account());
+    manager.add_account(account.clone()).await.expect("Failed to add account");
+    manager.load().await.expect("Failed to load accounts")
+        .accounts
+        .into_iter()
+        .find(|a| a.email() == account.email())
+        .expect("Account not saved")
+}
+
+#[derive(Debug, Clone)]
+pub struct MockMessage {
+    pub uid: u32,
+    pub flags: Vec<String>,
+    pub raw: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct MockMailbox {
+    pub name: String,
+    /// `\Sent`, `\Trash`... announced in LIST
+    pub special_use: Option<String>,
+    pub uid_validity: u32,
+    pub uid_next: u32,
+    pub messages: Vec<MockMessage>,
+}
+
+/// What went out through SMTP.
+#[derive(Debug, Clone)]
+pub struct SentMessage {
+    pub from: String,
+    pub recipients: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct MockMailState {
+    pub mailboxes: Vec<MockMailbox>,
+    pub sent: Vec<SentMessage>,
+    /// Files mail sent over SMTP in this mailbox, like Gmail does
+    pub files_sent_in: Option<String>,
+    /// Leaves UIDPLUS out of the capabilities, like some older servers
+    pub without_uidplus: bool,
+    /// Reads IMAP commands without ever answering, like a server that hung
+    pub stalled: bool,
+    /// SMTP connections accepted so far
+    pub smtp_sessions: usize,
+}
+
+impl MockMailState {
+    fn capabilities(&self) -> &'static str {
+        if self.without_uidplus { "IMAP4rev1 AUTH=PLAIN IDLE" } else { "IMAP4rev1 AUTH=PLAIN IDLE UIDPLUS" }
+    }
+
+    pub fn mailbox(&self, name: &str) -> Option<&MockMailbox> {
+        self.mailboxes.iter().find(|m| m.name.eq_ignore_ascii_case(name))
+    }
+
+    fn mailbox_mut(&mut self, name: &str) -> Option<&mut MockMailbox> {
+        self.mailboxes.iter_mut().find(|m| m.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A small in-process IMAP and SMTP server, plain text on localhost, that speaks enough of both
+/// protocols for the sync engine and the commands. Accepts any login.
+pub struct MockMailServer {
+    pub imap_port: u16,
+    pub smtp_port: u16,
+    pub state: Arc<Mutex<MockMailState>>,
+}
+
+impl MockMailServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockMailState::default()));
+        state.lock().unwrap().mailboxes.push(MockMailbox {
+            name: "INBOX".to_string(),
+            special_use: None,
+            uid_validity: 1,
+            uid_next: 1,
+            messages: Vec::new(),
+        });
+
+        let imap = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind IMAP port");
+        let smtp = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind SMTP port");
+        let imap_port = imap.local_addr().unwrap().port();
+        let smtp_port = smtp.local_addr().unwrap().port();
+
+        let imap_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = imap.accept().await {
+                tokio::spawn(serve_imap(stream, imap_state.clone()));
+            }
+        });
+        let smtp_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = smtp.accept().await {
+                tokio::spawn(serve_smtp(stream, smtp_state.clone()));
+            }
+        });
+
+        Self { imap_port, smtp_port, state }
+    }
+
+    pub fn account(&self) -> ImapSmtpAccount {
+        ImapSmtpAccount {
+            id: None,
+            email: "me@example.com".to_string(),
+            name: Some("Me".to_string()),
+            color: None,
+            label: None,
+            imap_host: "127.0.0.1".to_string(),
+            imap_port: self.imap_port,
+            imap_username: "me@example.com".to_string(),
+            imap_encryption: "none".to_string(),
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: self.smtp_port,
+            smtp_username: "me@example.com".to_string(),
+            smtp_encryption: "none".to_string(),
+            smtp_use_imap_credentials: true,
+            password: Some("secret".to_string()),
+            smtp_password: None,
+            trust: Default::default(),
+        }
+    }
+
+    pub fn add_mailbox(&self, name: &str, special_use: Option<&str>) {
+        self.state.lock().unwrap().mailboxes.push(MockMailbox {
+            name: name.to_string(),
+            special_use: special_use.map(str::to_string),
+            uid_validity: 1,
+            uid_next: 1,
+            messages: Vec::new(),
+        });
+    }
+
+    /// Stores a message and returns its UID.
+    pub fn add_message(&self, mailbox: &str, raw: &str, flags: &[&str]) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        let mailbox = state.mailbox_mut(mailbox).expect("Unknown mailbox");
+        let uid = mailbox.uid_next;
+        mailbox.uid_next += 1;
+        mailbox.messages.push(MockMessage {
+            uid,
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            raw: raw.replace("\r\n", "\n").replace('\n', "\r\n").into_bytes(),
+        });
+        uid
+    }
+
+    pub fn flags(&self, mailbox: &str, uid: u32) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.mailbox(mailbox)
+            .and_then(|m| m.messages.iter().find(|msg| msg.uid == uid))
+            .map(|msg| msg.flags.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replaces a message's flags, as another client would.
+    pub fn set_flags(&self, mailbox: &str, uid: u32, flags: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(message) = state.mailbox_mut(mailbox).and_then(|m| m.messages.iter_mut().find(|msg| msg.uid == uid)) {
+            message.flags = flags.iter().map(|f| f.to_string()).collect();
+        }
+    }
+
+    pub fn sent(&self) -> Vec<SentMessage> {
+        self.state.lock().unwrap().sent.clone()
+    }
+}
+
+/// A test message in the shape the mock serves best: single part, ASCII headers.
+pub fn mock_message(from: &str, subject: &str, message_id: &str, body: &str) -> String {
+    format!(
+        "From: {}\r\nTo: Me <me@example.com>\r\nSubject: {}\r\nDate: Thu, 01 Jan 2026 10:00:00 +0000\r\nMessage-ID: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+        from, subject, message_id, body
+    )
+}
+
+// ---- IMAP ----
+
+/// Reads one command, pulling in the literals (`{n}` / `{n+}`) it carries.
+async fn read_imap_command(reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>, writer: &mut tokio::net::tcp::OwnedWriteHalf) -> Option<Vec<u8>> {
+    let mut command = Vec::new();
+    loop {
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line).await.ok()? == 0 {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&line).trim_end().to_string();
+        let literal = text.strip_suffix('}')
+            .and_then(|t| t.rfind('{').map(|i| &t[i + 1..]))
+            .map(|n| (n.trim_end_matches('+').parse::<usize>().ok(), n.ends_with('+')));
+
+        match literal {
+            Some((Some(len), non_sync)) => {
+                command.extend_from_slice(text.as_bytes());
+                command.extend_from_slice(b"\r\n");
+                if !non_sync {
+                    writer.write_all(b"+ Ready for literal\r\n").await.ok()?;
+                }
+                let mut data = vec![0u8; len];
+                reader.read_exact(&mut data).await.ok()?;
+                command.extend_from_slice(&data);
+            }
+            _ => {
+                command.extend_from_slice(text.as_bytes());
+                return Some(command);
+            }
+        }
+    }
+}
+
+/// Splits on spaces outside of quotes, parentheses and brackets.
+fn imap_tokens(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0i32;
+    let mut quoted = false;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            '\\' if quoted => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '(' | '[' if !quoted => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' | ']' if !quoted => {
+                depth -= 1;
+                current.push(c);
+            }
+            ' ' if !quoted && depth == 0 => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+fn imap_string(value: &str) -> String {
+    if value.is_ascii() && !value.contains(['\r', '\n']) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("{{{}}}\r\n{}", value.len(), value)
+    }
+}
+
+fn imap_nstring(value: Option<&str>) -> String {
+    value.map(imap_string).unwrap_or_else(|| "NIL".to_string())
+}
+
+/// Expands a sequence set against `max`, the highest sequence number or UID.
+fn sequence_set(set: &str, max: u32) -> Vec<(u32, u32)> {
+    let bound = |v: &str| if v == "*" { max } else { v.parse().unwrap_or(0) };
+    set.split(',')
+        .map(|part| match part.split_once(':') {
+            Some((a, b)) => {
+                let (a, b) = (bound(a), bound(b));
+                (a.min(b), a.max(b))
+            }
+            None => (bound(part), bound(part)),
+        })
+        .collect()
+}
+
+fn in_set(ranges: &[(u32, u32)], value: u32) -> bool {
+    ranges.iter().any(|(a, b)| (*a..=*b).contains(&value))
+}
+
+/// Header and body of a raw message, split at the first empty line.
+fn split_message(raw: &[u8]) -> (&[u8], &[u8]) {
+    match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(i) => (&raw[..i + 4], &raw[i + 4..]),
+        None => (raw, &[]),
+    }
+}
+
+/// Unfolded `(name, value)` header pairs.
+fn headers(raw: &[u8]) -> Vec<(String, String)> {
+    let (header, _) = split_message(raw);
+    let mut out: Vec<(String, String)> = Vec::new();
+    for line in String::from_utf8_lossy(header).split("\r\n") {
+        if line.starts_with([' ', '\t']) {
+            if let Some(last) = out.last_mut() {
+                last.1.push(' ');
+                last.1.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            out.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    out
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+}
+
+/// `Name <a@b>, c@d` as an IMAP address list.
+fn imap_addresses(value: Option<&str>) -> String {
+    let Some(value) = value.filter(|v| !v.is_empty()) else { return "NIL".to_string() };
+    let addresses: Vec<String> = value.split(',')
+        .filter_map(|entry| {
+            let entry = entry.trim();
+            let (name, address) = match (entry.find('<'), entry.rfind('>')) {
+                (Some(start), Some(end)) if start < end => {
+                    let name = unquote(entry[..start].trim());
+                    (Some(name).filter(|n| !n.is_empty()), &entry[start + 1..end])
+                }
+                _ => (None, entry),
+            };
+            let (mailbox, host) = address.split_once('@')?;
+            Some(format!("({} NIL {} {})", imap_nstring(name.as_deref()), imap_string(mailbox), imap_string(host)))
+        })
+        .collect();
+    if addresses.is_empty() {
+        "NIL".to_string()
+    } else {
+        format!("({})", addresses.join(""))
+    }
+}
+
+fn imap_envelope(raw: &[u8]) -> String {
+    let h = headers(raw);
+    let from = imap_addresses(header(&h, "From"));
+    let sender = match header(&h, "Sender") {
+        Some(sender) => imap_addresses(Some(sender)),
+        None => from.clone(),
+    };
+    let reply_to = match header(&h, "Reply-To") {
+        Some(reply_to) => imap_addresses(Some(reply_to)),
+        None => from.clone(),
+    };
+    format!(
+        "({} {} {} {} {} {} {} {} {} {})",
+        imap_nstring(header(&h, "Date")),
+        imap_nstring(header(&h, "Subject")),
+        from,
+        sender,
+        reply_to,
+        imap_addresses(header(&h, "To")),
+        imap_addresses(header(&h, "Cc")),
+        imap_addresses(header(&h, "Bcc")),
+        imap_nstring(header(&h, "In-Reply-To")),
+        imap_nstring(header(&h, "Message-ID")),
+    )
+}
+
+/// Single part structure from the Content-Type header, the only kind of message the mock holds.
+fn imap_body_structure(raw: &[u8], extensible: bool) -> String {
+    let h = headers(raw);
+    let (_, body) = split_message(raw);
+    let content_type = header(&h, "Content-Type").unwrap_or("text/plain; charset=us-ascii");
+    let mut params = content_type.split(';');
+    let mime = params.next().unwrap_or("text/plain").trim();
+    let (kind, subtype) = mime.split_once('/').unwrap_or(("text", "plain"));
+    let charset = params
+        .filter_map(|p| p.trim().split_once('='))
+        .find(|(name, _)| name.eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| unquote(value))
+        .unwrap_or_else(|| "us-ascii".to_string());
+    let lines = body.iter().filter(|b| **b == b'\n').count();
+
+    format!(
+        "({} {} (\"CHARSET\" {}) NIL NIL \"7BIT\" {} {}{})",
+        imap_string(&kind.to_uppercase()),
+        imap_string(&subtype.to_uppercase()),
+        imap_string(&charset),
+        body.len(),
+        lines,
+        if extensible { " NIL NIL NIL NIL" } else { "" }
+    )
+}
+
+/// Applies `<offset.length>` and returns the label suffix for it.
+fn partial<'a>(data: &'a [u8], spec: &str) -> (&'a [u8], String) {
+    let Some((offset, length)) = spec.trim_matches(['<', '>']).split_once('.') else { return (data, String::new()) };
+    let offset = offset.parse::<usize>().unwrap_or(0).min(data.len());
+    let end = length.parse::<usize>().map(|l| (offset + l).min(data.len())).unwrap_or(data.len());
+    (&data[offset..end], format!("<{}>", offset))
+}
+
+/// Content of `BODY[section]` for a single part message.
+fn section_data(raw: &[u8], section: &str) -> Vec<u8> {
+    let (header, body) = split_message(raw);
+    let upper = section.to_uppercase();
+    if upper.is_empty() {
+        raw.to_vec()
+    } else if upper == "HEADER" {
+        header.to_vec()
+    } else if upper == "TEXT" || upper == "1" {
+        body.to_vec()
+    } else if let Some(fields) = upper.strip_prefix("HEADER.FIELDS") {
+        let not = fields.starts_with(".NOT");
+        let wanted: Vec<String> = fields.trim_start_matches(".NOT").trim().trim_matches(['(', ')']).split_whitespace().map(str::to_string).collect();
+        let mut out = String::new();
+        for (name, value) in headers(raw) {
+            if wanted.contains(&name.to_uppercase()) != not {
+                out.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        out.push_str("\r\n");
+        out.into_bytes()
+    } else {
+        Vec::new()
+    }
+}
+
+fn literal(data: &[u8]) -> Vec<u8> {
+    let mut out = format!("{{{}}}\r\n", data.len()).into_bytes();
+    out.extend_from_slice(data);
+    out
+}
+
+/// The FETCH response items for one message.
+fn fetch_items(seq: u32, message: &MockMessage, items: &[String], with_uid: bool) -> Vec<u8> {
+    let mut expanded: Vec<String> = Vec::new();
+    for item in items {
+        match item.to_uppercase().as_str() {
+            "ALL" => expanded.extend(["FLAGS", "INTERNALDATE", "RFC822.SIZE", "ENVELOPE"].map(String::from)),
+            "FAST" => expanded.extend(["FLAGS", "INTERNALDATE", "RFC822.SIZE"].map(String::from)),
+            "FULL" => expanded.extend(["FLAGS", "INTERNALDATE", "RFC822.SIZE", "ENVELOPE", "BODY"].map(String::from)),
+            _ => expanded.push(item.clone()),
+        }
+    }
+    if with_uid && !expanded.iter().any(|i| i.eq_ignore_ascii_case("UID")) {
+        expanded.insert(0, "UID".to_string());
+    }
+
+    let mut parts: Vec<Vec<u8>> = Vec::new();
+    for item in expanded {
+        let upper = item.to_uppercase();
+        let part = match upper.as_str() {
+            "UID" => format!("UID {}", message.uid).into_bytes(),
+            "FLAGS" => format!("FLAGS ({})", message.flags.join(" ")).into_bytes(),
+            "INTERNALDATE" => b"INTERNALDATE \"01-Jan-2026 10:00:00 +0000\"".to_vec(),
+            "RFC822.SIZE" => format!("RFC822.SIZE {}", message.raw.len()).into_bytes(),
+            "ENVELOPE" => format!("ENVELOPE {}", imap_envelope(&message.raw)).into_bytes(),
+            "BODYSTRUCTURE" => format!("BODYSTRUCTURE {}", imap_body_structure(&message.raw, true)).into_bytes(),
+            "BODY" => format!("BODY {}", imap_body_structure(&message.raw, false)).into_bytes(),
+            "RFC822" => [b"RFC822 ".to_vec(), literal(&message.raw)].concat(),
+            "RFC822.HEADER" => [b"RFC822.HEADER ".to_vec(), literal(&section_data(&message.raw, "HEADER"))].concat(),
+            "RFC822.TEXT" => [b"RFC822.TEXT ".to_vec(), literal(&section_data(&message.raw, "TEXT"))].concat(),
+            _ if upper.starts_with("BODY[") || upper.starts_with("BODY.PEEK[") => {
+                let start = item.find('[').unwrap_or(0);
+                let end = item.rfind(']').unwrap_or(item.len());
+                let section = &item[start + 1..end];
+                let data = section_data(&message.raw, section);
+                let (data, offset) = partial(&data, &item[(end + 1).min(item.len())..]);
+                [format!("BODY[{}]{} ", section, offset).into_bytes(), literal(data)].concat()
+            }
+            _ => continue,
+        };
+        parts.push(part);
+    }
+
+    let mut out = format!("* {} FETCH (", seq).into_bytes();
+    out.extend_from_slice(&parts.join(&b' '));
+    out.extend_from_slice(b")\r\n");
+    out
+}
+
+/// Applies `+FLAGS`, `-FLAGS` or `FLAGS` (optionally `.SILENT`) to a message.
+fn store_flags(message: &mut MockMessage, mode: &str, flags: &str) {
+    let flags: Vec<String> = flags.trim_matches(['(', ')']).split_whitespace().map(str::to_string).collect();
+    match mode.to_uppercase().trim_end_matches(".SILENT") {
+        "+FLAGS" => {
+            for flag in flags {
+                if !message.flags.iter().any(|f| f.eq_ignore_ascii_case(&flag)) {
+                    message.flags.push(flag);
+                }
+            }
+        }
+        "-FLAGS" => message.flags.retain(|f| !flags.iter().any(|flag| flag.eq_ignore_ascii_case(f))),
+        _ => message.flags = flags,
+    }
+}
+
+/// Runs one command, returning the bytes to send back and whether to close the connection.
+fn imap_response(state: &Arc<Mutex<MockMailState>>, selected: &mut Option<String>, tag: &str, command: &str, args: &[String], raw: &[u8]) -> (Vec<u8>, bool) {
+    let ok = |text: &str| format!("{} OK {}\r\n", tag, text).into_bytes();
+    let no = |text: &str| format!("{} NO {}\r\n", tag, text).into_bytes();
+    let mut state = state.lock().unwrap();
+
+    match command {
+        "CAPABILITY" => ([format!("* CAPABILITY {}\r\n", state.capabilities()).into_bytes(), ok("CAPABILITY completed")].concat(), false),
+        "LOGIN" | "AUTHENTICATE" => (ok("Logged in"), false),
+        "LOGOUT" => ([b"* BYE Logging out\r\n".to_vec(), ok("LOGOUT completed")].concat(), true),
+        "LIST" | "LSUB" => {
+            let mut out = Vec::new();
+            for mailbox in &state.mailboxes {
+                let attributes = mailbox.special_use.clone().map(|s| format!("\\HasNoChildren {}", s)).unwrap_or_else(|| "\\HasNoChildren".to_string());
+                out.extend_from_slice(format!("* {} ({}) \"/\" {}\r\n", command, attributes, imap_string(&mailbox.name)).as_bytes());
+            }
+            out.extend_from_slice(&ok("LIST completed"));
+            (out, false)
+        }
+        "SELECT" | "EXAMINE" => {
+            let name = unquote(args.first().map(String::as_str).unwrap_or_default());
+            let Some(mailbox) = state.mailbox(&name) else { return (no("No such mailbox"), false) };
+            let out = format!(
+                "* {} EXISTS\r\n* 0 RECENT\r\n* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n* OK [PERMANENTFLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft \\*)] Flags permitted\r\n* OK [UIDVALIDITY {}] UIDs valid\r\n* OK [UIDNEXT {}] Predicted next UID\r\n",
+                mailbox.messages.len(), mailbox.uid_validity, mailbox.uid_next
+            );
+            *selected = Some(mailbox.name.clone());
+            let mode = if command == "SELECT" { "[READ-WRITE] SELECT completed" } else { "[READ-ONLY] EXAMINE completed" };
+            ([out.into_bytes(), ok(mode)].concat(), false)
+        }
+        "STATUS" => {
+            let name = unquote(args.first().map(String::as_str).unwrap_or_default());
+            let Some(mailbox) = state.mailbox(&name) else { return (no("No such mailbox"), false) };
+            let wanted = args.get(1).map(|a| a.trim_matches(['(', ')']).to_uppercase()).unwrap_or_default();
+            let items: Vec<String> = wanted.split_whitespace().filter_map(|item| {
+                let value = match item {
+                    "MESSAGES" => mailbox.messages.len() as u32,
+                    "RECENT" => 0,
+                    "UIDNEXT" => mailbox.uid_next,
+                    "UIDVALIDITY" => mailbox.uid_validity,
+                    "UNSEEN" => mailbox.messages.iter().filter(|m| !m.flags.iter().any(|f| f.eq_ignore_ascii_case("\\Seen"))).count() as u32,
+                    _ => return None,
+                };
+                Some(format!("{} {}", item, value))
+            }).collect();
+            let out = format!("* STATUS {} ({})\r\n", imap_string(&mailbox.name), items.join(" "));
+            ([out.into_bytes(), ok("STATUS completed")].concat(), false)
+        }
+        "CREATE" => {
+            let name = unquote(args.first().map(String::as_str).unwrap_or_default());
+            if state.mailbox(&name).is_none() {
+                state.mailboxes.push(MockMailbox { name, special_use: None, uid_validity: 1, uid_next: 1, messages: Vec::new() });
+            }
+            (ok("CREATE completed"), false)
+        }
+        "APPEND" => {
+            let name = unquote(args.first().map(String::as_str).unwrap_or_default());
+            let flags = args.iter().find(|a| a.starts_with('(')).cloned().unwrap_or_default();
+            // The message is the literal closing the command
+            let message = match raw.windows(3).position(|w| w == b"}\r\n") {
+                Some(i) => raw[i + 3..].to_vec(),
+                None => Vec::new(),
+            };
+            let Some(mailbox) = state.mailbox_mut(&name) else { return (no("[TRYCREATE] No such mailbox"), false) };
+            let uid = mailbox.uid_next;
+            mailbox.uid_next += 1;
+            let mut appended = MockMessage { uid, flags: Vec::new(), raw: message };
+            store_flags(&mut appended, "FLAGS", &flags);
+            mailbox.messages.push(appended);
+            let validity = mailbox.uid_validity;
+            (ok(&format!("[APPENDUID {} {}] APPEND completed", validity, uid)), false)
+        }
+        "FETCH" | "UID FETCH" | "STORE" | "UID STORE" | "SEARCH" | "UID SEARCH" | "COPY" | "UID COPY" | "MOVE" | "UID MOVE" | "EXPUNGE" | "UID EXPUNGE" | "CLOSE" | "UNSELECT" => {
+            let Some(name) = selected.clone() else { return (no("No mailbox selected"), false) };
+            let by_uid = command.starts_with("UID ");
+            let verb = command.trim_start_matches("UID ");
+
+            if verb == "CLOSE" || verb == "UNSELECT" {
+                *selected = None;
+                return (ok(&format!("{} completed", verb)), false);
+            }
+
+            let mailbox = state.mailbox_mut(&name).expect("Selected mailbox exists");
+            let max = if by_uid { mailbox.messages.last().map(|m| m.uid).unwrap_or(0) } else { mailbox.messages.len() as u32 };
+            let matches = |set: &str, seq: u32, uid: u32| in_set(&sequence_set(set, max), if by_uid { uid } else { seq });
+            let mut out = Vec::new();
+
+            match verb {
+                "FETCH" => {
+                    let set = args.first().cloned().unwrap_or_default();
+                    let items_arg = args[1.min(args.len())..].join(" ");
+                    let items = imap_tokens(items_arg.trim().strip_prefix('(').and_then(|i| i.strip_suffix(')')).unwrap_or(&items_arg));
+                    for (i, message) in mailbox.messages.iter().enumerate() {
+                        if matches(&set, i as u32 + 1, message.uid) {
+                            out.extend(fetch_items(i as u32 + 1, message, &items, by_uid));
+                        }
+                    }
+                }
+                "STORE" => {
+                    let set = args.first().cloned().unwrap_or_default();
+                    let mode = args.get(1).cloned().unwrap_or_default();
+                    let flags = args[2.min(args.len())..].join(" ");
+                    for (i, message) in mailbox.messages.iter_mut().enumerate() {
+                        if matches(&set, i as u32 + 1, message.uid) {
+                            store_flags(message, &mode, &flags);
+                            if !mode.to_uppercase().ends_with(".SILENT") {
+                                out.extend(fetch_items(i as u32 + 1, message, &["FLAGS".to_string()], by_uid));
+                            }
+                        }
+                    }
+                }
+                "SEARCH" => {
+                    // Criteria other than a UID range and SEEN or UNSEEN match everything
+                    let uid_range = args.windows(2).find(|w| w[0].eq_ignore_ascii_case("UID")).map(|w| w[1].clone());
+                    let seen = args.iter().find_map(|a| match a.to_uppercase().as_str() {
+                        "SEEN" => Some(true),
+                        "UNSEEN" => Some(false),
+                        _ => None,
+                    });
+                    let found: Vec<String> = mailbox.messages.iter().enumerate()
+                        .filter(|(_, m)| uid_range.as_ref().is_none_or(|r| in_set(&sequence_set(r, max), m.uid)))
+                        .filter(|(_, m)| seen.is_none_or(|seen| m.flags.iter().any(|f| f.eq_ignore_ascii_case("\\Seen")) == seen))
+                        .map(|(i, m)| (if by_uid { m.uid } else { i as u32 + 1 }).to_string())
+                        .collect();
+                    out.extend_from_slice(format!("* SEARCH {}\r\n", found.join(" ")).trim_end().as_bytes());
+                    out.extend_from_slice(b"\r\n");
+                }
+                "COPY" | "MOVE" => {
+                    let set = args.first().cloned().unwrap_or_default();
+                    let target = unquote(args.get(1).map(String::as_str).unwrap_or_default());
+                    let picked: Vec<(u32, MockMessage)> = mailbox.messages.iter().enumerate()
+                        .filter(|(i, m)| matches(&set, *i as u32 + 1, m.uid))
+                        .map(|(i, m)| (i as u32 + 1, m.clone()))
+                        .collect();
+                    if verb == "MOVE" {
+                        for (seq, _) in picked.iter().rev() {
+                            mailbox.messages.remove(*seq as usize - 1);
+                            out.extend_from_slice(format!("* {} EXPUNGE\r\n", seq).as_bytes());
+                        }
+                    }
+                    let Some(target) = state.mailbox_mut(&target) else { return (no("[TRYCREATE] No such mailbox"), false) };
+                    for (_, mut message) in picked {
+                        message.uid = target.uid_next;
+                        target.uid_next += 1;
+                        target.messages.push(message);
+                    }
+                }
+                "EXPUNGE" => {
+                    // UID EXPUNGE only removes the deleted messages in its set
+                    let set = by_uid.then(|| sequence_set(args.first().map(String::as_str).unwrap_or_default(), max));
+                    let mut seq = 1;
+                    mailbox.messages.retain(|m| {
+                        let deleted = m.flags.iter().any(|f| f.eq_ignore_ascii_case("\\Deleted"))
+                            && set.as_ref().is_none_or(|set| in_set(set, m.uid));
+                        if deleted {
+                            out.extend_from_slice(format!("* {} EXPUNGE\r\n", seq).as_bytes());
+                        } else {
+                            seq += 1;
+                        }
+                        !deleted
+                    });
+                }
+                _ => {}
+            }
+            out.extend_from_slice(&ok(&format!("{} completed", verb)));
+            (out, false)
+        }
+        // NOOP, CHECK, ENABLE, ID and the like need nothing more than a success
+        _ => (ok(&format!("{} completed", command)), false),
+    }
+}
+
+async fn serve_imap(stream: TcpStream, state: Arc<Mutex<MockMailState>>) {
+    let (read, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let greeting = format!("* OK [CAPABILITY {}] Mock IMAP ready\r\n", state.lock().unwrap().capabilities());
+    if writer.write_all(greeting.as_bytes()).await.is_err() {
+        return;
+    }
+
+    let mut selected: Option<String> = None;
+    while let Some(raw) = read_imap_command(&mut reader, &mut writer).await {
+        let line = String::from_utf8_lossy(&raw).lines().next().unwrap_or_default().to_string();
+        let tokens = imap_tokens(&line);
+        let Some(tag) = tokens.first().cloned() else { continue };
+        let mut command = tokens.get(1).map(|c| c.to_uppercase()).unwrap_or_default();
+        let mut args = tokens[2.min(tokens.len())..].to_vec();
+        if command == "UID" && !args.is_empty() {
+            command = format!("UID {}", args.remove(0).to_uppercase());
+        }
+
+        if command == "AUTHENTICATE" && args.len() < 2 {
+            // No initial response, take the credentials from the next line
+            let mut credentials = String::new();
+            if writer.write_all(b"+ \r\n").await.is_err() || reader.read_line(&mut credentials).await.is_err() {
+                return;
+            }
+        }
+        if command == "IDLE" {
+            let mut done = String::new();
+            if writer.write_all(b"+ idling\r\n").await.is_err() || reader.read_line(&mut done).await.unwrap_or(0) == 0 {
+                return;
+            }
+        }
+
+        if state.lock().unwrap().stalled {
+            continue;
+        }
+        let (response, close) = imap_response(&state, &mut selected, &tag, &command, &args, &raw);
+        if writer.write_all(&response).await.is_err() || close {
+            return;
+        }
+    }
+}
+
+// ---- SMTP ----
+
+fn smtp_path(argument: &str) -> String {
+    let start = argument.find('<').map(|i| i + 1).unwrap_or(0);
+    let end = argument[start..].find('>').map(|i| start + i).unwrap_or(argument.len());
+    argument[start..end].to_string()
+}
+
+async fn serve_smtp(stream: TcpStream, state: Arc<Mutex<MockMailState>>) {
+    state.lock().unwrap().smtp_sessions += 1;
+    let (read, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    if writer.write_all(b"220 mock.localhost ESMTP ready\r\n").await.is_err() {
+        return;
+    }
+
+    let mut from = String::new();
+    let mut recipients = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let line = line.trim_end();
+        let verb = line.split_whitespace().next().unwrap_or_default().to_uppercase();
+
+        let reply: &[u8] = match verb.as_str() {
+            "EHLO" => b"250-mock.localhost\r\n250-AUTH PLAIN LOGIN\r\n250-8BITMIME\r\n250 SIZE 10485760\r\n",
+            "HELO" => b"250 mock.localhost\r\n",
+            "AUTH" => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                let prompts: &[&[u8]] = match (parts.get(1).map(|m| m.to_uppercase()).as_deref(), parts.len()) {
+                    (Some("LOGIN"), 2) => &[b"334 VXNlcm5hbWU6\r\n", b"334 UGFzc3dvcmQ6\r\n"],
+                    (Some("LOGIN"), _) => &[b"334 UGFzc3dvcmQ6\r\n"],
+                    (_, 2) => &[b"334 \r\n"],
+                    _ => &[],
+                };
+                for prompt in prompts {
+                    let mut answer = String::new();
+                    if writer.write_all(prompt).await.is_err() || reader.read_line(&mut answer).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                }
+                b"235 2.7.0 Authentication successful\r\n"
+            }
+            "MAIL" => {
+                from = smtp_path(line);
+                recipients.clear();
+                b"250 2.1.0 OK\r\n"
+            }
+            "RCPT" => {
+                recipients.push(smtp_path(line));
+                b"250 2.1.5 OK\r\n"
+            }
+            "DATA" => {
+                if writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await.is_err() {
+                    return;
+                }
+                let mut data = Vec::new();
+                loop {
+                    let mut data_line = Vec::new();
+                    if reader.read_until(b'\n', &mut data_line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    if data_line == b".\r\n" || data_line == b".\n" {
+                        break;
+                    }
+                    // Undo dot stuffing
+                    let content = if data_line.starts_with(b"..") { &data_line[1..] } else { &data_line[..] };
+                    data.extend_from_slice(content);
+                }
+                let mut state = state.lock().unwrap();
+                let filed_in = state.files_sent_in.clone();
+                if let Some(mailbox) = filed_in.and_then(|name| state.mailbox_mut(&name)) {
+                    let uid = mailbox.uid_next;
+                    mailbox.uid_next += 1;
+                    mailbox.messages.push(MockMessage { uid, flags: vec!["\\Seen".to_string()], raw: data.clone() });
+                }
+                state.sent.push(SentMessage { from: from.clone(), recipients: recipients.clone(), data });
+                b"250 2.0.0 OK queued\r\n"
+            }
+            "RSET" => {
+                from.clear();
+                recipients.clear();
+                b"250 2.0.0 OK\r\n"
+            }
+            "QUIT" => {
+                let _ = writer.write_all(b"221 2.0.0 Bye\r\n").await;
+                return;
+            }
+            _ => b"250 2.0.0 OK\r\n",
+        };
+        if writer.write_all(reply).await.is_err() {
+            return;
+        }
+    }
+}
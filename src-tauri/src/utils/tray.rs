@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use sqlx::SqlitePool;
+use tauri::image::Image;
+use tauri::{Listener, Manager, Theme};
+use crate::db::settings::SettingChanged;
+use crate::utils::badge;
+
+/// Id of the tray icon built at startup.
+pub const TRAY_ID: &str = "main";
+
+/// How long a burst of `emails-updated` events is gathered before the unread count is checked.
+const UNREAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+struct TrayLook {
+    theme: Theme,
+    unread: bool,
+}
+
+static LOOK: OnceLock<Mutex<TrayLook>> = OnceLock::new();
+static UNREAD_CHECK_PENDING: AtomicBool = AtomicBool::new(false);
+
+fn look() -> &'static Mutex<TrayLook> {
+    LOOK.get_or_init(|| Mutex::new(TrayLook { theme: Theme::Light, unread: false }))
+}
+
+/// macOS tints template images for the menu bar itself, elsewhere the glyph has to contrast
+/// with the system theme. The unread variants add a dot.
+fn icon(theme: Theme, unread: bool) -> Image<'static> {
+    if cfg!(target_os = "macos") {
+        return if unread {
+            tauri::include_image!("./icons/tray/template-unread.png")
+        } else {
+            tauri::include_image!("./icons/tray/template.png")
+        };
+    }
+    match (theme, unread) {
+        (Theme::Dark, false) => tauri::include_image!("./icons/tray/dark.png"),
+        (Theme::Dark, true) => tauri::include_image!("./icons/tray/dark-unread.png"),
+        (_, false) => tauri::include_image!("./icons/tray/light.png"),
+        (_, true) => tauri::include_image!("./icons/tray/light-unread.png"),
+    }
+}
+
+/// The icon for the current theme and unread state, to build the tray with.
+pub fn current_icon() -> Image<'static> {
+    let look = look().lock().unwrap_or_else(|e| e.into_inner());
+    icon(look.theme, look.unread)
+}
+
+fn refresh<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) {
+    let Some(tray) = app_handle.tray_by_id(TRAY_ID) else { return };
+    if let Err(e) = tray.set_icon(Some(current_icon())) {
+        log::warn!("Failed to update the tray icon: {}", e);
+    }
+    #[cfg(target_os = "macos")]
+    let _ = tray.set_icon_as_template(true);
+}
+
+/// Follows a system light/dark switch, reported through the main window.
+pub fn set_theme<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, theme: Theme) {
+    {
+        let mut look = look().lock().unwrap_or_else(|e| e.into_inner());
+        if look.theme == theme {
+            return;
+        }
+        look.theme = theme;
+    }
+    refresh(app_handle);
+}
+
+async fn check_unread<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) {
+    let Some(pool) = app_handle.try_state::<SqlitePool>() else { return };
+    let unread = badge::unread_count(&pool, true).await.unwrap_or(0);
+
+    {
+        let mut look = look().lock().unwrap_or_else(|e| e.into_inner());
+        if look.unread == (unread > 0) {
+            return;
+        }
+        look.unread = unread > 0;
+    }
+    refresh(app_handle);
+}
+
+/// Picks up the theme of the main window and the unread state, then follows changes to the
+/// unread state as mail arrives or is read, for the tray icon and the dock/taskbar badge.
+pub async fn init<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) {
+    if let Some(theme) = app_handle.get_webview_window("main").and_then(|w| w.theme().ok()) {
+        set_theme(app_handle, theme);
+    }
+    check_unread(app_handle).await;
+    badge::refresh(app_handle).await;
+
+    let handle = app_handle.clone();
+    app_handle.listen("emails-updated", move |_| {
+        if UNREAD_CHECK_PENDING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let handle = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(UNREAD_DEBOUNCE).await;
+            UNREAD_CHECK_PENDING.store(false, Ordering::SeqCst);
+            check_unread(&handle).await;
+            badge::refresh(&handle).await;
+        });
+    });
+
+    let handle = app_handle.clone();
+    app_handle.listen("settings-changed", move |event| {
+        if let Ok(change) = serde_json::from_str::<SettingChanged>(event.payload()) {
+            if change.key == "badgeEnabled" || change.key == "badgePrimaryOnly" {
+                let handle = handle.clone();
+                tauri::async_runtime::spawn(async move { badge::refresh(&handle).await });
+            }
+        }
+    });
+}
@@ -0,0 +1,135 @@
+use serde::Serialize;
+
+/// Update manifests per release channel, written by the release workflow.
+const STABLE_ENDPOINT: &str = "https://github.com/ShashiSrinath/dueam/releases/latest/download/latest.json";
+const BETA_ENDPOINT: &str = "https://github.com/ShashiSrinath/dueam/releases/download/beta/latest.json";
+
+/// Wait after startup before the first check, so it doesn't compete with the initial sync.
+#[cfg(desktop)]
+const STARTUP_CHECK_DELAY_SECS: u64 = 60;
+
+/// Payload of the `update-available` event and result of `check_for_updates`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub date: Option<String>,
+    /// Release notes from the manifest
+    pub changelog: Option<String>,
+}
+
+fn endpoint(channel: &str) -> &'static str {
+    match channel {
+        "beta" => BETA_ENDPOINT,
+        _ => STABLE_ENDPOINT,
+    }
+}
+
+#[cfg(desktop)]
+mod desktop {
+    use super::*;
+    use sqlx::SqlitePool;
+    use tauri::{Emitter, Manager};
+    use tauri_plugin_updater::{Update, UpdaterExt};
+    use crate::db::settings::Settings;
+
+    pub async fn check<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<Option<Update>, String> {
+        // Builds without a signing key can't verify updates, they are updated by hand
+        let pubkey = option_env!("UPDATER_PUBKEY").ok_or("Updates are not set up for this build")?;
+        let channel = Settings::load(&app_handle.state::<SqlitePool>()).await?.update_channel;
+        let url = endpoint(&channel).parse().map_err(|e: url::ParseError| e.to_string())?;
+
+        let updater = app_handle.updater_builder()
+            .pubkey(pubkey)
+            .endpoints(vec![url])
+            .map_err(|e| e.to_string())?
+            .build()
+            .map_err(|e| e.to_string())?;
+        updater.check().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn check_and_notify<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<Option<UpdateInfo>, String> {
+        let Some(update) = check(app_handle).await? else { return Ok(None) };
+        let info = UpdateInfo {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            date: update.date.map(|d| d.to_string()),
+            changelog: update.body.clone(),
+        };
+        log::info!("Update {} available", info.version);
+        let _ = app_handle.emit("update-available", info.clone());
+        Ok(Some(info))
+    }
+
+    pub fn plugin<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R, tauri_plugin_updater::Config> {
+        tauri_plugin_updater::Builder::new().build()
+    }
+
+    pub fn check_on_startup<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) {
+        if option_env!("UPDATER_PUBKEY").is_none() {
+            return;
+        }
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(STARTUP_CHECK_DELAY_SECS)).await;
+            if let Err(e) = check_and_notify(&app_handle).await {
+                log::warn!("Update check failed: {}", e);
+            }
+        });
+    }
+
+    pub async fn install<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
+        let update = check(app_handle).await?.ok_or("Already up to date")?;
+        log::info!("Installing update {}", update.version);
+        update.download_and_install(|_, _| {}, || {}).await.map_err(|e| e.to_string())?;
+
+        // Leave the database and IMAP sessions in a clean state before restarting
+        app_handle.state::<crate::email_backend::sync::SyncEngine<R>>().shutdown().await;
+        app_handle.restart()
+    }
+}
+
+#[cfg(mobile)]
+mod mobile {
+    use super::UpdateInfo;
+
+    const APP_STORE_ONLY: &str = "Updates come from the app store on this platform";
+
+    pub async fn check_and_notify<R: tauri::Runtime>(_app_handle: &tauri::AppHandle<R>) -> Result<Option<UpdateInfo>, String> {
+        Err(APP_STORE_ONLY.to_string())
+    }
+
+    pub async fn install<R: tauri::Runtime>(_app_handle: &tauri::AppHandle<R>) -> Result<(), String> {
+        Err(APP_STORE_ONLY.to_string())
+    }
+}
+
+#[cfg(desktop)]
+use desktop as platform;
+#[cfg(mobile)]
+use mobile as platform;
+#[cfg(desktop)]
+pub use desktop::{check_on_startup, plugin};
+
+/// Checks the configured channel, emitting `update-available` when there is a newer version.
+#[tauri::command]
+pub async fn check_for_updates<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<Option<UpdateInfo>, String> {
+    platform::check_and_notify(&app_handle).await
+}
+
+/// Downloads and installs the newest version, then restarts into it.
+#[tauri::command]
+pub async fn install_update<R: tauri::Runtime>(app_handle: tauri::AppHandle<R>) -> Result<(), String> {
+    platform::install(&app_handle).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_channel_falls_back_to_stable() {
+        assert_eq!(endpoint("beta"), BETA_ENDPOINT);
+        assert_eq!(endpoint("stable"), STABLE_ENDPOINT);
+        assert_eq!(endpoint("nightly"), STABLE_ENDPOINT);
+    }
+}
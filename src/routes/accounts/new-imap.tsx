@@ -103,7 +103,7 @@ function NewImapComponent() {
     try {
       setError(null);
       setIsSubmitting(true);
-      await invoke("add_imap_smtp_account", { account: values });
+      await invoke("login_with_imap_smtp", { account: values });
       await useEmailStore.getState().fetchAccountsAndFolders();
       navigate({ to: "/" });
     } catch (err: any) {